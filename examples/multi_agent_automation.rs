@@ -113,10 +113,12 @@ async fn main() -> Result<()> {
     println!("\nPlanner Agent History:");
     println!("--------------------");
     if let Some(agent) = agent_manager.get_agent(AgentType::Planner) {
-        for (i, (prompt, response)) in agent.history().iter().enumerate() {
+        for (i, exchange) in agent.history().chunks(2).enumerate() {
             println!("Step {}", i + 1);
-            println!("Prompt: {}", prompt);
-            println!("Response: {}", response);
+            println!("Prompt: {}", exchange[0].content);
+            if let Some(response) = exchange.get(1) {
+                println!("Response: {}", response.content);
+            }
             println!();
         }
     }
@@ -4,10 +4,18 @@
 //! a multi-agent architecture for planning, navigation, interaction, and recovery.
 
 use crate::error::{Error, Result};
-use crate::llms::{LlmProvider, LlmResponse};
+use crate::llms::{collect_stream, ChatMessage, GenerationOptions, LlmProvider, LlmResponse, ToolDefinition};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Token budget for the page snapshot handed to the navigator
+#[cfg(feature = "browser")]
+const PAGE_TOKEN_BUDGET: usize = 4000;
+
+/// How many rounds of tool calls are carried out for the interactor before it must reply without one
+#[cfg(feature = "browser")]
+const MAX_TOOL_ROUNDS: usize = 10;
+
 /// The type of agent
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AgentType {
@@ -117,8 +125,8 @@ pub struct Agent {
     config: AgentConfig,
     /// The LLM provider for the agent
     llm: Box<dyn LlmProvider>,
-    /// The conversation history for the agent
-    history: Vec<(String, String)>,
+    /// The conversation history for the agent, without the system message
+    history: Vec<ChatMessage>,
 }
 
 impl Agent {
@@ -132,26 +140,117 @@ impl Agent {
     }
 
     /// Run the agent with the given input
+    ///
+    /// The earlier prompts and replies in the history are sent along, so the
+    /// agent sees the whole conversation.
     pub async fn run(&mut self, input: &str) -> Result<String> {
         // Format the prompt using the template and input
         let prompt = self.config.prompt_template.replace("{objective}", input);
         
         // Get the response from the LLM
-        let response = self.llm.generate_response(&self.config.system_message, &prompt, self.config.temperature).await?;
+        let options = GenerationOptions::new().with_temperature(self.config.temperature);
+        let response = self.llm.chat(&self.messages(&prompt), &options).await?;
         
         // Add the interaction to the history
-        self.history.push((prompt, response.content.clone()));
+        self.history.push(ChatMessage::user(prompt));
+        self.history.push(response.message());
         
         Ok(response.content)
     }
 
+    /// Run the agent, handing the reply to `on_delta` piece by piece as it is generated
+    pub async fn run_streaming(&mut self, input: &str, on_delta: impl FnMut(&str)) -> Result<String> {
+        let prompt = self.config.prompt_template.replace("{objective}", input);
+        let options = GenerationOptions::new().with_temperature(self.config.temperature);
+        let stream = self.llm.chat_stream(&self.messages(&prompt), &options).await?;
+        let response = collect_stream(stream, on_delta).await?;
+        self.history.push(ChatMessage::user(prompt));
+        self.history.push(response.message());
+        Ok(response.content)
+    }
+
+    /// Run the agent offering it `tools`, returning the reply with any tool calls it makes
+    ///
+    /// The reply, calls included, is kept in the history; answer each call with
+    /// [`Agent::add_tool_result`] before continuing with [`Agent::resume_with_tools`].
+    pub async fn run_with_tools(&mut self, input: &str, tools: &[ToolDefinition]) -> Result<LlmResponse> {
+        let prompt = self.config.prompt_template.replace("{objective}", input);
+        self.history.push(ChatMessage::user(prompt));
+        let response = self.resume_with_tools(tools).await;
+        if response.is_err() {
+            self.history.pop();
+        }
+        response
+    }
+
+    /// Ask the agent to continue the conversation as it stands, offering it `tools`
+    pub async fn resume_with_tools(&mut self, tools: &[ToolDefinition]) -> Result<LlmResponse> {
+        let options = GenerationOptions::new().with_temperature(self.config.temperature);
+        let mut messages = vec![ChatMessage::system(self.config.system_message.as_str())];
+        messages.extend(self.history.iter().cloned());
+        let response = self.llm.chat_with_tools(&messages, tools, &options).await?;
+        self.history.push(response.message());
+        Ok(response)
+    }
+
+    /// Answer the tool call `call_id` made in the agent's last reply
+    pub fn add_tool_result(&mut self, call_id: &str, result: impl Into<String>) {
+        self.history.push(ChatMessage::tool_result(call_id, result));
+    }
+
+    /// Run the agent with the [`browser_tools`], carrying out the calls it makes until it replies without one
+    ///
+    /// Each result, or the error of a failed call, is handed back to the agent.
+    /// Returns one line per call with its result followed by the agent's final reply.
+    #[cfg(feature = "browser")]
+    pub async fn interact(&mut self, input: &str, browser: &dyn crate::browser::AsyncBrowser) -> Result<String> {
+        let tools = browser_tools();
+        let mut response = self.run_with_tools(input, &tools).await?;
+        let mut report = Vec::new();
+        for round in 0..=MAX_TOOL_ROUNDS {
+            if response.tool_calls.is_empty() {
+                report.extend(Some(response.content).filter(|content| !content.is_empty()));
+                return Ok(report.join("\n"));
+            }
+            if round == MAX_TOOL_ROUNDS {
+                break;
+            }
+            for call in &response.tool_calls {
+                let result = match run_browser_tool(browser, call).await {
+                    Ok(result) => {
+                        report.push(format!("{}: {}", call, result));
+                        result
+                    }
+                    Err(e) => {
+                        report.push(format!("{} failed: {}", call, e));
+                        format!("Failed: {}", e)
+                    }
+                };
+                self.add_tool_result(&call.id, result);
+            }
+            response = self.resume_with_tools(&tools).await?;
+        }
+        Err(Error::AgentError(format!(
+            "The agent was still calling tools after {} rounds",
+            MAX_TOOL_ROUNDS
+        )))
+    }
+
+    /// The conversation so far followed by `prompt`
+    fn messages(&self, prompt: &str) -> Vec<ChatMessage> {
+        let mut messages = vec![ChatMessage::system(self.config.system_message.as_str())];
+        messages.extend(self.history.iter().cloned());
+        messages.push(ChatMessage::user(prompt));
+        messages
+    }
+
     /// Clear the agent's conversation history
     pub fn clear_history(&mut self) {
         self.history.clear();
     }
 
-    /// Get the agent's conversation history
-    pub fn history(&self) -> &[(String, String)] {
+    /// Get the agent's conversation history: its prompts, replies and tool results
+    pub fn history(&self) -> &[ChatMessage] {
         &self.history
     }
 
@@ -168,6 +267,12 @@ pub struct AgentManager {
     agents: HashMap<AgentType, Agent>,
 }
 
+impl Default for AgentManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AgentManager {
     /// Create a new agent manager
     pub fn new() -> Self {
@@ -193,6 +298,44 @@ impl AgentManager {
 
     /// Execute a task using the multi-agent system
     pub async fn execute_task(&mut self, objective: &str) -> Result<String> {
+        self.execute(objective).await
+    }
+
+    /// Execute a task against the page loaded in a browser
+    ///
+    /// The navigator is shown a [`PageSnapshot`](crate::browser::PageSnapshot)
+    /// of the current page alongside the plan, and the interactor acts on the
+    /// page through the [`browser_tools`].
+    #[cfg(feature = "browser")]
+    pub async fn execute_task_with_browser(
+        &mut self,
+        objective: &str,
+        browser: &dyn crate::browser::AsyncBrowser,
+    ) -> Result<String> {
+        let (url, html) = (browser.current_url().await?, browser.get_html().await?);
+        let page = crate::browser::PageSnapshot::from_html(url, &html).render(PAGE_TOKEN_BUDGET);
+        let navigation = self.navigate(objective, Some(page)).await?;
+        
+        let interactor = self.get_agent_mut(AgentType::Interactor)
+            .ok_or_else(|| Error::GenericError("Interactor agent not found".to_string()))?;
+        
+        let interaction_result = interactor.interact(&navigation, browser).await?;
+        self.verify(interaction_result).await
+    }
+
+    async fn execute(&mut self, objective: &str) -> Result<String> {
+        let navigation = self.navigate(objective, None).await?;
+        
+        // Use the interactor to execute the interactions
+        let interactor = self.get_agent_mut(AgentType::Interactor)
+            .ok_or_else(|| Error::GenericError("Interactor agent not found".to_string()))?;
+        
+        let interaction_result = interactor.run(&navigation).await?;
+        self.verify(interaction_result).await
+    }
+
+    /// Plan the objective and pick the elements to interact with
+    async fn navigate(&mut self, objective: &str, page: Option<String>) -> Result<String> {
         // Use the planner to break down the objective
         let planner = self.get_agent_mut(AgentType::Planner)
             .ok_or_else(|| Error::GenericError("Planner agent not found".to_string()))?;
@@ -203,14 +346,14 @@ impl AgentManager {
         let navigator = self.get_agent_mut(AgentType::Navigator)
             .ok_or_else(|| Error::GenericError("Navigator agent not found".to_string()))?;
         
-        let navigation = navigator.run(&plan).await?;
-        
-        // Use the interactor to execute the interactions
-        let interactor = self.get_agent_mut(AgentType::Interactor)
-            .ok_or_else(|| Error::GenericError("Interactor agent not found".to_string()))?;
-        
-        let interaction_result = interactor.run(&navigation).await?;
-        
+        match page {
+            Some(page) => navigator.run(&format!("{}\n\nCurrent page:\n{}", plan, page)).await,
+            None => navigator.run(&plan).await,
+        }
+    }
+
+    /// Verify the interactions, recovering when they failed
+    async fn verify(&mut self, interaction_result: String) -> Result<String> {
         // Use the verifier to confirm the outcome
         let verifier = self.get_agent_mut(AgentType::Verifier)
             .ok_or_else(|| Error::GenericError("Verifier agent not found".to_string()))?;
@@ -235,4 +378,92 @@ impl AgentManager {
             agent.clear_history();
         }
     }
-} 
\ No newline at end of file
+} 
+
+/// Browser actions offered to agents as tools: `navigate`, `click`, `type` and `extract`
+///
+/// Elements are named by a selector in the query syntax, e.g. `text("Log in")`,
+/// or by a CSS selector.
+#[cfg(feature = "browser")]
+pub fn browser_tools() -> Vec<ToolDefinition> {
+    let selector = serde_json::json!({
+        "type": "string",
+        "description": "The element: a CSS selector, or a query such as text(\"Log in\") or id(\"email\")",
+    });
+    vec![
+        ToolDefinition::new(
+            "navigate",
+            "Load a URL in the browser",
+            serde_json::json!({
+                "type": "object",
+                "properties": { "url": { "type": "string", "description": "The URL to load" } },
+                "required": ["url"],
+            }),
+        ),
+        ToolDefinition::new(
+            "click",
+            "Click an element",
+            serde_json::json!({
+                "type": "object",
+                "properties": { "selector": selector },
+                "required": ["selector"],
+            }),
+        ),
+        ToolDefinition::new(
+            "type",
+            "Replace the text of an input with the given text",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "selector": selector,
+                    "text": { "type": "string", "description": "The text to type" },
+                },
+                "required": ["selector", "text"],
+            }),
+        ),
+        ToolDefinition::new(
+            "extract",
+            "Read the text of an element",
+            serde_json::json!({
+                "type": "object",
+                "properties": { "selector": selector },
+                "required": ["selector"],
+            }),
+        ),
+    ]
+}
+
+/// Carry out a call to one of the [`browser_tools`], returning its result for the model
+#[cfg(feature = "browser")]
+pub async fn run_browser_tool(
+    browser: &dyn crate::browser::AsyncBrowser,
+    call: &crate::llms::ToolCall,
+) -> Result<String> {
+    let argument = |name: &str| {
+        call.arguments[name].as_str().ok_or_else(|| {
+            Error::ValidationError(format!("Tool {} needs a string argument '{}'", call.name, name))
+        })
+    };
+    let selector = || -> Result<crate::browser::Selector> {
+        let query = argument("selector")?;
+        Ok(crate::browser::Selector::parse(query).unwrap_or_else(|_| crate::browser::Selector::css(query)))
+    };
+
+    match call.name.as_str() {
+        "navigate" => {
+            let url = argument("url")?;
+            browser.navigate(url).await?;
+            Ok(format!("Loaded {}", url))
+        }
+        "click" => {
+            browser.click(&selector()?).await?;
+            Ok("Clicked".to_string())
+        }
+        "type" => {
+            browser.type_text(&selector()?, argument("text")?).await?;
+            Ok("Typed".to_string())
+        }
+        "extract" => browser.get_text(&selector()?).await,
+        name => Err(Error::ValidationError(format!("Unknown browser tool '{}'", name))),
+    }
+}
@@ -0,0 +1,508 @@
+//! Accessibility-tree snapshots
+//!
+//! An [`AccessibilityNode`] tree describes the page the way assistive
+//! technology sees it: roles, accessible names, values and states. Chromium
+//! reports its own tree over CDP; other backends compute one from the DOM
+//! using ARIA attributes, label association and implicit roles.
+
+use super::dom::{self, Dom};
+use super::Selector;
+use crate::error::{Error, Result};
+use scraper::{ElementRef, Node};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Elements whose content is not exposed to assistive technology
+const NOT_RENDERED: &[&str] = &["script", "style", "noscript", "template", "head", "svg", "canvas", "iframe"];
+
+/// ARIA roles that make an element interactive
+const INTERACTIVE_ROLES: &[&str] = &[
+    "button", "checkbox", "combobox", "link", "listbox", "menuitem", "menuitemcheckbox", "menuitemradio",
+    "option", "radio", "searchbox", "slider", "spinbutton", "switch", "tab", "textbox", "treeitem",
+];
+
+/// Roles whose accessible name may come from their content
+const NAME_FROM_CONTENT: &[&str] = &[
+    "button", "cell", "checkbox", "columnheader", "gridcell", "heading", "link", "menuitem", "menuitemcheckbox",
+    "menuitemradio", "option", "radio", "row", "rowheader", "switch", "tab", "tooltip", "treeitem",
+];
+
+/// Chromium roles that only wrap other content and are flattened away
+const CDP_FLATTENED: &[&str] = &["generic", "none", "presentation", "InlineTextBox", "LineBreak", "LabelText"];
+
+/// A state of an accessibility node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AccessibilityState {
+    /// Checkbox, radio or switch is checked
+    Checked,
+    /// Interaction is disabled
+    Disabled,
+    /// Collapsible content is expanded
+    Expanded,
+    /// Option or tab is selected
+    Selected,
+    /// Toggle button is pressed
+    Pressed,
+    /// Form field must be filled in
+    Required,
+    /// Form field cannot be edited
+    ReadOnly,
+    /// Element can receive focus
+    Focusable,
+    /// Element has focus
+    Focused,
+    /// Value fails validation
+    Invalid,
+}
+
+impl fmt::Display for AccessibilityState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AccessibilityState::Checked => "checked",
+            AccessibilityState::Disabled => "disabled",
+            AccessibilityState::Expanded => "expanded",
+            AccessibilityState::Selected => "selected",
+            AccessibilityState::Pressed => "pressed",
+            AccessibilityState::Required => "required",
+            AccessibilityState::ReadOnly => "readonly",
+            AccessibilityState::Focusable => "focusable",
+            AccessibilityState::Focused => "focused",
+            AccessibilityState::Invalid => "invalid",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A node of the accessibility tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilityNode {
+    /// ARIA role, or `text` for runs of text
+    pub role: String,
+    /// Accessible name
+    pub name: String,
+    /// Current value of form fields and ranges
+    pub value: Option<String>,
+    /// Heading level
+    pub level: Option<u32>,
+    /// States that apply to the node
+    pub states: Vec<AccessibilityState>,
+    /// Selector reaching the node's element, when computed from the DOM
+    pub selector: Option<Selector>,
+    /// Child nodes
+    pub children: Vec<AccessibilityNode>,
+}
+
+impl AccessibilityNode {
+    /// Create a node with a role and name
+    pub fn new(role: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            name: name.into(),
+            value: None,
+            level: None,
+            states: Vec::new(),
+            selector: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Compute the accessibility tree of an HTML document
+    pub fn from_html(html: &str) -> Self {
+        let dom = Dom::parse(html);
+        let title = dom
+            .select(&Selector::css("title"))
+            .ok()
+            .and_then(|ids| ids.first().map(|id| dom.text(*id)))
+            .unwrap_or_default();
+
+        let mut root = Self::new("document", title);
+        root.children = dom_children(&dom, dom.root());
+        root
+    }
+
+    /// Build a tree from the nodes of a CDP `Accessibility.getFullAXTree` result
+    pub(crate) fn from_cdp(nodes: &Value) -> Result<Self> {
+        let nodes = nodes
+            .as_array()
+            .ok_or_else(|| Error::BrowserError("Accessibility tree missing from response".to_string()))?;
+        let by_id: HashMap<&str, &Value> = nodes
+            .iter()
+            .filter_map(|node| node["nodeId"].as_str().map(|id| (id, node)))
+            .collect();
+        let root = nodes
+            .iter()
+            .find(|node| node.get("parentId").is_none())
+            .ok_or_else(|| Error::BrowserError("Accessibility tree has no root".to_string()))?;
+
+        let mut converted = cdp_nodes(root, &by_id);
+        match converted.len() {
+            1 if converted[0].role == "document" => Ok(converted.remove(0)),
+            _ => {
+                let mut document = Self::new("document", "");
+                document.children = converted;
+                Ok(document)
+            }
+        }
+    }
+
+    /// Whether the node has a state
+    pub fn has_state(&self, state: AccessibilityState) -> bool {
+        self.states.contains(&state)
+    }
+
+    /// The node and all its descendants, depth first
+    pub fn descendants(&self) -> Vec<&AccessibilityNode> {
+        let mut out = vec![self];
+        for child in &self.children {
+            out.extend(child.descendants());
+        }
+        out
+    }
+
+    /// The first node with the given role and name
+    ///
+    /// Names are compared ignoring case and surrounding whitespace.
+    pub fn find(&self, role: &str, name: &str) -> Option<&AccessibilityNode> {
+        let name = dom::normalize(name).to_lowercase();
+        self.descendants()
+            .into_iter()
+            .find(|node| node.role == role && dom::normalize(&node.name).to_lowercase() == name)
+    }
+
+    /// All nodes with the given role
+    pub fn find_all(&self, role: &str) -> Vec<&AccessibilityNode> {
+        self.descendants().into_iter().filter(|node| node.role == role).collect()
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{}{}", "  ".repeat(depth), self.role)?;
+        if !self.name.is_empty() {
+            write!(f, " \"{}\"", self.name)?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, " value=\"{}\"", value)?;
+        }
+        if let Some(level) = self.level {
+            write!(f, " level={}", level)?;
+        }
+        if !self.states.is_empty() {
+            let states: Vec<String> = self.states.iter().map(|state| state.to_string()).collect();
+            write!(f, " [{}]", states.join(", "))?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.write_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AccessibilityNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// Accessibility nodes for the children of an element
+fn dom_children(dom: &Dom, element: ElementRef<'_>) -> Vec<AccessibilityNode> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let flush = |text: &mut String, out: &mut Vec<AccessibilityNode>| {
+        let run = dom::normalize(&std::mem::take(text));
+        if !run.is_empty() {
+            out.push(AccessibilityNode::new("text", run));
+        }
+    };
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(t) => text.push_str(t),
+            Node::Element(_) => {
+                let Some(el) = ElementRef::wrap(child) else {
+                    continue;
+                };
+                if NOT_RENDERED.contains(&el.value().name()) || is_hidden(el) {
+                    continue;
+                }
+                flush(&mut text, &mut out);
+                match dom_node(dom, el) {
+                    Some(node) => out.push(node),
+                    None => out.extend(dom_children(dom, el)),
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut text, &mut out);
+    out
+}
+
+/// The accessibility node for an element, or `None` if it only groups its children
+fn dom_node(dom: &Dom, el: ElementRef<'_>) -> Option<AccessibilityNode> {
+    let role = role(el).filter(|role| role != "none")?;
+    let attr = |name: &str| el.value().attr(name);
+    let aria = |name: &str| attr(name) == Some("true");
+
+    let mut node = AccessibilityNode::new(
+        role.as_str(),
+        accessible_name(dom, el, NAME_FROM_CONTENT.contains(&role.as_str())),
+    );
+    node.value = field_value(el);
+    node.level = attr("aria-level")
+        .and_then(|level| level.parse().ok())
+        .or_else(|| heading_level(el.value().name()).map(|level| level as u32));
+    node.selector = Some(Selector::css(dom.css_path(el.id())));
+
+    let states = [
+        (AccessibilityState::Checked, attr("checked").is_some() || aria("aria-checked")),
+        (AccessibilityState::Disabled, attr("disabled").is_some() || aria("aria-disabled")),
+        (AccessibilityState::Expanded, aria("aria-expanded") || (el.value().name() == "details" && attr("open").is_some())),
+        (AccessibilityState::Selected, attr("selected").is_some() || aria("aria-selected")),
+        (AccessibilityState::Pressed, aria("aria-pressed")),
+        (AccessibilityState::Required, attr("required").is_some() || aria("aria-required")),
+        (AccessibilityState::ReadOnly, attr("readonly").is_some() || aria("aria-readonly")),
+        (AccessibilityState::Focusable, is_interactive(el)),
+        (AccessibilityState::Invalid, aria("aria-invalid")),
+    ];
+    node.states = states.into_iter().filter(|(_, set)| *set).map(|(state, _)| state).collect();
+
+    // Form fields expose their content as a value rather than as children
+    if !matches!(el.value().name(), "textarea" | "input") {
+        node.children = dom_children(dom, el);
+    }
+    Some(node)
+}
+
+/// Convert a CDP node, flattening ignored and generic nodes into their children
+fn cdp_nodes(node: &Value, by_id: &HashMap<&str, &Value>) -> Vec<AccessibilityNode> {
+    let children: Vec<AccessibilityNode> = node["childIds"]
+        .as_array()
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str().and_then(|id| by_id.get(id)))
+                .flat_map(|child| cdp_nodes(child, by_id))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let role = node["role"]["value"].as_str().unwrap_or("generic");
+    if node["ignored"].as_bool().unwrap_or(false) || CDP_FLATTENED.contains(&role) {
+        return if role == "InlineTextBox" { Vec::new() } else { children };
+    }
+
+    let role = match role {
+        "RootWebArea" | "WebArea" => "document",
+        "StaticText" => "text",
+        other => other,
+    };
+    let mut converted = AccessibilityNode::new(role, node["name"]["value"].as_str().unwrap_or_default());
+    converted.value = match &node["value"]["value"] {
+        Value::Null => None,
+        Value::String(value) if value.is_empty() => None,
+        Value::String(value) => Some(value.clone()),
+        other => Some(other.to_string()),
+    };
+
+    for property in node["properties"].as_array().into_iter().flatten() {
+        let value = &property["value"]["value"];
+        let set = value.as_bool().unwrap_or(false) || value.as_str() == Some("true");
+        let state = match property["name"].as_str().unwrap_or_default() {
+            "level" => {
+                converted.level = value.as_u64().map(|level| level as u32);
+                continue;
+            }
+            "checked" => AccessibilityState::Checked,
+            "disabled" => AccessibilityState::Disabled,
+            "expanded" => AccessibilityState::Expanded,
+            "selected" => AccessibilityState::Selected,
+            "pressed" => AccessibilityState::Pressed,
+            "required" => AccessibilityState::Required,
+            "readonly" => AccessibilityState::ReadOnly,
+            "focusable" => AccessibilityState::Focusable,
+            "focused" => AccessibilityState::Focused,
+            "invalid" => AccessibilityState::Invalid,
+            _ => continue,
+        };
+        if set {
+            converted.states.push(state);
+        }
+    }
+
+    converted.children = children;
+    vec![converted]
+}
+
+/// The level of an `h1`–`h6` tag
+pub(crate) fn heading_level(tag: &str) -> Option<usize> {
+    match tag.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some((level - b'0') as usize),
+        _ => None,
+    }
+}
+
+/// Whether an element is hidden by markup or inline style
+pub(crate) fn is_hidden(el: ElementRef<'_>) -> bool {
+    let value = el.value();
+    let style: String = value
+        .attr("style")
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || (value.name() == "input" && value.attr("type").is_some_and(|t| t.eq_ignore_ascii_case("hidden")))
+        || style.contains("display:none")
+        || style.contains("visibility:hidden")
+}
+
+/// Whether an element can be clicked, typed into or focused
+pub(crate) fn is_interactive(el: ElementRef<'_>) -> bool {
+    let value = el.value();
+    match value.name() {
+        "a" => value.attr("href").is_some(),
+        "button" | "input" | "select" | "textarea" | "summary" => true,
+        _ => {
+            value
+                .attr("role")
+                .is_some_and(|role| role.split_whitespace().any(|r| INTERACTIVE_ROLES.contains(&r)))
+                || value.attr("onclick").is_some()
+                || value.attr("contenteditable").is_some_and(|c| c.is_empty() || c == "true")
+                || value.attr("tabindex").is_some_and(|t| t.trim().parse::<i32>().is_ok_and(|t| t >= 0))
+        }
+    }
+}
+
+/// The explicit ARIA role, or the one implied by the tag
+pub(crate) fn role(el: ElementRef<'_>) -> Option<String> {
+    let value = el.value();
+    if let Some(role) = value.attr("role").and_then(|r| r.split_whitespace().next()) {
+        return Some(if role == "presentation" { "none" } else { role }.to_string());
+    }
+
+    let role = match value.name() {
+        "a" | "area" if value.attr("href").is_some() => "link",
+        "article" => "article",
+        "aside" => "complementary",
+        "blockquote" => "blockquote",
+        "button" | "summary" => "button",
+        "details" | "fieldset" => "group",
+        "dialog" => "dialog",
+        "figure" => "figure",
+        "footer" => "contentinfo",
+        "form" => "form",
+        "header" => "banner",
+        "hr" => "separator",
+        "img" if value.attr("alt") == Some("") => "none",
+        "img" => "image",
+        "input" => match input_type(el).as_str() {
+            "checkbox" => "checkbox",
+            "radio" => "radio",
+            "button" | "submit" | "reset" | "image" | "file" => "button",
+            "range" => "slider",
+            "number" => "spinbutton",
+            "search" => "searchbox",
+            _ => "textbox",
+        },
+        "li" => "listitem",
+        "main" => "main",
+        "menu" | "ol" | "ul" => "list",
+        "nav" => "navigation",
+        "option" => "option",
+        "p" => "paragraph",
+        "progress" => "progressbar",
+        "section" => "region",
+        "select" if value.attr("multiple").is_some() => "listbox",
+        "select" => "combobox",
+        "table" => "table",
+        "tbody" | "tfoot" | "thead" => "rowgroup",
+        "td" => "cell",
+        "textarea" => "textbox",
+        "th" => "columnheader",
+        "tr" => "row",
+        tag if heading_level(tag).is_some() => "heading",
+        _ if value.attr("contenteditable").is_some_and(|c| c.is_empty() || c == "true") => "textbox",
+        _ => return None,
+    };
+    Some(role.to_string())
+}
+
+/// The lowercase `type` of an input, defaulting to `text`
+pub(crate) fn input_type(el: ElementRef<'_>) -> String {
+    el.value().attr("type").unwrap_or("text").to_ascii_lowercase()
+}
+
+/// The accessible name of an element, approximated from its markup
+///
+/// Follows the order of the accessible name computation: `aria-labelledby`,
+/// `aria-label`, associated labels, then content (when `from_content`) and
+/// finally `alt`, `title` and `placeholder`.
+pub(crate) fn accessible_name(dom: &Dom, el: ElementRef<'_>, from_content: bool) -> String {
+    let value = el.value();
+    let tag = value.name();
+    let text_of = |id: &str| {
+        dom.select(&Selector::id(id))
+            .ok()
+            .and_then(|ids| ids.first().map(|node| dom.text(*node)))
+            .unwrap_or_default()
+    };
+
+    let mut candidates: Vec<String> = Vec::new();
+    if let Some(ids) = value.attr("aria-labelledby") {
+        candidates.push(ids.split_whitespace().map(text_of).collect::<Vec<_>>().join(" "));
+    }
+    candidates.extend(value.attr("aria-label").map(str::to_string));
+    if matches!(tag, "input" | "select" | "textarea") {
+        if let Some(id) = value.id() {
+            let labels = dom
+                .select(&Selector::css("label[for]"))
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|node| dom.attributes(*node).get("for").map(String::as_str) == Some(id));
+            candidates.extend(labels.map(|node| dom.text(node)));
+        }
+        candidates.extend(dom.closest(el.id(), "label").map(|node| dom.text(node)));
+    }
+    if tag == "input" && matches!(input_type(el).as_str(), "button" | "submit" | "reset") {
+        candidates.extend(value.attr("value").map(str::to_string));
+    }
+    if from_content && !matches!(tag, "select" | "textarea") {
+        candidates.push(dom::visible_text(el));
+        candidates.extend(
+            el.select(&scraper::Selector::parse("img[alt]").expect("valid selector"))
+                .filter_map(|img| img.value().attr("alt"))
+                .map(str::to_string),
+        );
+    }
+    for name in ["alt", "title", "placeholder"] {
+        candidates.extend(value.attr(name).map(str::to_string));
+    }
+
+    candidates
+        .into_iter()
+        .map(|candidate| dom::normalize(&candidate))
+        .find(|candidate| !candidate.is_empty())
+        .unwrap_or_default()
+}
+
+/// The current value of a form field, with passwords masked
+pub(crate) fn field_value(el: ElementRef<'_>) -> Option<String> {
+    let value = el.value();
+    match value.name() {
+        "input" => match input_type(el).as_str() {
+            "checkbox" | "radio" | "button" | "submit" | "reset" | "image" | "file" => None,
+            "password" => value.attr("value").filter(|v| !v.is_empty()).map(|_| "••••".to_string()),
+            _ => value.attr("value").map(str::to_string),
+        },
+        "textarea" => Some(dom::visible_text(el)),
+        "select" => ["option[selected]", "option"]
+            .iter()
+            .find_map(|css| el.select(&scraper::Selector::parse(css).expect("valid selector")).next())
+            .map(dom::visible_text),
+        _ => None,
+    }
+}
@@ -0,0 +1,181 @@
+//! Actionability checks run before element actions
+//!
+//! Before a session clicks or types, it waits until the target element is
+//! attached, visible, stable, enabled and not covered by another element.
+//! [`ActionOptions`] override the wait for a single call, or skip it.
+
+use super::dom::Dom;
+use super::snapshot::BoundingBox;
+use super::Selector;
+use crate::error::{Error, Result};
+use ego_tree::NodeId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Time between actionability probes
+const PROBE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A condition an element must meet before it is acted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ActionCheck {
+    /// An element matches the selector
+    Attached,
+    /// The element has a non-empty box and is not hidden by styles
+    Visible,
+    /// The element's box did not move or resize between two probes
+    Stable,
+    /// The element is not a disabled form control
+    Enabled,
+    /// No other element covers the element's centre
+    Unobscured,
+}
+
+impl fmt::Display for ActionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Attached => "attached",
+            Self::Visible => "visible",
+            Self::Stable => "stable",
+            Self::Enabled => "enabled",
+            Self::Unobscured => "unobscured",
+        })
+    }
+}
+
+/// What an actionability probe saw of an element
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementState {
+    /// Whether the element is visible
+    pub visible: bool,
+    /// Whether the element is enabled
+    pub enabled: bool,
+    /// The element covering the element's centre, e.g. `div#overlay.modal`
+    pub obscured_by: Option<String>,
+    /// Bounding box, when the backend can measure it
+    pub bounds: Option<BoundingBox>,
+}
+
+impl ElementState {
+    /// The state of an element of a parsed document, as far as markup tells
+    pub(crate) fn from_dom(dom: &Dom, id: NodeId) -> Self {
+        Self {
+            visible: dom.is_visible(id),
+            enabled: dom.is_enabled(id),
+            obscured_by: None,
+            bounds: None,
+        }
+    }
+
+    /// The first check this state fails, given the state seen by the previous probe
+    ///
+    /// An element is stable once two probes in a row measure the same box;
+    /// elements the backend cannot measure are always stable.
+    pub fn failing_check(&self, previous: Option<&ElementState>) -> Option<ActionCheck> {
+        if !self.visible {
+            return Some(ActionCheck::Visible);
+        }
+        if self.bounds.is_some() && previous.map(|p| &p.bounds) != Some(&self.bounds) {
+            return Some(ActionCheck::Stable);
+        }
+        if !self.enabled {
+            return Some(ActionCheck::Enabled);
+        }
+        if self.obscured_by.is_some() {
+            return Some(ActionCheck::Unobscured);
+        }
+        None
+    }
+}
+
+/// Per-call options for element actions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionOptions {
+    /// Maximum time to wait for the element to become actionable, instead of the session timeout
+    pub timeout: Option<Duration>,
+    /// Act at once, skipping the actionability checks
+    pub force: bool,
+}
+
+impl ActionOptions {
+    /// Options that wait for the checks with the session timeout
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait at most `timeout` for the element to become actionable
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Skip the actionability checks
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+}
+
+/// Probe an element until it passes every check, or time out naming the check that failed
+pub(crate) fn wait(
+    selector: &Selector,
+    timeout: Duration,
+    mut probe: impl FnMut() -> Result<Option<ElementState>>,
+) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut previous: Option<ElementState> = None;
+    loop {
+        let state = probe()?;
+        let failing = match &state {
+            None => Some(ActionCheck::Attached),
+            Some(state) => state.failing_check(previous.as_ref()),
+        };
+        let Some(check) = failing else {
+            return Ok(());
+        };
+        if Instant::now() >= deadline {
+            return Err(timeout_error(selector, timeout, check, state.as_ref()));
+        }
+        previous = state;
+        std::thread::sleep(PROBE_INTERVAL);
+    }
+}
+
+/// Check a page whose content cannot change while waiting, failing at once
+///
+/// Nothing is waited for, so failures are reported without a timeout: a missing
+/// element as not found, any other failing check as a browser error.
+pub(crate) fn check_once(html: &str, selector: &Selector) -> Result<()> {
+    let dom = Dom::parse(html);
+    let Some(state) = dom.select(selector)?.first().map(|id| ElementState::from_dom(&dom, *id)) else {
+        return Err(Error::ElementNotFound(format!("{:?}", selector)));
+    };
+    match state.failing_check(None) {
+        Some(check) => Err(Error::BrowserError(format!(
+            "Element {:?} is not {}{}",
+            selector,
+            check,
+            covered_by(check, Some(&state))
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn timeout_error(selector: &Selector, timeout: Duration, check: ActionCheck, state: Option<&ElementState>) -> Error {
+    Error::TimeoutError(format!(
+        "Timed out after {:?} waiting for element {:?} to be {}{}",
+        timeout,
+        selector,
+        check,
+        covered_by(check, state)
+    ))
+}
+
+/// What covers an element that failed the unobscured check, for error messages
+fn covered_by(check: ActionCheck, state: Option<&ElementState>) -> String {
+    match state.and_then(|state| state.obscured_by.as_deref()) {
+        Some(cover) if check == ActionCheck::Unobscured => format!(" (covered by {})", cover),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,264 @@
+//! Async browser interface
+//!
+//! [`AsyncBrowser`] is the async browser API that agent code is written
+//! against. The backends behind it are still synchronous, and
+//! [`AsyncBrowserSession`] is a compatibility shim rather than an async-native
+//! browser: it runs each operation of a [`BrowserSession`] on tokio's blocking
+//! pool, which keeps runtime workers free but holds a blocking thread until the
+//! backend answers.
+//!
+//! Waits are the exception. They poll with single checks and sleep on the
+//! tokio timer in between, so a pending wait holds no thread and can be
+//! cancelled by dropping it. A backend with an async transport would implement
+//! [`AsyncBrowser`] directly instead.
+
+use super::{AccessibilityNode, BrowserConfig, BrowserSession, BrowserType, Selector};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Interval between checks while waiting
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Async counterpart of the browser trait
+#[async_trait]
+pub trait AsyncBrowser: Send + Sync {
+    /// Get the browser type
+    fn browser_type(&self) -> BrowserType;
+
+    /// Navigate to a URL
+    async fn navigate(&self, url: &str) -> Result<()>;
+
+    /// Get the current URL
+    async fn current_url(&self) -> Result<String>;
+
+    /// Click on an element
+    async fn click(&self, selector: &Selector) -> Result<()>;
+
+    /// Type text into an element
+    async fn type_text(&self, selector: &Selector, text: &str) -> Result<()>;
+
+    /// Get text from an element
+    async fn get_text(&self, selector: &Selector) -> Result<String>;
+
+    /// Get attributes of an element
+    async fn get_attributes(&self, selector: &Selector) -> Result<HashMap<String, String>>;
+
+    /// Check if an element exists
+    async fn element_exists(&self, selector: &Selector) -> Result<bool>;
+
+    /// Wait for an element to be visible
+    ///
+    /// Dropping the returned future cancels the wait.
+    async fn wait_for_element(&self, selector: &Selector, timeout: Duration) -> Result<()>;
+
+    /// Wait for navigation to complete
+    ///
+    /// Dropping the returned future cancels the wait.
+    async fn wait_for_navigation(&self, timeout: Duration) -> Result<()>;
+
+    /// Take a screenshot
+    async fn take_screenshot(&self, path: &str) -> Result<()>;
+
+    /// Execute JavaScript
+    async fn execute_js(&self, script: &str) -> Result<serde_json::Value>;
+
+    /// Get page HTML
+    async fn get_html(&self) -> Result<String>;
+
+    /// Get the accessibility tree of the page
+    async fn accessibility_snapshot(&self) -> Result<AccessibilityNode>;
+
+    /// Close the browser
+    async fn close(&self) -> Result<()>;
+}
+
+/// A synchronous browser session usable from async code
+///
+/// Each operation runs on tokio's blocking pool; see the module docs. Clones
+/// share the same underlying session, and operations on it are serialized.
+#[derive(Clone)]
+pub struct AsyncBrowserSession {
+    /// The wrapped session
+    session: Arc<Mutex<BrowserSession>>,
+    /// The browser type
+    browser_type: BrowserType,
+    /// The configuration
+    config: BrowserConfig,
+    /// Resolver for semantic selectors, shared with the wrapped session
+    #[cfg(feature = "llm")]
+    resolver: Option<Arc<super::SemanticResolver>>,
+}
+
+impl std::fmt::Debug for AsyncBrowserSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncBrowserSession")
+            .field("browser_type", &self.browser_type)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl AsyncBrowserSession {
+    /// Wrap a browser session
+    pub fn new(session: BrowserSession) -> Self {
+        Self {
+            browser_type: session.browser.browser_type(),
+            config: session.config.clone(),
+            #[cfg(feature = "llm")]
+            resolver: session.resolver.clone(),
+            session: Arc::new(Mutex::new(session)),
+        }
+    }
+
+    /// The session configuration
+    pub fn config(&self) -> &BrowserConfig {
+        &self.config
+    }
+
+    /// Run a closure against the wrapped session on the blocking pool
+    ///
+    /// This is the escape hatch for operations without an async wrapper.
+    /// Semantic selectors passed to the session inside `f` are resolved
+    /// synchronously; prefer the async methods for those.
+    pub async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut BrowserSession) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let session = Arc::clone(&self.session);
+        tokio::task::spawn_blocking(move || {
+            let mut session = session
+                .lock()
+                .map_err(|_| Error::BrowserError("Browser session lock poisoned".to_string()))?;
+            f(&mut session)
+        })
+        .await
+        .map_err(|e| Error::BrowserError(format!("Browser task failed: {}", e)))?
+    }
+
+    /// Turn a semantic selector into a concrete one without blocking the runtime
+    pub async fn resolve(&self, selector: &Selector) -> Result<Selector> {
+        #[cfg(feature = "llm")]
+        if let (Selector::Semantic(description), Some(resolver)) = (selector, &self.resolver) {
+            let page = {
+                let (resolver, description) = (Arc::clone(resolver), description.clone());
+                self.run(move |session| resolver.prepare(session.browser.as_ref(), &description))
+                    .await?
+            };
+            return resolver.finish(page, description).await;
+        }
+        Ok(selector.clone())
+    }
+
+    /// Poll a single-shot check until it passes or `timeout` elapses
+    ///
+    /// The check reports "not yet" with a timeout error. Between checks the
+    /// task sleeps on the tokio timer, so cancelling only ever abandons a
+    /// single in-flight check.
+    async fn poll<F>(&self, timeout: Duration, what: String, check: F) -> Result<()>
+    where
+        F: Fn(&mut BrowserSession) -> Result<()> + Clone + Send + 'static,
+    {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match self.run(check.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(Error::TimeoutError(_)) => {}
+                Err(e) => return Err(e),
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(Error::TimeoutError(format!("Timed out after {:?} waiting for {}", timeout, what)));
+            }
+            tokio::time::sleep(POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+}
+
+impl From<BrowserSession> for AsyncBrowserSession {
+    fn from(session: BrowserSession) -> Self {
+        Self::new(session)
+    }
+}
+
+#[async_trait]
+impl AsyncBrowser for AsyncBrowserSession {
+    fn browser_type(&self) -> BrowserType {
+        self.browser_type
+    }
+
+    async fn navigate(&self, url: &str) -> Result<()> {
+        let url = url.to_string();
+        self.run(move |session| session.navigate(&url)).await
+    }
+
+    async fn current_url(&self) -> Result<String> {
+        self.run(|session| session.current_url()).await
+    }
+
+    async fn click(&self, selector: &Selector) -> Result<()> {
+        let selector = self.resolve(selector).await?;
+        self.run(move |session| session.click(&selector)).await
+    }
+
+    async fn type_text(&self, selector: &Selector, text: &str) -> Result<()> {
+        let (selector, text) = (self.resolve(selector).await?, text.to_string());
+        self.run(move |session| session.type_text(&selector, &text)).await
+    }
+
+    async fn get_text(&self, selector: &Selector) -> Result<String> {
+        let selector = self.resolve(selector).await?;
+        self.run(move |session| session.get_text(&selector)).await
+    }
+
+    async fn get_attributes(&self, selector: &Selector) -> Result<HashMap<String, String>> {
+        let selector = self.resolve(selector).await?;
+        self.run(move |session| session.get_attributes(&selector)).await
+    }
+
+    async fn element_exists(&self, selector: &Selector) -> Result<bool> {
+        let selector = self.resolve(selector).await?;
+        self.run(move |session| session.element_exists(&selector)).await
+    }
+
+    async fn wait_for_element(&self, selector: &Selector, timeout: Duration) -> Result<()> {
+        let selector = self.resolve(selector).await?;
+        let what = format!("element {:?}", selector);
+        // A zero timeout makes every backend check exactly once
+        self.poll(timeout, what, move |session| session.browser.wait_for_element(&selector, Duration::ZERO))
+            .await
+    }
+
+    async fn wait_for_navigation(&self, timeout: Duration) -> Result<()> {
+        self.poll(timeout, "navigation".to_string(), |session| {
+            session.browser.wait_for_navigation(Duration::ZERO)
+        })
+        .await
+    }
+
+    async fn take_screenshot(&self, path: &str) -> Result<()> {
+        let path = path.to_string();
+        self.run(move |session| session.take_screenshot(&path)).await
+    }
+
+    async fn execute_js(&self, script: &str) -> Result<serde_json::Value> {
+        let script = script.to_string();
+        self.run(move |session| session.execute_js(&script)).await
+    }
+
+    async fn get_html(&self) -> Result<String> {
+        self.run(|session| session.get_html()).await
+    }
+
+    async fn accessibility_snapshot(&self) -> Result<AccessibilityNode> {
+        self.run(|session| session.accessibility_snapshot()).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.run(|session| session.close()).await
+    }
+}
@@ -0,0 +1,1669 @@
+//! Chrome DevTools Protocol backend
+//!
+//! [`CdpBrowser`] drives a Chromium-based browser over the DevTools websocket.
+//! It can either launch a local Chromium with `--remote-debugging-port` or
+//! attach to an already running browser's websocket endpoint.
+
+use super::{
+    js, mime_type_for, route_request, AccessibilityNode, Browser, BrowserConfig, BrowserType, Cookie, Dialog,
+    DialogKind, DialogPolicy, Download, ElementState, HandledDialog, HarEntry, HarOptions, HarRequest, HarResponse,
+    HarTimings, InterceptedRequest, KeyChord, MouseButton, OriginStorage, PageEvent, PageId, PageInfo, ResourceType,
+    Route, RouteAction, Selector, StorageState,
+};
+use super::download::unique_path;
+use super::input::{key_definition, KeyDefinition};
+use crate::error::{Error, Result};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+/// Executables tried, in order, when no explicit Chromium path is configured
+const CHROME_CANDIDATES: &[&str] = &[
+    "google-chrome",
+    "google-chrome-stable",
+    "chromium",
+    "chromium-browser",
+    "chrome",
+];
+
+/// Environment variable overriding the Chromium executable
+pub const CHROME_PATH_ENV: &str = "LLAMACLICK_CHROME_PATH";
+
+/// Maximum number of unconsumed protocol events kept in memory
+const MAX_BUFFERED_EVENTS: usize = 1024;
+
+/// Number of pointer moves between the source and target of a drag
+const DRAG_STEPS: u32 = 5;
+
+/// Interval between polls while waiting for page state
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// URL patterns blocked when `block_images` is enabled
+const IMAGE_PATTERNS: &[&str] = &[
+    "*.png", "*.jpg", "*.jpeg", "*.gif", "*.webp", "*.svg", "*.ico", "*.bmp", "*.avif",
+];
+
+/// A protocol event received from the browser
+#[derive(Debug, Clone)]
+pub struct CdpEvent {
+    /// The event method, e.g. `Page.loadEventFired`
+    pub method: String,
+    /// The event parameters
+    pub params: Value,
+    /// The session the event belongs to, if any
+    pub session_id: Option<String>,
+}
+
+/// A websocket connection speaking the DevTools protocol
+pub struct CdpConnection {
+    /// The websocket
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    /// The next command id
+    next_id: u64,
+    /// Events received while waiting for command responses
+    events: VecDeque<CdpEvent>,
+    /// Routes answering paused requests, when interception is on
+    routes: Vec<Route>,
+    /// `Network` events kept out of the bounded buffer, while recording
+    network_events: Option<Vec<CdpEvent>>,
+    /// How dialogs are answered
+    dialog_policy: DialogPolicy,
+    /// Dialogs answered but not yet taken
+    dialogs: Vec<HandledDialog>,
+    /// Downloads in progress, as URL and suggested file name by guid
+    downloads: HashMap<String, (String, String)>,
+    /// Downloads that completed or were canceled, oldest first
+    finished_downloads: VecDeque<FinishedDownload>,
+}
+
+/// A download that is no longer in progress
+struct FinishedDownload {
+    /// The download guid, also the name of the file in the downloads directory
+    guid: String,
+    /// The URL the file came from
+    url: String,
+    /// The file name the site suggested
+    suggested_filename: String,
+    /// Whether it completed rather than being canceled
+    completed: bool,
+}
+
+impl CdpConnection {
+    /// Open a connection to a DevTools websocket URL
+    pub fn open(ws_url: &str, timeout: Duration) -> Result<Self> {
+        let (socket, _) = tungstenite::connect(ws_url)
+            .map_err(|e| Error::NetworkError(format!("Failed to connect to {}: {}", ws_url, e)))?;
+
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+        }
+
+        Ok(Self {
+            socket,
+            next_id: 1,
+            events: VecDeque::new(),
+            routes: Vec::new(),
+            network_events: None,
+            dialog_policy: DialogPolicy::default(),
+            dialogs: Vec::new(),
+            downloads: HashMap::new(),
+            finished_downloads: VecDeque::new(),
+        })
+    }
+
+    /// Send a command and wait for its result
+    ///
+    /// Events received while waiting are buffered and can be retrieved with
+    /// [`CdpConnection::drain_events`].
+    pub fn call(&mut self, method: &str, params: Value, session_id: Option<&str>) -> Result<Value> {
+        let id = self.send(method, params, session_id)?;
+        loop {
+            let message = self.socket.read().map_err(|e| socket_error(method, e))?;
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(_) => {
+                    return Err(Error::BrowserError(format!(
+                        "DevTools connection closed while waiting for {}",
+                        method
+                    )))
+                }
+                _ => continue,
+            };
+
+            let payload: Value = serde_json::from_str(text.as_str())?;
+            match payload["id"].as_u64() {
+                Some(response_id) if response_id == id => {
+                    if let Some(error) = payload.get("error") {
+                        return Err(Error::BrowserError(format!(
+                            "{} failed: {} ({})",
+                            method,
+                            error["message"].as_str().unwrap_or("unknown error"),
+                            error["code"]
+                        )));
+                    }
+                    return Ok(payload["result"].clone());
+                }
+                Some(_) => continue,
+                None if payload["method"] == "Fetch.requestPaused" && !self.routes.is_empty() => {
+                    self.continue_paused(&payload)?
+                }
+                // The page is blocked until the dialog is answered, which may be what this call waits for
+                None if payload["method"] == "Page.javascriptDialogOpening" => self.answer_dialog(&payload)?,
+                None if payload["method"].as_str().is_some_and(|method| method.starts_with("Browser.download")) => {
+                    self.track_download(&payload)
+                }
+                None if self.network_events.is_some()
+                    && payload["method"].as_str().is_some_and(|method| method.starts_with("Network.")) =>
+                {
+                    let event = event_from_payload(&payload);
+                    self.network_events.get_or_insert_with(Vec::new).extend(event);
+                }
+                None => self.push_event(payload),
+            }
+        }
+    }
+
+    /// Answer `Fetch.requestPaused` events with these routes
+    ///
+    /// Paused requests are handled while waiting for command responses, so
+    /// requests made by the page are held until the next command is sent.
+    pub fn set_routes(&mut self, routes: Vec<Route>) {
+        self.routes = routes;
+    }
+
+    /// Keep every `Network` event for [`CdpConnection::take_network_events`]
+    ///
+    /// While on, these events bypass the bounded event buffer so none are
+    /// dropped during long recordings.
+    pub fn record_network(&mut self, on: bool) {
+        self.network_events = on.then(Vec::new);
+    }
+
+    /// Take the `Network` events kept while recording
+    pub fn take_network_events(&mut self) -> Vec<CdpEvent> {
+        self.network_events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Answer dialogs with this policy from now on
+    pub fn set_dialog_policy(&mut self, policy: DialogPolicy) {
+        self.dialog_policy = policy;
+    }
+
+    /// Take the dialogs answered so far
+    pub fn take_dialogs(&mut self) -> Vec<HandledDialog> {
+        std::mem::take(&mut self.dialogs)
+    }
+
+    /// Send a command without waiting for its result, returning its id
+    fn send(&mut self, method: &str, params: Value, session_id: Option<&str>) -> Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut message = json!({
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        if let Some(session_id) = session_id {
+            message["sessionId"] = json!(session_id);
+        }
+
+        self.socket
+            .send(Message::text(message.to_string()))
+            .map_err(|e| socket_error(method, e))?;
+        Ok(id)
+    }
+
+    /// Release a paused request as the routes decide
+    fn continue_paused(&mut self, payload: &Value) -> Result<()> {
+        let params = &payload["params"];
+        let request_id = params["requestId"].clone();
+        let headers = headers_from_cdp(&params["request"]["headers"]);
+        let request = InterceptedRequest {
+            url: params["request"]["url"].as_str().unwrap_or_default().to_string(),
+            method: params["request"]["method"].as_str().unwrap_or("GET").to_string(),
+            resource_type: ResourceType::from_cdp(params["resourceType"].as_str().unwrap_or_default()),
+            headers,
+        };
+        let header_entries = |headers: &[(String, String)]| {
+            headers
+                .iter()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect::<Vec<_>>()
+        };
+
+        let (method, command) = match route_request(&self.routes, &request) {
+            Some(RouteAction::Fulfill(response)) => (
+                "Fetch.fulfillRequest",
+                json!({
+                    "requestId": request_id,
+                    "responseCode": response.status,
+                    "responseHeaders": header_entries(&response.headers),
+                    "body": base64::engine::general_purpose::STANDARD.encode(&response.body),
+                }),
+            ),
+            Some(RouteAction::Abort) => (
+                "Fetch.failRequest",
+                json!({ "requestId": request_id, "errorReason": "BlockedByClient" }),
+            ),
+            Some(action @ RouteAction::Continue { headers }) if !headers.is_empty() => (
+                "Fetch.continueRequest",
+                json!({ "requestId": request_id, "headers": header_entries(&action.merge_headers(&request.headers)) }),
+            ),
+            _ => ("Fetch.continueRequest", json!({ "requestId": request_id })),
+        };
+        // The reply is skipped like any other unawaited response
+        self.send(method, command, payload["sessionId"].as_str())?;
+        Ok(())
+    }
+
+    /// Answer a `Page.javascriptDialogOpening` event as the dialog policy decides
+    fn answer_dialog(&mut self, payload: &Value) -> Result<()> {
+        let params = &payload["params"];
+        let dialog = Dialog {
+            kind: DialogKind::from_cdp(params["type"].as_str().unwrap_or_default()),
+            message: params["message"].as_str().unwrap_or_default().to_string(),
+            default_value: params["defaultPrompt"].as_str().map(str::to_string),
+            url: params["url"].as_str().unwrap_or_default().to_string(),
+        };
+        let action = self.dialog_policy.answer(&dialog);
+
+        let mut command = json!({ "accept": action.accepts() });
+        if let Some(text) = action.prompt_text() {
+            command["promptText"] = json!(text);
+        }
+        // The reply is skipped like any other unawaited response
+        self.send("Page.handleJavaScriptDialog", command, payload["sessionId"].as_str())?;
+        self.dialogs.push(HandledDialog { dialog, action });
+        Ok(())
+    }
+
+    /// Follow `Browser.downloadWillBegin` and `Browser.downloadProgress` events
+    fn track_download(&mut self, payload: &Value) {
+        let params = &payload["params"];
+        let guid = params["guid"].as_str().unwrap_or_default().to_string();
+        match payload["method"].as_str() {
+            Some("Browser.downloadWillBegin") => {
+                let url = params["url"].as_str().unwrap_or_default().to_string();
+                let name = params["suggestedFilename"].as_str().unwrap_or_default().to_string();
+                self.downloads.insert(guid, (url, name));
+            }
+            Some("Browser.downloadProgress") if params["state"] != "inProgress" => {
+                if let Some((url, suggested_filename)) = self.downloads.remove(&guid) {
+                    self.finished_downloads.push_back(FinishedDownload {
+                        guid,
+                        url,
+                        suggested_filename,
+                        completed: params["state"] == "completed",
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Forget routes, recording, dialogs, downloads and buffered events
+    fn reset(&mut self) {
+        self.routes.clear();
+        self.network_events = None;
+        self.dialog_policy = DialogPolicy::default();
+        self.dialogs.clear();
+        self.downloads.clear();
+        self.finished_downloads.clear();
+        self.events.clear();
+    }
+
+    /// Take all buffered events
+    pub fn drain_events(&mut self) -> Vec<CdpEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Close the websocket
+    pub fn close(&mut self) -> Result<()> {
+        // The peer may already have gone away (e.g. after `Browser.close`)
+        let _ = self.socket.close(None);
+        Ok(())
+    }
+
+    fn push_event(&mut self, payload: Value) {
+        let Some(event) = event_from_payload(&payload) else {
+            return;
+        };
+
+        if self.events.len() >= MAX_BUFFERED_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Turn the exception of a `Runtime.evaluate` result, if any, into an error
+fn check_exception(result: &Value, selector: Option<&Selector>) -> Result<()> {
+    match result.get("exceptionDetails") {
+        Some(details) => {
+            let message = details["exception"]["description"]
+                .as_str()
+                .or_else(|| details["text"].as_str())
+                .unwrap_or("unknown exception");
+            Err(js::exception_to_error(selector, message))
+        }
+        None => Ok(()),
+    }
+}
+
+fn event_from_payload(payload: &Value) -> Option<CdpEvent> {
+    Some(CdpEvent {
+        method: payload["method"].as_str()?.to_string(),
+        params: payload["params"].clone(),
+        session_id: payload["sessionId"].as_str().map(str::to_string),
+    })
+}
+
+/// Turn a protocol `Headers` object into name/value pairs
+fn headers_from_cdp(headers: &Value) -> Vec<(String, String)> {
+    headers
+        .as_object()
+        .map(|headers| {
+            headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.as_str().unwrap_or_default().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn socket_error(method: &str, err: tungstenite::Error) -> Error {
+    match err {
+        tungstenite::Error::Io(e)
+            if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) =>
+        {
+            Error::TimeoutError(format!("Timed out waiting for {}", method))
+        }
+        e => Error::NetworkError(format!("DevTools connection error during {}: {}", method, e)),
+    }
+}
+
+/// A page target known to the browser
+struct CdpTarget {
+    /// The target id, also used as the page id
+    id: String,
+    /// The flattened session, once attached
+    session_id: Option<String>,
+}
+
+/// A request seen while recording, not yet finished
+struct PendingExchange {
+    /// The session the request belongs to
+    session_id: Option<String>,
+    /// Wall-clock start of the request
+    started: SystemTime,
+    /// Protocol timestamp when the request was sent, in seconds
+    sent_at: f64,
+    /// The request
+    request: HarRequest,
+    /// The response, once received
+    response: Option<ReceivedResponse>,
+}
+
+/// Status and headers of a response seen while recording
+struct ReceivedResponse {
+    /// The status code
+    status: u16,
+    /// The response headers
+    headers: Vec<(String, String)>,
+    /// Protocol timestamp when the response arrived, in seconds
+    received_at: f64,
+}
+
+/// Chromium browser driven over the DevTools protocol
+pub struct CdpBrowser {
+    /// The DevTools connection
+    connection: Mutex<CdpConnection>,
+    /// The configuration
+    config: BrowserConfig,
+    /// Known page targets, in the order they were seen
+    targets: Vec<CdpTarget>,
+    /// Index of the target being controlled
+    active: usize,
+    /// The dedicated browser context, when one was created
+    context_id: Option<String>,
+    /// Page events found while syncing targets, not yet taken
+    page_events: Vec<PageEvent>,
+    /// HAR recording options, while recording
+    har: Option<HarOptions>,
+    /// Recorded requests still waiting for their response to finish, by request id
+    har_pending: HashMap<String, PendingExchange>,
+    /// The browser process, when launched by us
+    process: Option<Child>,
+    /// The temporary profile directory, when launched by us
+    user_data_dir: Option<PathBuf>,
+    /// Whether the browser has been closed
+    closed: bool,
+}
+
+impl CdpBrowser {
+    /// Launch a local Chromium and connect to it
+    ///
+    /// The executable is taken from `LLAMACLICK_CHROME_PATH` when set, and
+    /// otherwise looked up on the `PATH` among the usual Chromium names.
+    pub fn launch(config: BrowserConfig) -> Result<Self> {
+        if let Ok(path) = std::env::var(CHROME_PATH_ENV) {
+            return Self::launch_with(path, config);
+        }
+
+        for candidate in CHROME_CANDIDATES {
+            match Self::launch_with(candidate, config.clone()) {
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                result => return result,
+            }
+        }
+
+        Err(Error::BrowserError(format!(
+            "No Chromium executable found; set {} to the browser binary",
+            CHROME_PATH_ENV
+        )))
+    }
+
+    /// Launch the given Chromium executable and connect to it
+    pub fn launch_with(executable: impl Into<PathBuf>, config: BrowserConfig) -> Result<Self> {
+        let executable = executable.into();
+        let user_data_dir = std::env::temp_dir().join(format!(
+            "llamaclick-chrome-{}-{}",
+            std::process::id(),
+            crate::utils::timestamp_millis()
+        ));
+        crate::utils::ensure_dir(&user_data_dir)?;
+
+        let mut child = Command::new(&executable)
+            .args(launch_args(&config, &user_data_dir))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let ws_url = match wait_for_devtools_url(&mut child, config.timeout) {
+            Ok(url) => url,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = std::fs::remove_dir_all(&user_data_dir);
+                return Err(e);
+            }
+        };
+        log::debug!("{} listening on {}", executable.display(), ws_url);
+
+        let connection = CdpConnection::open(&ws_url, config.timeout)?;
+        let mut browser = Self::attach(connection, config, false)?;
+        browser.process = Some(child);
+        browser.user_data_dir = Some(user_data_dir);
+        Ok(browser)
+    }
+
+    /// Connect to a running browser's DevTools websocket endpoint
+    ///
+    /// `ws_url` is the browser-level endpoint reported by
+    /// `/json/version` (`webSocketDebuggerUrl`). The proxy setting, which can
+    /// only be applied at launch, is honoured by creating a dedicated browser
+    /// context.
+    pub fn connect(ws_url: &str, config: BrowserConfig) -> Result<Self> {
+        let connection = CdpConnection::open(ws_url, config.timeout)?;
+        Self::attach(connection, config, true)
+    }
+
+    /// Attach to a page target and apply the configuration
+    fn attach(mut connection: CdpConnection, config: BrowserConfig, proxy_via_context: bool) -> Result<Self> {
+        let proxy = config.proxy.as_ref().filter(|_| proxy_via_context);
+        let mut context_id = None;
+        let mut targets = Vec::new();
+        let target_id = match proxy {
+            Some(proxy) => {
+                let context = connection.call(
+                    "Target.createBrowserContext",
+                    json!({ "proxyServer": proxy }),
+                    None,
+                )?;
+                let target = connection.call(
+                    "Target.createTarget",
+                    json!({ "url": "about:blank", "browserContextId": context["browserContextId"] }),
+                    None,
+                )?;
+                context_id = Some(string_field(&context, "browserContextId")?);
+                string_field(&target, "targetId")?
+            }
+            None => {
+                // Pages that are already open are known, not reported as opened
+                let infos = connection.call("Target.getTargets", json!({}), None)?;
+                targets = infos["targetInfos"]
+                    .as_array()
+                    .map(|infos| {
+                        infos
+                            .iter()
+                            .filter(|info| info["type"] == "page")
+                            .filter_map(|info| info["targetId"].as_str())
+                            .map(|id| CdpTarget {
+                                id: id.to_string(),
+                                session_id: None,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                match targets.first() {
+                    Some(target) => target.id.clone(),
+                    None => {
+                        let target = connection.call("Target.createTarget", json!({ "url": "about:blank" }), None)?;
+                        string_field(&target, "targetId")?
+                    }
+                }
+            }
+        };
+
+        let attached = connection.call(
+            "Target.attachToTarget",
+            json!({ "targetId": target_id, "flatten": true }),
+            None,
+        )?;
+        let session_id = string_field(&attached, "sessionId")?;
+
+        let active = match targets.iter().position(|target| target.id == target_id) {
+            Some(index) => index,
+            None => {
+                targets.push(CdpTarget {
+                    id: target_id,
+                    session_id: None,
+                });
+                targets.len() - 1
+            }
+        };
+        targets[active].session_id = Some(session_id);
+
+        let browser = Self {
+            connection: Mutex::new(connection),
+            config,
+            targets,
+            active,
+            context_id,
+            page_events: Vec::new(),
+            har: None,
+            har_pending: HashMap::new(),
+            process: None,
+            user_data_dir: None,
+            closed: false,
+        };
+        browser.apply_config()?;
+        browser.enable_downloads();
+        Ok(browser)
+    }
+
+    /// Let pages download into the downloads directory, reporting progress as events
+    ///
+    /// Files are saved under their guid and renamed once waited for. Browsers
+    /// that refuse are still usable, just without downloads.
+    fn enable_downloads(&self) {
+        let mut params = json!({
+            "behavior": "allowAndName",
+            "downloadPath": self.config.download_dir(),
+            "eventsEnabled": true,
+        });
+        if let Some(context_id) = &self.context_id {
+            params["browserContextId"] = json!(context_id);
+        }
+        let enabled = self
+            .connection()
+            .and_then(|mut connection| connection.call("Browser.setDownloadBehavior", params, None));
+        if let Err(e) = enabled {
+            log::warn!("Downloads are unavailable: {}", e);
+        }
+    }
+
+    /// Apply the configuration to the attached page
+    fn apply_config(&self) -> Result<()> {
+        self.call("Page.enable", json!({}))?;
+        self.call("Runtime.enable", json!({}))?;
+        let emulation = &self.config.emulation;
+        self.call(
+            "Emulation.setDeviceMetricsOverride",
+            json!({
+                "width": self.config.window_width,
+                "height": self.config.window_height,
+                "deviceScaleFactor": emulation.device_scale_factor.unwrap_or(0.0),
+                "mobile": emulation.mobile,
+            }),
+        )?;
+        if emulation.touch {
+            self.call("Emulation.setTouchEmulationEnabled", json!({ "enabled": true, "maxTouchPoints": 5 }))?;
+        }
+
+        // The override is also what sets Accept-Language, so a locale needs it even without a user agent
+        if self.config.user_agent.is_some() || emulation.locale.is_some() {
+            let user_agent = match &self.config.user_agent {
+                Some(user_agent) => user_agent.clone(),
+                None => {
+                    let version = self.connection()?.call("Browser.getVersion", json!({}), None)?;
+                    string_field(&version, "userAgent")?
+                }
+            };
+            let mut params = json!({ "userAgent": user_agent });
+            if let Some(locale) = &emulation.locale {
+                params["acceptLanguage"] = json!(locale);
+            }
+            self.call("Network.setUserAgentOverride", params)?;
+        }
+        if let Some(locale) = &emulation.locale {
+            self.call("Emulation.setLocaleOverride", json!({ "locale": locale }))?;
+        }
+        if let Some(timezone) = &emulation.timezone {
+            self.call("Emulation.setTimezoneOverride", json!({ "timezoneId": timezone }))?;
+        }
+        if let Some(geolocation) = &emulation.geolocation {
+            let mut grant = json!({ "permissions": ["geolocation"] });
+            if let Some(context_id) = &self.context_id {
+                grant["browserContextId"] = json!(context_id);
+            }
+            self.connection()?.call("Browser.grantPermissions", grant, None)?;
+            self.call(
+                "Emulation.setGeolocationOverride",
+                json!({
+                    "latitude": geolocation.latitude,
+                    "longitude": geolocation.longitude,
+                    "accuracy": geolocation.accuracy,
+                }),
+            )?;
+        }
+        if let Some(color_scheme) = emulation.color_scheme {
+            self.call(
+                "Emulation.setEmulatedMedia",
+                json!({ "features": [{ "name": "prefers-color-scheme", "value": color_scheme.to_string() }] }),
+            )?;
+        }
+
+        if self.config.ignore_https_errors {
+            self.call("Security.setIgnoreCertificateErrors", json!({ "ignore": true }))?;
+        }
+
+        if !self.connection()?.routes.is_empty() {
+            self.call("Fetch.enable", json!({ "patterns": [{ "urlPattern": "*" }] }))?;
+        }
+
+        if self.config.block_images || self.har.is_some() || emulation.offline {
+            self.call("Network.enable", json!({}))?;
+        }
+        if emulation.offline {
+            self.call(
+                "Network.emulateNetworkConditions",
+                json!({ "offline": true, "latency": 0, "downloadThroughput": -1, "uploadThroughput": -1 }),
+            )?;
+        }
+        if self.config.block_images {
+            self.call("Network.setBlockedURLs", json!({ "urls": IMAGE_PATTERNS }))?;
+        }
+
+        Ok(())
+    }
+
+    /// Track a `Network` event, completing an entry when a request finishes
+    fn record_network_event(&mut self, event: CdpEvent, entries: &mut Vec<HarEntry>) -> Result<()> {
+        let Some(options) = self.har else {
+            return Ok(());
+        };
+        let params = &event.params;
+        let request_id = params["requestId"].as_str().unwrap_or_default().to_string();
+        let timestamp = params["timestamp"].as_f64().unwrap_or_default();
+
+        match event.method.as_str() {
+            "Network.requestWillBeSent" => {
+                // A redirect reuses the request id and finishes the previous hop
+                if let Some(redirect) = params.get("redirectResponse") {
+                    if let Some(mut pending) = self.har_pending.remove(&request_id) {
+                        let received = response_from_cdp(redirect, timestamp);
+                        let response = HarResponse::new(received.status, &received.headers, &[], false);
+                        pending.response = Some(received);
+                        entries.push(finish_exchange(pending, response, timestamp));
+                    }
+                }
+
+                let request = &params["request"];
+                let headers = headers_from_cdp(&request["headers"]);
+                let method = request["method"].as_str().unwrap_or("GET");
+                let url = request["url"].as_str().unwrap_or_default();
+                let mut recorded = HarRequest::new(method, url, &headers);
+                if let Some(body) = request["postData"].as_str() {
+                    let mime_type = headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                        .map_or("", |(_, value)| value.as_str());
+                    recorded = recorded.with_body(mime_type, body);
+                }
+                let started = params["wallTime"]
+                    .as_f64()
+                    .map_or_else(SystemTime::now, |secs| UNIX_EPOCH + Duration::from_secs_f64(secs));
+                self.har_pending.insert(
+                    request_id,
+                    PendingExchange {
+                        session_id: event.session_id,
+                        started,
+                        sent_at: timestamp,
+                        request: recorded,
+                        response: None,
+                    },
+                );
+            }
+            "Network.responseReceived" => {
+                if let Some(pending) = self.har_pending.get_mut(&request_id) {
+                    pending.response = Some(response_from_cdp(&params["response"], timestamp));
+                }
+            }
+            "Network.loadingFinished" => {
+                let Some(pending) = self.har_pending.remove(&request_id) else {
+                    return Ok(());
+                };
+                let body = if options.include_bodies {
+                    self.response_body(&request_id, pending.session_id.as_deref())
+                } else {
+                    Vec::new()
+                };
+                let (status, headers) = pending
+                    .response
+                    .as_ref()
+                    .map_or((0, &[][..]), |received| (received.status, received.headers.as_slice()));
+                let mut response = HarResponse::new(status, headers, &body, options.include_bodies);
+                if !options.include_bodies {
+                    response.content.size = params["encodedDataLength"].as_i64().unwrap_or(-1);
+                    response.body_size = response.content.size;
+                }
+                entries.push(finish_exchange(pending, response, timestamp));
+            }
+            "Network.loadingFailed" => {
+                if let Some(pending) = self.har_pending.remove(&request_id) {
+                    let error = params["errorText"].as_str().unwrap_or("Request failed");
+                    entries.push(finish_exchange(pending, HarResponse::failed(error), timestamp));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Fetch a finished response's body, empty when the browser no longer has it
+    fn response_body(&self, request_id: &str, session_id: Option<&str>) -> Vec<u8> {
+        let result = self.connection().and_then(|mut connection| {
+            connection.call("Network.getResponseBody", json!({ "requestId": request_id }), session_id)
+        });
+        let body = match result {
+            Ok(body) => body,
+            Err(e) => {
+                log::debug!("No body recorded for request {}: {}", request_id, e);
+                return Vec::new();
+            }
+        };
+
+        let text = body["body"].as_str().unwrap_or_default();
+        if body["base64Encoded"].as_bool().unwrap_or(false) {
+            base64::engine::general_purpose::STANDARD.decode(text).unwrap_or_default()
+        } else {
+            text.as_bytes().to_vec()
+        }
+    }
+
+    /// The id of the controlled target
+    pub fn target_id(&self) -> &str {
+        self.targets.get(self.active).map_or("", |target| target.id.as_str())
+    }
+
+    /// Send a raw protocol command to the controlled page
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let session_id = self
+            .targets
+            .get(self.active)
+            .and_then(|target| target.session_id.as_deref())
+            .ok_or_else(|| Error::BrowserError("No page is attached".to_string()))?;
+        self.connection()?.call(method, params, Some(session_id))
+    }
+
+    /// Page targets currently open in our browser context
+    fn page_targets(&self) -> Result<Vec<Value>> {
+        let result = self.connection()?.call("Target.getTargets", json!({}), None)?;
+        Ok(result["targetInfos"]
+            .as_array()
+            .map(|infos| {
+                infos
+                    .iter()
+                    .filter(|info| info["type"] == "page")
+                    .filter(|info| match &self.context_id {
+                        Some(context) => info["browserContextId"] == json!(context),
+                        None => true,
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Bring the known targets up to date, recording pages opened or closed by the browser
+    fn sync_targets(&mut self) -> Result<()> {
+        let infos = self.page_targets()?;
+        for info in &infos {
+            let id = info["targetId"].as_str().unwrap_or_default();
+            if self.targets.iter().all(|target| target.id != id) {
+                self.targets.push(CdpTarget {
+                    id: id.to_string(),
+                    session_id: None,
+                });
+                self.page_events.push(PageEvent::Opened {
+                    page: PageId::new(id),
+                    opener: info["openerId"].as_str().map(PageId::from),
+                    url: info["url"].as_str().unwrap_or_default().to_string(),
+                });
+            }
+        }
+
+        let mut active_closed = false;
+        let mut index = 0;
+        while index < self.targets.len() {
+            if infos.iter().any(|info| info["targetId"] == self.targets[index].id.as_str()) {
+                index += 1;
+                continue;
+            }
+            let target = self.targets.remove(index);
+            self.page_events.push(PageEvent::Closed {
+                page: PageId::new(target.id),
+            });
+            match index.cmp(&self.active) {
+                std::cmp::Ordering::Less => self.active -= 1,
+                std::cmp::Ordering::Equal => active_closed = true,
+                std::cmp::Ordering::Greater => {}
+            }
+        }
+
+        if active_closed && !self.targets.is_empty() {
+            self.activate(self.targets.len() - 1)?;
+        }
+        Ok(())
+    }
+
+    /// Index of a known page, syncing targets once if it is not known yet
+    fn target_index(&mut self, page: &PageId) -> Result<usize> {
+        let find = |targets: &[CdpTarget]| targets.iter().position(|target| target.id == page.as_str());
+        if let Some(index) = find(&self.targets) {
+            return Ok(index);
+        }
+        self.sync_targets()?;
+        find(&self.targets).ok_or_else(|| Error::BrowserError(format!("Unknown page {}", page)))
+    }
+
+    /// Make a target the controlled one, attaching to it on first use
+    fn activate(&mut self, index: usize) -> Result<()> {
+        if self.targets[index].session_id.is_some() {
+            self.active = index;
+            return Ok(());
+        }
+
+        let attached = self.connection()?.call(
+            "Target.attachToTarget",
+            json!({ "targetId": self.targets[index].id, "flatten": true }),
+            None,
+        )?;
+        self.targets[index].session_id = Some(string_field(&attached, "sessionId")?);
+        self.active = index;
+        self.apply_config()
+    }
+
+    /// Take all protocol events received so far
+    pub fn drain_events(&self) -> Result<Vec<CdpEvent>> {
+        Ok(self.connection()?.drain_events())
+    }
+
+    fn connection(&self) -> Result<std::sync::MutexGuard<'_, CdpConnection>> {
+        if self.closed {
+            return Err(Error::BrowserError("Browser has been closed".to_string()));
+        }
+        self.connection
+            .lock()
+            .map_err(|_| Error::BrowserError("DevTools connection lock poisoned".to_string()))
+    }
+
+    /// Evaluate an expression in the page and return its value
+    fn evaluate(&self, expression: &str, selector: Option<&Selector>) -> Result<Value> {
+        let result = self.call(
+            "Runtime.evaluate",
+            json!({
+                "expression": expression,
+                "returnByValue": true,
+                "awaitPromise": true,
+            }),
+        )?;
+
+        check_exception(&result, selector)?;
+        Ok(result["result"]["value"].clone())
+    }
+
+    /// The remote object id of the element `body` returns for the first match of `selector`
+    fn object_id(&self, selector: &Selector, body: &str) -> Result<String> {
+        let result = self.call("Runtime.evaluate", json!({ "expression": js::with_element(selector, body)? }))?;
+        check_exception(&result, Some(selector))?;
+        string_field(&result["result"], "objectId")
+    }
+
+    /// Evaluate `body` against the first element matching `selector`
+    fn evaluate_on(&self, selector: &Selector, body: &str) -> Result<Value> {
+        self.evaluate(&js::with_element(selector, body)?, Some(selector))
+    }
+
+    /// Scroll an element into view and return the centre of its box
+    fn center(&self, selector: &Selector) -> Result<(f64, f64)> {
+        let point = self.evaluate_on(selector, js::CENTER_POINT)?;
+        Ok((point["x"].as_f64().unwrap_or_default(), point["y"].as_f64().unwrap_or_default()))
+    }
+
+    /// Dispatch `clicks` clicks at the given viewport coordinates
+    fn click_at(&self, x: f64, y: f64, button: MouseButton, clicks: u32) -> Result<()> {
+        self.call("Input.dispatchMouseEvent", json!({ "type": "mouseMoved", "x": x, "y": y }))?;
+        for click_count in 1..=clicks {
+            for event_type in ["mousePressed", "mouseReleased"] {
+                self.call(
+                    "Input.dispatchMouseEvent",
+                    json!({
+                        "type": event_type,
+                        "x": x,
+                        "y": y,
+                        "button": button.cdp_name(),
+                        "clickCount": click_count,
+                    }),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Dispatch a key event for `key`
+    fn key_event(&self, event_type: &str, key: &KeyDefinition, modifiers: u32) -> Result<()> {
+        let mut params = json!({
+            "type": event_type,
+            "modifiers": modifiers,
+            "key": key.key,
+            "code": key.code,
+            "windowsVirtualKeyCode": key.key_code,
+        });
+        if let Some(text) = key.text.as_ref().filter(|_| event_type == "keyDown") {
+            params["text"] = json!(text);
+            params["unmodifiedText"] = json!(text);
+        }
+        self.call("Input.dispatchKeyEvent", params)?;
+        Ok(())
+    }
+
+    /// Poll `check` until it returns true or the timeout expires
+    fn poll_until(&self, timeout: Duration, what: &str, mut check: impl FnMut() -> Result<bool>) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if check()? {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::TimeoutError(format!("Timed out after {:?} waiting for {}", timeout, what)));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Browser for CdpBrowser {
+    fn browser_type(&self) -> BrowserType {
+        self.config.browser_type
+    }
+
+    fn navigate(&mut self, url: &str) -> Result<()> {
+        let result = self.call("Page.navigate", json!({ "url": url }))?;
+        if let Some(error) = result["errorText"].as_str().filter(|e| !e.is_empty()) {
+            return Err(Error::NetworkError(format!("Navigation to {} failed: {}", url, error)));
+        }
+        self.wait_for_navigation(self.config.timeout)
+    }
+
+    fn current_url(&self) -> Result<String> {
+        let value = self.evaluate("location.href", None)?;
+        Ok(value.as_str().unwrap_or_default().to_string())
+    }
+
+    fn click(&mut self, selector: &Selector) -> Result<()> {
+        let (x, y) = self.center(selector)?;
+        self.click_at(x, y, MouseButton::Left, 1)
+    }
+
+    fn type_text(&mut self, selector: &Selector, text: &str) -> Result<()> {
+        self.evaluate_on(selector, js::FOCUS_AND_SELECT)?;
+        self.call("Input.insertText", json!({ "text": text }))?;
+        Ok(())
+    }
+
+    fn hover(&mut self, selector: &Selector) -> Result<()> {
+        let (x, y) = self.center(selector)?;
+        self.call("Input.dispatchMouseEvent", json!({ "type": "mouseMoved", "x": x, "y": y }))?;
+        Ok(())
+    }
+
+    fn double_click(&mut self, selector: &Selector) -> Result<()> {
+        let (x, y) = self.center(selector)?;
+        self.click_at(x, y, MouseButton::Left, 2)
+    }
+
+    fn right_click(&mut self, selector: &Selector) -> Result<()> {
+        let (x, y) = self.center(selector)?;
+        self.click_at(x, y, MouseButton::Right, 1)
+    }
+
+    fn select_option(&mut self, selector: &Selector, choice: &str) -> Result<()> {
+        let selected = self.evaluate_on(selector, &js::select_option(choice)?)?;
+        if selected.as_bool() != Some(true) {
+            return Err(Error::ElementNotFound(format!("Option '{}' in {:?}", choice, selector)));
+        }
+        Ok(())
+    }
+
+    fn set_checked(&mut self, selector: &Selector, checked: bool) -> Result<()> {
+        let state = self.evaluate_on(selector, js::CHECKED_STATE)?;
+        if state["checked"].as_bool() == Some(checked) {
+            return Ok(());
+        }
+        if state["radio"].as_bool() == Some(true) && !checked {
+            return Err(Error::BrowserError(format!("Radio button {:?} cannot be unchecked", selector)));
+        }
+
+        self.click(selector)?;
+        let state = self.evaluate_on(selector, js::CHECKED_STATE)?;
+        if state["checked"].as_bool() != Some(checked) {
+            return Err(Error::BrowserError(format!(
+                "Clicking {:?} did not change its checked state",
+                selector
+            )));
+        }
+        Ok(())
+    }
+
+    fn set_input_files(&mut self, selector: &Selector, files: &[PathBuf]) -> Result<()> {
+        let object_id = self.object_id(selector, js::FILE_INPUT)?;
+        self.call("DOM.setFileInputFiles", json!({ "files": files, "objectId": object_id }))?;
+        Ok(())
+    }
+
+    fn scroll_into_view(&mut self, selector: &Selector) -> Result<()> {
+        self.evaluate_on(selector, js::SCROLL_INTO_VIEW)?;
+        Ok(())
+    }
+
+    fn scroll_by(&mut self, x: f64, y: f64) -> Result<()> {
+        // Wheel events go to whatever is under the middle of the viewport
+        self.call(
+            "Input.dispatchMouseEvent",
+            json!({
+                "type": "mouseWheel",
+                "x": self.config.window_width / 2,
+                "y": self.config.window_height / 2,
+                "deltaX": x,
+                "deltaY": y,
+            }),
+        )?;
+        Ok(())
+    }
+
+    fn drag_and_drop(&mut self, source: &Selector, target: &Selector) -> Result<()> {
+        let (to_x, to_y) = self.center(target)?;
+        let (from_x, from_y) = self.center(source)?;
+
+        self.call("Input.dispatchMouseEvent", json!({ "type": "mouseMoved", "x": from_x, "y": from_y }))?;
+        self.call(
+            "Input.dispatchMouseEvent",
+            json!({ "type": "mousePressed", "x": from_x, "y": from_y, "button": "left", "clickCount": 1 }),
+        )?;
+        // Intermediate moves let drag libraries see the pointer travel
+        for step in 1..=DRAG_STEPS {
+            let progress = f64::from(step) / f64::from(DRAG_STEPS);
+            self.call(
+                "Input.dispatchMouseEvent",
+                json!({
+                    "type": "mouseMoved",
+                    "x": from_x + (to_x - from_x) * progress,
+                    "y": from_y + (to_y - from_y) * progress,
+                    "button": "left",
+                    "buttons": 1,
+                }),
+            )?;
+        }
+        self.call(
+            "Input.dispatchMouseEvent",
+            json!({ "type": "mouseReleased", "x": to_x, "y": to_y, "button": "left", "clickCount": 1 }),
+        )?;
+        Ok(())
+    }
+
+    fn press_key(&mut self, chord: &KeyChord) -> Result<()> {
+        let mut held = 0;
+        for modifier in &chord.modifiers {
+            held |= modifier.cdp_bit();
+            self.key_event("rawKeyDown", &key_definition(modifier.key(), None), held)?;
+        }
+
+        let key = chord.definition();
+        let down = if key.text.is_some() { "keyDown" } else { "rawKeyDown" };
+        self.key_event(down, &key, chord.cdp_modifiers())?;
+        self.key_event("keyUp", &key, chord.cdp_modifiers())?;
+
+        for modifier in chord.modifiers.iter().rev() {
+            held &= !modifier.cdp_bit();
+            self.key_event("keyUp", &key_definition(modifier.key(), None), held)?;
+        }
+        Ok(())
+    }
+
+    fn get_text(&self, selector: &Selector) -> Result<String> {
+        let value = self.evaluate_on(selector, js::TEXT)?;
+        Ok(value.as_str().unwrap_or_default().to_string())
+    }
+
+    fn get_attributes(&self, selector: &Selector) -> Result<HashMap<String, String>> {
+        let value = self.evaluate_on(selector, js::ATTRIBUTES)?;
+        Ok(value
+            .as_object()
+            .map(|attributes| {
+                attributes
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn element_exists(&self, selector: &Selector) -> Result<bool> {
+        let expression = format!("{}.length > 0", js::find_all(selector)?);
+        Ok(self.evaluate(&expression, Some(selector))?.as_bool().unwrap_or(false))
+    }
+
+    fn wait_for_element(&mut self, selector: &Selector, timeout: Duration) -> Result<()> {
+        self.poll_until(timeout, &format!("element {:?}", selector), || {
+            match self.evaluate_on(selector, js::IS_VISIBLE) {
+                Ok(visible) => Ok(visible.as_bool().unwrap_or(false)),
+                Err(Error::ElementNotFound(_)) => Ok(false),
+                Err(e) => Err(e),
+            }
+        })
+    }
+
+    fn element_state(&self, selector: &Selector) -> Result<Option<ElementState>> {
+        match self.evaluate_on(selector, js::ELEMENT_STATE) {
+            Ok(state) => Ok(Some(serde_json::from_value(state)?)),
+            Err(Error::ElementNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn wait_for_navigation(&mut self, timeout: Duration) -> Result<()> {
+        self.poll_until(timeout, "navigation", || {
+            let state = self.evaluate("document.readyState", None)?;
+            Ok(state == "complete")
+        })
+    }
+
+    fn take_screenshot(&self, path: &str) -> Result<()> {
+        let result = self.call("Page.captureScreenshot", json!({ "format": "png" }))?;
+        let data = result["data"]
+            .as_str()
+            .ok_or_else(|| Error::BrowserError("Screenshot returned no data".to_string()))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| Error::BrowserError(format!("Invalid screenshot data: {}", e)))?;
+
+        let path = std::path::Path::new(path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            crate::utils::ensure_dir(dir)?;
+        }
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn execute_js(&mut self, script: &str) -> Result<serde_json::Value> {
+        self.evaluate(script, None)
+    }
+
+    fn get_html(&self) -> Result<String> {
+        let value = self.evaluate("document.documentElement.outerHTML", None)?;
+        Ok(value.as_str().unwrap_or_default().to_string())
+    }
+
+    fn accessibility_snapshot(&self) -> Result<AccessibilityNode> {
+        let result = self.call("Accessibility.getFullAXTree", json!({}))?;
+        AccessibilityNode::from_cdp(&result["nodes"])
+    }
+
+    fn new_page(&mut self, url: Option<&str>) -> Result<PageId> {
+        let mut params = json!({ "url": "about:blank" });
+        if let Some(context) = &self.context_id {
+            params["browserContextId"] = json!(context);
+        }
+        let target = self.connection()?.call("Target.createTarget", params, None)?;
+        let id = string_field(&target, "targetId")?;
+
+        // A sync may already have picked the new target up
+        let index = match self.targets.iter().position(|target| target.id == id) {
+            Some(index) => index,
+            None => {
+                self.targets.push(CdpTarget {
+                    id: id.clone(),
+                    session_id: None,
+                });
+                self.targets.len() - 1
+            }
+        };
+        self.activate(index)?;
+        if let Some(url) = url {
+            self.navigate(url)?;
+        }
+        Ok(PageId::new(id))
+    }
+
+    fn pages(&self) -> Result<Vec<PageInfo>> {
+        Ok(self
+            .page_targets()?
+            .iter()
+            .map(|info| PageInfo {
+                id: PageId::new(info["targetId"].as_str().unwrap_or_default()),
+                url: info["url"].as_str().unwrap_or_default().to_string(),
+                opener: info["openerId"].as_str().map(PageId::from),
+            })
+            .collect())
+    }
+
+    fn active_page(&self) -> Result<PageId> {
+        Ok(PageId::new(self.target_id()))
+    }
+
+    fn switch_to_page(&mut self, page: &PageId) -> Result<()> {
+        let index = self.target_index(page)?;
+        self.activate(index)?;
+        self.connection()?.call("Target.activateTarget", json!({ "targetId": page.as_str() }), None)?;
+        Ok(())
+    }
+
+    fn close_page(&mut self, page: &PageId) -> Result<()> {
+        let index = self.target_index(page)?;
+        if self.targets.len() == 1 {
+            return Err(Error::BrowserError("Cannot close the last page".to_string()));
+        }
+
+        self.connection()?.call("Target.closeTarget", json!({ "targetId": page.as_str() }), None)?;
+        self.targets.remove(index);
+        // Closing the active page activates the most recently opened one
+        if index == self.active {
+            self.activate(self.targets.len() - 1)?;
+        } else if index < self.active {
+            self.active -= 1;
+        }
+        Ok(())
+    }
+
+    fn take_page_events(&mut self) -> Result<Vec<PageEvent>> {
+        self.sync_targets()?;
+        Ok(std::mem::take(&mut self.page_events))
+    }
+
+    fn set_dialog_policy(&mut self, policy: DialogPolicy) -> Result<()> {
+        self.connection()?.set_dialog_policy(policy);
+        Ok(())
+    }
+
+    fn take_dialogs(&mut self) -> Result<Vec<HandledDialog>> {
+        Ok(self.connection()?.take_dialogs())
+    }
+
+    fn wait_for_download(&mut self, timeout: Duration) -> Result<Download> {
+        let mut finished = None;
+        self.poll_until(timeout, "a download", || {
+            let mut connection = self.connection()?;
+            if connection.finished_downloads.is_empty() {
+                // Any round trip reads the events that arrived meanwhile
+                connection.call("Browser.getVersion", json!({}), None)?;
+            }
+            finished = connection.finished_downloads.pop_front();
+            Ok(finished.is_some())
+        })?;
+        let Some(finished) = finished else {
+            return Err(Error::BrowserError("Download vanished while waiting".to_string()));
+        };
+        if !finished.completed {
+            return Err(Error::NetworkError(format!("Download of {} was canceled", finished.url)));
+        }
+
+        let dir = self.config.download_dir();
+        let path = unique_path(&dir, &finished.suggested_filename)?;
+        std::fs::rename(dir.join(&finished.guid), &path)?;
+        Ok(Download {
+            mime_type: mime_type_for(&finished.suggested_filename).to_string(),
+            url: finished.url,
+            suggested_filename: finished.suggested_filename,
+            path,
+        })
+    }
+
+    fn set_routes(&mut self, routes: &[Route]) -> Result<()> {
+        self.connection()?.set_routes(routes.to_vec());
+        let (method, params) = if routes.is_empty() {
+            ("Fetch.disable", json!({}))
+        } else {
+            ("Fetch.enable", json!({ "patterns": [{ "urlPattern": "*" }] }))
+        };
+
+        let mut connection = self.connection()?;
+        for session_id in self.targets.iter().filter_map(|target| target.session_id.as_deref()) {
+            connection.call(method, params.clone(), Some(session_id))?;
+        }
+        Ok(())
+    }
+
+    fn set_har_recording(&mut self, options: Option<HarOptions>) -> Result<()> {
+        self.connection()?.record_network(options.is_some());
+        self.har = options;
+        self.har_pending.clear();
+        if options.is_none() && (self.config.block_images || self.config.emulation.offline) {
+            return Ok(());
+        }
+
+        let method = if options.is_some() { "Network.enable" } else { "Network.disable" };
+        let mut connection = self.connection()?;
+        for session_id in self.targets.iter().filter_map(|target| target.session_id.as_deref()) {
+            connection.call(method, json!({}), Some(session_id))?;
+        }
+        Ok(())
+    }
+
+    fn take_har_entries(&mut self) -> Result<Vec<HarEntry>> {
+        let events = self.connection()?.take_network_events();
+        let mut entries = Vec::new();
+        for event in events {
+            self.record_network_event(event, &mut entries)?;
+        }
+        Ok(entries)
+    }
+
+    fn storage_state(&self) -> Result<StorageState> {
+        let mut params = json!({});
+        if let Some(context) = &self.context_id {
+            params["browserContextId"] = json!(context);
+        }
+        let result = self.connection()?.call("Storage.getCookies", params, None)?;
+        let cookies = result["cookies"]
+            .as_array()
+            .map(|cookies| cookies.iter().map(cookie_from_cdp).collect())
+            .unwrap_or_default();
+
+        // Web storage is read from the active page's origin
+        let storage: OriginStorage = serde_json::from_value(self.evaluate(js::STORAGE, None)?)?;
+        let origins = if storage.origin.starts_with("http") {
+            vec![storage]
+        } else {
+            Vec::new()
+        };
+        Ok(StorageState { cookies, origins })
+    }
+
+    fn set_storage_state(&mut self, state: &StorageState) -> Result<()> {
+        if !state.cookies.is_empty() {
+            let cookies: Vec<Value> = state.cookies.iter().map(cookie_to_cdp).collect();
+            let mut params = json!({ "cookies": cookies });
+            if let Some(context) = &self.context_id {
+                params["browserContextId"] = json!(context);
+            }
+            self.connection()?.call("Storage.setCookies", params, None)?;
+        }
+
+        if state.origins.is_empty() {
+            return Ok(());
+        }
+        // DOMStorage writes to any origin without navigating there
+        self.call("DOMStorage.enable", json!({}))?;
+        for storage in &state.origins {
+            for (items, is_local) in [(&storage.local_storage, true), (&storage.session_storage, false)] {
+                for item in items {
+                    self.call(
+                        "DOMStorage.setDOMStorageItem",
+                        json!({
+                            "storageId": { "securityOrigin": storage.origin, "isLocalStorage": is_local },
+                            "key": item.name,
+                            "value": item.value,
+                        }),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move to a fresh browser context, which starts without cookies, storage, history or pages
+    ///
+    /// The previous context is disposed of with its pages. Pages in the
+    /// default context are closed instead: all of them in a browser we
+    /// launched, otherwise only the ones we attached to.
+    fn reset(&mut self) -> Result<()> {
+        let old_pages: Vec<String> = match (&self.context_id, &self.process) {
+            (Some(_), _) => Vec::new(),
+            (None, Some(_)) => self
+                .page_targets()?
+                .iter()
+                .filter_map(|info| info["targetId"].as_str().map(str::to_string))
+                .collect(),
+            (None, None) => self
+                .targets
+                .iter()
+                .filter(|target| target.session_id.is_some())
+                .map(|target| target.id.clone())
+                .collect(),
+        };
+
+        // A connected browser takes the proxy per context; a launched one has it already
+        let mut params = json!({});
+        if let Some(proxy) = self.config.proxy.as_ref().filter(|_| self.process.is_none()) {
+            params["proxyServer"] = json!(proxy);
+        }
+        let context = self.connection()?.call("Target.createBrowserContext", params, None)?;
+        let context_id = string_field(&context, "browserContextId")?;
+        let target = self.connection()?.call(
+            "Target.createTarget",
+            json!({ "url": "about:blank", "browserContextId": context_id }),
+            None,
+        )?;
+        let target_id = string_field(&target, "targetId")?;
+
+        let old_context = self.context_id.replace(context_id);
+        {
+            let mut connection = self.connection()?;
+            if let Some(old_context) = old_context {
+                connection.call("Target.disposeBrowserContext", json!({ "browserContextId": old_context }), None)?;
+            }
+            for page in old_pages.iter().filter(|page| **page != target_id) {
+                connection.call("Target.closeTarget", json!({ "targetId": page }), None)?;
+            }
+            connection.reset();
+        }
+
+        self.targets = vec![CdpTarget {
+            id: target_id,
+            session_id: None,
+        }];
+        self.page_events.clear();
+        self.har = None;
+        self.har_pending.clear();
+        self.activate(0)?;
+        self.enable_downloads();
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+
+        {
+            let mut connection = self.connection()?;
+            if self.process.is_some() {
+                // Browser.close tears down the whole process we launched
+                let _ = connection.call("Browser.close", json!({}), None);
+            } else if let Some(context) = &self.context_id {
+                // Our own context goes with all of its pages
+                connection.call("Target.disposeBrowserContext", json!({ "browserContextId": context }), None)?;
+            } else {
+                // Only pages we attached to are ours to close
+                for target in self.targets.iter().filter(|target| target.session_id.is_some()) {
+                    connection.call("Target.closeTarget", json!({ "targetId": target.id }), None)?;
+                }
+            }
+            connection.close()?;
+        }
+        self.closed = true;
+
+        if let Some(mut child) = self.process.take() {
+            let _ = child.wait();
+        }
+        if let Some(dir) = self.user_data_dir.take() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CdpBrowser {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(dir) = self.user_data_dir.take() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Read a protocol `Response` that arrived at `timestamp`
+fn response_from_cdp(response: &Value, timestamp: f64) -> ReceivedResponse {
+    ReceivedResponse {
+        status: response["status"].as_u64().unwrap_or_default() as u16,
+        headers: headers_from_cdp(&response["headers"]),
+        received_at: timestamp,
+    }
+}
+
+/// Build the HAR entry of a request that finished at `finished_at`
+fn finish_exchange(pending: PendingExchange, response: HarResponse, finished_at: f64) -> HarEntry {
+    let millis = |from: f64, to: f64| ((to - from) * 1000.0).max(0.0);
+    let timings = match &pending.response {
+        Some(received) => HarTimings::new(
+            millis(pending.sent_at, received.received_at),
+            millis(received.received_at, finished_at),
+        ),
+        None => HarTimings::new(millis(pending.sent_at, finished_at), 0.0),
+    };
+    HarEntry::new(pending.started, pending.request, response, timings)
+}
+
+/// Convert a protocol cookie
+fn cookie_from_cdp(cookie: &Value) -> Cookie {
+    let text = |field: &str| cookie[field].as_str().unwrap_or_default().to_string();
+    Cookie {
+        name: text("name"),
+        value: text("value"),
+        domain: text("domain"),
+        path: text("path"),
+        // Session cookies report an expiry of -1
+        expires: cookie["expires"].as_f64().filter(|expires| *expires >= 0.0),
+        http_only: cookie["httpOnly"].as_bool().unwrap_or(false),
+        secure: cookie["secure"].as_bool().unwrap_or(false),
+        same_site: cookie["sameSite"].as_str().map(str::to_string),
+    }
+}
+
+/// Convert a cookie to a protocol `CookieParam`
+fn cookie_to_cdp(cookie: &Cookie) -> Value {
+    let mut param = json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.domain,
+        "path": cookie.path,
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+    });
+    if let Some(expires) = cookie.expires {
+        param["expires"] = json!(expires);
+    }
+    if let Some(same_site) = &cookie.same_site {
+        param["sameSite"] = json!(same_site);
+    }
+    param
+}
+
+/// Command-line arguments for launching Chromium with the given configuration
+fn launch_args(config: &BrowserConfig, user_data_dir: &std::path::Path) -> Vec<String> {
+    let mut args = vec![
+        "--remote-debugging-port=0".to_string(),
+        format!("--user-data-dir={}", user_data_dir.display()),
+        "--no-first-run".to_string(),
+        "--no-default-browser-check".to_string(),
+        format!("--window-size={},{}", config.window_width, config.window_height),
+    ];
+
+    if config.headless {
+        args.push("--headless=new".to_string());
+    }
+    if let Some(proxy) = &config.proxy {
+        args.push(format!("--proxy-server={}", proxy));
+    }
+    if let Some(user_agent) = &config.user_agent {
+        args.push(format!("--user-agent={}", user_agent));
+    }
+    if let Some(locale) = &config.emulation.locale {
+        args.push(format!("--lang={}", locale));
+    }
+    if config.ignore_https_errors {
+        args.push("--ignore-certificate-errors".to_string());
+    }
+    if config.block_images {
+        args.push("--blink-settings=imagesEnabled=false".to_string());
+    }
+
+    args.push("about:blank".to_string());
+    args
+}
+
+/// Read the browser's stderr until it reports its DevTools websocket URL
+fn wait_for_devtools_url(child: &mut Child, timeout: Duration) -> Result<String> {
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| Error::BrowserError("Browser stderr not captured".to_string()))?;
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        // Keep draining after the URL is found so the browser never blocks on a full pipe
+        for line in BufReader::new(stderr).lines().map_while(std::io::Result::ok) {
+            if let Some(url) = line.strip_prefix("DevTools listening on ") {
+                let _ = sender.send(url.trim().to_string());
+            }
+        }
+    });
+
+    receiver.recv_timeout(timeout).map_err(|_| {
+        Error::TimeoutError(format!("Browser did not report a DevTools endpoint within {:?}", timeout))
+    })
+}
+
+fn string_field(value: &Value, field: &str) -> Result<String> {
+    value[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::BrowserError(format!("DevTools response missing `{}`", field)))
+}
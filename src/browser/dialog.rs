@@ -0,0 +1,133 @@
+//! JavaScript dialogs
+//!
+//! `alert()`, `confirm()`, `prompt()` and `beforeunload` dialogs block the
+//! page until they are answered. A [`DialogPolicy`] decides the answer as
+//! each dialog opens, and every answered dialog is kept as a
+//! [`HandledDialog`] until taken.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// The kind of a JavaScript dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DialogKind {
+    /// `alert()`
+    Alert,
+    /// `confirm()`
+    Confirm,
+    /// `prompt()`
+    Prompt,
+    /// A `beforeunload` confirmation
+    BeforeUnload,
+}
+
+impl DialogKind {
+    /// Parse a DevTools `DialogType`
+    pub(crate) fn from_cdp(kind: &str) -> Self {
+        match kind {
+            "confirm" => Self::Confirm,
+            "prompt" => Self::Prompt,
+            "beforeunload" => Self::BeforeUnload,
+            _ => Self::Alert,
+        }
+    }
+}
+
+/// A dialog opened by a page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dialog {
+    /// The kind of dialog
+    pub kind: DialogKind,
+    /// The message shown
+    pub message: String,
+    /// The default text of a prompt
+    pub default_value: Option<String>,
+    /// The URL of the page that opened it
+    pub url: String,
+}
+
+/// How a dialog was answered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DialogAction {
+    /// Press OK
+    Accept,
+    /// Press OK after typing the text into a prompt
+    AcceptWith(String),
+    /// Press Cancel
+    Dismiss,
+}
+
+impl DialogAction {
+    /// Whether the dialog is accepted
+    pub fn accepts(&self) -> bool {
+        !matches!(self, DialogAction::Dismiss)
+    }
+
+    /// The text typed into a prompt, if any
+    pub fn prompt_text(&self) -> Option<&str> {
+        match self {
+            DialogAction::AcceptWith(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// A dialog and how it was answered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandledDialog {
+    /// The dialog
+    pub dialog: Dialog,
+    /// The answer
+    pub action: DialogAction,
+}
+
+/// Decides how dialogs are answered
+///
+/// The default dismisses every dialog, which answers `confirm()` with false
+/// and `prompt()` with null.
+#[derive(Clone, Default)]
+pub enum DialogPolicy {
+    /// Accept every dialog, leaving prompts at their default value
+    Accept,
+    /// Dismiss every dialog
+    #[default]
+    Dismiss,
+    /// Ask a function
+    ///
+    /// The function runs while the browser waits for the answer, so it must
+    /// not drive the browser itself.
+    Callback(Arc<dyn Fn(&Dialog) -> DialogAction + Send + Sync>),
+}
+
+impl DialogPolicy {
+    /// A policy asking `callback` for each dialog
+    pub fn callback(callback: impl Fn(&Dialog) -> DialogAction + Send + Sync + 'static) -> Self {
+        DialogPolicy::Callback(Arc::new(callback))
+    }
+
+    /// The answer for a dialog
+    pub fn answer(&self, dialog: &Dialog) -> DialogAction {
+        match self {
+            DialogPolicy::Accept => match &dialog.default_value {
+                Some(value) if dialog.kind == DialogKind::Prompt => DialogAction::AcceptWith(value.clone()),
+                _ => DialogAction::Accept,
+            },
+            DialogPolicy::Dismiss => DialogAction::Dismiss,
+            DialogPolicy::Callback(callback) => callback(dialog),
+        }
+    }
+}
+
+impl fmt::Debug for DialogPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogPolicy::Accept => write!(f, "Accept"),
+            DialogPolicy::Dismiss => write!(f, "Dismiss"),
+            DialogPolicy::Callback(_) => write!(f, "Callback(..)"),
+        }
+    }
+}
@@ -0,0 +1,463 @@
+//! HTML document model for backends without a live page
+//!
+//! The static and mock backends hold plain HTML rather than a running page.
+//! [`Dom`] parses that HTML and resolves [`Selector`]s against it, so the
+//! backends share one definition of what each selector variant matches.
+
+use super::Selector;
+use crate::error::{Error, Result};
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Node};
+use std::collections::HashMap;
+
+/// Attribute used to map XPath results back to parsed elements
+const NODE_MARKER: &str = "data-llamaclick-node";
+
+/// Elements whose text never contributes to visible page text
+const NON_VISIBLE: &[&str] = &["script", "style", "noscript", "template", "head"];
+
+/// Elements the `disabled` attribute applies to
+const DISABLEABLE: &[&str] = &["button", "input", "select", "textarea", "option", "optgroup", "fieldset"];
+
+/// A parsed HTML document
+pub struct Dom {
+    /// The parsed document
+    html: Html,
+}
+
+impl Dom {
+    /// Parse an HTML document
+    pub fn parse(source: &str) -> Self {
+        Self {
+            html: Html::parse_document(source),
+        }
+    }
+
+    /// The root `html` element
+    pub fn root(&self) -> ElementRef<'_> {
+        self.html.root_element()
+    }
+
+    /// Get an element by id
+    pub fn element(&self, id: NodeId) -> Option<ElementRef<'_>> {
+        self.html.tree.get(id).and_then(ElementRef::wrap)
+    }
+
+    /// Resolve a selector to every matching element, in document order
+    pub fn select(&self, selector: &Selector) -> Result<Vec<NodeId>> {
+        self.select_in(self.html.root_element(), selector)
+    }
+
+    /// Resolve a selector to the matching elements in `root`'s subtree
+    fn select_in(&self, root: ElementRef<'_>, selector: &Selector) -> Result<Vec<NodeId>> {
+        match selector {
+            Selector::Css(css) => self.select_css(root, css),
+            Selector::XPath(xpath) => Ok(self
+                .select_xpath(xpath)?
+                .into_iter()
+                .filter(|id| self.is_inside(*id, root.id()))
+                .collect()),
+            Selector::Id(id) => Ok(self.filter(root, |el| el.value().id() == Some(id.as_str()))),
+            Selector::Class(class) => Ok(self.filter(root, |el| el.value().classes().any(|c| c == class))),
+            Selector::Name(name) => Ok(self.filter(root, |el| el.value().attr("name") == Some(name.as_str()))),
+            Selector::Text(text) => Ok(self.select_text(root, text)),
+            Selector::Semantic(description) => Err(Error::BrowserError(format!(
+                "Semantic selector '{}' must be resolved before reaching the page",
+                description
+            ))),
+            Selector::Within(scope, inner) => {
+                let mut found = Vec::new();
+                for host in self.select_in(root, scope)? {
+                    let scope_root = self.scope_root(host, scope)?;
+                    for id in self.select_in(scope_root, inner)? {
+                        if id != scope_root.id() && !found.contains(&id) {
+                            found.push(id);
+                        }
+                    }
+                }
+                Ok(found)
+            }
+            Selector::Nth(inner, index) => Ok(self.select_in(root, inner)?.into_iter().skip(*index).take(1).collect()),
+            Selector::HasText(inner, text) => {
+                let needle = normalize(text);
+                Ok(self
+                    .select_in(root, inner)?
+                    .into_iter()
+                    .filter(|id| self.text(*id).contains(&needle))
+                    .collect())
+            }
+            Selector::Visible(inner) => Ok(self
+                .select_in(root, inner)?
+                .into_iter()
+                .filter(|id| self.is_visible(*id))
+                .collect()),
+            Selector::Or(selectors) => {
+                let mut found = Vec::new();
+                for selector in selectors {
+                    for id in self.select_in(root, selector)? {
+                        if !found.contains(&id) {
+                            found.push(id);
+                        }
+                    }
+                }
+                Ok(self.in_document_order(found))
+            }
+            Selector::Relative(..) => Err(Error::Unsupported(format!(
+                "Layout selector {} needs a rendered page",
+                selector
+            ))),
+        }
+    }
+
+    /// Whether an element may be visible: neither it nor an ancestor is hidden by its markup
+    ///
+    /// Without a layout only the `hidden` attribute, inline `display: none`
+    /// and `visibility: hidden` styles, hidden inputs and never-rendered
+    /// elements such as `<script>` count as hidden.
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        let mut node = self.html.tree.get(id);
+        while let Some(current) = node {
+            if let Some(el) = ElementRef::wrap(current) {
+                let value = el.value();
+                let shadow_root = value.name() == "template" && value.attr("shadowrootmode").is_some();
+                let hidden_input = value.name() == "input"
+                    && value.attr("type").is_some_and(|kind| kind.eq_ignore_ascii_case("hidden"));
+                let style: String = value
+                    .attr("style")
+                    .unwrap_or_default()
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                if (NON_VISIBLE.contains(&value.name()) && !shadow_root)
+                    || value.attr("hidden").is_some()
+                    || hidden_input
+                    || style.contains("display:none")
+                    || style.contains("visibility:hidden")
+                {
+                    return false;
+                }
+            }
+            node = current.parent();
+        }
+        true
+    }
+
+    /// Whether a form control is enabled
+    ///
+    /// Controls with a `disabled` attribute, inside a disabled `<fieldset>`,
+    /// or marked `aria-disabled="true"` are disabled.
+    pub fn is_enabled(&self, id: NodeId) -> bool {
+        let Some(el) = self.element(id) else {
+            return false;
+        };
+        let value = el.value();
+        if value.attr("aria-disabled") == Some("true") {
+            return false;
+        }
+        if !DISABLEABLE.contains(&value.name()) {
+            return true;
+        }
+        value.attr("disabled").is_none()
+            && !el
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| ancestor.value().name() == "fieldset" && ancestor.value().attr("disabled").is_some())
+    }
+
+    /// Sort elements into document order
+    fn in_document_order(&self, mut ids: Vec<NodeId>) -> Vec<NodeId> {
+        let positions: HashMap<NodeId, usize> = self
+            .html
+            .tree
+            .root()
+            .descendants()
+            .enumerate()
+            .map(|(position, node)| (node.id(), position))
+            .collect();
+        ids.sort_by_key(|id| positions.get(id).copied().unwrap_or(usize::MAX));
+        ids
+    }
+
+    /// The element a scoped search starts from
+    ///
+    /// Shadow hosts are searched through their declarative shadow root
+    /// (`<template shadowrootmode>`). Frame documents are never loaded, so
+    /// frames cannot be entered.
+    fn scope_root(&self, host: NodeId, scope: &Selector) -> Result<ElementRef<'_>> {
+        let el = self
+            .element(host)
+            .ok_or_else(|| Error::ElementNotFound(format!("{:?}", scope)))?;
+        if matches!(el.value().name(), "iframe" | "frame") {
+            return Err(Error::Unsupported(format!(
+                "Cannot look inside frame {:?}: frame documents are not loaded by this browser",
+                scope
+            )));
+        }
+        let shadow_root = el.child_elements().find(|child| {
+            child.value().name() == "template"
+                && (child.value().attr("shadowrootmode").is_some() || child.value().attr("shadowroot").is_some())
+        });
+        Ok(shadow_root.unwrap_or(el))
+    }
+
+    /// Whether `id` is `ancestor` or one of its descendants
+    fn is_inside(&self, id: NodeId, ancestor: NodeId) -> bool {
+        let mut node = self.html.tree.get(id);
+        while let Some(current) = node {
+            if current.id() == ancestor {
+                return true;
+            }
+            node = current.parent();
+        }
+        false
+    }
+
+    /// Resolve a selector to its first match
+    pub fn select_first(&self, selector: &Selector) -> Result<NodeId> {
+        self.select(selector)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::ElementNotFound(format!("{:?}", selector)))
+    }
+
+    /// The visible text of an element, with whitespace collapsed
+    pub fn text(&self, id: NodeId) -> String {
+        self.element(id).map(|el| visible_text(el)).unwrap_or_default()
+    }
+
+    /// All attributes of an element
+    pub fn attributes(&self, id: NodeId) -> HashMap<String, String> {
+        self.element(id)
+            .map(|el| {
+                el.value()
+                    .attrs()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The lowercase tag name of an element
+    pub fn tag(&self, id: NodeId) -> String {
+        self.element(id).map(|el| el.value().name().to_string()).unwrap_or_default()
+    }
+
+    /// The nearest ancestor (or the element itself) with the given tag name
+    pub fn closest(&self, id: NodeId, tag: &str) -> Option<NodeId> {
+        let mut node = self.html.tree.get(id);
+        while let Some(current) = node {
+            if let Some(el) = ElementRef::wrap(current) {
+                if el.value().name() == tag {
+                    return Some(current.id());
+                }
+            }
+            node = current.parent();
+        }
+        None
+    }
+
+    /// The `<option>` elements of a `<select>`, in document order
+    pub fn options(&self, select: NodeId) -> Vec<NodeId> {
+        self.select(&Selector::css("option"))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|option| self.closest(*option, "select") == Some(select))
+            .collect()
+    }
+
+    /// The value an `<option>` submits: its `value` attribute, or else its text
+    pub fn option_value(&self, option: NodeId) -> String {
+        self.attributes(option)
+            .get("value")
+            .cloned()
+            .unwrap_or_else(|| self.text(option))
+    }
+
+    /// The option of a `<select>` whose value, or else whose text, is `choice`
+    pub fn find_option(&self, select: NodeId, choice: &str) -> Option<NodeId> {
+        let options = self.options(select);
+        options
+            .iter()
+            .find(|option| self.option_value(**option) == choice)
+            .or_else(|| options.iter().find(|option| self.text(**option) == choice))
+            .copied()
+    }
+
+    /// A CSS selector uniquely identifying an element
+    ///
+    /// The path is anchored at the nearest ancestor with a unique, CSS-safe
+    /// id, and otherwise spelled out with `:nth-of-type` steps from `html`.
+    pub fn css_path(&self, id: NodeId) -> String {
+        let mut steps = Vec::new();
+        let mut node = self.html.tree.get(id);
+
+        while let Some(current) = node {
+            let Some(el) = ElementRef::wrap(current) else {
+                break;
+            };
+            let name = el.value().name();
+
+            if let Some(element_id) = el.value().id().filter(|i| is_css_identifier(i)) {
+                let unique = self.filter(self.html.root_element(), |other| other.value().id() == Some(element_id)).len() == 1;
+                if unique {
+                    steps.push(format!("#{}", element_id));
+                    break;
+                }
+            }
+
+            if current.parent().and_then(ElementRef::wrap).is_none() {
+                steps.push(name.to_string());
+                break;
+            }
+
+            let position = current
+                .prev_siblings()
+                .filter_map(ElementRef::wrap)
+                .filter(|sibling| sibling.value().name() == name)
+                .count()
+                + 1;
+            steps.push(format!("{}:nth-of-type({})", name, position));
+            node = current.parent();
+        }
+
+        steps.reverse();
+        steps.join(" > ")
+    }
+
+    fn filter(&self, root: ElementRef<'_>, predicate: impl Fn(&ElementRef<'_>) -> bool) -> Vec<NodeId> {
+        std::iter::once(root)
+            .chain(root.descendent_elements().skip(1))
+            .filter(|el| predicate(el))
+            .map(|el| el.id())
+            .collect()
+    }
+
+    fn select_css(&self, root: ElementRef<'_>, css: &str) -> Result<Vec<NodeId>> {
+        let parsed = scraper::Selector::parse(css)
+            .map_err(|e| Error::ValidationError(format!("Invalid CSS selector '{}': {}", css, e)))?;
+        let mut matches: Vec<NodeId> = Vec::new();
+        if parsed.matches(&root) {
+            matches.push(root.id());
+        }
+        matches.extend(root.select(&parsed).map(|el| el.id()));
+        Ok(matches)
+    }
+
+    fn select_text(&self, root: ElementRef<'_>, text: &str) -> Vec<NodeId> {
+        let needle = normalize(text);
+        let contains = |el: &ElementRef<'_>| visible_text(*el).contains(&needle);
+
+        // The deepest elements containing the text, like the in-page resolver
+        self.filter(root, |el| {
+            !NON_VISIBLE.contains(&el.value().name())
+                && contains(el)
+                && !el.child_elements().any(|child| contains(&child))
+        })
+    }
+
+    fn select_xpath(&self, xpath: &str) -> Result<Vec<NodeId>> {
+        let package = sxd_document::Package::new();
+        let document = package.as_document();
+        let mut ids = Vec::new();
+        let root = self.html.root_element();
+        let converted = convert(&document, root, &mut ids);
+        document.root().append_child(converted);
+
+        let factory = sxd_xpath::Factory::new();
+        let compiled = factory
+            .build(xpath)
+            .map_err(|e| Error::ValidationError(format!("Invalid XPath '{}': {}", xpath, e)))?
+            .ok_or_else(|| Error::ValidationError(format!("Empty XPath '{}'", xpath)))?;
+        let context = sxd_xpath::Context::new();
+        let value = compiled
+            .evaluate(&context, document.root())
+            .map_err(|e| Error::ValidationError(format!("Failed to evaluate XPath '{}': {}", xpath, e)))?;
+
+        let sxd_xpath::Value::Nodeset(nodes) = value else {
+            return Err(Error::ValidationError(format!("XPath '{}' does not select elements", xpath)));
+        };
+
+        Ok(nodes
+            .document_order()
+            .into_iter()
+            .filter_map(|node| node.element())
+            .filter_map(|el| el.attribute_value(NODE_MARKER))
+            .filter_map(|index| index.parse::<usize>().ok())
+            .filter_map(|index| ids.get(index).copied())
+            .collect())
+    }
+}
+
+/// Copy a parsed element into an XPath document, tagging each copy with its index in `ids`
+fn convert<'d>(
+    document: &sxd_document::dom::Document<'d>,
+    element: ElementRef<'_>,
+    ids: &mut Vec<NodeId>,
+) -> sxd_document::dom::Element<'d> {
+    let copy = document.create_element(element.value().name());
+    for (name, value) in element.value().attrs() {
+        copy.set_attribute_value(name, value);
+    }
+    copy.set_attribute_value(NODE_MARKER, &ids.len().to_string());
+    ids.push(element.id());
+
+    append_children(document, copy, *element, ids);
+    copy
+}
+
+fn append_children<'d>(
+    document: &sxd_document::dom::Document<'d>,
+    parent: sxd_document::dom::Element<'d>,
+    node: ego_tree::NodeRef<'_, Node>,
+    ids: &mut Vec<NodeId>,
+) {
+    for child in node.children() {
+        match child.value() {
+            Node::Element(_) => {
+                if let Some(el) = ElementRef::wrap(child) {
+                    parent.append_child(convert(document, el, ids));
+                }
+            }
+            Node::Text(text) => parent.append_child(document.create_text(text)),
+            // Template contents live in a fragment below the template element
+            Node::Fragment => append_children(document, parent, child, ids),
+            _ => {}
+        }
+    }
+}
+
+/// Whether `value` can be used after `#` in CSS without escaping
+fn is_css_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Collapse runs of whitespace into single spaces and trim
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Text of an element excluding scripts, styles and other non-rendered content
+pub fn visible_text(element: ElementRef<'_>) -> String {
+    let mut out = String::new();
+    collect_text(*element, &mut out);
+    normalize(&out)
+}
+
+fn collect_text(node: ego_tree::NodeRef<'_, Node>, out: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(el) if NON_VISIBLE.contains(&el.name()) => {}
+            Node::Element(el) => {
+                if matches!(el.name(), "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6") {
+                    out.push(' ');
+                }
+                collect_text(child, out);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Downloaded files
+//!
+//! Backends save each download into the configured downloads directory under
+//! the name the site suggested, made unique, and describe it with a
+//! [`Download`].
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name used for downloads without a usable suggested file name
+const FALLBACK_NAME: &str = "download";
+
+/// MIME types by lowercase file extension
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("csv", "text/csv"),
+    ("doc", "application/msword"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mp4", "video/mp4"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("txt", "text/plain"),
+    ("webp", "image/webp"),
+    ("xls", "application/vnd.ms-excel"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+/// A file downloaded by a page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    /// The URL the file came from
+    pub url: String,
+    /// The file name the site suggested
+    pub suggested_filename: String,
+    /// Where the file was saved
+    pub path: PathBuf,
+    /// The MIME type, from the response when known and the file extension otherwise
+    pub mime_type: String,
+}
+
+/// Guess a MIME type from a file name's extension
+pub fn mime_type_for(filename: &str) -> &'static str {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map_or("application/octet-stream", |(_, mime_type)| mime_type)
+}
+
+/// A path in `dir` for the suggested name that no existing file uses
+///
+/// Only the final component of the name is kept, so a hostile name cannot
+/// escape the directory; clashes get a ` (n)` suffix before the extension.
+pub(crate) fn unique_path(dir: &Path, suggested: &str) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let name = Path::new(suggested.trim())
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(FALLBACK_NAME);
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+
+    let mut path = dir.join(name);
+    let mut copy = 1;
+    while path.exists() {
+        path = dir.join(format!("{} ({}){}", stem, copy, extension));
+        copy += 1;
+    }
+    Ok(path)
+}
@@ -0,0 +1,230 @@
+//! Device and environment emulation
+//!
+//! [`Emulation`] holds what a page sees of its environment beyond the window
+//! size and user agent: screen density, touch, locale, timezone, location,
+//! colour scheme and connectivity. [`Device`] presets bundle the screen and
+//! user agent of common phones, tablets and desktops.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A position reported to the Geolocation API
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Geolocation {
+    /// Latitude in degrees
+    pub latitude: f64,
+    /// Longitude in degrees
+    pub longitude: f64,
+    /// Accuracy in metres
+    pub accuracy: f64,
+}
+
+impl Geolocation {
+    /// A position accurate to 10 metres
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            accuracy: 10.0,
+        }
+    }
+
+    /// Set the accuracy in metres
+    pub fn with_accuracy(mut self, accuracy: f64) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+}
+
+/// The `prefers-color-scheme` a page sees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    /// Light mode
+    Light,
+    /// Dark mode
+    Dark,
+}
+
+impl fmt::Display for ColorScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorScheme::Light => write!(f, "light"),
+            ColorScheme::Dark => write!(f, "dark"),
+        }
+    }
+}
+
+/// Environment settings applied to every page
+///
+/// The DevTools backend applies all of them. WebDriver sessions apply what
+/// their browser accepts at startup: Chrome and Edge take the screen,
+/// locale and colour scheme, Firefox also the timezone and location. The
+/// static backend sends the locale as `Accept-Language` and fails requests
+/// while offline.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Emulation {
+    /// Device pixels per CSS pixel, or the screen's own when unset
+    pub device_scale_factor: Option<f64>,
+    /// Whether to emulate a mobile browser, with its viewport meta handling and scrollbars
+    pub mobile: bool,
+    /// Whether to report a touch screen
+    pub touch: bool,
+    /// BCP 47 locale, e.g. `de-DE`
+    pub locale: Option<String>,
+    /// IANA timezone, e.g. `Europe/Berlin`
+    pub timezone: Option<String>,
+    /// Position reported to the Geolocation API, which is granted when set
+    pub geolocation: Option<Geolocation>,
+    /// Colour scheme preference
+    pub color_scheme: Option<ColorScheme>,
+    /// Whether the network is cut off
+    pub offline: bool,
+}
+
+/// A named device preset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Device {
+    /// The preset name, e.g. `iPhone 13`
+    pub name: &'static str,
+    /// The browser's user agent
+    pub user_agent: &'static str,
+    /// Viewport width in CSS pixels
+    pub width: u32,
+    /// Viewport height in CSS pixels
+    pub height: u32,
+    /// Device pixels per CSS pixel
+    pub device_scale_factor: f64,
+    /// Whether it is a mobile browser
+    pub mobile: bool,
+    /// Whether it has a touch screen
+    pub touch: bool,
+}
+
+const IPHONE_15_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 15_0 like Mac OS X) AppleWebKit/605.1.15 \
+                            (KHTML, like Gecko) Version/15.0 Mobile/15E148 Safari/604.1";
+const IPHONE_17_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 \
+                            (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+const IPAD_UA: &str = "Mozilla/5.0 (iPad; CPU OS 15_0 like Mac OS X) AppleWebKit/605.1.15 \
+                       (KHTML, like Gecko) Version/15.0 Mobile/15E148 Safari/604.1";
+const DESKTOP_CHROME_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                                 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// The built-in device presets
+pub const DEVICES: &[Device] = &[
+    Device {
+        name: "iPhone SE",
+        user_agent: IPHONE_15_UA,
+        width: 375,
+        height: 667,
+        device_scale_factor: 2.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "iPhone 13",
+        user_agent: IPHONE_15_UA,
+        width: 390,
+        height: 844,
+        device_scale_factor: 3.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "iPhone 13 Pro Max",
+        user_agent: IPHONE_15_UA,
+        width: 428,
+        height: 926,
+        device_scale_factor: 3.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "iPhone 15",
+        user_agent: IPHONE_17_UA,
+        width: 393,
+        height: 852,
+        device_scale_factor: 3.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "Pixel 5",
+        user_agent: "Mozilla/5.0 (Linux; Android 11; Pixel 5) AppleWebKit/537.36 \
+                     (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+        width: 393,
+        height: 851,
+        device_scale_factor: 2.75,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "Pixel 7",
+        user_agent: "Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 \
+                     (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+        width: 412,
+        height: 915,
+        device_scale_factor: 2.625,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "Galaxy S23",
+        user_agent: "Mozilla/5.0 (Linux; Android 13; SM-S911B) AppleWebKit/537.36 \
+                     (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+        width: 360,
+        height: 780,
+        device_scale_factor: 3.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "iPad Mini",
+        user_agent: IPAD_UA,
+        width: 768,
+        height: 1024,
+        device_scale_factor: 2.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "iPad Pro 11",
+        user_agent: IPAD_UA,
+        width: 834,
+        height: 1194,
+        device_scale_factor: 2.0,
+        mobile: true,
+        touch: true,
+    },
+    Device {
+        name: "Desktop Chrome",
+        user_agent: DESKTOP_CHROME_UA,
+        width: 1280,
+        height: 720,
+        device_scale_factor: 1.0,
+        mobile: false,
+        touch: false,
+    },
+    Device {
+        name: "Desktop Chrome HiDPI",
+        user_agent: DESKTOP_CHROME_UA,
+        width: 1280,
+        height: 720,
+        device_scale_factor: 2.0,
+        mobile: false,
+        touch: false,
+    },
+];
+
+impl Device {
+    /// Look up a preset by name, ignoring case
+    pub fn named(name: &str) -> Option<&'static Device> {
+        DEVICES.iter().find(|device| device.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The names of all presets
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        DEVICES.iter().map(|device| device.name)
+    }
+}
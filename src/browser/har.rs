@@ -0,0 +1,424 @@
+//! HTTP Archive (HAR 1.2) recording and replay
+//!
+//! Backends that can observe network traffic collect a [`HarEntry`] per
+//! request while recording is on. A saved [`Har`] can be turned back into
+//! routes so a test replays the recorded responses without the network.
+
+use super::{Route, RouteResponse};
+use crate::error::{Error, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a HAR recording captures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HarOptions {
+    /// Whether response bodies are stored in the archive
+    pub include_bodies: bool,
+}
+
+impl HarOptions {
+    /// Record response bodies as well as headers and timings
+    pub fn with_bodies(mut self) -> Self {
+        self.include_bodies = true;
+        self
+    }
+}
+
+/// An HTTP archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Har {
+    /// The archive contents
+    pub log: HarLog,
+}
+
+/// The `log` object of an archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarLog {
+    /// The HAR format version
+    pub version: String,
+    /// The tool that produced the archive
+    pub creator: HarCreator,
+    /// The recorded requests, in the order they completed
+    pub entries: Vec<HarEntry>,
+}
+
+/// The tool that produced an archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarCreator {
+    /// The tool name
+    pub name: String,
+    /// The tool version
+    pub version: String,
+}
+
+/// A name/value pair, used for headers, cookies and query parameters
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarNameValue {
+    /// The name
+    pub name: String,
+    /// The value
+    pub value: String,
+}
+
+/// A recorded request and its response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    /// When the request started, in ISO 8601
+    pub started_date_time: String,
+    /// Total time in milliseconds
+    pub time: f64,
+    /// The request
+    pub request: HarRequest,
+    /// The response
+    pub response: HarResponse,
+    /// Cache information, always empty
+    #[serde(default)]
+    pub cache: serde_json::Map<String, serde_json::Value>,
+    /// Timing breakdown
+    pub timings: HarTimings,
+}
+
+impl HarEntry {
+    /// Build an entry for a request started at `started`
+    pub fn new(started: SystemTime, request: HarRequest, response: HarResponse, timings: HarTimings) -> Self {
+        Self {
+            started_date_time: format_timestamp(started),
+            time: timings.send + timings.wait + timings.receive,
+            request,
+            response,
+            cache: serde_json::Map::new(),
+            timings,
+        }
+    }
+}
+
+/// A recorded request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    /// The HTTP method
+    pub method: String,
+    /// The absolute URL
+    pub url: String,
+    /// The HTTP version
+    pub http_version: String,
+    /// Cookies sent, not broken out by any backend yet
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    /// The request headers
+    pub headers: Vec<HarNameValue>,
+    /// Query parameters parsed from the URL
+    #[serde(default)]
+    pub query_string: Vec<HarNameValue>,
+    /// The request body, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    /// Size of the headers in bytes, -1 if unknown
+    pub headers_size: i64,
+    /// Size of the body in bytes, -1 if unknown
+    pub body_size: i64,
+}
+
+impl HarRequest {
+    /// A request without a body
+    pub fn new(method: &str, url: &str, headers: &[(String, String)]) -> Self {
+        let query_string = url::Url::parse(url)
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| HarNameValue {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            method: method.to_ascii_uppercase(),
+            url: url.to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            cookies: Vec::new(),
+            headers: name_values(headers),
+            query_string,
+            post_data: None,
+            headers_size: -1,
+            body_size: 0,
+        }
+    }
+
+    /// Attach a request body
+    pub fn with_body(mut self, mime_type: &str, text: impl Into<String>) -> Self {
+        let text = text.into();
+        self.body_size = text.len() as i64;
+        self.post_data = Some(HarPostData {
+            mime_type: mime_type.to_string(),
+            text,
+        });
+        self
+    }
+}
+
+/// A recorded request body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    /// The body's MIME type
+    pub mime_type: String,
+    /// The body
+    pub text: String,
+}
+
+/// A recorded response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    /// The status code, 0 when the request failed
+    pub status: u16,
+    /// The status text
+    pub status_text: String,
+    /// The HTTP version
+    pub http_version: String,
+    /// Cookies set, not broken out by any backend yet
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+    /// The response headers
+    pub headers: Vec<HarNameValue>,
+    /// The response body
+    pub content: HarContent,
+    /// The `Location` header of redirects
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    /// Size of the headers in bytes, -1 if unknown
+    pub headers_size: i64,
+    /// Size of the body in bytes, -1 if unknown
+    pub body_size: i64,
+    /// Why the request failed, for requests that got no response
+    #[serde(rename = "_error", default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HarResponse {
+    /// A response whose body is stored only when `include_body` is set
+    pub fn new(status: u16, headers: &[(String, String)], body: &[u8], include_body: bool) -> Self {
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let (text, encoding) = match std::str::from_utf8(body) {
+            _ if !include_body => (None, None),
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (
+                Some(base64::engine::general_purpose::STANDARD.encode(body)),
+                Some("base64".to_string()),
+            ),
+        };
+        Self {
+            status,
+            status_text: reqwest::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|code| code.canonical_reason())
+                .unwrap_or_default()
+                .to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            cookies: Vec::new(),
+            headers: name_values(headers),
+            content: HarContent {
+                size: body.len() as i64,
+                mime_type: header("content-type").unwrap_or_default(),
+                text,
+                encoding,
+            },
+            redirect_url: header("location").unwrap_or_default(),
+            headers_size: -1,
+            body_size: body.len() as i64,
+            error: None,
+        }
+    }
+
+    /// A request that failed without a response
+    pub fn failed(error: impl Into<String>) -> Self {
+        Self {
+            error: Some(error.into()),
+            ..Self::new(0, &[], &[], false)
+        }
+    }
+
+    /// The recorded body, empty if it was not stored
+    pub fn body(&self) -> Result<Vec<u8>> {
+        let text = self.content.text.as_deref().unwrap_or_default();
+        match self.content.encoding.as_deref() {
+            Some("base64") => base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|e| Error::ValidationError(format!("Invalid base64 body in HAR: {}", e))),
+            _ => Ok(text.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// A recorded response body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    /// Length of the body in bytes
+    pub size: i64,
+    /// The body's MIME type
+    pub mime_type: String,
+    /// The body, if it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` for binary bodies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// Timing breakdown of an entry in milliseconds, -1 where not measured
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HarTimings {
+    /// Time queued before the request was sent
+    pub blocked: f64,
+    /// DNS resolution
+    pub dns: f64,
+    /// Connection setup
+    pub connect: f64,
+    /// Sending the request
+    pub send: f64,
+    /// Waiting for the first response byte
+    pub wait: f64,
+    /// Reading the response
+    pub receive: f64,
+    /// TLS negotiation
+    pub ssl: f64,
+}
+
+impl HarTimings {
+    /// Timings with only the wait and receive phases measured
+    pub fn new(wait: f64, receive: f64) -> Self {
+        Self {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            send: 0.0,
+            wait,
+            receive,
+            ssl: -1.0,
+        }
+    }
+}
+
+impl Default for HarTimings {
+    fn default() -> Self {
+        Self::new(0.0, 0.0)
+    }
+}
+
+impl Har {
+    /// An archive holding `entries`
+    pub fn new(entries: Vec<HarEntry>) -> Self {
+        Self {
+            log: HarLog {
+                version: "1.2".to_string(),
+                creator: HarCreator {
+                    name: "LlamaClick".to_string(),
+                    version: crate::VERSION.to_string(),
+                },
+                entries,
+            },
+        }
+    }
+
+    /// Serialize to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse an archive
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::ConfigurationError(format!("Invalid HAR file: {}", e)))
+    }
+
+    /// Write to a file, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Read from a file
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Routes fulfilling each recorded request with its recorded response
+    ///
+    /// Routes match on method and exact URL; when a URL was recorded more
+    /// than once, the last response wins. Failed requests are skipped.
+    pub fn routes(&self) -> Result<Vec<Route>> {
+        self.log
+            .entries
+            .iter()
+            .filter(|entry| entry.response.status != 0)
+            .map(|entry| {
+                let recorded = &entry.response;
+                let mut response = RouteResponse::new(recorded.status, recorded.body()?);
+                response.headers = recorded
+                    .headers
+                    .iter()
+                    .filter(|header| !is_framing_header(&header.name))
+                    .map(|header| (header.name.clone(), header.value.clone()))
+                    .collect();
+                Ok(Route::fulfill(entry.request.url.clone(), response).with_method(&entry.request.method))
+            })
+            .collect()
+    }
+}
+
+/// Headers describing the original transfer, which no longer match a replayed body
+fn is_framing_header(name: &str) -> bool {
+    ["content-length", "content-encoding", "transfer-encoding"]
+        .iter()
+        .any(|framing| name.eq_ignore_ascii_case(framing))
+}
+
+fn name_values(pairs: &[(String, String)]) -> Vec<HarNameValue> {
+    pairs
+        .iter()
+        .map(|(name, value)| HarNameValue {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+/// Format a time as an ISO 8601 UTC timestamp with milliseconds
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let millis = time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis()) as i64;
+    let (days, rest) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rest / 3_600_000,
+        rest / 60_000 % 60,
+        rest / 1000 % 60,
+        rest % 1000
+    )
+}
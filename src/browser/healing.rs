@@ -0,0 +1,406 @@
+//! Self-healing selectors
+//!
+//! While self-healing is on, a session records an [`ElementFingerprint`] of
+//! the element each element action's selector reaches. When a selector
+//! later stops matching, e.g. after a class rename, the page is searched for
+//! the element most similar to the fingerprint and the action is retried on
+//! it. Each such repair is reported as a [`HealedSelector`].
+
+use super::dom::{self, Dom};
+use super::page::url_pattern;
+use super::Selector;
+use crate::error::Result;
+#[cfg(feature = "llm")]
+use crate::llms::LlmProvider;
+use ego_tree::NodeId;
+use scraper::ElementRef;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Default minimum similarity for a candidate to replace a broken selector
+pub const DEFAULT_HEALING_THRESHOLD: f64 = 0.6;
+
+/// Candidates scoring within this much of the best are considered tied
+const TIE_MARGIN: f64 = 0.05;
+
+/// Maximum characters of text kept in a fingerprint
+const MAX_TEXT_CHARS: usize = 200;
+
+/// Number of sibling elements on each side whose text is kept
+const NEIGHBOURS: usize = 2;
+
+/// Weights of the fingerprint parts in the similarity score
+const TAG_WEIGHT: f64 = 0.2;
+const TEXT_WEIGHT: f64 = 0.3;
+const ATTRIBUTE_WEIGHT: f64 = 0.25;
+const PATH_WEIGHT: f64 = 0.1;
+const NEIGHBOUR_WEIGHT: f64 = 0.15;
+
+/// System message for breaking ties between healing candidates
+#[cfg(feature = "llm")]
+const TIE_BREAK_MESSAGE: &str = "You repair broken web automation selectors. You are given the \
+    fingerprint of an element that can no longer be found and numbered candidate elements from the \
+    current page. Reply with only the number of the candidate that is the same element, or -1 if \
+    none of them is.";
+
+/// What an element looked like when a selector last reached it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementFingerprint {
+    /// Lowercase tag name
+    pub tag: String,
+    /// Visible text, whitespace collapsed
+    pub text: String,
+    /// Attributes, except inline styles
+    pub attributes: BTreeMap<String, String>,
+    /// Tag names from `html` down to the element
+    pub path: Vec<String>,
+    /// Text of the nearest non-empty sibling elements
+    pub neighbours: Vec<String>,
+}
+
+impl ElementFingerprint {
+    /// Fingerprint an element of a parsed document
+    pub fn capture(dom: &Dom, id: NodeId) -> Self {
+        let element = dom.element(id);
+        let mut path: Vec<String> = element
+            .into_iter()
+            .flat_map(|el| el.ancestors())
+            .filter_map(ElementRef::wrap)
+            .map(|el| el.value().name().to_string())
+            .collect();
+        path.reverse();
+        path.push(dom.tag(id));
+
+        let sibling_text = |sibling: ElementRef<'_>| Some(dom::visible_text(sibling)).filter(|t| !t.is_empty());
+        let mut neighbours: Vec<String> = element
+            .into_iter()
+            .flat_map(|el| el.prev_siblings())
+            .filter_map(ElementRef::wrap)
+            .filter_map(sibling_text)
+            .take(NEIGHBOURS)
+            .collect();
+        neighbours.reverse();
+        neighbours.extend(
+            element
+                .into_iter()
+                .flat_map(|el| el.next_siblings())
+                .filter_map(ElementRef::wrap)
+                .filter_map(sibling_text)
+                .take(NEIGHBOURS),
+        );
+
+        Self {
+            tag: dom.tag(id),
+            text: truncate(&dom.text(id), MAX_TEXT_CHARS),
+            attributes: dom
+                .attributes(id)
+                .into_iter()
+                .filter(|(name, _)| name != "style")
+                .collect(),
+            path,
+            neighbours: neighbours.iter().map(|t| truncate(t, MAX_TEXT_CHARS)).collect(),
+        }
+    }
+
+    /// Fingerprint the first element of `html` matching `selector`
+    pub fn from_html(html: &str, selector: &Selector) -> Result<Self> {
+        let dom = Dom::parse(html);
+        let id = dom.select_first(selector)?;
+        Ok(Self::capture(&dom, id))
+    }
+
+    /// How alike two fingerprints are, from 0 (nothing shared) to 1 (identical)
+    pub fn similarity(&self, other: &ElementFingerprint) -> f64 {
+        let tag = if self.tag == other.tag { 1.0 } else { 0.0 };
+        let text = if self.text == other.text {
+            1.0
+        } else {
+            jaccard(self.text.split_whitespace(), other.text.split_whitespace())
+        };
+        let attributes = jaccard(attribute_tokens(&self.attributes), attribute_tokens(&other.attributes));
+        let common_suffix = self
+            .path
+            .iter()
+            .rev()
+            .zip(other.path.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let path = common_suffix as f64 / self.path.len().max(other.path.len()).max(1) as f64;
+        let neighbours = jaccard(self.neighbours.iter(), other.neighbours.iter());
+
+        TAG_WEIGHT * tag
+            + TEXT_WEIGHT * text
+            + ATTRIBUTE_WEIGHT * attributes
+            + PATH_WEIGHT * path
+            + NEIGHBOUR_WEIGHT * neighbours
+    }
+}
+
+/// Attribute name/value pairs, with each class as its own pair
+fn attribute_tokens(attributes: &BTreeMap<String, String>) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for (name, value) in attributes {
+        if name == "class" {
+            tokens.extend(value.split_whitespace().map(|class| format!("class={}", class)));
+        } else {
+            tokens.push(format!("{}={}", name, value));
+        }
+    }
+    tokens
+}
+
+/// Shared items over all distinct items; two empty sets are identical
+fn jaccard<T: Ord>(a: impl IntoIterator<Item = T>, b: impl IntoIterator<Item = T>) -> f64 {
+    let a: std::collections::BTreeSet<T> = a.into_iter().collect();
+    let b: std::collections::BTreeSet<T> = b.into_iter().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+/// A fingerprint recorded for a selector on pages matching a URL pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredFingerprint {
+    /// The page's URL pattern, see [`url_pattern`]
+    url_pattern: String,
+    /// The selector as the caller wrote it
+    selector: Selector,
+    /// The element the selector last reached
+    fingerprint: ElementFingerprint,
+}
+
+/// Element fingerprints by URL pattern and selector
+///
+/// Saving the store after a run and loading it in the next lets selectors
+/// heal across runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FingerprintStore {
+    /// Recorded fingerprints
+    entries: Vec<StoredFingerprint>,
+}
+
+impl FingerprintStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The fingerprint recorded for a selector on pages like `url`
+    pub fn get(&self, url: &str, selector: &Selector) -> Option<&ElementFingerprint> {
+        let pattern = url_pattern(url);
+        self.entries
+            .iter()
+            .find(|entry| entry.url_pattern == pattern && entry.selector == *selector)
+            .map(|entry| &entry.fingerprint)
+    }
+
+    /// Record the fingerprint of the element a selector reaches on `url`
+    pub fn insert(&mut self, url: &str, selector: &Selector, fingerprint: ElementFingerprint) {
+        let pattern = url_pattern(url);
+        match self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.url_pattern == pattern && entry.selector == *selector)
+        {
+            Some(entry) => entry.fingerprint = fingerprint,
+            None => self.entries.push(StoredFingerprint {
+                url_pattern: pattern,
+                selector: selector.clone(),
+                fingerprint,
+            }),
+        }
+    }
+
+    /// Number of recorded fingerprints
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no fingerprint is recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Serialize to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Write the store to a JSON file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::utils::write_to_file(path, &self.to_json()?)
+    }
+
+    /// Read the store from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_json(&crate::utils::read_from_file(path)?)
+    }
+}
+
+/// A broken selector that was replaced by a similar element's selector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealedSelector {
+    /// The page URL
+    pub url: String,
+    /// The selector that no longer matched
+    pub selector: Selector,
+    /// The suggested replacement, which the action was retried with
+    pub replacement: Selector,
+    /// Similarity of the replacement to the recorded fingerprint
+    pub confidence: f64,
+}
+
+/// Self-healing settings and the fingerprints recorded so far
+#[derive(Debug)]
+pub struct SelfHealing {
+    /// Recorded fingerprints
+    store: FingerprintStore,
+    /// Minimum similarity for a replacement
+    threshold: f64,
+    /// LLM asked to choose between equally good candidates
+    #[cfg(feature = "llm")]
+    llm: Option<Box<dyn LlmProvider>>,
+}
+
+impl Default for SelfHealing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelfHealing {
+    /// Heal selectors with the default threshold and an empty store
+    pub fn new() -> Self {
+        Self {
+            store: FingerprintStore::new(),
+            threshold: DEFAULT_HEALING_THRESHOLD,
+            #[cfg(feature = "llm")]
+            llm: None,
+        }
+    }
+
+    /// Start from previously recorded fingerprints
+    pub fn with_store(mut self, store: FingerprintStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Set the minimum similarity, from 0 to 1, for a replacement
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Ask an LLM to choose when several candidates are about equally similar
+    ///
+    /// Without one, tied candidates are not healed at all.
+    #[cfg(feature = "llm")]
+    pub fn with_llm(mut self, llm: Box<dyn LlmProvider>) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    /// The recorded fingerprints
+    pub fn store(&self) -> &FingerprintStore {
+        &self.store
+    }
+
+    /// The minimum similarity for a replacement
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Record the element `selector` reaches in `dom`, if it reaches one
+    pub(crate) fn remember(&mut self, url: &str, selector: &Selector, concrete: &Selector, dom: &Dom) {
+        if let Ok(id) = dom.select_first(concrete) {
+            self.store.insert(url, selector, ElementFingerprint::capture(dom, id));
+        }
+    }
+
+    /// Find the element of `dom` most like the one `selector` used to reach
+    ///
+    /// Returns `None` without a recorded fingerprint, when no candidate
+    /// reaches the threshold, or when candidates tie and no LLM settles it.
+    pub(crate) fn heal(&mut self, url: &str, selector: &Selector, dom: &Dom) -> Result<Option<HealedSelector>> {
+        let Some(fingerprint) = self.store.get(url, selector).cloned() else {
+            return Ok(None);
+        };
+
+        let mut candidates: Vec<(NodeId, ElementFingerprint, f64)> = dom
+            .select(&Selector::css("body *"))?
+            .into_iter()
+            .filter(|id| dom.is_visible(*id))
+            .map(|id| {
+                let candidate = ElementFingerprint::capture(dom, id);
+                let score = fingerprint.similarity(&candidate);
+                (id, candidate, score)
+            })
+            .filter(|(_, _, score)| *score >= self.threshold)
+            .collect();
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        let Some(best) = candidates.first().map(|candidate| candidate.2) else {
+            log::debug!("No element on {} is similar enough to heal {}", url, selector);
+            return Ok(None);
+        };
+        candidates.retain(|candidate| best - candidate.2 <= TIE_MARGIN);
+        let chosen = if candidates.len() == 1 {
+            candidates.pop()
+        } else {
+            self.break_tie(&fingerprint, candidates)?
+        };
+        let Some((id, candidate, confidence)) = chosen else {
+            log::debug!("Could not choose between similar elements to heal {}", selector);
+            return Ok(None);
+        };
+
+        self.store.insert(url, selector, candidate);
+        Ok(Some(HealedSelector {
+            url: url.to_string(),
+            selector: selector.clone(),
+            replacement: Selector::css(dom.css_path(id)),
+            confidence,
+        }))
+    }
+
+    #[cfg(feature = "llm")]
+    fn break_tie(
+        &self,
+        fingerprint: &ElementFingerprint,
+        mut candidates: Vec<(NodeId, ElementFingerprint, f64)>,
+    ) -> Result<Option<(NodeId, ElementFingerprint, f64)>> {
+        let Some(llm) = &self.llm else {
+            return Ok(None);
+        };
+        let mut prompt = format!("Element: {}\n\nCandidates:\n", serde_json::to_string(fingerprint)?);
+        for (i, (_, candidate, _)) in candidates.iter().enumerate() {
+            prompt.push_str(&format!("{}. {}\n", i + 1, serde_json::to_string(candidate)?));
+        }
+        let response = crate::utils::block_on(llm.generate_response(TIE_BREAK_MESSAGE, &prompt, 0.0))??;
+        let choice = super::semantic::parse_index(&response.content)
+            .and_then(|index| usize::try_from(index).ok())
+            .filter(|index| (1..=candidates.len()).contains(index));
+        Ok(choice.map(|index| candidates.swap_remove(index - 1)))
+    }
+
+    #[cfg(not(feature = "llm"))]
+    fn break_tie(
+        &self,
+        _fingerprint: &ElementFingerprint,
+        _candidates: Vec<(NodeId, ElementFingerprint, f64)>,
+    ) -> Result<Option<(NodeId, ElementFingerprint, f64)>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,259 @@
+//! Keyboard chords and mouse buttons
+//!
+//! A [`KeyChord`] is written like `Control+Shift+A` or `Enter`: modifiers
+//! joined by `+`, ending in a key name or a single character. Backends
+//! translate chords to DevTools key events or WebDriver key actions.
+
+use crate::error::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// The primary button
+    Left,
+    /// The wheel button
+    Middle,
+    /// The secondary button
+    Right,
+}
+
+impl MouseButton {
+    /// The DevTools protocol name
+    pub(crate) fn cdp_name(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Middle => "middle",
+            Self::Right => "right",
+        }
+    }
+
+    /// The WebDriver button number
+    pub(crate) fn webdriver_button(self) -> u8 {
+        match self {
+            Self::Left => 0,
+            Self::Middle => 1,
+            Self::Right => 2,
+        }
+    }
+}
+
+/// A modifier key held during a chord
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    /// Alt, or Option on macOS
+    Alt,
+    /// Control
+    Control,
+    /// Meta, i.e. Command or the Windows key
+    Meta,
+    /// Shift
+    Shift,
+}
+
+impl Modifier {
+    /// Parse a modifier name, accepting the usual aliases
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "alt" | "option" => Some(Self::Alt),
+            "control" | "ctrl" => Some(Self::Control),
+            "meta" | "cmd" | "command" | "super" => Some(Self::Meta),
+            "shift" => Some(Self::Shift),
+            _ => None,
+        }
+    }
+
+    /// The key name of the modifier itself
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Alt => "Alt",
+            Self::Control => "Control",
+            Self::Meta => "Meta",
+            Self::Shift => "Shift",
+        }
+    }
+
+    /// The bit the DevTools protocol uses for the modifier
+    pub(crate) fn cdp_bit(self) -> u32 {
+        match self {
+            Self::Alt => 1,
+            Self::Control => 2,
+            Self::Meta => 4,
+            Self::Shift => 8,
+        }
+    }
+}
+
+/// Named keys: name, DOM `code`, Windows virtual key code and WebDriver code point
+const NAMED_KEYS: &[(&str, &str, u32, char)] = &[
+    ("Enter", "Enter", 13, '\u{E007}'),
+    ("Tab", "Tab", 9, '\u{E004}'),
+    ("Escape", "Escape", 27, '\u{E00C}'),
+    ("Backspace", "Backspace", 8, '\u{E003}'),
+    ("Delete", "Delete", 46, '\u{E017}'),
+    ("Insert", "Insert", 45, '\u{E016}'),
+    ("Space", "Space", 32, '\u{E00D}'),
+    ("ArrowLeft", "ArrowLeft", 37, '\u{E012}'),
+    ("ArrowUp", "ArrowUp", 38, '\u{E013}'),
+    ("ArrowRight", "ArrowRight", 39, '\u{E014}'),
+    ("ArrowDown", "ArrowDown", 40, '\u{E015}'),
+    ("Home", "Home", 36, '\u{E011}'),
+    ("End", "End", 35, '\u{E010}'),
+    ("PageUp", "PageUp", 33, '\u{E00E}'),
+    ("PageDown", "PageDown", 34, '\u{E00F}'),
+    ("Alt", "AltLeft", 18, '\u{E00A}'),
+    ("Control", "ControlLeft", 17, '\u{E009}'),
+    ("Meta", "MetaLeft", 91, '\u{E03D}'),
+    ("Shift", "ShiftLeft", 16, '\u{E008}'),
+    ("F1", "F1", 112, '\u{E031}'),
+    ("F2", "F2", 113, '\u{E032}'),
+    ("F3", "F3", 114, '\u{E033}'),
+    ("F4", "F4", 115, '\u{E034}'),
+    ("F5", "F5", 116, '\u{E035}'),
+    ("F6", "F6", 117, '\u{E036}'),
+    ("F7", "F7", 118, '\u{E037}'),
+    ("F8", "F8", 119, '\u{E038}'),
+    ("F9", "F9", 120, '\u{E039}'),
+    ("F10", "F10", 121, '\u{E03A}'),
+    ("F11", "F11", 122, '\u{E03B}'),
+    ("F12", "F12", 123, '\u{E03C}'),
+];
+
+/// How a key is described to the DevTools protocol and WebDriver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KeyDefinition {
+    /// The DOM `key` value
+    pub key: String,
+    /// The DOM `code` value
+    pub code: String,
+    /// The Windows virtual key code
+    pub key_code: u32,
+    /// The text the key inserts, if any
+    pub text: Option<String>,
+    /// The WebDriver key value
+    pub webdriver: String,
+}
+
+/// A key pressed while holding modifiers, e.g. `Control+A`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    /// Modifiers held down, in the order they are pressed
+    pub modifiers: Vec<Modifier>,
+    /// A key name such as `Enter`, or a single character
+    pub key: String,
+}
+
+impl KeyChord {
+    /// Parse a chord such as `Control+Shift+Tab`, `Enter` or `a`
+    pub fn parse(chord: &str) -> Result<Self> {
+        // A trailing `+` is the plus key itself, as in `Control++`
+        let (head, key) = match chord.strip_suffix("++") {
+            Some(head) => (head, "+"),
+            None if chord == "+" => ("", "+"),
+            None => chord.rsplit_once('+').unwrap_or(("", chord)),
+        };
+
+        let mut modifiers = Vec::new();
+        for name in head.split('+').filter(|name| !name.is_empty()) {
+            let modifier = Modifier::parse(name.trim()).ok_or_else(|| {
+                Error::ValidationError(format!("Unknown modifier '{}' in key chord '{}'", name, chord))
+            })?;
+            if !modifiers.contains(&modifier) {
+                modifiers.push(modifier);
+            }
+        }
+
+        let key = match key {
+            " " => "Space".to_string(),
+            key if key.trim().chars().count() == 1 => key.trim().to_string(),
+            key => NAMED_KEYS
+                .iter()
+                .map(|(name, ..)| *name)
+                .find(|name| name.eq_ignore_ascii_case(key.trim()))
+                .or_else(|| Modifier::parse(key.trim()).map(Modifier::key))
+                .ok_or_else(|| Error::ValidationError(format!("Unknown key '{}' in key chord '{}'", key, chord)))?
+                .to_string(),
+        };
+        Ok(Self { modifiers, key })
+    }
+
+    /// Whether a modifier is held
+    pub fn has(&self, modifier: Modifier) -> bool {
+        self.modifiers.contains(&modifier)
+    }
+
+    /// The character the chord types, if it types one
+    ///
+    /// Chords holding Control, Alt or Meta are shortcuts and type nothing.
+    pub fn text(&self) -> Option<String> {
+        if self.has(Modifier::Control) || self.has(Modifier::Alt) || self.has(Modifier::Meta) {
+            return None;
+        }
+        match self.key.as_str() {
+            "Space" => Some(" ".to_string()),
+            "Enter" => Some("\r".to_string()),
+            key if key.chars().count() == 1 && self.has(Modifier::Shift) => Some(key.to_uppercase()),
+            key if key.chars().count() == 1 => Some(key.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The DevTools modifier bit mask
+    pub(crate) fn cdp_modifiers(&self) -> u32 {
+        self.modifiers.iter().map(|modifier| modifier.cdp_bit()).sum()
+    }
+
+    /// Describe the chord's key
+    pub(crate) fn definition(&self) -> KeyDefinition {
+        key_definition(&self.key, self.text())
+    }
+}
+
+/// Describe a key name or character
+pub(crate) fn key_definition(key: &str, text: Option<String>) -> KeyDefinition {
+    if let Some((name, code, key_code, webdriver)) = NAMED_KEYS.iter().find(|(name, ..)| *name == key) {
+        let key = if *name == "Space" { " " } else { name };
+        return KeyDefinition {
+            key: key.to_string(),
+            code: code.to_string(),
+            key_code: *key_code,
+            text,
+            webdriver: webdriver.to_string(),
+        };
+    }
+
+    let c = key.chars().next().unwrap_or_default();
+    let code = if c.is_ascii_alphabetic() {
+        format!("Key{}", c.to_ascii_uppercase())
+    } else if c.is_ascii_digit() {
+        format!("Digit{}", c)
+    } else {
+        String::new()
+    };
+    let key = text.clone().unwrap_or_else(|| key.to_string());
+    KeyDefinition {
+        webdriver: key.clone(),
+        key,
+        code,
+        key_code: if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() as u32 } else { 0 },
+        text,
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = Error;
+
+    fn from_str(chord: &str) -> Result<Self> {
+        Self::parse(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier.key())?;
+        }
+        f.write_str(&self.key)
+    }
+}
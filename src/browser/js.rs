@@ -0,0 +1,298 @@
+//! In-page JavaScript helpers shared by script-capable backends
+//!
+//! Backends that can evaluate JavaScript in the page (CDP, WebDriver) resolve
+//! selectors inside the page rather than translating every variant to a
+//! driver-specific strategy. The selector is serialized to JSON and handed to
+//! a small resolver function that returns the matching elements.
+
+use super::{OriginStorage, Selector};
+use crate::error::{Error, Result};
+
+/// Marker thrown by the resolver when no element matches
+pub(crate) const NOT_FOUND_MARKER: &str = "__llamaclick_not_found__";
+
+/// Marker thrown by the resolver when a scope is a frame it cannot enter
+pub(crate) const CROSS_ORIGIN_MARKER: &str = "__llamaclick_cross_origin__";
+
+/// Resolver function: `(selector, root) => Element[]`
+const RESOLVER: &str = r#"function __llamaclickResolve(sel, ctx) {
+  const kind = Object.keys(sel)[0];
+  const value = sel[kind];
+  const all = (css) => Array.from(ctx.querySelectorAll(css));
+  const byXPath = (expr) => {
+    const out = [];
+    const doc = ctx.ownerDocument || ctx;
+    const res = doc.evaluate(expr, ctx, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+    for (let i = 0; i < res.snapshotLength; i++) {
+      const node = res.snapshotItem(i);
+      if (node.nodeType === 1) out.push(node);
+    }
+    return out;
+  };
+  const norm = (s) => (s || '').replace(/\s+/g, ' ').trim();
+  switch (kind) {
+    case 'Css': return all(value);
+    case 'XPath': return byXPath(value);
+    case 'Id': return all('#' + CSS.escape(value));
+    case 'Class': return all('.' + CSS.escape(value));
+    case 'Name': return all('[name="' + value.replace(/"/g, '\\"') + '"]');
+    case 'Text': {
+      const needle = norm(value);
+      return all('*').filter((el) => norm(el.textContent).includes(needle)
+        && !Array.from(el.children).some((c) => norm(c.textContent).includes(needle)));
+    }
+    case 'Within': {
+      const enter = (host) => {
+        if (host.tagName === 'IFRAME' || host.tagName === 'FRAME') {
+          let doc = null;
+          try { doc = host.contentDocument; } catch (e) {}
+          if (!doc) throw new Error('__llamaclick_cross_origin__ ' + (host.src || 'about:blank'));
+          return doc;
+        }
+        return host.shadowRoot || host;
+      };
+      const found = [];
+      for (const host of __llamaclickResolve(value[0], ctx)) {
+        for (const el of __llamaclickResolve(value[1], enter(host))) {
+          if (el !== host && !found.includes(el)) found.push(el);
+        }
+      }
+      return found;
+    }
+    case 'Nth': {
+      const found = __llamaclickResolve(value[0], ctx);
+      return value[1] < found.length ? [found[value[1]]] : [];
+    }
+    case 'HasText': {
+      const needle = norm(value[1]);
+      return __llamaclickResolve(value[0], ctx).filter((el) => norm(el.textContent).includes(needle));
+    }
+    case 'Visible':
+      return __llamaclickResolve(value, ctx).filter((el) => {
+        const r = el.getBoundingClientRect();
+        const s = el.ownerDocument.defaultView.getComputedStyle(el);
+        return r.width > 0 && r.height > 0 && s.visibility !== 'hidden' && s.display !== 'none';
+      });
+    case 'Or': {
+      const found = [];
+      for (const alternative of value) {
+        for (const el of __llamaclickResolve(alternative, ctx)) {
+          if (!found.includes(el)) found.push(el);
+        }
+      }
+      return found.sort((a, b) => (a.compareDocumentPosition(b) & Node.DOCUMENT_POSITION_FOLLOWING) ? -1 : 1);
+    }
+    case 'Relative': {
+      const relation = typeof value[1] === 'string' ? value[1] : 'Near';
+      // Distance from box t to box a when t lies in the relation to a, else null
+      const score = (t, a) => {
+        const dx = Math.max(0, a.left - t.right, t.left - a.right);
+        const dy = Math.max(0, a.top - t.bottom, t.top - a.bottom);
+        switch (relation) {
+          case 'Near': {
+            const distance = Math.hypot(dx, dy);
+            return distance <= value[1].Near ? distance : null;
+          }
+          case 'Above': return t.bottom <= a.top ? a.top - t.bottom + dx : null;
+          case 'Below': return t.top >= a.bottom ? t.top - a.bottom + dx : null;
+          case 'LeftOf': return t.right <= a.left ? a.left - t.right + dy : null;
+          case 'RightOf': return t.left >= a.right ? t.left - a.right + dy : null;
+          default: throw new Error('unsupported relation: ' + relation);
+        }
+      };
+      const anchors = __llamaclickResolve(value[2], ctx);
+      return __llamaclickResolve(value[0], ctx)
+        .map((el) => {
+          const t = el.getBoundingClientRect();
+          const scores = anchors.filter((a) => a !== el)
+            .map((a) => score(t, a.getBoundingClientRect()))
+            .filter((s) => s !== null);
+          return { el, best: scores.length ? Math.min(...scores) : null };
+        })
+        .filter((m) => m.best !== null)
+        .sort((a, b) => a.best - b.best)
+        .map((m) => m.el);
+    }
+    case 'Semantic':
+      throw new Error('semantic selectors must be resolved before reaching the page');
+    default:
+      throw new Error('unsupported selector: ' + kind);
+  }
+}"#;
+
+/// Build an expression that evaluates to every element matching `selector`
+pub(crate) fn find_all(selector: &Selector) -> Result<String> {
+    let json = serde_json::to_string(selector)?;
+    Ok(format!("(() => {{ {}\nreturn __llamaclickResolve({}, document); }})()", RESOLVER, json))
+}
+
+/// Build a script body returning every element matching `selector` inside `arguments[0]`
+///
+/// Without an argument the whole document is searched. This lets WebDriver
+/// resolve selectors that have no native locator strategy.
+pub(crate) fn find_all_in_argument(selector: &Selector) -> Result<String> {
+    let json = serde_json::to_string(selector)?;
+    Ok(format!("{}\nreturn __llamaclickResolve({}, arguments[0] || document);", RESOLVER, json))
+}
+
+/// Build an expression that runs `body` with `el` bound to the first match
+///
+/// `body` is the body of a function taking `el`; its return value becomes the
+/// value of the expression. When nothing matches, the expression throws an
+/// error carrying [`NOT_FOUND_MARKER`].
+pub(crate) fn with_element(selector: &Selector, body: &str) -> Result<String> {
+    let json = serde_json::to_string(selector)?;
+    Ok(format!(
+        "(() => {{ {}\nconst el = __llamaclickResolve({}, document)[0];\nif (!el) throw new Error('{}');\nreturn ((el) => {{ {} }})(el); }})()",
+        RESOLVER, json, NOT_FOUND_MARKER, body
+    ))
+}
+
+/// Map a script exception message to an error, recognising the resolver's markers
+pub(crate) fn exception_to_error(selector: Option<&Selector>, message: &str) -> Error {
+    if let Some(at) = message.find(CROSS_ORIGIN_MARKER) {
+        let frame = message[at + CROSS_ORIGIN_MARKER.len()..].split_whitespace().next().unwrap_or_default();
+        return match selector {
+            Some(selector) => Error::BrowserError(format!(
+                "Cannot access cross-origin frame {} while resolving {:?}",
+                frame, selector
+            )),
+            None => Error::BrowserError(format!("Cannot access cross-origin frame {}", frame)),
+        };
+    }
+    match selector {
+        Some(selector) if message.contains(NOT_FOUND_MARKER) => {
+            Error::ElementNotFound(format!("{:?}", selector))
+        }
+        _ => Error::BrowserError(format!("Script error: {}", message)),
+    }
+}
+
+/// Scroll the element into view and return the centre of its bounding box
+///
+/// Coordinates are relative to the top-level viewport, adding the offsets of
+/// any frames the element is in.
+pub(crate) const CENTER_POINT: &str = "el.scrollIntoView({ block: 'center', inline: 'center' });
+const r = el.getBoundingClientRect();
+const point = { x: r.left + r.width / 2, y: r.top + r.height / 2 };
+for (let w = el.ownerDocument.defaultView; w && w.frameElement; w = w.parent) {
+  const f = w.frameElement.getBoundingClientRect();
+  point.x += f.left + w.frameElement.clientLeft;
+  point.y += f.top + w.frameElement.clientTop;
+}
+return point;";
+
+/// Focus the element and select its contents so typed text replaces them
+pub(crate) const FOCUS_AND_SELECT: &str = "el.scrollIntoView({ block: 'center', inline: 'center' });
+el.focus();
+if (typeof el.select === 'function') { el.select(); }
+else if (el.isContentEditable) { el.ownerDocument.execCommand('selectAll', false, null); }
+return true;";
+
+/// Scroll the element to the centre of the viewport
+pub(crate) const SCROLL_INTO_VIEW: &str = "el.scrollIntoView({ block: 'center', inline: 'center' });
+return true;";
+
+/// Whether the element is a radio button and whether it is checked
+pub(crate) const CHECKED_STATE: &str = "if (el.type !== 'checkbox' && el.type !== 'radio') {
+  throw new Error('element is not a checkbox or radio button');
+}
+return { radio: el.type === 'radio', checked: el.checked };";
+
+/// Return the element, which must be a file input
+pub(crate) const FILE_INPUT: &str = "if (el.localName !== 'input' || el.type !== 'file') {
+  throw new Error('element is not a file input');
+}
+return el;";
+
+/// Build a function body choosing a `<select>` option by value, or else by label
+///
+/// The body returns `false` when no option matches, and fires `input` and
+/// `change` events like a user's choice would.
+pub(crate) fn select_option(choice: &str) -> Result<String> {
+    let json = serde_json::to_string(choice)?;
+    Ok(format!(
+        "if (el.tagName !== 'SELECT') throw new Error('element is not a <select>');
+const choice = {};
+const options = Array.from(el.options);
+const option = options.find((o) => o.value === choice)
+  || options.find((o) => o.text.replace(/\\s+/g, ' ').trim() === choice);
+if (!option) return false;
+option.selected = true;
+el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+el.dispatchEvent(new Event('change', {{ bubbles: true }}));
+return true;",
+        json
+    ))
+}
+
+/// Visible text of the element
+pub(crate) const TEXT: &str = "return el.innerText !== undefined ? el.innerText : el.textContent;";
+
+/// All attributes of the element as an object
+pub(crate) const ATTRIBUTES: &str =
+    "return Object.fromEntries(Array.from(el.attributes).map((a) => [a.name, a.value]));";
+
+/// Whether the element is rendered with a non-empty box
+pub(crate) const IS_VISIBLE: &str = "const r = el.getBoundingClientRect();
+const s = el.ownerDocument.defaultView.getComputedStyle(el);
+return r.width > 0 && r.height > 0 && s.visibility !== 'hidden' && s.display !== 'none';";
+
+/// Actionability state of the element as an `ElementState`
+///
+/// An element outside the viewport is scrolled into view first, so that
+/// hit-testing its centre can tell whether another element covers it.
+pub(crate) const ELEMENT_STATE: &str = "const view = el.ownerDocument.defaultView;
+const s = view.getComputedStyle(el);
+let r = el.getBoundingClientRect();
+const visible = r.width > 0 && r.height > 0 && s.visibility !== 'hidden' && s.display !== 'none';
+const enabled = !(('disabled' in el && el.disabled) || el.closest('fieldset[disabled]')
+  || el.getAttribute('aria-disabled') === 'true');
+let obscuredBy = null;
+if (visible) {
+  if (r.bottom < 0 || r.right < 0 || r.top > view.innerHeight || r.left > view.innerWidth) {
+    el.scrollIntoView({ block: 'center', inline: 'center' });
+    r = el.getBoundingClientRect();
+  }
+  const root = el.getRootNode();
+  const centre = [r.left + r.width / 2, r.top + r.height / 2];
+  const hit = (root.elementFromPoint ? root : el.ownerDocument).elementFromPoint(...centre);
+  if (hit && hit !== el && !el.contains(hit)) {
+    obscuredBy = hit.tagName.toLowerCase() + (hit.id ? '#' + hit.id : '')
+      + Array.from(hit.classList).map((c) => '.' + c).join('');
+  }
+}
+return { visible, enabled, obscuredBy, bounds: { x: r.left, y: r.top, width: r.width, height: r.height } };";
+
+/// Build an expression giving `[x, y, width, height]` (or null) for each CSS selector
+pub(crate) fn bounding_boxes(selectors: &[String]) -> Result<String> {
+    let json = serde_json::to_string(selectors)?;
+    Ok(format!(
+        "({}).map((css) => {{ const el = document.querySelector(css);\nif (!el) return null;\nconst r = el.getBoundingClientRect();\nreturn [r.left, r.top, r.width, r.height]; }})",
+        json
+    ))
+}
+
+/// Expression reading the page origin's storage as an `OriginStorage`
+pub(crate) const STORAGE: &str = "(() => {
+  const items = (s) => Object.keys(s).map((name) => ({ name, value: s.getItem(name) }));
+  return { origin: location.origin, localStorage: items(localStorage), sessionStorage: items(sessionStorage) };
+})()";
+
+/// Expression emptying the current page's web storage, where the page has any
+pub(crate) const CLEAR_STORAGE: &str = "(() => {
+  try { localStorage.clear(); sessionStorage.clear(); } catch (e) {}
+  return true;
+})()";
+
+/// Build an expression writing an origin's storage into the current page
+pub(crate) fn restore_storage(storage: &OriginStorage) -> Result<String> {
+    let json = serde_json::to_string(storage)?;
+    Ok(format!(
+        "((s) => {{
+  s.localStorage.forEach((i) => localStorage.setItem(i.name, i.value));
+  s.sessionStorage.forEach((i) => sessionStorage.setItem(i.name, i.value));
+}})({})",
+        json
+    ))
+}
@@ -0,0 +1,965 @@
+//! Scriptable in-memory browser for tests
+//!
+//! [`MockBrowser`] serves HTML fixtures from memory, resolves selectors
+//! against them like the static backend does, and records every call so
+//! tests can assert on what an automation did. Clones share state, so a test
+//! can keep a handle while the browser itself is boxed into a session.
+
+use super::actionability;
+use super::dom::Dom;
+use super::download::unique_path;
+use super::{
+    route_request, AccessibilityNode, Browser, BrowserConfig, BrowserType, Dialog, DialogAction, DialogKind,
+    DialogPolicy, Download, ElementState, HandledDialog, HarEntry, HarOptions, HarRequest, HarResponse, HarTimings,
+    InterceptedRequest, KeyChord, PageEvent, PageId, PageInfo, ResourceType, Route, RouteAction, RouteResponse,
+    Selector, StorageState,
+};
+use crate::error::{Error, Result};
+use ego_tree::NodeId;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+/// URL of the page loaded by [`MockBrowser::from_html`]
+pub const FIXTURE_URL: &str = "about:blank";
+
+/// A call made to the mock browser
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockAction {
+    /// `navigate`
+    Navigate(String),
+    /// `click`
+    Click(Selector),
+    /// `type_text`
+    TypeText(Selector, String),
+    /// `hover`
+    Hover(Selector),
+    /// `double_click`
+    DoubleClick(Selector),
+    /// `right_click`
+    RightClick(Selector),
+    /// `select_option`
+    SelectOption(Selector, String),
+    /// `set_checked`
+    SetChecked(Selector, bool),
+    /// `set_input_files`
+    SetInputFiles(Selector, Vec<PathBuf>),
+    /// `scroll_into_view`
+    ScrollIntoView(Selector),
+    /// `scroll_by`
+    ScrollBy(f64, f64),
+    /// `drag_and_drop`, with the source and target
+    DragAndDrop(Selector, Selector),
+    /// `press_key`, with the chord as written by `KeyChord`'s `Display`
+    PressKey(String),
+    /// `get_text`
+    GetText(Selector),
+    /// `get_attributes`
+    GetAttributes(Selector),
+    /// `element_exists`
+    ElementExists(Selector),
+    /// `wait_for_element`
+    WaitForElement(Selector),
+    /// `wait_for_navigation`
+    WaitForNavigation,
+    /// `take_screenshot`
+    TakeScreenshot(String),
+    /// `execute_js`
+    ExecuteJs(String),
+    /// `get_html`
+    GetHtml,
+    /// `accessibility_snapshot`
+    AccessibilitySnapshot,
+    /// `new_page`
+    NewPage(Option<String>),
+    /// `switch_to_page`
+    SwitchToPage(PageId),
+    /// `close_page`
+    ClosePage(PageId),
+    /// `storage_state`
+    StorageState,
+    /// `set_storage_state`
+    SetStorageState,
+    /// `set_routes`
+    SetRoutes,
+    /// `set_har_recording`, with whether recording is on
+    SetHarRecording(bool),
+    /// `set_dialog_policy`
+    SetDialogPolicy,
+    /// `wait_for_download`
+    WaitForDownload,
+    /// `reset`
+    Reset,
+    /// `close`
+    Close,
+}
+
+impl MockAction {
+    /// The selector the action targeted, if any
+    pub fn selector(&self) -> Option<&Selector> {
+        match self {
+            MockAction::Click(selector)
+            | MockAction::TypeText(selector, _)
+            | MockAction::Hover(selector)
+            | MockAction::DoubleClick(selector)
+            | MockAction::RightClick(selector)
+            | MockAction::SelectOption(selector, _)
+            | MockAction::SetChecked(selector, _)
+            | MockAction::SetInputFiles(selector, _)
+            | MockAction::ScrollIntoView(selector)
+            | MockAction::DragAndDrop(selector, _)
+            | MockAction::GetText(selector)
+            | MockAction::GetAttributes(selector)
+            | MockAction::ElementExists(selector)
+            | MockAction::WaitForElement(selector) => Some(selector),
+            _ => None,
+        }
+    }
+}
+
+/// Factory for injected errors
+type ErrorFactory = Arc<dyn Fn() -> Error + Send + Sync>;
+
+/// An injected failure
+struct Failure {
+    /// Remaining failures, or `None` to fail forever
+    remaining: Option<usize>,
+    /// Builds the error to return
+    error: ErrorFactory,
+}
+
+/// A download started by [`MockBrowser::start_download`]
+struct PendingDownload {
+    url: String,
+    suggested_filename: String,
+    mime_type: String,
+    body: Vec<u8>,
+}
+
+/// An open tab
+struct MockTab {
+    /// The page id
+    id: PageId,
+    /// Current URL
+    url: String,
+    /// Current HTML
+    html: String,
+    /// Values typed into form controls on the current page
+    values: HashMap<NodeId, String>,
+    /// Checkbox and radio states set on the current page
+    checked: HashMap<NodeId, bool>,
+    /// Files chosen in file inputs on the current page
+    files: HashMap<NodeId, Vec<PathBuf>>,
+    /// The tab that opened this one
+    opener: Option<PageId>,
+}
+
+/// Shared mock state
+struct MockState {
+    /// Fixture pages by URL
+    pages: HashMap<String, String>,
+    /// Open tabs, in the order they were opened
+    tabs: Vec<MockTab>,
+    /// Index of the active tab
+    active: usize,
+    /// Number of tabs opened so far
+    opened: usize,
+    /// Page events not yet taken
+    events: Vec<PageEvent>,
+    /// Cookies and web storage
+    storage: StorageState,
+    /// Request routes, oldest first
+    routes: Vec<Route>,
+    /// Requests made so far
+    requests: Vec<InterceptedRequest>,
+    /// HAR recording options, while recording
+    har: Option<HarOptions>,
+    /// Recorded requests not yet taken
+    har_entries: Vec<HarEntry>,
+    /// How dialogs are answered
+    dialog_policy: DialogPolicy,
+    /// Dialogs answered but not yet taken
+    dialogs: Vec<HandledDialog>,
+    /// Where downloads are saved
+    downloads_dir: PathBuf,
+    /// Downloads started but not yet waited for
+    downloads: VecDeque<PendingDownload>,
+    /// Results returned by `execute_js`, by script
+    scripts: HashMap<String, serde_json::Value>,
+    /// Injected failures, by selector
+    failures: HashMap<Selector, Failure>,
+    /// Injected delays, by selector
+    delays: HashMap<Selector, Duration>,
+    /// Every call made, in order
+    actions: Vec<MockAction>,
+    /// Whether the browser has been closed
+    closed: bool,
+}
+
+impl MockState {
+    /// The active tab
+    fn tab(&self) -> &MockTab {
+        &self.tabs[self.active]
+    }
+
+    fn tab_mut(&mut self) -> &mut MockTab {
+        let active = self.active;
+        &mut self.tabs[active]
+    }
+
+    /// Open a blank tab and return its index
+    fn open_tab(&mut self, opener: Option<PageId>) -> usize {
+        self.opened += 1;
+        self.tabs.push(MockTab {
+            id: PageId::new(format!("tab-{}", self.opened)),
+            url: FIXTURE_URL.to_string(),
+            html: String::new(),
+            values: HashMap::new(),
+            checked: HashMap::new(),
+            files: HashMap::new(),
+            opener,
+        });
+        self.tabs.len() - 1
+    }
+
+    fn index_of(&self, page: &PageId) -> Result<usize> {
+        self.tabs
+            .iter()
+            .position(|tab| tab.id == *page)
+            .ok_or_else(|| Error::BrowserError(format!("Unknown page {}", page)))
+    }
+
+    /// Load a fixture page into a tab
+    fn load(&mut self, index: usize, url: &str) -> Result<()> {
+        let html = self
+            .request("GET", url, ResourceType::Document)?
+            .ok_or_else(|| Error::NetworkError(format!("No mock page registered for {}", url)))?
+            .text();
+        let tab = &mut self.tabs[index];
+        tab.url = url.to_string();
+        tab.html = html;
+        tab.values.clear();
+        tab.checked.clear();
+        tab.files.clear();
+        Ok(())
+    }
+
+    /// Send a request through the routes, falling back to the fixture pages
+    ///
+    /// Gives `None` when neither a route nor a fixture page answers.
+    fn request(&mut self, method: &str, url: &str, resource_type: ResourceType) -> Result<Option<RouteResponse>> {
+        let request = InterceptedRequest {
+            url: url.to_string(),
+            method: method.to_ascii_uppercase(),
+            resource_type,
+            headers: Vec::new(),
+        };
+        let action = route_request(&self.routes, &request).cloned();
+        self.requests.push(request);
+
+        let response = match action {
+            Some(RouteAction::Fulfill(response)) => Ok(Some(response)),
+            Some(RouteAction::Abort) => Err(Error::NetworkError(format!("Request to {} was aborted", url))),
+            _ => Ok(self.pages.get(url).map(|html| RouteResponse::html(html.clone()))),
+        };
+
+        if let Some(options) = self.har {
+            let recorded = match &response {
+                Ok(Some(response)) => {
+                    HarResponse::new(response.status, &response.headers, &response.body, options.include_bodies)
+                }
+                Ok(None) => HarResponse::new(404, &[], &[], false),
+                Err(e) => HarResponse::failed(e.to_string()),
+            };
+            self.har_entries.push(HarEntry::new(
+                SystemTime::now(),
+                HarRequest::new(method, url, &[]),
+                recorded,
+                HarTimings::default(),
+            ));
+        }
+        response
+    }
+
+    /// Open `url` in a new tab on behalf of the active page
+    fn open_popup(&mut self, url: &str) -> Result<PageId> {
+        let opener = self.tab().id.clone();
+        let index = self.open_tab(Some(opener.clone()));
+        if let Err(e) = self.load(index, url) {
+            self.tabs.remove(index);
+            return Err(e);
+        }
+
+        let page = self.tabs[index].id.clone();
+        self.events.push(PageEvent::Opened {
+            page: page.clone(),
+            opener: Some(opener),
+            url: url.to_string(),
+        });
+        Ok(page)
+    }
+}
+
+/// In-memory browser serving HTML fixtures
+#[derive(Clone)]
+pub struct MockBrowser {
+    /// The browser type reported to callers
+    browser_type: BrowserType,
+    /// Shared state
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockBrowser {
+    /// Create an empty mock browser with no pages
+    pub fn new() -> Self {
+        let mut state = MockState {
+            pages: HashMap::new(),
+            tabs: Vec::new(),
+            active: 0,
+            opened: 0,
+            events: Vec::new(),
+            storage: StorageState::default(),
+            routes: Vec::new(),
+            requests: Vec::new(),
+            har: None,
+            har_entries: Vec::new(),
+            dialog_policy: DialogPolicy::default(),
+            dialogs: Vec::new(),
+            downloads_dir: BrowserConfig::default().download_dir(),
+            downloads: VecDeque::new(),
+            scripts: HashMap::new(),
+            failures: HashMap::new(),
+            delays: HashMap::new(),
+            actions: Vec::new(),
+            closed: false,
+        };
+        state.open_tab(None);
+
+        Self {
+            browser_type: BrowserType::Chrome,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Create a mock browser with a single fixture already loaded
+    pub fn from_html(html: impl Into<String>) -> Self {
+        let browser = Self::new();
+        {
+            let mut state = browser.state();
+            state.tab_mut().html = html.into();
+            let html = state.tab().html.clone();
+            state.pages.insert(FIXTURE_URL.to_string(), html);
+        }
+        browser
+    }
+
+    /// Create a mock browser serving the given URL to HTML map
+    pub fn from_pages<K, V>(pages: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let browser = Self::new();
+        for (url, html) in pages {
+            browser.state().pages.insert(url.into(), html.into());
+        }
+        browser
+    }
+
+    /// Add a page served at `url`
+    pub fn with_page(self, url: impl Into<String>, html: impl Into<String>) -> Self {
+        self.state().pages.insert(url.into(), html.into());
+        self
+    }
+
+    /// Report a different browser type
+    pub fn with_browser_type(mut self, browser_type: BrowserType) -> Self {
+        self.browser_type = browser_type;
+        self
+    }
+
+    /// Save downloads into `dir` instead of the default downloads directory
+    pub fn with_downloads_dir(self, dir: impl Into<PathBuf>) -> Self {
+        self.state().downloads_dir = dir.into();
+        self
+    }
+
+    /// Return `value` whenever `script` is executed
+    pub fn with_script_result(self, script: impl Into<String>, value: serde_json::Value) -> Self {
+        self.state().scripts.insert(script.into(), value);
+        self
+    }
+
+    /// Make every action on `selector` fail with the error built by `error`
+    pub fn fail_on(&self, selector: Selector, error: impl Fn() -> Error + Send + Sync + 'static) {
+        self.state().failures.insert(selector, Failure { remaining: None, error: Arc::new(error) });
+    }
+
+    /// Make the next `times` actions on `selector` fail, then succeed again
+    pub fn fail_times(&self, selector: Selector, times: usize, error: impl Fn() -> Error + Send + Sync + 'static) {
+        self.state().failures.insert(selector, Failure { remaining: Some(times), error: Arc::new(error) });
+    }
+
+    /// Delay every action on `selector` by `delay`
+    pub fn delay_on(&self, selector: Selector, delay: Duration) {
+        self.state().delays.insert(selector, delay);
+    }
+
+    /// Remove injected failures and delays for `selector`
+    pub fn clear_injections(&self, selector: &Selector) {
+        let mut state = self.state();
+        state.failures.remove(selector);
+        state.delays.remove(selector);
+    }
+
+    /// Every call made so far, in order
+    pub fn actions(&self) -> Vec<MockAction> {
+        self.state().actions.clone()
+    }
+
+    /// Forget recorded calls
+    pub fn clear_actions(&self) {
+        self.state().actions.clear();
+    }
+
+    /// Number of clicks on `selector`
+    pub fn click_count(&self, selector: &Selector) -> usize {
+        self.count(|action| matches!(action, MockAction::Click(s) if s == selector))
+    }
+
+    /// Texts typed into `selector`, in order
+    pub fn typed_text(&self, selector: &Selector) -> Vec<String> {
+        self.state()
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                MockAction::TypeText(s, text) if s == selector => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Key chords pressed, in order
+    pub fn pressed_keys(&self) -> Vec<String> {
+        self.state()
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                MockAction::PressKey(chord) => Some(chord.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// URLs navigated to, in order
+    pub fn navigations(&self) -> Vec<String> {
+        self.state()
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                MockAction::Navigate(url) => Some(url.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Assert that `selector` was clicked exactly `times` times
+    #[track_caller]
+    pub fn assert_clicked_times(&self, selector: &Selector, times: usize) {
+        let count = self.click_count(selector);
+        assert_eq!(
+            count, times,
+            "expected {:?} to be clicked {} time(s), but it was clicked {} time(s); actions: {:#?}",
+            selector, times, count, self.actions()
+        );
+    }
+
+    /// Assert that `selector` was clicked exactly once
+    #[track_caller]
+    pub fn assert_clicked_once(&self, selector: &Selector) {
+        self.assert_clicked_times(selector, 1);
+    }
+
+    /// Assert that `selector` was never clicked
+    #[track_caller]
+    pub fn assert_not_clicked(&self, selector: &Selector) {
+        self.assert_clicked_times(selector, 0);
+    }
+
+    /// Assert that `text` was typed into `selector`
+    #[track_caller]
+    pub fn assert_typed(&self, selector: &Selector, text: &str) {
+        let typed = self.typed_text(selector);
+        assert!(
+            typed.iter().any(|t| t == text),
+            "expected {:?} to be typed into {:?}, typed: {:?}",
+            text, selector, typed
+        );
+    }
+
+    /// Assert that the browser navigated to `url`
+    #[track_caller]
+    pub fn assert_navigated_to(&self, url: &str) {
+        let navigations = self.navigations();
+        assert!(
+            navigations.iter().any(|u| u == url),
+            "expected a navigation to {}, navigations: {:?}",
+            url, navigations
+        );
+    }
+
+    /// Make a request as if the current page called `fetch`
+    ///
+    /// Routes apply as in a real browser; unrouted requests are answered from
+    /// the fixture pages, or with a 404.
+    pub fn fetch(&self, method: &str, url: &str) -> Result<RouteResponse> {
+        let response = self.state().request(method, url, ResourceType::Fetch)?;
+        Ok(response.unwrap_or_else(|| RouteResponse::new(404, Vec::new())))
+    }
+
+    /// Requests made so far, including navigations
+    pub fn requests(&self) -> Vec<InterceptedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Open `url` in a new tab as if the current page called `window.open`
+    ///
+    /// The popup stays in the background and is reported as a page event.
+    pub fn open_popup(&self, url: &str) -> Result<PageId> {
+        self.state().open_popup(url)
+    }
+
+    /// Open a dialog as if the current page called `alert()`, `confirm()` or `prompt()`
+    ///
+    /// The dialog policy answers it, and the answer is returned.
+    pub fn open_dialog(&self, kind: DialogKind, message: &str) -> DialogAction {
+        let mut state = self.state();
+        let dialog = Dialog {
+            kind,
+            message: message.to_string(),
+            default_value: (kind == DialogKind::Prompt).then(String::new),
+            url: state.tab().url.clone(),
+        };
+        let action = state.dialog_policy.answer(&dialog);
+        state.dialogs.push(HandledDialog {
+            dialog,
+            action: action.clone(),
+        });
+        action
+    }
+
+    /// Start a download of `body`, as if the current page linked to `url`
+    ///
+    /// The file is written when [`Browser::wait_for_download`] picks it up.
+    pub fn start_download(&self, url: &str, suggested_filename: &str, mime_type: &str, body: impl Into<Vec<u8>>) {
+        self.state().downloads.push_back(PendingDownload {
+            url: url.to_string(),
+            suggested_filename: suggested_filename.to_string(),
+            mime_type: mime_type.to_string(),
+            body: body.into(),
+        });
+    }
+
+    /// Files chosen in the file input matching `selector`
+    pub fn input_files(&self, selector: &Selector) -> Vec<PathBuf> {
+        let state = self.state();
+        let Ok(id) = Dom::parse(&state.tab().html).select_first(selector) else {
+            return Vec::new();
+        };
+        state.tab().files.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn count(&self, predicate: impl Fn(&MockAction) -> bool) -> usize {
+        self.state().actions.iter().filter(|action| predicate(action)).count()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // A panicking assertion must not make every later call panic too
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record an action, then apply injected delays and failures
+    fn record(&self, action: MockAction) -> Result<MutexGuard<'_, MockState>> {
+        let delay = {
+            let mut state = self.state();
+            if state.closed && action != MockAction::Close {
+                return Err(Error::BrowserError("Browser has been closed".to_string()));
+            }
+            let selector = action.selector().cloned();
+            state.actions.push(action);
+
+            if let Some(selector) = selector {
+                if let Some(failure) = state.failures.get_mut(&selector) {
+                    let error = (failure.error)();
+                    match failure.remaining.as_mut() {
+                        None => return Err(error),
+                        Some(0) => {}
+                        Some(remaining) => {
+                            *remaining -= 1;
+                            return Err(error);
+                        }
+                    }
+                }
+                state.delays.get(&selector).copied()
+            } else {
+                None
+            }
+        };
+
+        if let Some(delay) = delay {
+            std::thread::sleep(delay);
+        }
+        Ok(self.state())
+    }
+
+    /// Resolve a link target against the current URL
+    fn resolve(state: &MockState, href: &str) -> String {
+        match url::Url::parse(&state.tab().url).and_then(|base| base.join(href)) {
+            Ok(url) if !state.pages.contains_key(href) => url.to_string(),
+            _ => href.to_string(),
+        }
+    }
+}
+
+impl Browser for MockBrowser {
+    fn browser_type(&self) -> BrowserType {
+        self.browser_type
+    }
+
+    fn navigate(&mut self, url: &str) -> Result<()> {
+        let mut state = self.record(MockAction::Navigate(url.to_string()))?;
+        let active = state.active;
+        state.load(active, url)
+    }
+
+    fn current_url(&self) -> Result<String> {
+        Ok(self.state().tab().url.clone())
+    }
+
+    fn click(&mut self, selector: &Selector) -> Result<()> {
+        let mut state = self.record(MockAction::Click(selector.clone()))?;
+        let dom = Dom::parse(&state.tab().html);
+        let id = dom.select_first(selector)?;
+
+        // Links to other fixture pages are followed, in a new tab for `target="_blank"`
+        if let Some(link) = dom.closest(id, "a") {
+            let attributes = dom.attributes(link);
+            if let Some(href) = attributes.get("href") {
+                let target = Self::resolve(&state, href);
+                if state.pages.contains_key(&target) {
+                    if attributes.get("target").map(String::as_str) == Some("_blank") {
+                        return state.open_popup(&target).map(|_| ());
+                    }
+                    let active = state.active;
+                    return state.load(active, &target);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn type_text(&mut self, selector: &Selector, text: &str) -> Result<()> {
+        let mut state = self.record(MockAction::TypeText(selector.clone(), text.to_string()))?;
+        let id = Dom::parse(&state.tab().html).select_first(selector)?;
+        state.tab_mut().values.insert(id, text.to_string());
+        Ok(())
+    }
+
+    fn hover(&mut self, selector: &Selector) -> Result<()> {
+        let state = self.record(MockAction::Hover(selector.clone()))?;
+        Dom::parse(&state.tab().html).select_first(selector).map(|_| ())
+    }
+
+    fn double_click(&mut self, selector: &Selector) -> Result<()> {
+        let state = self.record(MockAction::DoubleClick(selector.clone()))?;
+        Dom::parse(&state.tab().html).select_first(selector).map(|_| ())
+    }
+
+    fn right_click(&mut self, selector: &Selector) -> Result<()> {
+        let state = self.record(MockAction::RightClick(selector.clone()))?;
+        Dom::parse(&state.tab().html).select_first(selector).map(|_| ())
+    }
+
+    fn select_option(&mut self, selector: &Selector, choice: &str) -> Result<()> {
+        let mut state = self.record(MockAction::SelectOption(selector.clone(), choice.to_string()))?;
+        let dom = Dom::parse(&state.tab().html);
+        let id = dom.select_first(selector)?;
+        let option = dom
+            .find_option(id, choice)
+            .ok_or_else(|| Error::ElementNotFound(format!("Option '{}' in {:?}", choice, selector)))?;
+        state.tab_mut().values.insert(id, dom.option_value(option));
+        Ok(())
+    }
+
+    fn set_checked(&mut self, selector: &Selector, checked: bool) -> Result<()> {
+        let mut state = self.record(MockAction::SetChecked(selector.clone(), checked))?;
+        let id = Dom::parse(&state.tab().html).select_first(selector)?;
+        state.tab_mut().checked.insert(id, checked);
+        Ok(())
+    }
+
+    fn set_input_files(&mut self, selector: &Selector, files: &[PathBuf]) -> Result<()> {
+        let mut state = self.record(MockAction::SetInputFiles(selector.clone(), files.to_vec()))?;
+        let dom = Dom::parse(&state.tab().html);
+        let id = dom.select_first(selector)?;
+        let is_file_input = dom.tag(id) == "input"
+            && dom.attributes(id).get("type").is_some_and(|kind| kind.eq_ignore_ascii_case("file"));
+        if !is_file_input {
+            return Err(Error::BrowserError(format!("{:?} is not a file input", selector)));
+        }
+        state.tab_mut().files.insert(id, files.to_vec());
+        Ok(())
+    }
+
+    fn scroll_into_view(&mut self, selector: &Selector) -> Result<()> {
+        let state = self.record(MockAction::ScrollIntoView(selector.clone()))?;
+        Dom::parse(&state.tab().html).select_first(selector).map(|_| ())
+    }
+
+    fn scroll_by(&mut self, x: f64, y: f64) -> Result<()> {
+        self.record(MockAction::ScrollBy(x, y)).map(|_| ())
+    }
+
+    fn drag_and_drop(&mut self, source: &Selector, target: &Selector) -> Result<()> {
+        let state = self.record(MockAction::DragAndDrop(source.clone(), target.clone()))?;
+        let dom = Dom::parse(&state.tab().html);
+        dom.select_first(source)?;
+        dom.select_first(target).map(|_| ())
+    }
+
+    fn press_key(&mut self, chord: &KeyChord) -> Result<()> {
+        self.record(MockAction::PressKey(chord.to_string())).map(|_| ())
+    }
+
+    fn get_text(&self, selector: &Selector) -> Result<String> {
+        let state = self.record(MockAction::GetText(selector.clone()))?;
+        let dom = Dom::parse(&state.tab().html);
+        let id = dom.select_first(selector)?;
+
+        if let Some(value) = state.tab().values.get(&id) {
+            return Ok(value.clone());
+        }
+        match dom.tag(id).as_str() {
+            "input" => Ok(dom.attributes(id).get("value").cloned().unwrap_or_default()),
+            _ => Ok(dom.text(id)),
+        }
+    }
+
+    fn get_attributes(&self, selector: &Selector) -> Result<HashMap<String, String>> {
+        let state = self.record(MockAction::GetAttributes(selector.clone()))?;
+        let dom = Dom::parse(&state.tab().html);
+        let id = dom.select_first(selector)?;
+
+        let mut attributes = dom.attributes(id);
+        if let Some(value) = state.tab().values.get(&id) {
+            attributes.insert("value".to_string(), value.clone());
+        }
+        match state.tab().checked.get(&id) {
+            Some(true) => {
+                attributes.insert("checked".to_string(), String::new());
+            }
+            Some(false) => {
+                attributes.remove("checked");
+            }
+            None => {}
+        }
+        Ok(attributes)
+    }
+
+    fn element_exists(&self, selector: &Selector) -> Result<bool> {
+        let state = self.record(MockAction::ElementExists(selector.clone()))?;
+        Ok(!Dom::parse(&state.tab().html).select(selector)?.is_empty())
+    }
+
+    fn wait_for_element(&mut self, selector: &Selector, timeout: Duration) -> Result<()> {
+        let state = self.record(MockAction::WaitForElement(selector.clone()))?;
+        if Dom::parse(&state.tab().html).select(selector)?.is_empty() {
+            return Err(Error::TimeoutError(format!(
+                "Timed out after {:?} waiting for element {:?}",
+                timeout, selector
+            )));
+        }
+        Ok(())
+    }
+
+    // Actionability probes are not recorded, so action lists only show what the caller did
+    fn element_state(&self, selector: &Selector) -> Result<Option<ElementState>> {
+        let dom = Dom::parse(&self.state().tab().html);
+        Ok(dom.select(selector)?.first().map(|id| ElementState::from_dom(&dom, *id)))
+    }
+
+    fn wait_for_actionable(&mut self, selector: &Selector, _timeout: Duration) -> Result<()> {
+        // Fixture pages only change through actions, so there is nothing to wait for
+        let html = self.state().tab().html.clone();
+        actionability::check_once(&html, selector)
+    }
+
+    fn wait_for_navigation(&mut self, _timeout: Duration) -> Result<()> {
+        self.record(MockAction::WaitForNavigation).map(|_| ())
+    }
+
+    fn take_screenshot(&self, path: &str) -> Result<()> {
+        self.record(MockAction::TakeScreenshot(path.to_string())).map(|_| ())
+    }
+
+    fn execute_js(&mut self, script: &str) -> Result<serde_json::Value> {
+        let state = self.record(MockAction::ExecuteJs(script.to_string()))?;
+        Ok(state.scripts.get(script).cloned().unwrap_or(serde_json::Value::Null))
+    }
+
+    fn get_html(&self) -> Result<String> {
+        let state = self.record(MockAction::GetHtml)?;
+        Ok(state.tab().html.clone())
+    }
+
+    fn accessibility_snapshot(&self) -> Result<AccessibilityNode> {
+        let state = self.record(MockAction::AccessibilitySnapshot)?;
+        Ok(AccessibilityNode::from_html(&state.tab().html))
+    }
+
+    fn new_page(&mut self, url: Option<&str>) -> Result<PageId> {
+        let mut state = self.record(MockAction::NewPage(url.map(str::to_string)))?;
+        let index = state.open_tab(None);
+        if let Some(url) = url {
+            if let Err(e) = state.load(index, url) {
+                state.tabs.remove(index);
+                return Err(e);
+            }
+        }
+        state.active = index;
+        Ok(state.tab().id.clone())
+    }
+
+    fn pages(&self) -> Result<Vec<PageInfo>> {
+        let state = self.state();
+        Ok(state
+            .tabs
+            .iter()
+            .map(|tab| PageInfo {
+                id: tab.id.clone(),
+                url: tab.url.clone(),
+                opener: tab.opener.clone(),
+            })
+            .collect())
+    }
+
+    fn active_page(&self) -> Result<PageId> {
+        Ok(self.state().tab().id.clone())
+    }
+
+    fn switch_to_page(&mut self, page: &PageId) -> Result<()> {
+        let mut state = self.record(MockAction::SwitchToPage(page.clone()))?;
+        state.active = state.index_of(page)?;
+        Ok(())
+    }
+
+    fn close_page(&mut self, page: &PageId) -> Result<()> {
+        let mut state = self.record(MockAction::ClosePage(page.clone()))?;
+        let index = state.index_of(page)?;
+        if state.tabs.len() == 1 {
+            return Err(Error::BrowserError("Cannot close the last page".to_string()));
+        }
+
+        state.tabs.remove(index);
+        // Closing the active tab activates the most recently opened one
+        if index == state.active {
+            state.active = state.tabs.len() - 1;
+        } else if index < state.active {
+            state.active -= 1;
+        }
+        Ok(())
+    }
+
+    fn take_page_events(&mut self) -> Result<Vec<PageEvent>> {
+        Ok(std::mem::take(&mut self.state().events))
+    }
+
+    fn set_dialog_policy(&mut self, policy: DialogPolicy) -> Result<()> {
+        let mut state = self.record(MockAction::SetDialogPolicy)?;
+        state.dialog_policy = policy;
+        Ok(())
+    }
+
+    fn take_dialogs(&mut self) -> Result<Vec<HandledDialog>> {
+        Ok(std::mem::take(&mut self.state().dialogs))
+    }
+
+    fn wait_for_download(&mut self, timeout: Duration) -> Result<Download> {
+        let mut state = self.record(MockAction::WaitForDownload)?;
+        // Downloads only start through `start_download`, so there is nothing to wait for
+        let download = state
+            .downloads
+            .pop_front()
+            .ok_or_else(|| Error::TimeoutError(format!("Timed out after {:?} waiting for a download", timeout)))?;
+        let path = unique_path(&state.downloads_dir, &download.suggested_filename)?;
+        std::fs::write(&path, &download.body)?;
+        Ok(Download {
+            url: download.url,
+            suggested_filename: download.suggested_filename,
+            path,
+            mime_type: download.mime_type,
+        })
+    }
+
+    fn storage_state(&self) -> Result<StorageState> {
+        let state = self.record(MockAction::StorageState)?;
+        Ok(state.storage.clone())
+    }
+
+    fn set_storage_state(&mut self, storage: &StorageState) -> Result<()> {
+        let mut state = self.record(MockAction::SetStorageState)?;
+        state.storage.merge(storage);
+        Ok(())
+    }
+
+    fn set_routes(&mut self, routes: &[Route]) -> Result<()> {
+        let mut state = self.record(MockAction::SetRoutes)?;
+        state.routes = routes.to_vec();
+        Ok(())
+    }
+
+    fn set_har_recording(&mut self, options: Option<HarOptions>) -> Result<()> {
+        let mut state = self.record(MockAction::SetHarRecording(options.is_some()))?;
+        state.har = options;
+        Ok(())
+    }
+
+    fn take_har_entries(&mut self) -> Result<Vec<HarEntry>> {
+        Ok(std::mem::take(&mut self.state().har_entries))
+    }
+
+    fn health_check(&self) -> Result<()> {
+        if self.state().closed {
+            return Err(Error::BrowserError("Browser has been closed".to_string()));
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        let mut state = self.record(MockAction::Reset)?;
+        state.tabs.clear();
+        state.active = state.open_tab(None);
+        state.events.clear();
+        state.storage = StorageState::default();
+        state.routes.clear();
+        state.har = None;
+        state.har_entries.clear();
+        state.dialog_policy = DialogPolicy::default();
+        state.dialogs.clear();
+        state.downloads.clear();
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        let mut state = self.record(MockAction::Close)?;
+        state.closed = true;
+        Ok(())
+    }
+}
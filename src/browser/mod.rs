@@ -0,0 +1,1250 @@
+//! Browser automation module for LlamaClick
+//!
+//! This module provides interfaces and implementations for browser automation
+//! using headless browsers and various drivers.
+
+mod accessibility;
+mod actionability;
+mod async_browser;
+mod cdp;
+mod dialog;
+mod download;
+mod emulation;
+pub(crate) mod dom;
+mod har;
+mod healing;
+mod input;
+pub(crate) mod js;
+mod mock;
+mod page;
+mod pool;
+mod query;
+mod route;
+#[cfg(feature = "llm")]
+mod semantic;
+mod snapshot;
+mod static_http;
+mod storage;
+mod webdriver;
+
+pub use accessibility::{AccessibilityNode, AccessibilityState};
+pub use actionability::{ActionCheck, ActionOptions, ElementState};
+pub use async_browser::{AsyncBrowser, AsyncBrowserSession};
+pub use cdp::{CdpBrowser, CdpConnection, CdpEvent, CHROME_PATH_ENV};
+pub use dialog::{Dialog, DialogAction, DialogKind, DialogPolicy, HandledDialog};
+pub use download::{mime_type_for, Download};
+pub use emulation::{ColorScheme, Device, Emulation, Geolocation, DEVICES};
+pub use har::{
+    Har, HarContent, HarCreator, HarEntry, HarLog, HarNameValue, HarOptions, HarPostData, HarRequest, HarResponse,
+    HarTimings,
+};
+pub use healing::{ElementFingerprint, FingerprintStore, HealedSelector, SelfHealing, DEFAULT_HEALING_THRESHOLD};
+pub use input::{KeyChord, Modifier, MouseButton};
+pub use mock::{MockAction, MockBrowser, FIXTURE_URL};
+pub use page::{url_pattern, PageEvent, PageId, PageInfo, DEFAULT_PAGE_ID};
+pub use pool::{BrowserFactory, BrowserPool, PoolStats, PooledSession};
+pub use query::{Relation, DEFAULT_NEAR_DISTANCE};
+pub use route::{glob_match, route_request, InterceptedRequest, ResourceType, Route, RouteAction, RouteResponse};
+#[cfg(feature = "llm")]
+pub use semantic::SemanticResolver;
+pub use snapshot::{estimate_tokens, BoundingBox, PageSnapshot, SnapshotElement};
+pub use static_http::StaticBrowser;
+pub use storage::{Cookie, OriginStorage, StorageItem, StorageState, PROFILE_PASSPHRASE_ENV};
+pub use webdriver::{locator, Locator, WebDriverBrowser, ELEMENT_KEY, SHADOW_ROOT_KEY, WEBDRIVER_PATH_ENV};
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::PathBuf;
+#[cfg(feature = "llm")]
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Browser type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BrowserType {
+    /// Chrome browser
+    Chrome,
+    /// Firefox browser
+    Firefox,
+    /// Edge browser
+    Edge,
+    /// Safari browser
+    Safari,
+}
+
+impl fmt::Display for BrowserType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrowserType::Chrome => write!(f, "Chrome"),
+            BrowserType::Firefox => write!(f, "Firefox"),
+            BrowserType::Edge => write!(f, "Edge"),
+            BrowserType::Safari => write!(f, "Safari"),
+        }
+    }
+}
+
+/// Browser configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserConfig {
+    /// Browser type
+    pub browser_type: BrowserType,
+    /// Whether to run in headless mode
+    pub headless: bool,
+    /// Default timeout for operations
+    pub timeout: Duration,
+    /// User agent
+    pub user_agent: Option<String>,
+    /// Proxy URL
+    pub proxy: Option<String>,
+    /// Whether to ignore HTTPS errors
+    pub ignore_https_errors: bool,
+    /// Whether to block images
+    pub block_images: bool,
+    /// Window width
+    pub window_width: u32,
+    /// Window height
+    pub window_height: u32,
+    /// Device and environment emulation
+    #[serde(default)]
+    pub emulation: Emulation,
+    /// Directory downloads are saved to
+    #[serde(default)]
+    pub downloads_dir: Option<PathBuf>,
+}
+
+impl Default for BrowserConfig {
+    fn default() -> Self {
+        Self {
+            browser_type: BrowserType::Chrome,
+            headless: true,
+            timeout: Duration::from_secs(30),
+            user_agent: None,
+            proxy: None,
+            ignore_https_errors: false,
+            block_images: false,
+            window_width: 1280,
+            window_height: 800,
+            emulation: Emulation::default(),
+            downloads_dir: None,
+        }
+    }
+}
+
+impl BrowserConfig {
+    /// Create a new browser configuration
+    pub fn new(browser_type: BrowserType) -> Self {
+        Self {
+            browser_type,
+            ..Default::default()
+        }
+    }
+    
+    /// Set whether to run in headless mode
+    pub fn with_headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+    
+    /// Set the default timeout for operations
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Set the user agent
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+    
+    /// Set the proxy URL
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+    
+    /// Set whether to ignore HTTPS errors
+    pub fn with_ignore_https_errors(mut self, ignore_https_errors: bool) -> Self {
+        self.ignore_https_errors = ignore_https_errors;
+        self
+    }
+    
+    /// Set whether to block images
+    pub fn with_block_images(mut self, block_images: bool) -> Self {
+        self.block_images = block_images;
+        self
+    }
+    
+    /// Set the window size
+    pub fn with_window_size(mut self, width: u32, height: u32) -> Self {
+        self.window_width = width;
+        self.window_height = height;
+        self
+    }
+    
+    /// Emulate a device preset: its window size, user agent, pixel density, mobile flag and touch
+    pub fn with_device(mut self, device: &Device) -> Self {
+        self.window_width = device.width;
+        self.window_height = device.height;
+        self.user_agent = Some(device.user_agent.to_string());
+        self.emulation.device_scale_factor = Some(device.device_scale_factor);
+        self.emulation.mobile = device.mobile;
+        self.emulation.touch = device.touch;
+        self
+    }
+    
+    /// Set the emulation settings
+    pub fn with_emulation(mut self, emulation: Emulation) -> Self {
+        self.emulation = emulation;
+        self
+    }
+    
+    /// Set the number of device pixels per CSS pixel
+    pub fn with_device_scale_factor(mut self, device_scale_factor: f64) -> Self {
+        self.emulation.device_scale_factor = Some(device_scale_factor);
+        self
+    }
+    
+    /// Set whether to emulate a mobile browser
+    pub fn with_mobile(mut self, mobile: bool) -> Self {
+        self.emulation.mobile = mobile;
+        self
+    }
+    
+    /// Set whether to report a touch screen
+    pub fn with_touch(mut self, touch: bool) -> Self {
+        self.emulation.touch = touch;
+        self
+    }
+    
+    /// Set the locale, e.g. `de-DE`
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.emulation.locale = Some(locale.into());
+        self
+    }
+    
+    /// Set the timezone, e.g. `Europe/Berlin`
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.emulation.timezone = Some(timezone.into());
+        self
+    }
+    
+    /// Set the position reported to the Geolocation API
+    pub fn with_geolocation(mut self, geolocation: Geolocation) -> Self {
+        self.emulation.geolocation = Some(geolocation);
+        self
+    }
+    
+    /// Set the preferred colour scheme
+    pub fn with_color_scheme(mut self, color_scheme: ColorScheme) -> Self {
+        self.emulation.color_scheme = Some(color_scheme);
+        self
+    }
+    
+    /// Set whether the network is cut off
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.emulation.offline = offline;
+        self
+    }
+    
+    /// Set the directory downloads are saved to
+    pub fn with_downloads_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.downloads_dir = Some(dir.into());
+        self
+    }
+    
+    /// The absolute directory downloads are saved to, `llamaclick-downloads` in the temp dir unless set
+    pub fn download_dir(&self) -> PathBuf {
+        match &self.downloads_dir {
+            Some(dir) if dir.is_relative() => std::env::current_dir().map_or_else(|_| dir.clone(), |cwd| cwd.join(dir)),
+            Some(dir) => dir.clone(),
+            None => std::env::temp_dir().join("llamaclick-downloads"),
+        }
+    }
+}
+
+/// Element selector
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Selector {
+    /// CSS selector
+    Css(String),
+    /// XPath selector
+    XPath(String),
+    /// Text selector
+    Text(String),
+    /// ID selector
+    Id(String),
+    /// Class selector
+    Class(String),
+    /// Name selector
+    Name(String),
+    /// Semantic selector using AI for matching
+    Semantic(String),
+    /// The inner selector, matched inside the elements the scope selector matches
+    ///
+    /// A scope matching an `<iframe>` enters the frame's document, and one
+    /// matching a shadow host enters its open shadow root; any other scope
+    /// limits the search to the element's descendants.
+    Within(Box<Selector>, Box<Selector>),
+    /// Matches of the first selector in a layout relation to a match of the anchor (last) selector
+    ///
+    /// Needs a rendered page. Matches are ordered by their distance to the
+    /// nearest anchor.
+    Relative(Box<Selector>, Relation, Box<Selector>),
+    /// The match at an index, counting from 0
+    Nth(Box<Selector>, usize),
+    /// Matches whose text contains the string
+    HasText(Box<Selector>, String),
+    /// Matches that are visible
+    Visible(Box<Selector>),
+    /// Matches of any of the selectors, in document order
+    Or(Vec<Selector>),
+}
+
+impl Selector {
+    /// Create a new CSS selector
+    pub fn css(selector: impl Into<String>) -> Self {
+        Self::Css(selector.into())
+    }
+    
+    /// Create a new XPath selector
+    pub fn xpath(selector: impl Into<String>) -> Self {
+        Self::XPath(selector.into())
+    }
+    
+    /// Create a new text selector
+    pub fn text(selector: impl Into<String>) -> Self {
+        Self::Text(selector.into())
+    }
+    
+    /// Create a new ID selector
+    pub fn id(selector: impl Into<String>) -> Self {
+        Self::Id(selector.into())
+    }
+    
+    /// Create a new class selector
+    pub fn class(selector: impl Into<String>) -> Self {
+        Self::Class(selector.into())
+    }
+    
+    /// Create a new name selector
+    pub fn name(selector: impl Into<String>) -> Self {
+        Self::Name(selector.into())
+    }
+    
+    /// Create a new semantic selector
+    pub fn semantic(selector: impl Into<String>) -> Self {
+        Self::Semantic(selector.into())
+    }
+    
+    /// Create a selector matching `inner` inside a frame, shadow host or element matched by `scope`
+    pub fn within(scope: Selector, inner: Selector) -> Self {
+        Self::Within(Box::new(scope), Box::new(inner))
+    }
+    
+    /// Keep matches within [`DEFAULT_NEAR_DISTANCE`] pixels of an anchor
+    pub fn near(self, anchor: Selector) -> Self {
+        self.relative(Relation::Near(DEFAULT_NEAR_DISTANCE), anchor)
+    }
+    
+    /// Keep matches above an anchor
+    pub fn above(self, anchor: Selector) -> Self {
+        self.relative(Relation::Above, anchor)
+    }
+    
+    /// Keep matches below an anchor
+    pub fn below(self, anchor: Selector) -> Self {
+        self.relative(Relation::Below, anchor)
+    }
+    
+    /// Keep matches left of an anchor
+    pub fn left_of(self, anchor: Selector) -> Self {
+        self.relative(Relation::LeftOf, anchor)
+    }
+    
+    /// Keep matches right of an anchor
+    pub fn right_of(self, anchor: Selector) -> Self {
+        self.relative(Relation::RightOf, anchor)
+    }
+    
+    /// Keep matches in a layout relation to an anchor
+    pub fn relative(self, relation: Relation, anchor: Selector) -> Self {
+        Self::Relative(Box::new(self), relation, Box::new(anchor))
+    }
+    
+    /// Keep only the match at an index, counting from 0
+    pub fn nth(self, index: usize) -> Self {
+        Self::Nth(Box::new(self), index)
+    }
+    
+    /// Keep matches whose text contains `text`
+    pub fn has_text(self, text: impl Into<String>) -> Self {
+        Self::HasText(Box::new(self), text.into())
+    }
+    
+    /// Keep visible matches
+    pub fn visible(self) -> Self {
+        Self::Visible(Box::new(self))
+    }
+    
+    /// Match this selector or another
+    pub fn or(self, other: Selector) -> Self {
+        match self {
+            Self::Or(mut selectors) => {
+                selectors.push(other);
+                Self::Or(selectors)
+            }
+            first => Self::Or(vec![first, other]),
+        }
+    }
+}
+
+/// Browser interface
+pub trait Browser: Send + Sync {
+    /// Get the browser type
+    fn browser_type(&self) -> BrowserType;
+    
+    /// Navigate to a URL
+    fn navigate(&mut self, url: &str) -> Result<()>;
+    
+    /// Get the current URL
+    fn current_url(&self) -> Result<String>;
+    
+    /// Click on an element
+    fn click(&mut self, selector: &Selector) -> Result<()>;
+    
+    /// Type text into an element
+    fn type_text(&mut self, selector: &Selector, text: &str) -> Result<()>;
+    
+    /// Get text from an element
+    fn get_text(&self, selector: &Selector) -> Result<String>;
+    
+    /// Get attributes of an element
+    fn get_attributes(&self, selector: &Selector) -> Result<std::collections::HashMap<String, String>>;
+    
+    /// Check if an element exists
+    fn element_exists(&self, selector: &Selector) -> Result<bool>;
+    
+    /// Wait for an element to be visible
+    fn wait_for_element(&mut self, selector: &Selector, timeout: Duration) -> Result<()>;
+    
+    /// Wait for navigation to complete
+    fn wait_for_navigation(&mut self, timeout: Duration) -> Result<()>;
+    
+    /// Take a screenshot
+    fn take_screenshot(&self, path: &str) -> Result<()>;
+    
+    /// Execute JavaScript
+    fn execute_js(&mut self, script: &str) -> Result<serde_json::Value>;
+    
+    /// Get page HTML
+    fn get_html(&self) -> Result<String>;
+    
+    /// Get the accessibility tree of the page
+    ///
+    /// The default implementation computes the tree from [`Browser::get_html`].
+    fn accessibility_snapshot(&self) -> Result<AccessibilityNode> {
+        Ok(AccessibilityNode::from_html(&self.get_html()?))
+    }
+    
+    /// Probe the first element matching a selector for the actionability checks
+    ///
+    /// Returns `None` when nothing matches. The default implementation reads
+    /// [`Browser::get_html`], so the element is never obscured or measured.
+    fn element_state(&self, selector: &Selector) -> Result<Option<ElementState>> {
+        let dom = dom::Dom::parse(&self.get_html()?);
+        Ok(dom.select(selector)?.first().map(|id| ElementState::from_dom(&dom, *id)))
+    }
+    
+    /// Wait until an element is attached, visible, stable, enabled and unobscured
+    ///
+    /// The timeout error names the check that was still failing.
+    fn wait_for_actionable(&mut self, selector: &Selector, timeout: Duration) -> Result<()> {
+        actionability::wait(selector, timeout, || self.element_state(selector))
+    }
+    
+    /// Move the mouse over an element
+    fn hover(&mut self, selector: &Selector) -> Result<()> {
+        let _ = selector;
+        Err(Error::Unsupported("Hovering is not supported by this browser".to_string()))
+    }
+    
+    /// Double-click an element
+    fn double_click(&mut self, selector: &Selector) -> Result<()> {
+        let _ = selector;
+        Err(Error::Unsupported("Double-clicking is not supported by this browser".to_string()))
+    }
+    
+    /// Right-click an element
+    fn right_click(&mut self, selector: &Selector) -> Result<()> {
+        let _ = selector;
+        Err(Error::Unsupported("Right-clicking is not supported by this browser".to_string()))
+    }
+    
+    /// Choose the option of a `<select>` whose value, or else whose label, is `choice`
+    fn select_option(&mut self, selector: &Selector, choice: &str) -> Result<()> {
+        let _ = (selector, choice);
+        Err(Error::Unsupported("Selecting options is not supported by this browser".to_string()))
+    }
+    
+    /// Check or uncheck a checkbox or radio button, clicking it only if needed
+    fn set_checked(&mut self, selector: &Selector, checked: bool) -> Result<()> {
+        let _ = (selector, checked);
+        Err(Error::Unsupported("Setting checked state is not supported by this browser".to_string()))
+    }
+    
+    /// Set the files chosen in a file input
+    fn set_input_files(&mut self, selector: &Selector, files: &[PathBuf]) -> Result<()> {
+        let _ = (selector, files);
+        Err(Error::Unsupported("File uploads are not supported by this browser".to_string()))
+    }
+    
+    /// Scroll the page so an element is in view
+    fn scroll_into_view(&mut self, selector: &Selector) -> Result<()> {
+        let _ = selector;
+        Err(Error::Unsupported("Scrolling is not supported by this browser".to_string()))
+    }
+    
+    /// Scroll the page by the given number of pixels
+    fn scroll_by(&mut self, x: f64, y: f64) -> Result<()> {
+        let _ = (x, y);
+        Err(Error::Unsupported("Scrolling is not supported by this browser".to_string()))
+    }
+    
+    /// Drag one element and drop it onto another
+    fn drag_and_drop(&mut self, source: &Selector, target: &Selector) -> Result<()> {
+        let _ = (source, target);
+        Err(Error::Unsupported("Drag and drop is not supported by this browser".to_string()))
+    }
+    
+    /// Press a key chord in the focused element
+    fn press_key(&mut self, chord: &KeyChord) -> Result<()> {
+        let _ = chord;
+        Err(Error::Unsupported("Key presses are not supported by this browser".to_string()))
+    }
+    
+    /// Open a new page, optionally loading a URL, and make it active
+    fn new_page(&mut self, url: Option<&str>) -> Result<PageId> {
+        let _ = url;
+        Err(Error::Unsupported("Multiple pages are not supported by this browser".to_string()))
+    }
+    
+    /// List the open pages
+    fn pages(&self) -> Result<Vec<PageInfo>> {
+        Ok(vec![PageInfo {
+            id: self.active_page()?,
+            url: self.current_url()?,
+            opener: None,
+        }])
+    }
+    
+    /// The page that operations currently target
+    fn active_page(&self) -> Result<PageId> {
+        Ok(PageId::new(DEFAULT_PAGE_ID))
+    }
+    
+    /// Make a page the target of subsequent operations
+    fn switch_to_page(&mut self, page: &PageId) -> Result<()> {
+        if *page == self.active_page()? {
+            return Ok(());
+        }
+        Err(Error::Unsupported("Multiple pages are not supported by this browser".to_string()))
+    }
+    
+    /// Close a page; closing the active page activates the most recently opened remaining one
+    fn close_page(&mut self, page: &PageId) -> Result<()> {
+        let _ = page;
+        Err(Error::Unsupported("Multiple pages are not supported by this browser".to_string()))
+    }
+    
+    /// Take the page events that occurred since the last call
+    fn take_page_events(&mut self) -> Result<Vec<PageEvent>> {
+        Ok(Vec::new())
+    }
+    
+    /// Set how JavaScript dialogs are answered
+    fn set_dialog_policy(&mut self, policy: DialogPolicy) -> Result<()> {
+        let _ = policy;
+        Err(Error::Unsupported("Dialog handling is not supported by this browser".to_string()))
+    }
+    
+    /// Take the dialogs answered since the last call
+    fn take_dialogs(&mut self) -> Result<Vec<HandledDialog>> {
+        Ok(Vec::new())
+    }
+    
+    /// Wait for a download to finish, returning the saved file
+    fn wait_for_download(&mut self, timeout: Duration) -> Result<Download> {
+        let _ = timeout;
+        Err(Error::Unsupported("Downloads are not supported by this browser".to_string()))
+    }
+    
+    /// Get the cookies and storage of the browser
+    fn storage_state(&self) -> Result<StorageState> {
+        Err(Error::Unsupported("Storage state is not supported by this browser".to_string()))
+    }
+    
+    /// Add cookies and storage to the browser
+    fn set_storage_state(&mut self, state: &StorageState) -> Result<()> {
+        let _ = state;
+        Err(Error::Unsupported("Storage state is not supported by this browser".to_string()))
+    }
+    
+    /// Replace the request routes; an empty list turns interception off
+    fn set_routes(&mut self, routes: &[Route]) -> Result<()> {
+        if routes.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Unsupported("Request routing is not supported by this browser".to_string()))
+    }
+    
+    /// Start recording network traffic, or stop with `None`
+    fn set_har_recording(&mut self, options: Option<HarOptions>) -> Result<()> {
+        if options.is_none() {
+            return Ok(());
+        }
+        Err(Error::Unsupported("HAR recording is not supported by this browser".to_string()))
+    }
+    
+    /// Take the requests completed since the last call while recording
+    fn take_har_entries(&mut self) -> Result<Vec<HarEntry>> {
+        Ok(Vec::new())
+    }
+    
+    /// Check that the browser still responds
+    fn health_check(&self) -> Result<()> {
+        self.current_url().map(|_| ())
+    }
+    
+    /// Discard cookies, storage, routes and every page, leaving one blank page
+    fn reset(&mut self) -> Result<()> {
+        Err(Error::Unsupported("Resetting browser state is not supported by this browser".to_string()))
+    }
+    
+    /// Close the browser
+    fn close(&mut self) -> Result<()>;
+}
+
+/// Launch a browser for the configured browser type
+///
+/// Chrome is driven directly over the DevTools protocol; Firefox, Edge and
+/// Safari go through their WebDriver implementations.
+pub fn launch(config: BrowserConfig) -> Result<Box<dyn Browser>> {
+    match config.browser_type {
+        BrowserType::Chrome => Ok(Box::new(CdpBrowser::launch(config)?)),
+        BrowserType::Firefox | BrowserType::Edge | BrowserType::Safari => {
+            Ok(Box::new(WebDriverBrowser::launch(config)?))
+        }
+    }
+}
+
+/// An action performed through a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionRecord {
+    /// The page that was active when the action started
+    pub page: PageId,
+    /// The operation, e.g. `click`
+    pub action: String,
+    /// The selector, for element actions
+    pub selector: Option<Selector>,
+    /// The URL, for navigations and new pages
+    pub url: Option<String>,
+    /// Milliseconds since the epoch when the action started
+    pub timestamp: u128,
+    /// The error message, if the action failed
+    pub error: Option<String>,
+}
+
+/// Browser session
+pub struct BrowserSession {
+    /// The browser
+    browser: Box<dyn Browser>,
+    /// The configuration
+    config: BrowserConfig,
+    /// Actions performed so far
+    log: Vec<ActionRecord>,
+    /// Page events taken from the browser but not yet handed out
+    page_events: Vec<PageEvent>,
+    /// Request routes, oldest first
+    routes: Vec<Route>,
+    /// Entries recorded so far, while HAR recording is on
+    har: Option<Vec<HarEntry>>,
+    /// Fingerprints for healing broken selectors, when enabled
+    healing: Option<SelfHealing>,
+    /// Selectors healed so far
+    healed: Vec<HealedSelector>,
+    /// Resolver for semantic selectors
+    #[cfg(feature = "llm")]
+    resolver: Option<Arc<SemanticResolver>>,
+}
+
+impl BrowserSession {
+    /// Create a new browser session
+    pub fn new(browser: Box<dyn Browser>, config: BrowserConfig) -> Self {
+        Self {
+            browser,
+            config,
+            log: Vec::new(),
+            page_events: Vec::new(),
+            routes: Vec::new(),
+            har: None,
+            healing: None,
+            healed: Vec::new(),
+            #[cfg(feature = "llm")]
+            resolver: None,
+        }
+    }
+
+    /// Resolve semantic selectors through the given resolver
+    #[cfg(feature = "llm")]
+    pub fn with_semantic_resolver(mut self, resolver: SemanticResolver) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// The semantic selector resolver, if one is configured
+    #[cfg(feature = "llm")]
+    pub fn semantic_resolver(&self) -> Option<&SemanticResolver> {
+        self.resolver.as_deref()
+    }
+
+    /// Heal the selectors of element actions when they stop matching
+    ///
+    /// Each action first fingerprints the element its selector reaches, which
+    /// costs one extra read of the page HTML.
+    pub fn with_self_healing(mut self, healing: SelfHealing) -> Self {
+        self.healing = Some(healing);
+        self
+    }
+    
+    /// The self-healing settings and recorded fingerprints, if enabled
+    pub fn self_healing(&self) -> Option<&SelfHealing> {
+        self.healing.as_ref()
+    }
+    
+    /// Selectors healed so far, oldest first
+    pub fn healed_selectors(&self) -> &[HealedSelector] {
+        &self.healed
+    }
+    
+    /// Turn a semantic selector into a concrete one, passing others through
+    pub fn resolve(&self, selector: &Selector) -> Result<Selector> {
+        self.concrete(selector).map(Cow::into_owned)
+    }
+
+    fn concrete<'a>(&self, selector: &'a Selector) -> Result<Cow<'a, Selector>> {
+        match selector {
+            #[cfg(feature = "llm")]
+            Selector::Semantic(_) => match &self.resolver {
+                Some(resolver) => resolver.resolve_blocking(self.browser.as_ref(), selector).map(Cow::Owned),
+                None => Ok(Cow::Borrowed(selector)),
+            },
+            // A semantic scope describes the top-level page, so it can be resolved there
+            Selector::Within(scope, inner) => match self.concrete(scope)? {
+                Cow::Owned(scope) => Ok(Cow::Owned(Selector::Within(Box::new(scope), inner.clone()))),
+                Cow::Borrowed(_) => Ok(Cow::Borrowed(selector)),
+            },
+            _ => Ok(Cow::Borrowed(selector)),
+        }
+    }
+    
+    /// Navigate to a URL
+    pub fn navigate(&mut self, url: &str) -> Result<()> {
+        self.logged("navigate", None, Some(url), |session| session.browser.navigate(url))
+    }
+    
+    /// Get the current URL
+    pub fn current_url(&self) -> Result<String> {
+        self.browser.current_url()
+    }
+    
+    /// Click on an element once it is actionable
+    pub fn click(&mut self, selector: &Selector) -> Result<()> {
+        self.click_with(selector, &ActionOptions::default())
+    }
+    
+    /// Click on an element, waiting for it as the options say
+    pub fn click_with(&mut self, selector: &Selector, options: &ActionOptions) -> Result<()> {
+        self.logged("click", Some(selector), None, |session| {
+            session.act(selector, options, |browser, selector| browser.click(selector))
+        })
+    }
+    
+    /// Type text into an element once it is actionable
+    pub fn type_text(&mut self, selector: &Selector, text: &str) -> Result<()> {
+        self.type_text_with(selector, text, &ActionOptions::default())
+    }
+    
+    /// Type text into an element, waiting for it as the options say
+    pub fn type_text_with(&mut self, selector: &Selector, text: &str, options: &ActionOptions) -> Result<()> {
+        self.logged("type_text", Some(selector), None, |session| {
+            session.act(selector, options, |browser, selector| browser.type_text(selector, text))
+        })
+    }
+    
+    /// Move the mouse over an element
+    pub fn hover(&mut self, selector: &Selector) -> Result<()> {
+        self.logged("hover", Some(selector), None, |session| {
+            session.act(selector, &ActionOptions::default(), |browser, selector| browser.hover(selector))
+        })
+    }
+    
+    /// Double-click an element
+    pub fn double_click(&mut self, selector: &Selector) -> Result<()> {
+        self.logged("double_click", Some(selector), None, |session| {
+            session.act(selector, &ActionOptions::default(), |browser, selector| browser.double_click(selector))
+        })
+    }
+    
+    /// Right-click an element
+    pub fn right_click(&mut self, selector: &Selector) -> Result<()> {
+        self.logged("right_click", Some(selector), None, |session| {
+            session.act(selector, &ActionOptions::default(), |browser, selector| browser.right_click(selector))
+        })
+    }
+    
+    /// Choose the option of a `<select>` by value, or else by label
+    pub fn select_option(&mut self, selector: &Selector, choice: &str) -> Result<()> {
+        self.logged("select_option", Some(selector), None, |session| {
+            session.act(selector, &ActionOptions::default(), |browser, selector| {
+                browser.select_option(selector, choice)
+            })
+        })
+    }
+    
+    /// Check a checkbox or radio button
+    pub fn check(&mut self, selector: &Selector) -> Result<()> {
+        self.logged("check", Some(selector), None, |session| {
+            session.act(selector, &ActionOptions::default(), |browser, selector| browser.set_checked(selector, true))
+        })
+    }
+    
+    /// Uncheck a checkbox
+    pub fn uncheck(&mut self, selector: &Selector) -> Result<()> {
+        self.logged("uncheck", Some(selector), None, |session| {
+            session.act(selector, &ActionOptions::default(), |browser, selector| browser.set_checked(selector, false))
+        })
+    }
+    
+    /// Choose the files of a file input
+    ///
+    /// The input only has to be attached, since file inputs are often hidden
+    /// behind a styled button. Every file must exist.
+    pub fn set_input_files<P: AsRef<std::path::Path>>(&mut self, selector: &Selector, files: &[P]) -> Result<()> {
+        self.logged("set_input_files", Some(selector), None, |session| {
+            let files = files
+                .iter()
+                .map(|file| {
+                    let file = file.as_ref();
+                    file.canonicalize().map_err(|e| {
+                        Error::ValidationError(format!("Cannot upload {}: {}", file.display(), e))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let selector = session.concrete(selector)?;
+            session.browser.set_input_files(&selector, &files)
+        })
+    }
+    
+    /// Scroll the page so an element is in view
+    pub fn scroll_into_view(&mut self, selector: &Selector) -> Result<()> {
+        self.logged("scroll_into_view", Some(selector), None, |session| {
+            let selector = session.concrete(selector)?;
+            session.browser.scroll_into_view(&selector)
+        })
+    }
+    
+    /// Scroll the page by the given number of pixels
+    pub fn scroll_by(&mut self, x: f64, y: f64) -> Result<()> {
+        self.logged("scroll_by", None, None, |session| session.browser.scroll_by(x, y))
+    }
+    
+    /// Drag one element and drop it onto another
+    pub fn drag_and_drop(&mut self, source: &Selector, target: &Selector) -> Result<()> {
+        self.logged("drag_and_drop", Some(source), None, |session| {
+            let target = session.concrete(target)?.into_owned();
+            let timeout = session.config.timeout;
+            session.act(source, &ActionOptions::default(), |browser, source| {
+                browser.wait_for_actionable(&target, timeout)?;
+                browser.drag_and_drop(source, &target)
+            })
+        })
+    }
+    
+    /// Press a key chord such as `Enter` or `Control+A` in the focused element
+    pub fn press_key(&mut self, chord: &str) -> Result<()> {
+        self.logged("press_key", None, None, |session| {
+            let chord = KeyChord::parse(chord)?;
+            session.browser.press_key(&chord)
+        })
+    }
+    
+    /// Get text from an element
+    pub fn get_text(&self, selector: &Selector) -> Result<String> {
+        self.browser.get_text(&*self.concrete(selector)?)
+    }
+    
+    /// Get attributes of an element
+    pub fn get_attributes(&self, selector: &Selector) -> Result<std::collections::HashMap<String, String>> {
+        self.browser.get_attributes(&*self.concrete(selector)?)
+    }
+    
+    /// Check if an element exists
+    pub fn element_exists(&self, selector: &Selector) -> Result<bool> {
+        self.browser.element_exists(&*self.concrete(selector)?)
+    }
+    
+    /// Wait for an element to be visible with the default timeout
+    pub fn wait_for_element(&mut self, selector: &Selector) -> Result<()> {
+        let selector = self.concrete(selector)?;
+        self.browser.wait_for_element(&selector, self.config.timeout)
+    }
+    
+    /// Wait for navigation to complete with the default timeout
+    pub fn wait_for_navigation(&mut self) -> Result<()> {
+        self.browser.wait_for_navigation(self.config.timeout)
+    }
+    
+    /// Take a screenshot
+    pub fn take_screenshot(&self, path: &str) -> Result<()> {
+        self.browser.take_screenshot(path)
+    }
+    
+    /// Execute JavaScript
+    pub fn execute_js(&mut self, script: &str) -> Result<serde_json::Value> {
+        self.logged("execute_js", None, None, |session| session.browser.execute_js(script))
+    }
+    
+    /// Get page HTML
+    pub fn get_html(&self) -> Result<String> {
+        self.browser.get_html()
+    }
+    
+    /// Get the accessibility tree of the page
+    pub fn accessibility_snapshot(&self) -> Result<AccessibilityNode> {
+        self.browser.accessibility_snapshot()
+    }
+    
+    /// Open a new page, optionally loading a URL, and make it active
+    pub fn new_page(&mut self, url: Option<&str>) -> Result<PageId> {
+        self.logged("new_page", None, url, |session| session.browser.new_page(url))
+    }
+    
+    /// List the open pages
+    pub fn pages(&self) -> Result<Vec<PageInfo>> {
+        self.browser.pages()
+    }
+    
+    /// The page that operations currently target
+    pub fn active_page(&self) -> Result<PageId> {
+        self.browser.active_page()
+    }
+    
+    /// Make a page the target of subsequent operations
+    pub fn switch_to_page(&mut self, page: &PageId) -> Result<()> {
+        self.logged("switch_to_page", None, None, |session| session.browser.switch_to_page(page))
+    }
+    
+    /// Close a page
+    pub fn close_page(&mut self, page: &PageId) -> Result<()> {
+        self.logged("close_page", None, None, |session| session.browser.close_page(page))
+    }
+    
+    /// Take the page events that occurred since the last call
+    pub fn take_page_events(&mut self) -> Result<Vec<PageEvent>> {
+        let mut events = std::mem::take(&mut self.page_events);
+        events.extend(self.browser.take_page_events()?);
+        Ok(events)
+    }
+    
+    /// Set how JavaScript dialogs are answered
+    pub fn set_dialog_policy(&mut self, policy: DialogPolicy) -> Result<()> {
+        self.browser.set_dialog_policy(policy)
+    }
+    
+    /// Take the dialogs answered since the last call
+    pub fn take_dialogs(&mut self) -> Result<Vec<HandledDialog>> {
+        self.browser.take_dialogs()
+    }
+    
+    /// Wait for a download to finish, returning where it was saved and its MIME type
+    ///
+    /// Downloads go to the configured [`BrowserConfig::download_dir`].
+    pub fn wait_for_download(&mut self, timeout: Duration) -> Result<Download> {
+        self.browser.wait_for_download(timeout)
+    }
+    
+    /// Wait for the active page to open a popup, returning its id
+    ///
+    /// Other page events seen while waiting stay available from
+    /// [`BrowserSession::take_page_events`].
+    pub fn wait_for_popup(&mut self, timeout: Duration) -> Result<PageId> {
+        let opener = self.active_page()?;
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            let mut events = self.take_page_events()?;
+            let popup = events
+                .iter()
+                .position(|event| matches!(event, PageEvent::Opened { opener: Some(o), .. } if *o == opener))
+                .map(|index| events.remove(index));
+            self.page_events = events;
+            if let Some(PageEvent::Opened { page, .. }) = popup {
+                return Ok(page);
+            }
+
+            if std::time::Instant::now() >= deadline {
+                return Err(Error::TimeoutError(format!(
+                    "No popup opened by page {} within {:?}",
+                    opener, timeout
+                )));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+    
+    /// Get the cookies of the browser and the storage of the active page's origin
+    pub fn storage_state(&self) -> Result<StorageState> {
+        self.browser.storage_state()
+    }
+    
+    /// Add cookies and storage to the browser
+    pub fn set_storage_state(&mut self, state: &StorageState) -> Result<()> {
+        self.browser.set_storage_state(state)
+    }
+    
+    /// Save cookies and storage to a JSON file
+    pub fn save_storage_state(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.storage_state()?.save(path.as_ref())
+    }
+    
+    /// Restore cookies and storage saved with [`BrowserSession::save_storage_state`]
+    pub fn load_storage_state(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let state = StorageState::load(path.as_ref())?;
+        self.set_storage_state(&state)
+    }
+    
+    /// Save cookies and storage as a named profile under the config directory
+    pub fn save_profile(&self, name: &str) -> Result<std::path::PathBuf> {
+        self.storage_state()?.save_profile(name)
+    }
+    
+    /// Restore a named profile, returning `false` if it has not been saved yet
+    pub fn load_profile(&mut self, name: &str) -> Result<bool> {
+        match StorageState::load_profile(name)? {
+            Some(state) => self.set_storage_state(&state).map(|_| true),
+            None => Ok(false),
+        }
+    }
+    
+    /// Save cookies and storage to a file encrypted with `passphrase`
+    #[cfg(feature = "security")]
+    pub fn save_storage_state_encrypted(&self, path: impl AsRef<std::path::Path>, passphrase: &str) -> Result<()> {
+        self.storage_state()?.save_encrypted(path.as_ref(), passphrase)
+    }
+    
+    /// Restore cookies and storage saved with [`BrowserSession::save_storage_state_encrypted`]
+    #[cfg(feature = "security")]
+    pub fn load_storage_state_encrypted(&mut self, path: impl AsRef<std::path::Path>, passphrase: &str) -> Result<()> {
+        let state = StorageState::load_encrypted(path.as_ref(), passphrase)?;
+        self.set_storage_state(&state)
+    }
+    
+    /// Route matching requests; later routes take precedence over earlier ones
+    ///
+    /// The DevTools backend answers intercepted requests while the session is
+    /// talking to the browser, e.g. during `navigate` or a wait. A request the
+    /// page makes while the session is idle stays paused until its next call.
+    pub fn route(&mut self, route: Route) -> Result<()> {
+        self.routes.push(route);
+        if let Err(e) = self.browser.set_routes(&self.routes) {
+            self.routes.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+    
+    /// Remove the routes registered for a URL glob
+    pub fn unroute(&mut self, url: &str) -> Result<()> {
+        self.routes.retain(|route| route.url != url);
+        self.browser.set_routes(&self.routes)
+    }
+    
+    /// Remove all routes
+    pub fn clear_routes(&mut self) -> Result<()> {
+        self.routes.clear();
+        self.browser.set_routes(&self.routes)
+    }
+    
+    /// The registered routes, oldest first
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+    
+    /// Answer requests from a HAR file, aborting requests it does not contain
+    ///
+    /// The recorded responses take precedence over routes added earlier;
+    /// routes added later still win over the archive.
+    pub fn route_from_har(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let routes = Har::load(path.as_ref())?.routes()?;
+        let count = self.routes.len();
+        self.routes.push(Route::abort("**"));
+        self.routes.extend(routes);
+        if let Err(e) = self.browser.set_routes(&self.routes) {
+            self.routes.truncate(count);
+            return Err(e);
+        }
+        Ok(())
+    }
+    
+    /// Start recording network traffic as a HAR
+    pub fn record_har(&mut self, options: HarOptions) -> Result<()> {
+        self.browser.set_har_recording(Some(options))?;
+        self.har.get_or_insert_with(Vec::new);
+        Ok(())
+    }
+    
+    /// The traffic recorded so far
+    pub fn har(&mut self) -> Result<Har> {
+        let entries = self.browser.take_har_entries()?;
+        let recorded = self
+            .har
+            .as_mut()
+            .ok_or_else(|| Error::BrowserError("HAR recording has not been started".to_string()))?;
+        recorded.extend(entries);
+        Ok(Har::new(recorded.clone()))
+    }
+    
+    /// Write the traffic recorded so far to a HAR file
+    pub fn save_har(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.har()?.save(path.as_ref())
+    }
+    
+    /// Stop recording and return everything recorded
+    pub fn stop_har(&mut self) -> Result<Har> {
+        let har = self.har()?;
+        self.har = None;
+        self.browser.set_har_recording(None)?;
+        Ok(har)
+    }
+    
+    /// Actions performed through this session, oldest first
+    pub fn action_log(&self) -> &[ActionRecord] {
+        &self.log
+    }
+    
+    /// Forget the recorded actions
+    pub fn clear_action_log(&mut self) {
+        self.log.clear();
+    }
+    
+    /// Check that the browser still responds
+    pub fn health_check(&self) -> Result<()> {
+        self.browser.health_check()
+    }
+    
+    /// Return the session to a blank page with no cookies, storage, routes or history
+    ///
+    /// Healing fingerprints are kept, since they describe pages rather than state.
+    pub fn reset(&mut self) -> Result<()> {
+        self.browser.reset()?;
+        self.log.clear();
+        self.page_events.clear();
+        self.routes.clear();
+        self.har = None;
+        self.healed.clear();
+        Ok(())
+    }
+    
+    /// Close the browser
+    pub fn close(&mut self) -> Result<()> {
+        self.logged("close", None, None, |session| session.browser.close())
+    }
+    
+    /// Wait until an element is actionable and act on it, healing its selector if it is gone
+    fn act<T>(
+        &mut self,
+        selector: &Selector,
+        options: &ActionOptions,
+        action: impl Fn(&mut dyn Browser, &Selector) -> Result<T>,
+    ) -> Result<T> {
+        let concrete = self.concrete(selector)?.into_owned();
+        let timeout = options.timeout.unwrap_or(self.config.timeout);
+        let Some(healing) = self.healing.as_mut() else {
+            return Self::act_on(self.browser.as_mut(), &concrete, options, timeout, &action);
+        };
+
+        let url = self.browser.current_url()?;
+        let dom = dom::Dom::parse(&self.browser.get_html()?);
+        healing.remember(&url, selector, &concrete, &dom);
+        // A selector matching nothing is healed at once rather than after waiting out the timeout
+        let result = if self.browser.element_exists(&concrete)? {
+            let result = Self::act_on(self.browser.as_mut(), &concrete, options, timeout, &action);
+            let gone = match &result {
+                Err(Error::ElementNotFound(_)) => true,
+                Err(Error::TimeoutError(_)) => !self.browser.element_exists(&concrete)?,
+                _ => false,
+            };
+            if !gone {
+                return result;
+            }
+            Some(result)
+        } else {
+            None
+        };
+
+        let Some(healed) = healing.heal(&url, selector, &dom)? else {
+            // With nothing to heal to, a missing element may still turn up
+            return match result {
+                Some(result) => result,
+                None => Self::act_on(self.browser.as_mut(), &concrete, options, timeout, &action),
+            };
+        };
+        log::warn!(
+            "Healed selector {} on {}: use {} instead (confidence {:.2})",
+            healed.selector,
+            healed.url,
+            healed.replacement,
+            healed.confidence
+        );
+        let replacement = healed.replacement.clone();
+        self.healed.push(healed);
+        Self::act_on(self.browser.as_mut(), &replacement, options, timeout, &action)
+    }
+    
+    fn act_on<T>(
+        browser: &mut dyn Browser,
+        selector: &Selector,
+        options: &ActionOptions,
+        timeout: Duration,
+        action: &impl Fn(&mut dyn Browser, &Selector) -> Result<T>,
+    ) -> Result<T> {
+        if !options.force {
+            browser.wait_for_actionable(selector, timeout)?;
+        }
+        action(browser, selector)
+    }
+    
+    /// Run an action and record it against the active page
+    fn logged<T>(
+        &mut self,
+        action: &str,
+        selector: Option<&Selector>,
+        url: Option<&str>,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let page = self.browser.active_page().unwrap_or_else(|_| PageId::new(DEFAULT_PAGE_ID));
+        let timestamp = crate::utils::timestamp_millis();
+        let result = f(self);
+        self.log.push(ActionRecord {
+            page,
+            action: action.to_string(),
+            selector: selector.cloned(),
+            url: url.map(str::to_string),
+            timestamp,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+        result
+    }
+} 
\ No newline at end of file
@@ -0,0 +1,108 @@
+//! Page (tab) handles
+//!
+//! A browser can hold several pages: tabs opened by the caller, and popups
+//! opened by the site itself. [`PageId`] names a page, and [`PageEvent`]s
+//! report pages the caller did not open or close itself.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Id of the only page of backends without multi-page support
+pub const DEFAULT_PAGE_ID: &str = "main";
+
+/// Identifier of a page within a browser
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PageId(String);
+
+impl PageId {
+    /// Create a page id
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    /// The id as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for PageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for PageId {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<String> for PageId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+/// An open page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageInfo {
+    /// The page id
+    pub id: PageId,
+    /// The page URL
+    pub url: String,
+    /// The page that opened this one, for popups
+    pub opener: Option<PageId>,
+}
+
+/// A change to the set of open pages not made by the caller
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageEvent {
+    /// A page opened a popup or a link in a new tab
+    Opened {
+        /// The new page
+        page: PageId,
+        /// The page that opened it, when known
+        opener: Option<PageId>,
+        /// The URL the new page was opened with
+        url: String,
+    },
+    /// A page closed itself
+    Closed {
+        /// The closed page
+        page: PageId,
+    },
+}
+
+/// Reduce a URL to a pattern shared by pages of the same kind
+///
+/// The query and fragment are dropped, and path segments that look like
+/// identifiers (numbers, UUIDs, long hex or mixed alphanumeric tokens) are
+/// replaced by `*`, so `https://shop.test/orders/1234?x=1` and
+/// `https://shop.test/orders/98` share `https://shop.test/orders/*`.
+pub fn url_pattern(url: &str) -> String {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map(|segments| segments.map(|s| if looks_like_id(s) { "*" } else { s }).collect())
+        .unwrap_or_default();
+
+    let origin = parsed.origin().ascii_serialization();
+    if segments.is_empty() {
+        origin
+    } else {
+        format!("{}/{}", origin, segments.join("/"))
+    }
+}
+
+fn looks_like_id(segment: &str) -> bool {
+    let digits = segment.chars().filter(|c| c.is_ascii_digit()).count();
+    let hexish = segment.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+    !segment.is_empty()
+        && (digits == segment.len()
+            || (hexish && segment.len() >= 16)
+            || (digits >= 4 && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')))
+}
@@ -0,0 +1,422 @@
+//! Pools of browser sessions for parallel work
+//!
+//! A [`BrowserPool`] launches up to a fixed number of browsers and leases
+//! them out as [`PooledSession`]s. Returned sessions are reset so the next
+//! lease starts from a clean context, and recycled after a number of uses
+//! or as soon as they stop responding.
+
+use super::{launch, Browser, BrowserConfig, BrowserSession};
+use crate::error::{Error, Result};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Creates the browsers of a pool
+pub type BrowserFactory = Arc<dyn Fn(&BrowserConfig) -> Result<Box<dyn Browser>> + Send + Sync>;
+
+/// Counters describing a pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Sessions waiting to be leased
+    pub idle: usize,
+    /// Sessions currently leased
+    pub leased: usize,
+    /// Browsers launched so far
+    pub launched: usize,
+    /// Browsers closed because they crashed, were discarded or reached their use limit
+    pub recycled: usize,
+}
+
+/// An idle session and how many leases it has served
+struct Idle {
+    session: BrowserSession,
+    uses: usize,
+}
+
+/// A lease reserved while holding the lock
+enum Reservation {
+    /// An idle session to hand out
+    Idle(Box<Idle>),
+    /// Room to launch a new browser
+    Slot,
+}
+
+/// Everything behind the pool's lock
+struct PoolState {
+    idle: Vec<Idle>,
+    leased: usize,
+    launched: usize,
+    recycled: usize,
+    shutting_down: bool,
+}
+
+/// State shared between a pool and its leases
+struct Shared {
+    config: BrowserConfig,
+    factory: BrowserFactory,
+    max_sessions: usize,
+    max_uses: Option<usize>,
+    isolated: bool,
+    acquire_timeout: Duration,
+    state: Mutex<PoolState>,
+    /// Signalled whenever a lease ends
+    returned: Condvar,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, PoolState> {
+        // A panicking lease holder must not take the whole pool down with it
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn launch(&self) -> Result<BrowserSession> {
+        let browser = (self.factory)(&self.config)?;
+        Ok(BrowserSession::new(browser, self.config.clone()))
+    }
+
+    /// Take a session back from a lease, keeping it only if it is still fit to lease
+    fn release(&self, mut session: BrowserSession, uses: usize, discarded: bool) {
+        let keep = !discarded
+            && !self.state().shutting_down
+            && self.max_uses.map_or(true, |max| uses < max)
+            && session.health_check().is_ok()
+            // Browsers that cannot reset are replaced to keep leases isolated
+            && (!self.isolated || session.reset().is_ok());
+        if !keep {
+            if let Err(e) = session.close() {
+                log::debug!("Closing a recycled browser failed: {}", e);
+            }
+        }
+
+        let mut state = self.state();
+        state.leased -= 1;
+        if keep {
+            state.idle.push(Idle { session, uses });
+        } else {
+            state.recycled += 1;
+        }
+        drop(state);
+        self.returned.notify_all();
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let idle = std::mem::take(&mut self.state().idle);
+        for mut idle in idle {
+            let _ = idle.session.close();
+        }
+    }
+}
+
+/// A bounded pool of browser sessions
+///
+/// Browsers are launched lazily, the first time a lease needs one. Clones
+/// share the same sessions.
+#[derive(Clone)]
+pub struct BrowserPool {
+    shared: Arc<Shared>,
+}
+
+impl BrowserPool {
+    /// Create a pool of at most `max_sessions` browsers started with [`launch`]
+    pub fn new(config: BrowserConfig, max_sessions: usize) -> Self {
+        let acquire_timeout = config.timeout;
+        Self {
+            shared: Arc::new(Shared {
+                config,
+                factory: Arc::new(|config: &BrowserConfig| launch(config.clone())),
+                max_sessions: max_sessions.max(1),
+                max_uses: None,
+                isolated: true,
+                acquire_timeout,
+                state: Mutex::new(PoolState {
+                    idle: Vec::new(),
+                    leased: 0,
+                    launched: 0,
+                    recycled: 0,
+                    shutting_down: false,
+                }),
+                returned: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Create browsers with the given function instead of [`launch`]
+    pub fn with_factory(
+        self,
+        factory: impl Fn(&BrowserConfig) -> Result<Box<dyn Browser>> + Send + Sync + 'static,
+    ) -> Self {
+        self.configure(|shared| shared.factory = Arc::new(factory))
+    }
+
+    /// Close each browser after it has served this many leases
+    pub fn with_max_uses(self, max_uses: usize) -> Self {
+        self.configure(|shared| shared.max_uses = Some(max_uses.max(1)))
+    }
+
+    /// Reset sessions between leases, which is on by default
+    ///
+    /// Browsers that cannot reset their state are closed and replaced instead.
+    pub fn with_isolation(self, isolated: bool) -> Self {
+        self.configure(|shared| shared.isolated = isolated)
+    }
+
+    /// Set how long [`BrowserPool::acquire`] waits for a free session
+    ///
+    /// Defaults to the browser configuration's timeout.
+    pub fn with_acquire_timeout(self, timeout: Duration) -> Self {
+        self.configure(|shared| shared.acquire_timeout = timeout)
+    }
+
+    fn configure(mut self, apply: impl FnOnce(&mut Shared)) -> Self {
+        match Arc::get_mut(&mut self.shared) {
+            Some(shared) => apply(shared),
+            None => log::warn!("Browser pool settings cannot change once the pool is shared"),
+        }
+        self
+    }
+
+    /// The most sessions the pool holds at once
+    pub fn max_sessions(&self) -> usize {
+        self.shared.max_sessions
+    }
+
+    /// Lease a session, waiting until one is free
+    ///
+    /// Idle sessions are health-checked first; ones that no longer respond
+    /// are replaced with a freshly launched browser.
+    pub fn acquire(&self) -> Result<PooledSession> {
+        let timeout = self.shared.acquire_timeout;
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state();
+        loop {
+            if let Some(reservation) = self.reserve(&mut state)? {
+                drop(state);
+                return self.check_out(reservation);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::TimeoutError(format!(
+                    "Timed out after {:?} waiting for one of {} pooled sessions",
+                    timeout, self.shared.max_sessions
+                )));
+            }
+            state = self
+                .shared
+                .returned
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+
+    /// Lease a session if one is free right now
+    pub fn try_acquire(&self) -> Result<Option<PooledSession>> {
+        let reservation = self.reserve(&mut self.shared.state())?;
+        reservation.map(|reservation| self.check_out(reservation)).transpose()
+    }
+
+    /// Reserve an idle session or room for a new one, giving `None` when the pool is full
+    fn reserve(&self, state: &mut PoolState) -> Result<Option<Reservation>> {
+        if state.shutting_down {
+            return Err(Error::BrowserError("Browser pool has been shut down".to_string()));
+        }
+        if let Some(idle) = state.idle.pop() {
+            state.leased += 1;
+            return Ok(Some(Reservation::Idle(Box::new(idle))));
+        }
+        if state.leased < self.shared.max_sessions {
+            state.leased += 1;
+            return Ok(Some(Reservation::Slot));
+        }
+        Ok(None)
+    }
+
+    /// Turn a reservation into a lease, launching a browser where needed
+    fn check_out(&self, reservation: Reservation) -> Result<PooledSession> {
+        let idle = match reservation {
+            Reservation::Idle(mut idle) => match idle.session.health_check() {
+                Ok(()) => Some(*idle),
+                Err(e) => {
+                    log::warn!("Recycling a pooled browser that failed its health check: {}", e);
+                    let _ = idle.session.close();
+                    self.shared.state().recycled += 1;
+                    None
+                }
+            },
+            Reservation::Slot => None,
+        };
+        let idle = match idle {
+            Some(idle) => idle,
+            None => match self.shared.launch() {
+                Ok(session) => {
+                    self.shared.state().launched += 1;
+                    Idle { session, uses: 0 }
+                }
+                Err(e) => {
+                    self.shared.state().leased -= 1;
+                    self.shared.returned.notify_all();
+                    return Err(e);
+                }
+            },
+        };
+        Ok(PooledSession {
+            session: Some(idle.session),
+            uses: idle.uses + 1,
+            discarded: false,
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    /// Run a task for each input on pooled sessions, returning the results in input order
+    ///
+    /// At most [`BrowserPool::max_sessions`] tasks run at once, each on its own lease.
+    /// A task that panics fails with an error and its browser is closed.
+    pub fn run_each<I, T, F>(&self, inputs: Vec<I>, task: F) -> Vec<Result<T>>
+    where
+        I: Send,
+        T: Send,
+        F: Fn(&mut BrowserSession, I) -> Result<T> + Sync,
+    {
+        let count = inputs.len();
+        let queue = Mutex::new(inputs.into_iter().enumerate());
+        let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<Result<T>>>>());
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.shared.max_sessions.min(count) {
+                scope.spawn(|| loop {
+                    let next = queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).next();
+                    let Some((index, input)) = next else {
+                        break;
+                    };
+                    let result = self.acquire().and_then(|mut lease| {
+                        // A panicking task fails its input alone; its browser is not trusted again
+                        panic::catch_unwind(AssertUnwindSafe(|| task(&mut lease, input))).unwrap_or_else(|_| {
+                            lease.discard();
+                            Err(Error::GenericError("A pooled task panicked".to_string()))
+                        })
+                    });
+                    results.lock().unwrap_or_else(|poisoned| poisoned.into_inner())[index] = Some(result);
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .into_iter()
+            .map(|result| result.expect("every input is run"))
+            .collect()
+    }
+
+    /// Counters describing the pool right now
+    pub fn stats(&self) -> PoolStats {
+        let state = self.shared.state();
+        PoolStats {
+            idle: state.idle.len(),
+            leased: state.leased,
+            launched: state.launched,
+            recycled: state.recycled,
+        }
+    }
+
+    /// Stop leasing, close idle browsers and wait for leased ones to come back
+    ///
+    /// Sessions returned after the call are closed rather than kept. Fails
+    /// with a timeout error if leases are still out when `timeout` passes.
+    pub fn shutdown(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state();
+        state.shutting_down = true;
+        let idle = std::mem::take(&mut state.idle);
+        drop(state);
+        for mut idle in idle {
+            if let Err(e) = idle.session.close() {
+                log::debug!("Closing a pooled browser failed: {}", e);
+            }
+        }
+
+        let mut state = self.shared.state();
+        while state.leased > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::TimeoutError(format!(
+                    "{} pooled sessions were still leased after {:?}",
+                    state.leased, timeout
+                )));
+            }
+            state = self
+                .shared
+                .returned
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for BrowserPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BrowserPool")
+            .field("max_sessions", &self.shared.max_sessions)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// A session leased from a [`BrowserPool`], returned to it when dropped
+pub struct PooledSession {
+    /// The session, until it goes back to the pool
+    session: Option<BrowserSession>,
+    /// Leases the browser has served, this one included
+    uses: usize,
+    /// Whether to close the browser instead of returning it
+    discarded: bool,
+    shared: Arc<Shared>,
+}
+
+impl PooledSession {
+    /// How many leases the browser has served, this one included
+    pub fn uses(&self) -> usize {
+        self.uses
+    }
+
+    /// Close the browser instead of returning it to the pool
+    pub fn discard(mut self) {
+        self.discarded = true;
+    }
+}
+
+impl std::fmt::Debug for PooledSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledSession")
+            .field("uses", &self.uses)
+            .field("discarded", &self.discarded)
+            .finish()
+    }
+}
+
+impl Deref for PooledSession {
+    type Target = BrowserSession;
+
+    fn deref(&self) -> &BrowserSession {
+        self.session.as_ref().expect("pooled session is present until dropped")
+    }
+}
+
+impl DerefMut for PooledSession {
+    fn deref_mut(&mut self) -> &mut BrowserSession {
+        self.session.as_mut().expect("pooled session is present until dropped")
+    }
+}
+
+impl Drop for PooledSession {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            self.shared.release(session, self.uses, self.discarded);
+        }
+    }
+}
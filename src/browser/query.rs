@@ -0,0 +1,310 @@
+//! Selector query syntax
+//!
+//! Selectors can be written as strings, e.g. in workflow files. Every selector
+//! is a function call: `within(has-text(css("tr"), "Invoice 42"), text("Delete"))`
+//! is the Delete button in the row mentioning Invoice 42. [`Selector`]'s
+//! `Display` writes the same syntax, so selectors round-trip through text.
+
+use super::Selector;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Default distance, in CSS pixels, within which [`Selector::near`] matches
+pub const DEFAULT_NEAR_DISTANCE: u32 = 50;
+
+/// Where an element lies relative to an anchor element on the rendered page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Relation {
+    /// Within the given number of CSS pixels of the anchor
+    Near(u32),
+    /// Entirely above the anchor
+    Above,
+    /// Entirely below the anchor
+    Below,
+    /// Entirely left of the anchor
+    LeftOf,
+    /// Entirely right of the anchor
+    RightOf,
+}
+
+impl Relation {
+    /// The function name of the relation in the query syntax
+    fn name(self) -> &'static str {
+        match self {
+            Self::Near(_) => "near",
+            Self::Above => "above",
+            Self::Below => "below",
+            Self::LeftOf => "left-of",
+            Self::RightOf => "right-of",
+        }
+    }
+}
+
+impl Selector {
+    /// Parse a selector written in the query syntax
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parser = Parser { source: query, pos: 0 };
+        let selector = parser.selector()?;
+        parser.skip_whitespace();
+        if parser.pos < query.len() {
+            return Err(parser.error("unexpected text after the selector"));
+        }
+        Ok(selector)
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        Self::parse(query)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Css(css) => write!(f, "css({})", quote(css)),
+            Self::XPath(xpath) => write!(f, "xpath({})", quote(xpath)),
+            Self::Text(text) => write!(f, "text({})", quote(text)),
+            Self::Id(id) => write!(f, "id({})", quote(id)),
+            Self::Class(class) => write!(f, "class({})", quote(class)),
+            Self::Name(name) => write!(f, "name({})", quote(name)),
+            Self::Semantic(description) => write!(f, "semantic({})", quote(description)),
+            Self::Within(scope, inner) => write!(f, "within({}, {})", scope, inner),
+            Self::Relative(target, Relation::Near(distance), anchor) => {
+                write!(f, "near({}, {}, {})", target, anchor, distance)
+            }
+            Self::Relative(target, relation, anchor) => write!(f, "{}({}, {})", relation.name(), target, anchor),
+            Self::Nth(inner, index) => write!(f, "nth({}, {})", inner, index),
+            Self::HasText(inner, text) => write!(f, "has-text({}, {})", inner, quote(text)),
+            Self::Visible(inner) => write!(f, "visible({})", inner),
+            Self::Or(selectors) => {
+                f.write_str("or(")?;
+                for (i, selector) in selectors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", selector)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Quote a string argument, escaping it like a JSON string
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// An argument of a selector function
+enum Arg {
+    Selector(Selector),
+    String(String),
+    Number(u64),
+}
+
+/// A recursive descent parser over the query syntax
+struct Parser<'a> {
+    /// The whole query, for error messages
+    source: &'a str,
+    /// Byte offset of the next unread character
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::ValidationError(format!(
+            "Invalid selector '{}' at offset {}: {}",
+            self.source, self.pos, message
+        ))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        if !self.rest().starts_with(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.pos += c.len_utf8();
+        Ok(())
+    }
+
+    /// A function call: a name and its parenthesised arguments
+    fn selector(&mut self) -> Result<Selector> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let name_len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphabetic() || c == '-'))
+            .unwrap_or(self.rest().len());
+        if name_len == 0 {
+            return Err(self.error("expected a selector such as css(\"...\")"));
+        }
+        let name = &self.source[start..start + name_len];
+        self.pos += name_len;
+
+        self.expect('(')?;
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if !self.rest().starts_with(')') {
+            loop {
+                args.push(self.arg()?);
+                self.skip_whitespace();
+                if !self.rest().starts_with(',') {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+        self.expect(')')?;
+
+        build(name, args).map_err(|message| {
+            Error::ValidationError(format!(
+                "Invalid selector '{}' at offset {}: {}",
+                self.source, start, message
+            ))
+        })
+    }
+
+    fn arg(&mut self) -> Result<Arg> {
+        self.skip_whitespace();
+        match self.rest().chars().next() {
+            Some('"') => self.string().map(Arg::String),
+            Some(c) if c.is_ascii_digit() => self.number().map(Arg::Number),
+            _ => self.selector().map(Arg::Selector),
+        }
+    }
+
+    /// A double-quoted string with JSON escapes
+    fn string(&mut self) -> Result<String> {
+        let mut escaped = false;
+        let end = self.rest()[1..].find(|c: char| {
+            let closing = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            closing
+        });
+        let Some(end) = end else {
+            return Err(self.error("unterminated string"));
+        };
+        let literal = &self.rest()[..end + 2];
+        let value = serde_json::from_str(literal).map_err(|e| self.error(&format!("invalid string: {}", e)))?;
+        self.pos += literal.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<u64> {
+        let len = self.rest().find(|c: char| !c.is_ascii_digit()).unwrap_or(self.rest().len());
+        let value = self.rest()[..len].parse().map_err(|_| self.error("number out of range"))?;
+        self.pos += len;
+        Ok(value)
+    }
+}
+
+/// Build the selector a function call describes
+fn build(name: &str, args: Vec<Arg>) -> std::result::Result<Selector, String> {
+    let count = args.len();
+    let mut args = args.into_iter();
+    let selector = |args: &mut std::vec::IntoIter<Arg>| match args.next() {
+        Some(Arg::Selector(selector)) => Ok(Box::new(selector)),
+        _ => Err(format!("{}() expects a selector argument", name)),
+    };
+    let arity = |expected: usize| {
+        if count == expected {
+            Ok(())
+        } else {
+            Err(format!("{}() takes {} argument(s), not {}", name, expected, count))
+        }
+    };
+
+    let built = match name {
+        "css" | "xpath" | "text" | "id" | "class" | "name" | "semantic" => {
+            arity(1)?;
+            let Some(Arg::String(value)) = args.next() else {
+                return Err(format!("{}() expects a quoted string", name));
+            };
+            match name {
+                "css" => Selector::Css(value),
+                "xpath" => Selector::XPath(value),
+                "text" => Selector::Text(value),
+                "id" => Selector::Id(value),
+                "class" => Selector::Class(value),
+                "name" => Selector::Name(value),
+                _ => Selector::Semantic(value),
+            }
+        }
+        "within" => {
+            arity(2)?;
+            Selector::Within(selector(&mut args)?, selector(&mut args)?)
+        }
+        "near" => {
+            if count != 2 && count != 3 {
+                return Err(format!("near() takes 2 or 3 arguments, not {}", count));
+            }
+            let (target, anchor) = (selector(&mut args)?, selector(&mut args)?);
+            let distance = match args.next() {
+                None => DEFAULT_NEAR_DISTANCE,
+                Some(Arg::Number(distance)) => {
+                    u32::try_from(distance).map_err(|_| "near() distance is too large".to_string())?
+                }
+                Some(_) => return Err("near() expects a distance in pixels".to_string()),
+            };
+            Selector::Relative(target, Relation::Near(distance), anchor)
+        }
+        "above" | "below" | "left-of" | "right-of" => {
+            arity(2)?;
+            let relation = match name {
+                "above" => Relation::Above,
+                "below" => Relation::Below,
+                "left-of" => Relation::LeftOf,
+                _ => Relation::RightOf,
+            };
+            let target = selector(&mut args)?;
+            Selector::Relative(target, relation, selector(&mut args)?)
+        }
+        "nth" => {
+            arity(2)?;
+            let inner = selector(&mut args)?;
+            let Some(Arg::Number(index)) = args.next() else {
+                return Err("nth() expects an index".to_string());
+            };
+            let index = usize::try_from(index).map_err(|_| "nth() index is too large".to_string())?;
+            Selector::Nth(inner, index)
+        }
+        "has-text" => {
+            arity(2)?;
+            let inner = selector(&mut args)?;
+            let Some(Arg::String(text)) = args.next() else {
+                return Err("has-text() expects a quoted string".to_string());
+            };
+            Selector::HasText(inner, text)
+        }
+        "visible" => {
+            arity(1)?;
+            Selector::Visible(selector(&mut args)?)
+        }
+        "or" => {
+            if count == 0 {
+                return Err("or() needs at least one selector".to_string());
+            }
+            let mut selectors = Vec::with_capacity(count);
+            for _ in 0..count {
+                selectors.push(*selector(&mut args)?);
+            }
+            Selector::Or(selectors)
+        }
+        _ => return Err(format!("unknown selector function '{}'", name)),
+    };
+    Ok(built)
+}
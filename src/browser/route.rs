@@ -0,0 +1,247 @@
+//! Network request routing
+//!
+//! A [`Route`] matches requests by URL glob, method and resource type and
+//! decides what happens to them: fulfill with a canned response, abort,
+//! or continue, optionally with modified headers. Routes are tried from the
+//! most recently added to the oldest, and the first match wins.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Kind of resource a request loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ResourceType {
+    /// A top-level or frame document
+    Document,
+    /// A stylesheet
+    Stylesheet,
+    /// An image
+    Image,
+    /// Audio or video
+    Media,
+    /// A web font
+    Font,
+    /// A script
+    Script,
+    /// An `XMLHttpRequest`
+    Xhr,
+    /// A `fetch()` request
+    Fetch,
+    /// A websocket handshake
+    WebSocket,
+    /// Anything else
+    Other,
+}
+
+impl ResourceType {
+    /// Map a DevTools protocol resource type
+    pub fn from_cdp(name: &str) -> Self {
+        match name {
+            "Document" => Self::Document,
+            "Stylesheet" => Self::Stylesheet,
+            "Image" => Self::Image,
+            "Media" => Self::Media,
+            "Font" => Self::Font,
+            "Script" => Self::Script,
+            "XHR" => Self::Xhr,
+            "Fetch" => Self::Fetch,
+            "WebSocket" => Self::WebSocket,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Document => "document",
+            Self::Stylesheet => "stylesheet",
+            Self::Image => "image",
+            Self::Media => "media",
+            Self::Font => "font",
+            Self::Script => "script",
+            Self::Xhr => "xhr",
+            Self::Fetch => "fetch",
+            Self::WebSocket => "websocket",
+            Self::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A request seen by the router
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterceptedRequest {
+    /// The request URL
+    pub url: String,
+    /// The HTTP method, upper case
+    pub method: String,
+    /// The resource type
+    pub resource_type: ResourceType,
+    /// The request headers
+    pub headers: Vec<(String, String)>,
+}
+
+/// A canned response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteResponse {
+    /// The status code
+    pub status: u16,
+    /// The response headers
+    pub headers: Vec<(String, String)>,
+    /// The response body
+    pub body: Vec<u8>,
+}
+
+impl RouteResponse {
+    /// A response with the given status and body
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// A 200 response with a JSON body
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self::new(200, value.to_string()).with_header("Content-Type", "application/json")
+    }
+
+    /// A 200 response with an HTML body
+    pub fn html(body: impl Into<String>) -> Self {
+        Self::new(200, body.into()).with_header("Content-Type", "text/html; charset=utf-8")
+    }
+
+    /// Add a header
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The body as text
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// What to do with a matched request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteAction {
+    /// Answer with a canned response without touching the network
+    Fulfill(RouteResponse),
+    /// Fail the request
+    Abort,
+    /// Send the request, adding or replacing the given headers
+    Continue {
+        /// Headers to set on the request
+        headers: Vec<(String, String)>,
+    },
+}
+
+impl RouteAction {
+    /// Send the request unchanged
+    pub fn pass() -> Self {
+        Self::Continue { headers: Vec::new() }
+    }
+
+    /// Apply header overrides to a request's headers
+    pub fn merge_headers(&self, headers: &[(String, String)]) -> Vec<(String, String)> {
+        let Self::Continue { headers: overrides } = self else {
+            return headers.to_vec();
+        };
+        let mut merged: Vec<(String, String)> = headers
+            .iter()
+            .filter(|(name, _)| !overrides.iter().any(|(o, _)| o.eq_ignore_ascii_case(name)))
+            .cloned()
+            .collect();
+        merged.extend(overrides.iter().cloned());
+        merged
+    }
+}
+
+/// A request matcher and the action applied to matching requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// URL glob: `*` matches within a path segment, `**` across segments, `?` one character
+    pub url: String,
+    /// Method to match, any when `None`
+    pub method: Option<String>,
+    /// Resource type to match, any when `None`
+    pub resource_type: Option<ResourceType>,
+    /// The action
+    pub action: RouteAction,
+}
+
+impl Route {
+    /// Route requests whose URL matches `url` to `action`
+    pub fn new(url: impl Into<String>, action: RouteAction) -> Self {
+        Self {
+            url: url.into(),
+            method: None,
+            resource_type: None,
+            action,
+        }
+    }
+
+    /// Fulfill matching requests with a canned response
+    pub fn fulfill(url: impl Into<String>, response: RouteResponse) -> Self {
+        Self::new(url, RouteAction::Fulfill(response))
+    }
+
+    /// Abort matching requests
+    pub fn abort(url: impl Into<String>) -> Self {
+        Self::new(url, RouteAction::Abort)
+    }
+
+    /// Only match requests with this method
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into().to_ascii_uppercase());
+        self
+    }
+
+    /// Only match requests for this resource type
+    pub fn with_resource_type(mut self, resource_type: ResourceType) -> Self {
+        self.resource_type = Some(resource_type);
+        self
+    }
+
+    /// Whether the route applies to a request
+    pub fn matches(&self, request: &InterceptedRequest) -> bool {
+        self.method.as_ref().map_or(true, |method| method.eq_ignore_ascii_case(&request.method))
+            && self.resource_type.map_or(true, |kind| kind == request.resource_type)
+            && glob_match(&self.url, &request.url)
+    }
+}
+
+/// The action of the most recently added route matching `request`
+pub fn route_request<'a>(routes: &'a [Route], request: &InterceptedRequest) -> Option<&'a RouteAction> {
+    routes.iter().rev().find(|route| route.matches(request)).map(|route| &route.action)
+}
+
+/// Match a URL against a glob
+///
+/// `*` matches any run of characters except `/`, `**` matches anything and
+/// `?` matches a single character. Everything else matches literally.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_from(&pattern, &text)
+}
+
+fn glob_match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            (0..=text.len()).any(|skip| glob_match_from(rest, &text[skip..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            let segment = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+            (0..=segment).any(|skip| glob_match_from(rest, &text[skip..]))
+        }
+        Some('?') => !text.is_empty() && glob_match_from(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match_from(&pattern[1..], &text[1..]),
+    }
+}
@@ -0,0 +1,174 @@
+//! Resolution of semantic selectors through an LLM
+//!
+//! A [`Selector::Semantic`] describes an element in natural language. The
+//! [`SemanticResolver`] takes a [`PageSnapshot`] of the page, asks an
+//! [`LlmProvider`] which numbered element matches, and turns the answer
+//! into a concrete CSS selector. Answers are cached per URL pattern and
+//! description, so repeated runs over similar pages skip the LLM call.
+
+use super::page::url_pattern;
+use super::snapshot::PageSnapshot;
+use super::{Browser, Selector};
+use crate::error::{Error, Result};
+use crate::llms::LlmProvider;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Default token budget for the page listing sent to the LLM
+const DEFAULT_TOKEN_BUDGET: usize = 4000;
+
+/// System message for element selection
+const SYSTEM_MESSAGE: &str = "You identify elements on web pages. You are given a page with numbered \
+    interactive elements and a description. Reply with only the number of the element that best \
+    matches the description, or -1 if none of them match.";
+
+/// Resolves semantic selectors to concrete ones using an LLM
+pub struct SemanticResolver {
+    /// The LLM provider
+    llm: Box<dyn LlmProvider>,
+    /// Resolved selectors by (URL pattern, description)
+    cache: Mutex<HashMap<(String, String), Selector>>,
+    /// Sampling temperature for the LLM call
+    temperature: f32,
+    /// Token budget for the page listing
+    token_budget: usize,
+}
+
+impl std::fmt::Debug for SemanticResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SemanticResolver")
+            .field("llm", &self.llm)
+            .field("cached", &self.cache.lock().map(|c| c.len()).unwrap_or_default())
+            .finish()
+    }
+}
+
+impl SemanticResolver {
+    /// Create a resolver backed by the given LLM provider
+    pub fn new(llm: Box<dyn LlmProvider>) -> Self {
+        Self {
+            llm,
+            cache: Mutex::new(HashMap::new()),
+            temperature: 0.0,
+            token_budget: DEFAULT_TOKEN_BUDGET,
+        }
+    }
+
+    /// Set the sampling temperature used for resolution
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Set the token budget for the page listing sent to the LLM
+    pub fn with_token_budget(mut self, token_budget: usize) -> Self {
+        self.token_budget = token_budget;
+        self
+    }
+
+    /// Resolve a selector, passing concrete selectors through unchanged
+    pub async fn resolve(&self, browser: &dyn Browser, selector: &Selector) -> Result<Selector> {
+        match selector {
+            Selector::Semantic(description) => {
+                let page = self.prepare(browser, description)?;
+                self.finish(page, description).await
+            }
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// Resolve a selector from synchronous code
+    ///
+    /// See [`crate::utils::block_on`] for the runtime requirements.
+    pub fn resolve_blocking(&self, browser: &dyn Browser, selector: &Selector) -> Result<Selector> {
+        match selector {
+            Selector::Semantic(description) => {
+                // Browser calls stay outside the runtime; only the LLM call is awaited
+                let page = self.prepare(browser, description)?;
+                crate::utils::block_on(self.finish(page, description))?
+            }
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// The cached resolution for a URL and description, if any
+    pub fn cached(&self, url: &str, description: &str) -> Option<Selector> {
+        self.cache().ok()?.get(&(url_pattern(url), description.to_string())).cloned()
+    }
+
+    /// Forget all cached resolutions
+    pub fn clear_cache(&self) {
+        if let Ok(mut cache) = self.cache() {
+            cache.clear();
+        }
+    }
+
+    /// Look up the cache, or gather what the LLM needs to pick an element
+    pub(crate) fn prepare(&self, browser: &dyn Browser, description: &str) -> Result<PageState> {
+        let url = browser.current_url()?;
+        if let Some(selector) = self.cached(&url, description) {
+            if browser.element_exists(&selector)? {
+                log::debug!("Semantic selector '{}' resolved from cache to {:?}", description, selector);
+                return Ok(PageState::Cached(selector));
+            }
+        }
+
+        let snapshot = PageSnapshot::from_html(url, &browser.get_html()?);
+        Ok(PageState::Fresh(snapshot))
+    }
+
+    /// Ask the LLM to pick an element of the snapshot and cache the answer
+    pub(crate) async fn finish(&self, page: PageState, description: &str) -> Result<Selector> {
+        let snapshot = match page {
+            PageState::Cached(selector) => return Ok(selector),
+            PageState::Fresh(snapshot) => snapshot,
+        };
+        if snapshot.elements.is_empty() {
+            return Err(Error::ElementNotFound(format!(
+                "No interactive elements on {} to match '{}'",
+                snapshot.url, description
+            )));
+        }
+
+        let prompt = format!("Description: {}\n\n{}", description, snapshot.render(self.token_budget));
+        let response = self.llm.generate_response(SYSTEM_MESSAGE, &prompt, self.temperature).await?;
+        let index = parse_index(&response.content).ok_or_else(|| {
+            Error::LlmError(format!("Could not parse an element number from '{}'", response.content.trim()))
+        })?;
+        let selector = usize::try_from(index)
+            .ok()
+            .and_then(|index| snapshot.element(index))
+            .map(|element| element.selector.clone())
+            .ok_or_else(|| {
+                Error::ElementNotFound(format!("No element on {} matches '{}'", snapshot.url, description))
+            })?;
+
+        log::debug!("Semantic selector '{}' resolved to {:?}", description, selector);
+        self.cache()?
+            .insert((url_pattern(&snapshot.url), description.to_string()), selector.clone());
+        Ok(selector)
+    }
+
+    fn cache(&self) -> Result<std::sync::MutexGuard<'_, HashMap<(String, String), Selector>>> {
+        self.cache
+            .lock()
+            .map_err(|_| Error::GenericError("Semantic selector cache lock poisoned".to_string()))
+    }
+}
+
+/// Page information gathered before the LLM call
+pub(crate) enum PageState {
+    /// A cached selector that still matches
+    Cached(Selector),
+    /// A fresh snapshot to choose from
+    Fresh(PageSnapshot),
+}
+
+/// The first (possibly negative) integer in an LLM answer
+pub(crate) fn parse_index(answer: &str) -> Option<i64> {
+    let start = answer.find(|c: char| c.is_ascii_digit())?;
+    let negative = answer[..start].ends_with('-');
+    let digits: String = answer[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    let value: i64 = digits.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
@@ -0,0 +1,397 @@
+//! Compact page representations for LLM prompts
+//!
+//! A [`PageSnapshot`] distills a page into its readable text and a numbered
+//! list of interactive elements, dropping scripts, styles, hidden content and
+//! layout wrappers. Agents read the rendered form and refer back to elements
+//! by index.
+
+use super::accessibility::{self, heading_level, is_hidden, is_interactive};
+use super::dom::{self, Dom};
+use super::{js, Browser, Selector};
+use crate::error::{Error, Result};
+use scraper::{ElementRef, Node};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Elements whose content never appears in a snapshot
+const SKIPPED: &[&str] = &["script", "style", "noscript", "template", "head", "svg", "canvas", "iframe"];
+
+/// Elements that start a new line of text
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "details", "dialog", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "footer", "form", "header", "hr", "li", "main", "nav", "ol", "p",
+    "pre", "section", "table", "td", "th", "tr", "ul",
+];
+
+/// Maximum characters of a text block in the rendered form
+const MAX_TEXT_CHARS: usize = 300;
+
+/// Maximum characters of an element label or value in the rendered form
+const MAX_LABEL_CHARS: usize = 80;
+
+/// Characters kept back for the omission note when a render is truncated
+const NOTE_CHARS: usize = 64;
+
+/// Rough number of LLM tokens in a piece of text
+///
+/// Uses the common approximation of four characters per token, which is
+/// close enough for budgeting English page text.
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.chars().count() + 3) / 4
+}
+
+/// Position and size of an element in CSS pixels, relative to the viewport
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    /// Left edge
+    pub x: f64,
+    /// Top edge
+    pub y: f64,
+    /// Width
+    pub width: f64,
+    /// Height
+    pub height: f64,
+}
+
+/// An interactive element in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotElement {
+    /// Position in the snapshot's element list
+    pub index: usize,
+    /// ARIA role, explicit or implied by the tag
+    pub role: String,
+    /// Accessible name
+    pub label: String,
+    /// Lowercase tag name
+    pub tag: String,
+    /// Selector that reaches the element on the live page
+    pub selector: Selector,
+    /// Current value of form fields
+    pub value: Option<String>,
+    /// Link target
+    pub href: Option<String>,
+    /// Checked state of checkboxes and radio buttons
+    pub checked: Option<bool>,
+    /// Whether the element is disabled
+    pub disabled: bool,
+    /// Bounding box, when the backend can measure it
+    pub bounds: Option<BoundingBox>,
+}
+
+impl fmt::Display for SnapshotElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.index, self.role)?;
+        if !self.label.is_empty() {
+            write!(f, " \"{}\"", truncate(&self.label, MAX_LABEL_CHARS))?;
+        }
+        if let Some(value) = self.value.as_deref().filter(|v| !v.is_empty()) {
+            write!(f, " value=\"{}\"", truncate(value, MAX_LABEL_CHARS))?;
+        }
+        if let Some(href) = &self.href {
+            write!(f, " -> {}", truncate(href, MAX_LABEL_CHARS))?;
+        }
+        if self.checked == Some(true) {
+            write!(f, " checked")?;
+        }
+        if self.disabled {
+            write!(f, " disabled")?;
+        }
+        if let Some(b) = &self.bounds {
+            write!(f, " @{},{} {}x{}", b.x.round(), b.y.round(), b.width.round(), b.height.round())?;
+        }
+        Ok(())
+    }
+}
+
+/// A piece of page content in document order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Block {
+    /// A run of readable text
+    Text(String),
+    /// A heading and its level
+    Heading(usize, String),
+    /// An interactive element, by index
+    Element(usize),
+}
+
+/// A distilled view of a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageSnapshot {
+    /// The page URL
+    pub url: String,
+    /// The document title
+    pub title: String,
+    /// Interactive elements, indexed in document order
+    pub elements: Vec<SnapshotElement>,
+    /// Page content in document order
+    blocks: Vec<Block>,
+}
+
+impl PageSnapshot {
+    /// Build a snapshot from HTML alone, without bounding boxes
+    pub fn from_html(url: impl Into<String>, html: &str) -> Self {
+        let dom = Dom::parse(html);
+        let title = dom
+            .select(&Selector::css("title"))
+            .ok()
+            .and_then(|ids| ids.first().map(|id| dom.text(*id)))
+            .unwrap_or_default();
+
+        let mut builder = Builder {
+            dom: &dom,
+            elements: Vec::new(),
+            blocks: Vec::new(),
+            text: String::new(),
+            heading: None,
+            muted: false,
+        };
+        builder.walk(dom.root());
+        builder.flush();
+
+        Self {
+            url: url.into(),
+            title,
+            elements: builder.elements,
+            blocks: builder.blocks,
+        }
+    }
+
+    /// Snapshot the page currently loaded in a browser
+    ///
+    /// Bounding boxes are measured with a script when the backend supports
+    /// JavaScript; otherwise they are left empty.
+    pub fn capture(browser: &mut dyn Browser) -> Result<Self> {
+        let mut snapshot = Self::from_html(browser.current_url()?, &browser.get_html()?);
+        if let Err(e) = snapshot.measure(browser) {
+            log::debug!("Page snapshot without bounding boxes: {}", e);
+        }
+        Ok(snapshot)
+    }
+
+    /// Look up an element by index
+    pub fn element(&self, index: usize) -> Option<&SnapshotElement> {
+        self.elements.get(index)
+    }
+
+    /// The selector for the element at an index
+    pub fn selector(&self, index: usize) -> Result<Selector> {
+        self.element(index)
+            .map(|element| element.selector.clone())
+            .ok_or_else(|| Error::ElementNotFound(format!("No element [{}] in the page snapshot", index)))
+    }
+
+    /// Render the snapshot as text within a token budget
+    ///
+    /// Interactive elements take priority over page text. Anything that does
+    /// not fit is dropped and summarized in a closing note.
+    pub fn render(&self, max_tokens: usize) -> String {
+        let mut header = String::new();
+        if !self.title.is_empty() {
+            header.push_str(&format!("Page: {}\n", self.title));
+        }
+        header.push_str(&format!("URL: {}\n", self.url));
+
+        let lines: Vec<(bool, String)> = self
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Text(text) => (false, truncate(text, MAX_TEXT_CHARS)),
+                Block::Heading(level, text) => (false, format!("{} {}", "#".repeat(*level), truncate(text, MAX_TEXT_CHARS))),
+                Block::Element(index) => (true, self.elements[*index].to_string()),
+            })
+            .collect();
+
+        let cost = |line: &str| line.chars().count() + 1;
+        let budget = max_tokens.saturating_mul(4);
+        let total = header.chars().count() + 1 + lines.iter().map(|(_, line)| cost(line)).sum::<usize>();
+
+        let mut keep = vec![true; lines.len()];
+        let (mut dropped_elements, mut dropped_text) = (0, 0);
+        if total > budget {
+            let mut remaining = budget.saturating_sub(header.chars().count() + 1 + NOTE_CHARS);
+            // Elements first, then text; each pass keeps a prefix in document order
+            for elements in [true, false] {
+                let mut full = false;
+                for (i, (is_element, line)) in lines.iter().enumerate() {
+                    if *is_element != elements {
+                        continue;
+                    }
+                    if !full && cost(line) <= remaining {
+                        remaining -= cost(line);
+                    } else {
+                        full = true;
+                        keep[i] = false;
+                        if elements {
+                            dropped_elements += 1;
+                        } else {
+                            dropped_text += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut out = header;
+        out.push('\n');
+        for ((_, line), kept) in lines.iter().zip(keep) {
+            if kept {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        if dropped_elements + dropped_text > 0 {
+            out.push_str(&format!(
+                "({} elements and {} text blocks omitted)\n",
+                dropped_elements, dropped_text
+            ));
+        }
+        out
+    }
+
+    /// Fill in bounding boxes by measuring the elements on the live page
+    fn measure(&mut self, browser: &mut dyn Browser) -> Result<()> {
+        if self.elements.is_empty() {
+            return Ok(());
+        }
+
+        let selectors: Vec<String> = self
+            .elements
+            .iter()
+            .map(|element| match &element.selector {
+                Selector::Css(css) => css.clone(),
+                _ => String::new(),
+            })
+            .collect();
+        let value = browser.execute_js(&js::bounding_boxes(&selectors)?)?;
+
+        let Some(boxes) = value.as_array() else {
+            return Ok(());
+        };
+        for (element, rect) in self.elements.iter_mut().zip(boxes) {
+            let numbers: Vec<f64> = rect
+                .as_array()
+                .map(|values| values.iter().filter_map(|v| v.as_f64()).collect())
+                .unwrap_or_default();
+            if let [x, y, width, height] = numbers[..] {
+                element.bounds = Some(BoundingBox { x, y, width, height });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PageSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(usize::MAX / 4))
+    }
+}
+
+/// Walks the parsed document collecting blocks and elements
+struct Builder<'a> {
+    /// The document
+    dom: &'a Dom,
+    /// Elements found so far
+    elements: Vec<SnapshotElement>,
+    /// Blocks found so far
+    blocks: Vec<Block>,
+    /// Text of the block being collected
+    text: String,
+    /// Level of the enclosing heading
+    heading: Option<usize>,
+    /// Whether text is being skipped because it already labels an element
+    muted: bool,
+}
+
+impl Builder<'_> {
+    fn walk(&mut self, element: ElementRef<'_>) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) if !self.muted => self.text.push_str(text),
+                Node::Element(_) => {
+                    let Some(el) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    let name = el.value().name();
+                    if SKIPPED.contains(&name) || is_hidden(el) {
+                        continue;
+                    }
+
+                    if is_interactive(el) {
+                        self.flush();
+                        self.push_element(el);
+                        continue;
+                    }
+
+                    let level = heading_level(name);
+                    if level.is_some() || BLOCKS.contains(&name) {
+                        self.flush();
+                    }
+                    let (outer, muted) = (self.heading, self.muted);
+                    if level.is_some() {
+                        self.heading = level;
+                    }
+                    // Label text shows up as the element's label instead
+                    if name == "label" && (el.value().attr("for").is_some() || labels_control(el)) {
+                        self.muted = true;
+                    }
+                    self.walk(el);
+                    self.muted = muted;
+                    if level.is_some() || BLOCKS.contains(&name) {
+                        self.flush();
+                    }
+                    self.heading = outer;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Finish the current run of text
+    fn flush(&mut self) {
+        let text = dom::normalize(&std::mem::take(&mut self.text));
+        if text.is_empty() {
+            return;
+        }
+        self.blocks.push(match self.heading {
+            Some(level) => Block::Heading(level, text),
+            None => Block::Text(text),
+        });
+    }
+
+    fn push_element(&mut self, el: ElementRef<'_>) {
+        let value = el.value();
+        let tag = value.name().to_string();
+        let input_type = accessibility::input_type(el);
+        let mut label = accessibility::accessible_name(self.dom, el, true);
+        if label.is_empty() {
+            label = value.attr("name").unwrap_or_default().to_string();
+        }
+
+        let index = self.elements.len();
+        self.elements.push(SnapshotElement {
+            index,
+            label,
+            role: accessibility::role(el).filter(|role| role != "none").unwrap_or_else(|| "generic".to_string()),
+            selector: Selector::css(self.dom.css_path(el.id())),
+            value: accessibility::field_value(el),
+            href: if tag == "a" { value.attr("href").map(str::to_string) } else { None },
+            checked: (tag == "input" && matches!(input_type.as_str(), "checkbox" | "radio"))
+                .then(|| value.attr("checked").is_some()),
+            disabled: value.attr("disabled").is_some() || value.attr("aria-disabled") == Some("true"),
+            tag,
+            bounds: None,
+        });
+        self.blocks.push(Block::Element(index));
+    }
+}
+
+fn labels_control(label: ElementRef<'_>) -> bool {
+    label.descendent_elements().skip(1).any(|el| matches!(el.value().name(), "input" | "select" | "textarea"))
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    }
+}
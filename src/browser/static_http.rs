@@ -0,0 +1,827 @@
+//! Static HTTP backend
+//!
+//! [`StaticBrowser`] fetches pages over plain HTTP and works on the returned
+//! HTML without running any JavaScript. It is much lighter than a real
+//! browser and well suited to server-rendered sites.
+
+use super::actionability;
+use super::dom::Dom;
+use super::storage::CookieJar;
+use super::{
+    route_request, Browser, BrowserConfig, BrowserType, HarEntry, HarOptions, HarRequest, HarResponse, HarTimings,
+    InterceptedRequest, KeyChord, PageEvent, PageId, PageInfo, ResourceType, Route, RouteAction, RouteResponse,
+    Selector, StorageState,
+};
+use crate::error::{Error, Result};
+use ego_tree::NodeId;
+use reqwest::blocking::{Client, Request, RequestBuilder};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use url::Url;
+
+/// Input types whose value is typed by the user
+const TEXT_INPUT_TYPES: &[&str] = &[
+    "text", "search", "email", "password", "tel", "url", "number", "date", "datetime-local", "month",
+    "week", "time", "color", "range", "hidden",
+];
+
+/// A loaded page and the state of its form controls
+struct Page {
+    /// The final URL after redirects
+    url: Url,
+    /// The HTTP status code
+    status: u16,
+    /// The HTML as received
+    html: String,
+    /// Values typed into form controls
+    values: HashMap<NodeId, String>,
+    /// Checkbox and radio states changed by clicks
+    checked: HashMap<NodeId, bool>,
+    /// The control that last received text or a click
+    focused: Option<NodeId>,
+}
+
+/// An open tab
+struct Tab {
+    /// The page id
+    id: PageId,
+    /// The loaded page, if any
+    page: Option<Page>,
+    /// The tab that opened this one
+    opener: Option<PageId>,
+}
+
+/// Browser working on server-rendered HTML fetched over HTTP
+pub struct StaticBrowser {
+    /// The HTTP client
+    client: Client,
+    /// The client's cookies
+    cookies: Arc<CookieJar>,
+    /// The configuration
+    config: BrowserConfig,
+    /// Open tabs, in the order they were opened
+    tabs: Vec<Tab>,
+    /// Index of the active tab
+    active: usize,
+    /// Number of tabs opened so far
+    opened: usize,
+    /// Page events not yet taken
+    events: Vec<PageEvent>,
+    /// Request routes, oldest first
+    routes: Vec<Route>,
+    /// HAR recording options, while recording
+    har: Option<HarOptions>,
+    /// Recorded requests not yet taken
+    har_entries: Mutex<Vec<HarEntry>>,
+    /// Whether the browser has been closed
+    closed: bool,
+}
+
+impl StaticBrowser {
+    /// Create a new static browser
+    pub fn new(config: BrowserConfig) -> Result<Self> {
+        let cookies = Arc::new(CookieJar::default());
+        let mut builder = Client::builder()
+            .cookie_provider(Arc::clone(&cookies))
+            .timeout(config.timeout)
+            .danger_accept_invalid_certs(config.ignore_https_errors);
+
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent.clone());
+        }
+        if let Some(locale) = &config.emulation.locale {
+            let value = HeaderValue::from_str(locale)
+                .map_err(|e| Error::ConfigurationError(format!("Invalid locale '{}': {}", locale, e)))?;
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::ACCEPT_LANGUAGE, value);
+            builder = builder.default_headers(headers);
+        }
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(
+                reqwest::Proxy::all(proxy)
+                    .map_err(|e| Error::ConfigurationError(format!("Invalid proxy '{}': {}", proxy, e)))?,
+            );
+        }
+
+        let mut browser = Self {
+            client: builder.build()?,
+            cookies,
+            config,
+            tabs: Vec::new(),
+            active: 0,
+            opened: 0,
+            events: Vec::new(),
+            routes: Vec::new(),
+            har: None,
+            har_entries: Mutex::new(Vec::new()),
+            closed: false,
+        };
+        browser.open_tab(None);
+        Ok(browser)
+    }
+
+    /// The HTTP status code of the current page
+    pub fn status(&self) -> Result<u16> {
+        Ok(self.page()?.status)
+    }
+
+    fn page(&self) -> Result<&Page> {
+        if self.closed {
+            return Err(Error::BrowserError("Browser has been closed".to_string()));
+        }
+        self.tabs[self.active]
+            .page
+            .as_ref()
+            .ok_or_else(|| Error::BrowserError("No page loaded; call navigate first".to_string()))
+    }
+
+    fn page_mut(&mut self) -> Result<&mut Page> {
+        self.page()?;
+        let active = self.active;
+        Ok(self.tabs[active].page.as_mut().expect("page checked above"))
+    }
+
+    /// Open a blank tab and return its index
+    fn open_tab(&mut self, opener: Option<PageId>) -> usize {
+        self.opened += 1;
+        self.tabs.push(Tab {
+            id: PageId::new(format!("tab-{}", self.opened)),
+            page: None,
+            opener,
+        });
+        self.tabs.len() - 1
+    }
+
+    fn index_of(&self, page: &PageId) -> Result<usize> {
+        self.tabs
+            .iter()
+            .position(|tab| tab.id == *page)
+            .ok_or_else(|| Error::BrowserError(format!("Unknown page {}", page)))
+    }
+
+    fn ensure_open(&self) -> Result<()> {
+        if self.closed {
+            return Err(Error::BrowserError("Browser has been closed".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Send a request and make the response the current page
+    fn load(&mut self, request: RequestBuilder) -> Result<()> {
+        let page = self.fetch(request)?;
+        let active = self.active;
+        self.tabs[active].page = Some(page);
+        Ok(())
+    }
+
+    /// Send a request and load the response in a background tab opened by the current page
+    fn load_popup(&mut self, request: RequestBuilder) -> Result<()> {
+        let page = self.fetch(request)?;
+        let opener = self.tabs[self.active].id.clone();
+        let url = page.url.to_string();
+        let index = self.open_tab(Some(opener.clone()));
+        self.tabs[index].page = Some(page);
+        self.events.push(PageEvent::Opened {
+            page: self.tabs[index].id.clone(),
+            opener: Some(opener),
+            url,
+        });
+        Ok(())
+    }
+
+    /// Send a request through the routes and build a page from the response
+    ///
+    /// Routes see the initial request only, not redirects followed after it.
+    fn fetch(&self, request: RequestBuilder) -> Result<Page> {
+        self.ensure_open()?;
+        let request = request.build()?;
+        let Some(options) = self.har else {
+            return self.send(request).map(|(page, _)| page);
+        };
+
+        let recorded = har_request(&request);
+        let started = SystemTime::now();
+        let clock = Instant::now();
+        let result = self.send(request);
+        let response = match &result {
+            Ok((_, response)) => {
+                HarResponse::new(response.status, &response.headers, &response.body, options.include_bodies)
+            }
+            Err(e) => HarResponse::failed(e.to_string()),
+        };
+        let timings = HarTimings::new(clock.elapsed().as_secs_f64() * 1000.0, 0.0);
+        self.har_entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(HarEntry::new(started, recorded, response, timings));
+        result.map(|(page, _)| page)
+    }
+
+    /// Send a request, returning the page and the response it was built from
+    fn send(&self, mut request: Request) -> Result<(Page, RouteResponse)> {
+        let intercepted = InterceptedRequest {
+            url: request.url().to_string(),
+            method: request.method().to_string(),
+            resource_type: ResourceType::Document,
+            headers: header_pairs(request.headers()),
+        };
+        match route_request(&self.routes, &intercepted) {
+            Some(RouteAction::Fulfill(response)) => {
+                let page = Page {
+                    url: request.url().clone(),
+                    status: response.status,
+                    html: response.text(),
+                    values: HashMap::new(),
+                    checked: HashMap::new(),
+                    focused: None,
+                };
+                return Ok((page, response.clone()));
+            }
+            Some(RouteAction::Abort) => {
+                return Err(Error::NetworkError(format!("Request to {} was aborted", intercepted.url)));
+            }
+            Some(RouteAction::Continue { headers }) => {
+                for (name, value) in headers {
+                    let name = HeaderName::from_bytes(name.as_bytes())
+                        .map_err(|e| Error::ValidationError(format!("Invalid header name '{}': {}", name, e)))?;
+                    let value = HeaderValue::from_str(value)
+                        .map_err(|e| Error::ValidationError(format!("Invalid value for header {}: {}", name, e)))?;
+                    request.headers_mut().insert(name, value);
+                }
+            }
+            None => {}
+        }
+
+        if self.config.emulation.offline {
+            return Err(Error::NetworkError(format!("Request to {} failed: the browser is offline", intercepted.url)));
+        }
+        let response = self.client.execute(request)?;
+        let url = response.url().clone();
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            log::warn!("{} returned HTTP {}", url, status);
+        }
+        let headers = header_pairs(response.headers());
+        let html = response.text()?;
+
+        let raw = RouteResponse {
+            status,
+            headers,
+            body: html.clone().into_bytes(),
+        };
+        let page = Page {
+            url,
+            status,
+            html,
+            values: HashMap::new(),
+            checked: HashMap::new(),
+            focused: None,
+        };
+        Ok((page, raw))
+    }
+
+    /// Resolve a possibly relative URL against the current page
+    fn resolve_url(&self, href: &str) -> Result<Url> {
+        let base = self.page()?.url.clone();
+        base.join(href)
+            .map_err(|e| Error::ValidationError(format!("Invalid URL '{}': {}", href, e)))
+    }
+
+    /// Whether a checkbox or radio is checked, taking clicks into account
+    fn is_checked(page: &Page, dom: &Dom, id: NodeId) -> bool {
+        page.checked
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| dom.attributes(id).contains_key("checked"))
+    }
+
+    /// The current value of a form control, taking typed text into account
+    fn control_value(page: &Page, dom: &Dom, id: NodeId) -> String {
+        if let Some(value) = page.values.get(&id) {
+            return value.clone();
+        }
+        match dom.tag(id).as_str() {
+            "textarea" => dom.element(id).map(|el| el.text().collect()).unwrap_or_default(),
+            "select" => {
+                let options = dom.options(id);
+                let selected = options
+                    .iter()
+                    .find(|option| dom.attributes(**option).contains_key("selected"))
+                    .or_else(|| options.first());
+                selected.map(|option| dom.option_value(*option)).unwrap_or_default()
+            }
+            _ => dom.attributes(id).get("value").cloned().unwrap_or_default(),
+        }
+    }
+
+    /// The form a control belongs to, honouring the `form` attribute
+    fn owner_form(dom: &Dom, id: NodeId) -> Option<NodeId> {
+        match dom.attributes(id).get("form") {
+            Some(form_id) => dom.select(&Selector::id(form_id.clone())).ok()?.into_iter().next(),
+            None => dom.closest(id, "form"),
+        }
+    }
+
+    /// Whether the element is a control that accepts typed text
+    fn is_editable(dom: &Dom, id: NodeId) -> bool {
+        match dom.tag(id).as_str() {
+            "textarea" => true,
+            "input" => {
+                let kind = dom.attributes(id).get("type").map(|t| t.to_ascii_lowercase());
+                kind.map_or(true, |kind| TEXT_INPUT_TYPES.contains(&kind.as_str()))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether clicking the element submits its form
+    fn is_submitter(dom: &Dom, id: NodeId) -> bool {
+        let attributes = dom.attributes(id);
+        let kind = attributes.get("type").map(|t| t.to_ascii_lowercase());
+        match dom.tag(id).as_str() {
+            "button" => matches!(kind.as_deref(), None | Some("submit")),
+            "input" => matches!(kind.as_deref(), Some("submit") | Some("image")),
+            _ => false,
+        }
+    }
+
+    /// Submit a form the way a browser would, with the given submit button
+    fn submit(&mut self, dom: &Dom, form: NodeId, submitter: Option<NodeId>) -> Result<()> {
+        let page = self.page()?;
+        let mut fields = Vec::new();
+
+        for control in dom.select(&Selector::css("input, select, textarea, button"))? {
+            if Self::owner_form(dom, control) != Some(form) {
+                continue;
+            }
+            let attributes = dom.attributes(control);
+            let Some(name) = attributes.get("name").filter(|name| !name.is_empty()) else {
+                continue;
+            };
+            if attributes.contains_key("disabled") {
+                continue;
+            }
+
+            let kind = attributes
+                .get("type")
+                .map(|t| t.to_ascii_lowercase())
+                .unwrap_or_else(|| "text".to_string());
+            let included = match dom.tag(control).as_str() {
+                "button" => Some(control) == submitter,
+                "input" => match kind.as_str() {
+                    "checkbox" | "radio" => Self::is_checked(page, dom, control),
+                    "submit" | "image" => Some(control) == submitter,
+                    "button" | "reset" | "file" => false,
+                    _ => true,
+                },
+                _ => true,
+            };
+            if !included {
+                continue;
+            }
+
+            let value = match kind.as_str() {
+                "checkbox" | "radio" => attributes.get("value").cloned().unwrap_or_else(|| "on".to_string()),
+                _ => Self::control_value(page, dom, control),
+            };
+            fields.push((name.clone(), value));
+        }
+
+        let form_attributes = dom.attributes(form);
+        let submitter_attributes = submitter.map(|id| dom.attributes(id)).unwrap_or_default();
+        let method = submitter_attributes
+            .get("formmethod")
+            .or_else(|| form_attributes.get("method"))
+            .map(|m| m.to_ascii_lowercase())
+            .unwrap_or_else(|| "get".to_string());
+        let action = submitter_attributes
+            .get("formaction")
+            .or_else(|| form_attributes.get("action"))
+            .cloned()
+            .unwrap_or_default();
+        let mut url = self.resolve_url(&action)?;
+
+        let request = if method == "post" {
+            let body = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&fields)
+                .finish();
+            self.client
+                .post(url)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(body)
+        } else {
+            url.set_query(None);
+            if !fields.is_empty() {
+                url.query_pairs_mut().extend_pairs(&fields);
+            }
+            self.client.get(url)
+        };
+
+        self.load(request)
+    }
+}
+
+impl Browser for StaticBrowser {
+    fn browser_type(&self) -> BrowserType {
+        self.config.browser_type
+    }
+
+    fn navigate(&mut self, url: &str) -> Result<()> {
+        let url = match &self.tabs[self.active].page {
+            Some(_) => self.resolve_url(url)?,
+            None => Url::parse(url).map_err(|e| Error::ValidationError(format!("Invalid URL '{}': {}", url, e)))?,
+        };
+        let request = self.client.get(url);
+        self.load(request)
+    }
+
+    fn current_url(&self) -> Result<String> {
+        Ok(self.page()?.url.to_string())
+    }
+
+    fn click(&mut self, selector: &Selector) -> Result<()> {
+        let dom = Dom::parse(&self.page()?.html);
+        let id = dom.select_first(selector)?;
+
+        if let Some(link) = dom.closest(id, "a") {
+            let attributes = dom.attributes(link);
+            if let Some(href) = attributes.get("href") {
+                if href.starts_with("javascript:") {
+                    return Err(Error::Unsupported(format!(
+                        "Link '{}' requires JavaScript, which the static HTTP browser does not run",
+                        href
+                    )));
+                }
+                let target = self.resolve_url(href)?;
+                let current = &self.page()?.url;
+                if target.as_str().split('#').next() == current.as_str().split('#').next() {
+                    // Fragment-only navigation stays on the loaded page
+                    self.page_mut()?.url = target;
+                    return Ok(());
+                }
+                let request = self.client.get(target);
+                if attributes.get("target").map(String::as_str) == Some("_blank") {
+                    return self.load_popup(request);
+                }
+                return self.load(request);
+            }
+        }
+
+        if Self::is_submitter(&dom, id) {
+            return match Self::owner_form(&dom, id) {
+                Some(form) => self.submit(&dom, form, Some(id)),
+                None => Ok(()),
+            };
+        }
+
+        let attributes = dom.attributes(id);
+        let kind = attributes.get("type").map(|t| t.to_ascii_lowercase());
+        if dom.tag(id) == "input" && attributes.contains_key("disabled") {
+            return Ok(());
+        }
+        match kind.as_deref() {
+            Some("checkbox") if dom.tag(id) == "input" => {
+                let checked = Self::is_checked(self.page()?, &dom, id);
+                self.page_mut()?.checked.insert(id, !checked);
+            }
+            Some("radio") if dom.tag(id) == "input" => {
+                let form = Self::owner_form(&dom, id);
+                let group: Vec<NodeId> = match attributes.get("name") {
+                    Some(name) => dom
+                        .select(&Selector::name(name.clone()))?
+                        .into_iter()
+                        .filter(|other| Self::owner_form(&dom, *other) == form)
+                        .collect(),
+                    None => Vec::new(),
+                };
+                let page = self.page_mut()?;
+                for other in group {
+                    page.checked.insert(other, false);
+                }
+                page.checked.insert(id, true);
+            }
+            _ if Self::is_editable(&dom, id) => self.page_mut()?.focused = Some(id),
+            _ => log::debug!("Click on {:?} has no effect without JavaScript", selector),
+        }
+        Ok(())
+    }
+
+    fn hover(&mut self, selector: &Selector) -> Result<()> {
+        Dom::parse(&self.page()?.html).select_first(selector)?;
+        log::debug!("Hover over {:?} has no effect without JavaScript", selector);
+        Ok(())
+    }
+
+    fn select_option(&mut self, selector: &Selector, choice: &str) -> Result<()> {
+        let dom = Dom::parse(&self.page()?.html);
+        let id = dom.select_first(selector)?;
+        if dom.tag(id) != "select" {
+            return Err(Error::BrowserError(format!("Element {:?} is not a <select>", selector)));
+        }
+
+        let option = dom
+            .find_option(id, choice)
+            .ok_or_else(|| Error::ElementNotFound(format!("Option '{}' in {:?}", choice, selector)))?;
+        self.page_mut()?.values.insert(id, dom.option_value(option));
+        Ok(())
+    }
+
+    fn set_checked(&mut self, selector: &Selector, checked: bool) -> Result<()> {
+        let dom = Dom::parse(&self.page()?.html);
+        let id = dom.select_first(selector)?;
+        let kind = dom.attributes(id).get("type").map(|t| t.to_ascii_lowercase());
+        let radio = match kind.as_deref() {
+            Some("checkbox") if dom.tag(id) == "input" => false,
+            Some("radio") if dom.tag(id) == "input" => true,
+            _ => {
+                return Err(Error::BrowserError(format!(
+                    "Element {:?} is not a checkbox or radio button",
+                    selector
+                )))
+            }
+        };
+
+        if Self::is_checked(self.page()?, &dom, id) == checked {
+            return Ok(());
+        }
+        if radio && !checked {
+            return Err(Error::BrowserError(format!("Radio button {:?} cannot be unchecked", selector)));
+        }
+        self.click(selector)
+    }
+
+    fn scroll_into_view(&mut self, selector: &Selector) -> Result<()> {
+        // Without layout every element counts as in view
+        Dom::parse(&self.page()?.html).select_first(selector)?;
+        Ok(())
+    }
+
+    fn scroll_by(&mut self, _x: f64, _y: f64) -> Result<()> {
+        self.page()?;
+        Ok(())
+    }
+
+    fn press_key(&mut self, chord: &KeyChord) -> Result<()> {
+        let page = self.page()?;
+        let id = page.focused.ok_or_else(|| {
+            Error::BrowserError("No element has focus; type into or click a text field first".to_string())
+        })?;
+        let dom = Dom::parse(&page.html);
+        let mut value = Self::control_value(page, &dom, id);
+
+        match (chord.key.as_str(), chord.text()) {
+            // Enter in a single-line field submits its form with the default button
+            ("Enter", Some(_)) if dom.tag(id) == "input" => {
+                let Some(form) = Self::owner_form(&dom, id) else {
+                    return Ok(());
+                };
+                let submitter = dom
+                    .select(&Selector::css("button, input"))?
+                    .into_iter()
+                    .find(|control| {
+                        Self::is_submitter(&dom, *control) && Self::owner_form(&dom, *control) == Some(form)
+                    });
+                return self.submit(&dom, form, submitter);
+            }
+            ("Enter", Some(_)) => value.push('\n'),
+            ("Backspace", _) => {
+                value.pop();
+            }
+            (_, Some(text)) => value.push_str(&text),
+            _ => {
+                log::debug!("Key {} has no effect without JavaScript", chord);
+                return Ok(());
+            }
+        }
+        self.page_mut()?.values.insert(id, value);
+        Ok(())
+    }
+
+    fn type_text(&mut self, selector: &Selector, text: &str) -> Result<()> {
+        let dom = Dom::parse(&self.page()?.html);
+        let id = dom.select_first(selector)?;
+
+        if !Self::is_editable(&dom, id) {
+            return Err(Error::BrowserError(format!("Element {:?} does not accept text input", selector)));
+        }
+
+        let page = self.page_mut()?;
+        page.values.insert(id, text.to_string());
+        page.focused = Some(id);
+        Ok(())
+    }
+
+    fn get_text(&self, selector: &Selector) -> Result<String> {
+        let page = self.page()?;
+        let dom = Dom::parse(&page.html);
+        let id = dom.select_first(selector)?;
+
+        match dom.tag(id).as_str() {
+            "input" | "textarea" | "select" => Ok(Self::control_value(page, &dom, id)),
+            _ => Ok(dom.text(id)),
+        }
+    }
+
+    fn get_attributes(&self, selector: &Selector) -> Result<HashMap<String, String>> {
+        let page = self.page()?;
+        let dom = Dom::parse(&page.html);
+        let id = dom.select_first(selector)?;
+
+        let mut attributes = dom.attributes(id);
+        if let Some(value) = page.values.get(&id) {
+            attributes.insert("value".to_string(), value.clone());
+        }
+        match page.checked.get(&id) {
+            Some(true) => {
+                attributes.insert("checked".to_string(), String::new());
+            }
+            Some(false) => {
+                attributes.remove("checked");
+            }
+            None => {}
+        }
+        Ok(attributes)
+    }
+
+    fn element_exists(&self, selector: &Selector) -> Result<bool> {
+        let dom = Dom::parse(&self.page()?.html);
+        Ok(!dom.select(selector)?.is_empty())
+    }
+
+    fn wait_for_element(&mut self, selector: &Selector, timeout: Duration) -> Result<()> {
+        // Static content never changes, so there is nothing to wait for
+        if self.element_exists(selector)? {
+            Ok(())
+        } else {
+            Err(Error::TimeoutError(format!(
+                "Element {:?} not present on static page (waited {:?})",
+                selector, timeout
+            )))
+        }
+    }
+
+    fn wait_for_actionable(&mut self, selector: &Selector, _timeout: Duration) -> Result<()> {
+        // Static content never changes, so the first check is final
+        actionability::check_once(&self.page()?.html, selector)
+    }
+
+    fn wait_for_navigation(&mut self, _timeout: Duration) -> Result<()> {
+        // Navigation completes synchronously
+        self.page().map(|_| ())
+    }
+
+    fn take_screenshot(&self, _path: &str) -> Result<()> {
+        Err(Error::Unsupported(
+            "Screenshots are not supported by the static HTTP browser".to_string(),
+        ))
+    }
+
+    fn execute_js(&mut self, _script: &str) -> Result<serde_json::Value> {
+        Err(Error::Unsupported(
+            "JavaScript execution is not supported by the static HTTP browser".to_string(),
+        ))
+    }
+
+    fn get_html(&self) -> Result<String> {
+        Ok(self.page()?.html.clone())
+    }
+
+    fn new_page(&mut self, url: Option<&str>) -> Result<PageId> {
+        self.ensure_open()?;
+        let page = match url {
+            Some(url) => {
+                let url = Url::parse(url).map_err(|e| Error::ValidationError(format!("Invalid URL '{}': {}", url, e)))?;
+                Some(self.fetch(self.client.get(url))?)
+            }
+            None => None,
+        };
+        let index = self.open_tab(None);
+        self.tabs[index].page = page;
+        self.active = index;
+        Ok(self.tabs[index].id.clone())
+    }
+
+    fn pages(&self) -> Result<Vec<PageInfo>> {
+        self.ensure_open()?;
+        Ok(self
+            .tabs
+            .iter()
+            .map(|tab| PageInfo {
+                id: tab.id.clone(),
+                url: tab.page.as_ref().map(|page| page.url.to_string()).unwrap_or_else(|| "about:blank".to_string()),
+                opener: tab.opener.clone(),
+            })
+            .collect())
+    }
+
+    fn active_page(&self) -> Result<PageId> {
+        Ok(self.tabs[self.active].id.clone())
+    }
+
+    fn switch_to_page(&mut self, page: &PageId) -> Result<()> {
+        self.ensure_open()?;
+        self.active = self.index_of(page)?;
+        Ok(())
+    }
+
+    fn close_page(&mut self, page: &PageId) -> Result<()> {
+        self.ensure_open()?;
+        let index = self.index_of(page)?;
+        if self.tabs.len() == 1 {
+            return Err(Error::BrowserError("Cannot close the last page".to_string()));
+        }
+
+        self.tabs.remove(index);
+        // Closing the active tab activates the most recently opened one
+        if index == self.active {
+            self.active = self.tabs.len() - 1;
+        } else if index < self.active {
+            self.active -= 1;
+        }
+        Ok(())
+    }
+
+    fn take_page_events(&mut self) -> Result<Vec<PageEvent>> {
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn storage_state(&self) -> Result<StorageState> {
+        self.ensure_open()?;
+        // Without JavaScript there is no web storage, only cookies
+        Ok(StorageState {
+            cookies: self.cookies.cookies(),
+            origins: Vec::new(),
+        })
+    }
+
+    fn set_storage_state(&mut self, state: &StorageState) -> Result<()> {
+        self.ensure_open()?;
+        for cookie in &state.cookies {
+            self.cookies.insert(cookie)?;
+        }
+        if !state.origins.is_empty() {
+            log::debug!("Ignoring web storage, which the static HTTP browser does not support");
+        }
+        Ok(())
+    }
+
+    fn set_routes(&mut self, routes: &[Route]) -> Result<()> {
+        self.routes = routes.to_vec();
+        Ok(())
+    }
+
+    fn set_har_recording(&mut self, options: Option<HarOptions>) -> Result<()> {
+        self.har = options;
+        Ok(())
+    }
+
+    fn take_har_entries(&mut self) -> Result<Vec<HarEntry>> {
+        let entries = self.har_entries.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(std::mem::take(entries))
+    }
+
+    fn health_check(&self) -> Result<()> {
+        self.ensure_open()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.ensure_open()?;
+        // A fresh client starts with an empty cookie jar and one blank tab
+        *self = Self::new(self.config.clone())?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        for tab in &mut self.tabs {
+            tab.page = None;
+        }
+        self.closed = true;
+        Ok(())
+    }
+}
+
+fn header_pairs(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_string()))
+        .collect()
+}
+
+/// Describe a request for the HAR log
+fn har_request(request: &Request) -> HarRequest {
+    let headers = header_pairs(request.headers());
+    let recorded = HarRequest::new(request.method().as_str(), request.url().as_str(), &headers);
+    match request.body().and_then(|body| body.as_bytes()) {
+        Some(body) => {
+            let mime_type = request
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            recorded.with_body(mime_type, String::from_utf8_lossy(body))
+        }
+        None => recorded,
+    }
+}
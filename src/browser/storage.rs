@@ -0,0 +1,377 @@
+//! Browser storage state
+//!
+//! [`StorageState`] captures cookies plus local and session storage per
+//! origin, so that a logged-in session can be saved and restored on a later
+//! run. Named profiles live under the config directory. With the `security`
+//! feature, state files can be encrypted with a passphrase.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the passphrase for encrypted profiles
+pub const PROFILE_PASSPHRASE_ENV: &str = "LLAMACLICK_PROFILE_PASSPHRASE";
+
+/// Directory under the config dir holding named profiles
+const PROFILES_DIR: &str = "profiles";
+
+/// A browser cookie
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    /// The cookie name
+    pub name: String,
+    /// The cookie value
+    pub value: String,
+    /// The domain; a leading dot marks a cookie shared with subdomains
+    pub domain: String,
+    /// The path
+    pub path: String,
+    /// Expiry as seconds since the epoch; `None` for session cookies
+    #[serde(default)]
+    pub expires: Option<f64>,
+    /// Whether the cookie is hidden from scripts
+    #[serde(default)]
+    pub http_only: bool,
+    /// Whether the cookie is only sent over HTTPS
+    #[serde(default)]
+    pub secure: bool,
+    /// The SameSite policy (`Strict`, `Lax` or `None`), when set
+    #[serde(default)]
+    pub same_site: Option<String>,
+}
+
+impl Cookie {
+    /// Create a session cookie for `domain` with path `/`
+    pub fn new(name: impl Into<String>, value: impl Into<String>, domain: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            domain: domain.into(),
+            path: "/".to_string(),
+            expires: None,
+            http_only: false,
+            secure: false,
+            same_site: None,
+        }
+    }
+
+    /// The domain without the leading dot
+    pub fn host(&self) -> &str {
+        self.domain.trim_start_matches('.')
+    }
+
+    /// Whether the cookie would be sent to `host`
+    pub fn matches_host(&self, host: &str) -> bool {
+        let domain = self.host();
+        host == domain || (self.domain.starts_with('.') && host.ends_with(&format!(".{}", domain)))
+    }
+}
+
+/// A storage entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageItem {
+    /// The key
+    pub name: String,
+    /// The value
+    pub value: String,
+}
+
+/// Local and session storage of one origin
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginStorage {
+    /// The origin, e.g. `https://example.com`
+    pub origin: String,
+    /// Entries of `localStorage`
+    #[serde(default)]
+    pub local_storage: Vec<StorageItem>,
+    /// Entries of `sessionStorage`
+    #[serde(default)]
+    pub session_storage: Vec<StorageItem>,
+}
+
+/// Cookies and per-origin storage of a browser
+///
+/// Cookies are captured for every site, but browsers only hand out the web
+/// storage of the page that is loaded: the DevTools and WebDriver backends
+/// capture the active page's origin alone, so visit each origin whose storage
+/// should be kept and combine the states with [`StorageState::merge`].
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct StorageState {
+    /// All cookies
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    /// Storage per origin
+    #[serde(default)]
+    pub origins: Vec<OriginStorage>,
+}
+
+/// On-disk envelope of an encrypted state file
+#[cfg(feature = "security")]
+#[derive(Serialize, Deserialize)]
+struct Encrypted {
+    /// The cipher, always `chacha20poly1305`
+    encrypted: String,
+    /// Base64 Argon2id salt
+    salt: String,
+    /// Base64 nonce
+    nonce: String,
+    /// Base64 ciphertext of the JSON state
+    data: String,
+}
+
+impl StorageState {
+    /// Whether the state holds nothing
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty() && self.origins.is_empty()
+    }
+
+    /// The storage of an origin
+    pub fn origin(&self, origin: &str) -> Option<&OriginStorage> {
+        self.origins.iter().find(|storage| storage.origin == origin)
+    }
+
+    /// Add the cookies and storage of `other`, replacing entries with the same key
+    ///
+    /// Cookies are keyed by name, domain and path; storage by origin.
+    pub fn merge(&mut self, other: &StorageState) {
+        for cookie in &other.cookies {
+            self.cookies
+                .retain(|c| (&c.name, &c.domain, &c.path) != (&cookie.name, &cookie.domain, &cookie.path));
+            self.cookies.push(cookie.clone());
+        }
+        for storage in &other.origins {
+            self.origins.retain(|s| s.origin != storage.origin);
+            self.origins.push(storage.clone());
+        }
+    }
+
+    /// Serialize to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        if value.get("encrypted").is_some() {
+            return Err(Error::ConfigurationError(
+                "Storage state is encrypted; a passphrase is required to load it".to_string(),
+            ));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Write the state to a JSON file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::utils::write_to_file(path, &self.to_json()?)
+    }
+
+    /// Read the state from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_json(&crate::utils::read_from_file(path)?)
+    }
+
+    /// Write the state to a file encrypted with `passphrase`
+    ///
+    /// The key is derived with Argon2id from the passphrase and a random
+    /// salt, and the JSON is sealed with ChaCha20-Poly1305.
+    #[cfg(feature = "security")]
+    pub fn save_encrypted(&self, path: &Path, passphrase: &str) -> Result<()> {
+        use base64::Engine;
+        use chacha20poly1305::aead::rand_core::RngCore;
+        use chacha20poly1305::aead::{Aead, AeadCore, OsRng};
+        use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?.into());
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let data = cipher
+            .encrypt(&nonce, self.to_json()?.as_bytes())
+            .map_err(|_| Error::GenericError("Failed to encrypt storage state".to_string()))?;
+
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let envelope = Encrypted {
+            encrypted: "chacha20poly1305".to_string(),
+            salt: base64.encode(salt),
+            nonce: base64.encode(nonce),
+            data: base64.encode(data),
+        };
+        crate::utils::write_to_file(path, &serde_json::to_string_pretty(&envelope)?)
+    }
+
+    /// Read a state file encrypted with [`StorageState::save_encrypted`]
+    #[cfg(feature = "security")]
+    pub fn load_encrypted(path: &Path, passphrase: &str) -> Result<Self> {
+        use base64::Engine;
+        use chacha20poly1305::aead::Aead;
+        use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce};
+
+        let envelope: Encrypted = serde_json::from_str(&crate::utils::read_from_file(path)?)
+            .map_err(|e| Error::ConfigurationError(format!("{} is not an encrypted storage state: {}", path.display(), e)))?;
+        if envelope.encrypted != "chacha20poly1305" {
+            return Err(Error::ConfigurationError(format!("Unsupported cipher '{}'", envelope.encrypted)));
+        }
+
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let decode = |field: &str| {
+            base64
+                .decode(field)
+                .map_err(|e| Error::ConfigurationError(format!("Corrupt storage state: {}", e)))
+        };
+        let (salt, nonce, data) = (decode(&envelope.salt)?, decode(&envelope.nonce)?, decode(&envelope.data)?);
+        if nonce.len() != 12 {
+            return Err(Error::ConfigurationError("Corrupt storage state: bad nonce".to_string()));
+        }
+
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?.into());
+        let json = cipher
+            .decrypt(Nonce::from_slice(&nonce), data.as_slice())
+            .map_err(|_| Error::ConfigurationError("Wrong passphrase or corrupt storage state".to_string()))?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// Path of the state file of a named profile
+    ///
+    /// Profiles are stored as `profiles/<name>.json` under the config
+    /// directory. Names may contain letters, digits, `-` and `_`.
+    pub fn profile_path(name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(Error::ValidationError(format!(
+                "Invalid profile name '{}'; use letters, digits, '-' and '_'",
+                name
+            )));
+        }
+        Ok(crate::utils::config_dir()?.join(PROFILES_DIR).join(format!("{}.json", name)))
+    }
+
+    /// Load a named profile, or `None` if it has not been saved yet
+    ///
+    /// With the `security` feature, the profile is decrypted with the
+    /// passphrase in `LLAMACLICK_PROFILE_PASSPHRASE` when that is set.
+    pub fn load_profile(name: &str) -> Result<Option<Self>> {
+        let path = Self::profile_path(name)?;
+        if !crate::utils::file_exists(&path) {
+            return Ok(None);
+        }
+        #[cfg(feature = "security")]
+        if let Ok(passphrase) = std::env::var(PROFILE_PASSPHRASE_ENV) {
+            return Self::load_encrypted(&path, &passphrase).map(Some);
+        }
+        Self::load(&path).map(Some)
+    }
+
+    /// Save the state as a named profile, returning the file path
+    ///
+    /// With the `security` feature, the profile is encrypted with the
+    /// passphrase in `LLAMACLICK_PROFILE_PASSPHRASE` when that is set.
+    pub fn save_profile(&self, name: &str) -> Result<PathBuf> {
+        let path = Self::profile_path(name)?;
+        #[cfg(feature = "security")]
+        if let Ok(passphrase) = std::env::var(PROFILE_PASSPHRASE_ENV) {
+            self.save_encrypted(&path, &passphrase)?;
+            return Ok(path);
+        }
+        self.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// Derive a 256-bit key from a passphrase
+#[cfg(feature = "security")]
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::GenericError(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+/// Cookie store for HTTP clients that can export and import its cookies
+#[derive(Default)]
+pub(crate) struct CookieJar(std::sync::RwLock<cookie_store::CookieStore>);
+
+impl CookieJar {
+    /// All unexpired cookies
+    pub(crate) fn cookies(&self) -> Vec<Cookie> {
+        let store = self.0.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        store
+            .iter_unexpired()
+            .map(|cookie| Cookie {
+                name: cookie.name().to_string(),
+                value: cookie.value().to_string(),
+                domain: match &cookie.domain {
+                    cookie_store::CookieDomain::Suffix(domain) => format!(".{}", domain),
+                    cookie_store::CookieDomain::HostOnly(host) => host.clone(),
+                    _ => String::new(),
+                },
+                path: String::from(&cookie.path),
+                expires: match &cookie.expires {
+                    cookie_store::CookieExpiration::AtUtc(at) => Some(at.unix_timestamp() as f64),
+                    cookie_store::CookieExpiration::SessionEnd => None,
+                },
+                http_only: cookie.http_only().unwrap_or(false),
+                secure: cookie.secure().unwrap_or(false),
+                same_site: cookie.same_site().map(|same_site| same_site.to_string()),
+            })
+            .collect()
+    }
+
+    /// Add a cookie, replacing any with the same name, domain and path
+    pub(crate) fn insert(&self, cookie: &Cookie) -> Result<()> {
+        let scheme = if cookie.secure { "https" } else { "http" };
+        let url = url::Url::parse(&format!("{}://{}{}", scheme, cookie.host(), cookie.path))
+            .map_err(|e| Error::ValidationError(format!("Invalid cookie domain '{}': {}", cookie.domain, e)))?;
+
+        let mut header = format!("{}={}; Path={}", cookie.name, cookie.value, cookie.path);
+        if cookie.domain.starts_with('.') {
+            header.push_str(&format!("; Domain={}", cookie.host()));
+        }
+        if let Some(expires) = cookie.expires {
+            let max_age = expires as i64 - crate::utils::timestamp() as i64;
+            header.push_str(&format!("; Max-Age={}", max_age.max(0)));
+        }
+        if cookie.secure {
+            header.push_str("; Secure");
+        }
+        if cookie.http_only {
+            header.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = &cookie.same_site {
+            header.push_str(&format!("; SameSite={}", same_site));
+        }
+
+        let mut store = self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        store
+            .parse(&header, &url)
+            .map_err(|e| Error::ValidationError(format!("Invalid cookie '{}': {}", cookie.name, e)))?;
+        Ok(())
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &reqwest::header::HeaderValue>, url: &url::Url) {
+        let cookies = cookie_headers
+            .filter_map(|header| header.to_str().ok())
+            .filter_map(|header| cookie_store::RawCookie::parse(header.to_string()).ok());
+        let mut store = self.0.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        store.store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &url::Url) -> Option<reqwest::header::HeaderValue> {
+        let store = self.0.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let header = store
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        reqwest::header::HeaderValue::from_str(&header).ok()
+    }
+}
@@ -0,0 +1,1054 @@
+//! W3C WebDriver backend
+//!
+//! [`WebDriverBrowser`] drives Firefox, Edge (or any other W3C compliant
+//! browser) through its driver's HTTP endpoint, e.g. geckodriver or
+//! msedgedriver.
+
+use super::{
+    js, Browser, BrowserConfig, BrowserType, ColorScheme, Cookie, ElementState, KeyChord, MouseButton, OriginStorage,
+    PageEvent, PageId, PageInfo, Selector, StorageState,
+};
+use super::input::key_definition;
+use crate::error::{Error, Result};
+use base64::Engine;
+use reqwest::blocking::Client;
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Key under which WebDriver returns element references
+pub const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// Key under which WebDriver returns shadow root references
+pub const SHADOW_ROOT_KEY: &str = "shadow-6066-11e4-a52e-4f735466cecf";
+
+/// Environment variable overriding the driver executable
+pub const WEBDRIVER_PATH_ENV: &str = "LLAMACLICK_WEBDRIVER_PATH";
+
+/// Interval between polls while waiting for page state
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Duration of the pointer move between the source and target of a drag
+const DRAG_DURATION_MS: u64 = 250;
+
+/// A WebDriver locator strategy and value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locator {
+    /// The strategy, e.g. `css selector` or `xpath`
+    pub using: &'static str,
+    /// The selector expression
+    pub value: String,
+}
+
+impl Locator {
+    fn css(value: String) -> Self {
+        Self { using: "css selector", value }
+    }
+
+    fn xpath(value: String) -> Self {
+        Self { using: "xpath", value }
+    }
+}
+
+/// Map a selector to a WebDriver locator strategy
+pub fn locator(selector: &Selector) -> Result<Locator> {
+    match selector {
+        Selector::Css(css) => Ok(Locator::css(css.clone())),
+        Selector::XPath(xpath) => Ok(Locator::xpath(xpath.clone())),
+        Selector::Id(id) => Ok(Locator::css(format!("[id={}]", css_string(id)))),
+        Selector::Class(class) => Ok(Locator::css(format!("[class~={}]", css_string(class)))),
+        Selector::Name(name) => Ok(Locator::css(format!("[name={}]", css_string(name)))),
+        Selector::Text(text) => {
+            // Deepest elements whose text contains the needle
+            let needle = xpath_string(text);
+            Ok(Locator::xpath(format!(
+                "//*[contains(normalize-space(.), {0})][not(*[contains(normalize-space(.), {0})])]",
+                needle
+            )))
+        }
+        Selector::Semantic(description) => Err(Error::BrowserError(format!(
+            "Semantic selector '{}' must be resolved before reaching the driver",
+            description
+        ))),
+        Selector::Within(..) => Err(Error::BrowserError(format!(
+            "Scoped selector {:?} is resolved one step at a time and has no single locator",
+            selector
+        ))),
+        Selector::Relative(..) | Selector::Nth(..) | Selector::HasText(..) | Selector::Visible(_) | Selector::Or(_) => {
+            Err(Error::BrowserError(format!(
+                "Compound selector {} is resolved by script and has no locator",
+                selector
+            )))
+        }
+    }
+}
+
+/// Where a WebDriver element search starts
+enum SearchContext {
+    /// The current frame's document
+    Document,
+    /// The descendants of an element
+    Element(String),
+    /// An element's shadow root
+    ShadowRoot(String),
+}
+
+impl SearchContext {
+    /// The script argument standing for the context, if it is not the document
+    fn script_args(&self) -> Vec<Value> {
+        match self {
+            Self::Document => Vec::new(),
+            Self::Element(element) => vec![json!({ ELEMENT_KEY: element })],
+            Self::ShadowRoot(root) => vec![json!({ SHADOW_ROOT_KEY: root })],
+        }
+    }
+
+    /// The context's Find Elements endpoint
+    fn elements_path(&self) -> String {
+        match self {
+            Self::Document => "elements".to_string(),
+            Self::Element(element) => format!("element/{}/elements", element),
+            Self::ShadowRoot(root) => format!("shadow/{}/elements", root),
+        }
+    }
+}
+
+/// Quote a string for use in a CSS attribute selector
+fn css_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quote a string as an XPath 1.0 literal
+fn xpath_string(value: &str) -> String {
+    if !value.contains('\'') {
+        format!("'{}'", value)
+    } else if !value.contains('"') {
+        format!("\"{}\"", value)
+    } else {
+        let parts: Vec<String> = value.split('\'').map(|part| format!("'{}'", part)).collect();
+        format!("concat({})", parts.join(", \"'\", "))
+    }
+}
+
+/// Browser driven over the W3C WebDriver protocol
+pub struct WebDriverBrowser {
+    /// The HTTP client
+    client: Client,
+    /// The driver endpoint, e.g. `http://localhost:4444`
+    driver_url: String,
+    /// The WebDriver session id
+    session_id: String,
+    /// The configuration
+    config: BrowserConfig,
+    /// Known window handles, in the order they were seen
+    handles: Vec<String>,
+    /// The window handle commands go to
+    current: String,
+    /// Page events found while syncing handles, not yet taken
+    page_events: Vec<PageEvent>,
+    /// The driver process, when launched by us
+    process: Option<Child>,
+    /// Whether the session has been closed
+    closed: bool,
+}
+
+impl WebDriverBrowser {
+    /// Launch the driver for the configured browser and start a session
+    ///
+    /// The driver is taken from `LLAMACLICK_WEBDRIVER_PATH` when set, and
+    /// otherwise the browser's usual driver (geckodriver, msedgedriver, ...)
+    /// is looked up on the `PATH`.
+    pub fn launch(config: BrowserConfig) -> Result<Self> {
+        let executable = std::env::var(WEBDRIVER_PATH_ENV)
+            .unwrap_or_else(|_| driver_executable(config.browser_type).to_string());
+
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let port_args = match config.browser_type {
+            BrowserType::Safari => vec!["-p".to_string(), port.to_string()],
+            _ => vec![format!("--port={}", port)],
+        };
+
+        let mut child = Command::new(&executable)
+            .args(port_args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::BrowserError(format!("Failed to start {}: {}", executable, e)))?;
+
+        let driver_url = format!("http://127.0.0.1:{}", port);
+        let client = Client::builder().timeout(config.timeout).build()?;
+        if let Err(e) = wait_for_driver(&client, &driver_url, config.timeout) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+
+        let mut browser = Self::connect(&driver_url, config)?;
+        browser.process = Some(child);
+        Ok(browser)
+    }
+
+    /// Start a session on a running driver
+    pub fn connect(driver_url: &str, config: BrowserConfig) -> Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        let driver_url = driver_url.trim_end_matches('/').to_string();
+
+        let response = send(
+            &client,
+            Method::POST,
+            &format!("{}/session", driver_url),
+            Some(json!({ "capabilities": { "alwaysMatch": capabilities(&config) } })),
+        )?;
+        let session_id = response["sessionId"]
+            .as_str()
+            .ok_or_else(|| Error::BrowserError("WebDriver did not return a session id".to_string()))?
+            .to_string();
+
+        let mut browser = Self {
+            client,
+            driver_url,
+            session_id,
+            config,
+            handles: Vec::new(),
+            current: String::new(),
+            page_events: Vec::new(),
+            process: None,
+            closed: false,
+        };
+        browser.command(
+            Method::POST,
+            "window/rect",
+            Some(json!({ "width": browser.config.window_width, "height": browser.config.window_height })),
+        )?;
+        let current = browser.command(Method::GET, "window", None)?;
+        browser.current = current.as_str().unwrap_or_default().to_string();
+        browser.handles.push(browser.current.clone());
+        Ok(browser)
+    }
+
+    /// The WebDriver session id
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Send a command relative to the session, returning its `value`
+    pub fn command(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        if self.closed {
+            return Err(Error::BrowserError("Browser has been closed".to_string()));
+        }
+        let url = format!("{}/session/{}/{}", self.driver_url, self.session_id, path);
+        send(&self.client, method, &url, body)
+    }
+
+    /// Switch the session to a window
+    fn switch_window(&self, handle: &str) -> Result<()> {
+        self.command(Method::POST, "window", Some(json!({ "handle": handle })))?;
+        Ok(())
+    }
+
+    /// The URL loaded in a window, switching back to the current one afterwards
+    fn window_url(&self, handle: &str) -> Result<String> {
+        if handle == self.current {
+            return self.current_url();
+        }
+        self.switch_window(handle)?;
+        let url = self.current_url();
+        self.switch_window(&self.current)?;
+        url
+    }
+
+    /// Bring the known handles up to date, recording windows opened or closed by the browser
+    fn sync_handles(&mut self) -> Result<()> {
+        let value = self.command(Method::GET, "window/handles", None)?;
+        let handles: Vec<String> = value
+            .as_array()
+            .map(|handles| handles.iter().filter_map(|h| h.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+
+        for handle in &handles {
+            if !self.handles.contains(handle) {
+                self.handles.push(handle.clone());
+                // WebDriver does not report openers; new windows come from the current page
+                self.page_events.push(PageEvent::Opened {
+                    page: PageId::new(handle.as_str()),
+                    opener: Some(PageId::new(self.current.as_str())),
+                    url: self.window_url(handle)?,
+                });
+            }
+        }
+
+        let closed: Vec<String> = self.handles.iter().filter(|h| !handles.contains(h)).cloned().collect();
+        for handle in closed {
+            self.handles.retain(|h| *h != handle);
+            self.page_events.push(PageEvent::Closed {
+                page: PageId::new(handle.as_str()),
+            });
+            if handle == self.current {
+                if let Some(last) = self.handles.last().cloned() {
+                    self.switch_window(&last)?;
+                    self.current = last;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Look up a known window, syncing handles once if it is not known yet
+    fn known_handle(&mut self, page: &PageId) -> Result<String> {
+        if !self.handles.iter().any(|h| h == page.as_str()) {
+            self.sync_handles()?;
+        }
+        self.handles
+            .iter()
+            .find(|h| *h == page.as_str())
+            .cloned()
+            .ok_or_else(|| Error::BrowserError(format!("Unknown page {}", page)))
+    }
+
+    /// Find all elements matching the selector in `context`, returning their ids
+    ///
+    /// Scoped selectors switch into frames as they go, counting the frames
+    /// entered in `depth`. A search only stays in a frame when it found
+    /// matches there, and the first frame with matches wins, since elements
+    /// from different frames cannot be used together.
+    fn search(&self, context: &SearchContext, selector: &Selector, depth: &mut usize) -> Result<Vec<String>> {
+        let (scope, inner) = match selector {
+            Selector::Within(scope, inner) => (scope, inner),
+            Selector::Relative(..)
+            | Selector::Nth(..)
+            | Selector::HasText(..)
+            | Selector::Visible(_)
+            | Selector::Or(_) => {
+                let value = self.execute(&js::find_all_in_argument(selector)?, context.script_args())?;
+                return Ok(element_ids(&value));
+            }
+            _ => {
+                let locator = locator(selector)?;
+                let value = self.command(
+                    Method::POST,
+                    &context.elements_path(),
+                    Some(json!({ "using": locator.using, "value": locator.value })),
+                )?;
+                return Ok(element_ids(&value));
+            }
+        };
+
+        let mut found: Vec<String> = Vec::new();
+        for host in self.search(context, scope, depth)? {
+            let tag = self.command(Method::GET, &format!("element/{}/name", host), None)?;
+            let is_frame = matches!(tag.as_str().map(str::to_ascii_lowercase).as_deref(), Some("iframe" | "frame"));
+            if is_frame && !found.is_empty() {
+                continue;
+            }
+
+            let start = *depth;
+            let matches = if is_frame {
+                self.command(Method::POST, "frame", Some(json!({ "id": { ELEMENT_KEY: host } })))?;
+                *depth += 1;
+                let matches = self.search(&SearchContext::Document, inner, depth)?;
+                if matches.is_empty() {
+                    self.command(Method::POST, "frame/parent", Some(json!({})))?;
+                    *depth -= 1;
+                }
+                matches
+            } else {
+                let scope = match self.shadow_root(&host)? {
+                    Some(root) => SearchContext::ShadowRoot(root),
+                    None => SearchContext::Element(host),
+                };
+                self.search(&scope, inner, depth)?
+            };
+
+            if *depth > start {
+                return Ok(matches);
+            }
+            for element in matches {
+                if !found.contains(&element) {
+                    found.push(element);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// The open shadow root attached to an element, if any
+    fn shadow_root(&self, element: &str) -> Result<Option<String>> {
+        match self.command(Method::GET, &format!("element/{}/shadow", element), None) {
+            Ok(value) => Ok(value[SHADOW_ROOT_KEY].as_str().map(str::to_string)),
+            Err(Error::BrowserError(message)) if message.starts_with("no such shadow root") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Run `f` on the elements matching the selector, then return to the top-level document
+    fn with_elements<T>(&self, selector: &Selector, f: impl FnOnce(&[String]) -> Result<T>) -> Result<T> {
+        let mut depth = 0;
+        let result = self
+            .search(&SearchContext::Document, selector, &mut depth)
+            .and_then(|elements| f(&elements));
+        if depth > 0 {
+            self.command(Method::POST, "frame", Some(json!({ "id": null })))?;
+        }
+        result
+    }
+
+    /// Run `f` on the first element matching the selector, then return to the top-level document
+    fn with_element<T>(&self, selector: &Selector, f: impl FnOnce(&str) -> Result<T>) -> Result<T> {
+        self.with_elements(selector, |elements| {
+            let element = elements
+                .first()
+                .ok_or_else(|| Error::ElementNotFound(format!("{:?}", selector)))?;
+            f(element)
+        })
+    }
+
+    /// Run a synchronous script with the given arguments
+    fn execute(&self, script: &str, args: Vec<Value>) -> Result<Value> {
+        self.command(Method::POST, "execute/sync", Some(json!({ "script": script, "args": args })))
+    }
+
+    /// Run a function body with `el` bound to the first element matching `selector`
+    fn execute_on(&self, selector: &Selector, body: &str) -> Result<Value> {
+        self.with_element(selector, |element| {
+            self.execute(
+                &format!("const el = arguments[0];\n{}", body),
+                vec![json!({ ELEMENT_KEY: element })],
+            )
+        })
+    }
+
+    /// Perform a sequence of input source actions, then release every key and button
+    fn perform_actions(&self, actions: Value) -> Result<()> {
+        let result = self.command(Method::POST, "actions", Some(json!({ "actions": actions })));
+        self.command(Method::DELETE, "actions", None)?;
+        result.map(|_| ())
+    }
+
+    /// Move the pointer to an element's centre, then press and release `button` `clicks` times
+    fn click_element(&self, selector: &Selector, button: MouseButton, clicks: usize) -> Result<()> {
+        self.with_element(selector, |element| {
+            let mut steps = vec![pointer_move(element)];
+            for _ in 0..clicks {
+                steps.push(json!({ "type": "pointerDown", "button": button.webdriver_button() }));
+                steps.push(json!({ "type": "pointerUp", "button": button.webdriver_button() }));
+            }
+            self.perform_actions(json!([mouse(steps)]))
+        })
+    }
+
+    /// Poll `check` until it returns true or the timeout expires
+    fn poll_until(&self, timeout: Duration, what: &str, mut check: impl FnMut() -> Result<bool>) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if check()? {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::TimeoutError(format!("Timed out after {:?} waiting for {}", timeout, what)));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Browser for WebDriverBrowser {
+    fn browser_type(&self) -> BrowserType {
+        self.config.browser_type
+    }
+
+    fn navigate(&mut self, url: &str) -> Result<()> {
+        // Navigate To blocks until the page load strategy is satisfied
+        self.command(Method::POST, "url", Some(json!({ "url": url })))?;
+        Ok(())
+    }
+
+    fn current_url(&self) -> Result<String> {
+        let value = self.command(Method::GET, "url", None)?;
+        Ok(value.as_str().unwrap_or_default().to_string())
+    }
+
+    fn click(&mut self, selector: &Selector) -> Result<()> {
+        self.with_element(selector, |element| {
+            self.command(Method::POST, &format!("element/{}/click", element), Some(json!({})))?;
+            Ok(())
+        })
+    }
+
+    fn type_text(&mut self, selector: &Selector, text: &str) -> Result<()> {
+        self.with_element(selector, |element| {
+            self.command(Method::POST, &format!("element/{}/clear", element), Some(json!({})))?;
+            self.command(Method::POST, &format!("element/{}/value", element), Some(json!({ "text": text })))?;
+            Ok(())
+        })
+    }
+
+    fn hover(&mut self, selector: &Selector) -> Result<()> {
+        self.with_element(selector, |element| self.perform_actions(json!([mouse(vec![pointer_move(element)])])))
+    }
+
+    fn double_click(&mut self, selector: &Selector) -> Result<()> {
+        self.click_element(selector, MouseButton::Left, 2)
+    }
+
+    fn right_click(&mut self, selector: &Selector) -> Result<()> {
+        self.click_element(selector, MouseButton::Right, 1)
+    }
+
+    fn select_option(&mut self, selector: &Selector, choice: &str) -> Result<()> {
+        let selected = self.execute_on(selector, &js::select_option(choice)?)?;
+        if selected.as_bool() != Some(true) {
+            return Err(Error::ElementNotFound(format!("Option '{}' in {:?}", choice, selector)));
+        }
+        Ok(())
+    }
+
+    fn set_checked(&mut self, selector: &Selector, checked: bool) -> Result<()> {
+        let state = self.execute_on(selector, js::CHECKED_STATE)?;
+        if state["checked"].as_bool() == Some(checked) {
+            return Ok(());
+        }
+        if state["radio"].as_bool() == Some(true) && !checked {
+            return Err(Error::BrowserError(format!("Radio button {:?} cannot be unchecked", selector)));
+        }
+
+        self.click(selector)?;
+        let state = self.execute_on(selector, js::CHECKED_STATE)?;
+        if state["checked"].as_bool() != Some(checked) {
+            return Err(Error::BrowserError(format!(
+                "Clicking {:?} did not change its checked state",
+                selector
+            )));
+        }
+        Ok(())
+    }
+
+    fn scroll_into_view(&mut self, selector: &Selector) -> Result<()> {
+        self.execute_on(selector, js::SCROLL_INTO_VIEW)?;
+        Ok(())
+    }
+
+    fn scroll_by(&mut self, x: f64, y: f64) -> Result<()> {
+        self.execute("window.scrollBy(arguments[0], arguments[1]);", vec![json!(x), json!(y)])?;
+        Ok(())
+    }
+
+    fn drag_and_drop(&mut self, source: &Selector, target: &Selector) -> Result<()> {
+        // A reference is usable again once its frame is current, so a source
+        // in the target's frame survives the target lookup
+        let source = self.with_element(source, |source| Ok(source.to_string()))?;
+        self.with_element(target, |target| {
+            let mut drop = pointer_move(target);
+            drop["duration"] = json!(DRAG_DURATION_MS);
+            self.perform_actions(json!([mouse(vec![
+                pointer_move(&source),
+                json!({ "type": "pointerDown", "button": 0 }),
+                drop,
+                json!({ "type": "pointerUp", "button": 0 }),
+            ])]))
+        })
+    }
+
+    fn press_key(&mut self, chord: &KeyChord) -> Result<()> {
+        let modifiers: Vec<String> = chord
+            .modifiers
+            .iter()
+            .map(|modifier| key_definition(modifier.key(), None).webdriver)
+            .collect();
+        let key = chord.definition().webdriver;
+
+        let mut steps = Vec::new();
+        for value in modifiers.iter().chain([&key]) {
+            steps.push(json!({ "type": "keyDown", "value": value }));
+        }
+        for value in [&key].into_iter().chain(modifiers.iter().rev()) {
+            steps.push(json!({ "type": "keyUp", "value": value }));
+        }
+        self.perform_actions(json!([{ "type": "key", "id": "keyboard", "actions": steps }]))
+    }
+
+    fn get_text(&self, selector: &Selector) -> Result<String> {
+        let value = self.with_element(selector, |element| {
+            self.command(Method::GET, &format!("element/{}/text", element), None)
+        })?;
+        Ok(value.as_str().unwrap_or_default().to_string())
+    }
+
+    fn get_attributes(&self, selector: &Selector) -> Result<HashMap<String, String>> {
+        let value = self.with_element(selector, |element| {
+            self.execute(
+                "return Object.fromEntries(Array.from(arguments[0].attributes).map((a) => [a.name, a.value]));",
+                vec![json!({ ELEMENT_KEY: element })],
+            )
+        })?;
+
+        Ok(value
+            .as_object()
+            .map(|attributes| {
+                attributes
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn element_exists(&self, selector: &Selector) -> Result<bool> {
+        self.with_elements(selector, |elements| Ok(!elements.is_empty()))
+    }
+
+    fn wait_for_element(&mut self, selector: &Selector, timeout: Duration) -> Result<()> {
+        self.poll_until(timeout, &format!("element {:?}", selector), || {
+            self.with_elements(selector, |elements| {
+                let Some(element) = elements.first() else {
+                    return Ok(false);
+                };
+                match self.command(Method::GET, &format!("element/{}/displayed", element), None) {
+                    Ok(displayed) => Ok(displayed.as_bool().unwrap_or(false)),
+                    // The element may have been replaced between the two calls
+                    Err(Error::BrowserError(message)) if message.starts_with("stale element reference") => Ok(false),
+                    Err(e) => Err(e),
+                }
+            })
+        })
+    }
+
+    fn element_state(&self, selector: &Selector) -> Result<Option<ElementState>> {
+        match self.execute_on(selector, js::ELEMENT_STATE) {
+            Ok(state) => Ok(Some(serde_json::from_value(state)?)),
+            Err(Error::ElementNotFound(_)) => Ok(None),
+            // The element may have been replaced since it was found
+            Err(Error::BrowserError(message)) if message.starts_with("stale element reference") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn wait_for_navigation(&mut self, timeout: Duration) -> Result<()> {
+        self.poll_until(timeout, "navigation", || {
+            let state = self.execute("return document.readyState;", Vec::new())?;
+            Ok(state == "complete")
+        })
+    }
+
+    fn take_screenshot(&self, path: &str) -> Result<()> {
+        let value = self.command(Method::GET, "screenshot", None)?;
+        let data = value
+            .as_str()
+            .ok_or_else(|| Error::BrowserError("Screenshot returned no data".to_string()))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| Error::BrowserError(format!("Invalid screenshot data: {}", e)))?;
+
+        let path = std::path::Path::new(path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            crate::utils::ensure_dir(dir)?;
+        }
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn execute_js(&mut self, script: &str) -> Result<serde_json::Value> {
+        // Evaluate as an expression, matching the other backends
+        self.execute("return eval(arguments[0]);", vec![json!(script)])
+    }
+
+    fn get_html(&self) -> Result<String> {
+        let value = self.command(Method::GET, "source", None)?;
+        Ok(value.as_str().unwrap_or_default().to_string())
+    }
+
+    fn new_page(&mut self, url: Option<&str>) -> Result<PageId> {
+        let window = self.command(Method::POST, "window/new", Some(json!({ "type": "tab" })))?;
+        let handle = window["handle"]
+            .as_str()
+            .ok_or_else(|| Error::BrowserError("WebDriver did not return a window handle".to_string()))?
+            .to_string();
+
+        self.handles.push(handle.clone());
+        self.switch_window(&handle)?;
+        self.current = handle.clone();
+        if let Some(url) = url {
+            self.navigate(url)?;
+        }
+        Ok(PageId::new(handle))
+    }
+
+    fn pages(&self) -> Result<Vec<PageInfo>> {
+        let value = self.command(Method::GET, "window/handles", None)?;
+        value
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|handle| handle.as_str())
+            .map(|handle| {
+                Ok(PageInfo {
+                    id: PageId::new(handle),
+                    url: self.window_url(handle)?,
+                    opener: None,
+                })
+            })
+            .collect()
+    }
+
+    fn active_page(&self) -> Result<PageId> {
+        Ok(PageId::new(self.current.as_str()))
+    }
+
+    fn switch_to_page(&mut self, page: &PageId) -> Result<()> {
+        let handle = self.known_handle(page)?;
+        self.switch_window(&handle)?;
+        self.current = handle;
+        Ok(())
+    }
+
+    fn close_page(&mut self, page: &PageId) -> Result<()> {
+        let handle = self.known_handle(page)?;
+        if self.handles.len() == 1 {
+            return Err(Error::BrowserError("Cannot close the last page".to_string()));
+        }
+
+        if handle != self.current {
+            self.switch_window(&handle)?;
+        }
+        self.command(Method::DELETE, "window", None)?;
+        self.handles.retain(|h| *h != handle);
+
+        // Closing the active page activates the most recently opened one
+        if handle == self.current {
+            self.current = self.handles.last().cloned().unwrap_or_default();
+        }
+        self.switch_window(&self.current)
+    }
+
+    fn take_page_events(&mut self) -> Result<Vec<PageEvent>> {
+        self.sync_handles()?;
+        Ok(std::mem::take(&mut self.page_events))
+    }
+
+    fn storage_state(&self) -> Result<StorageState> {
+        // WebDriver only exposes the cookies and storage of the current page
+        let value = self.command(Method::GET, "cookie", None)?;
+        let cookies = value
+            .as_array()
+            .map(|cookies| cookies.iter().map(cookie_from_webdriver).collect())
+            .unwrap_or_default();
+
+        let storage: OriginStorage = serde_json::from_value(self.execute(&format!("return {};", js::STORAGE), vec![])?)?;
+        let origins = if storage.origin.starts_with("http") {
+            vec![storage]
+        } else {
+            Vec::new()
+        };
+        Ok(StorageState { cookies, origins })
+    }
+
+    /// Apply the parts of `state` belonging to the current page's site
+    ///
+    /// WebDriver can only set cookies and storage for the page that is
+    /// loaded, so load the state after navigating to the site.
+    fn set_storage_state(&mut self, state: &StorageState) -> Result<()> {
+        let current = url::Url::parse(&self.current_url()?)
+            .map_err(|e| Error::BrowserError(format!("Invalid current URL: {}", e)))?;
+        let host = current.host_str().unwrap_or_default();
+
+        for cookie in &state.cookies {
+            if !cookie.matches_host(host) {
+                log::warn!("Skipping cookie {} for {}: not the current site", cookie.name, cookie.domain);
+                continue;
+            }
+            self.command(Method::POST, "cookie", Some(json!({ "cookie": cookie_to_webdriver(cookie) })))?;
+        }
+
+        let origin = current.origin().ascii_serialization();
+        for storage in &state.origins {
+            if storage.origin != origin {
+                log::warn!("Skipping storage for {}: not the current origin", storage.origin);
+                continue;
+            }
+            self.execute(&format!("{};", js::restore_storage(storage)?), vec![])?;
+        }
+        Ok(())
+    }
+
+    /// Clear every window's cookies and storage, close all but one and leave it blank
+    ///
+    /// WebDriver only reaches the cookies and storage of the page a window
+    /// has loaded, so sites visited earlier in a window keep theirs.
+    fn reset(&mut self) -> Result<()> {
+        self.sync_handles()?;
+        let handles = std::mem::take(&mut self.handles);
+        let Some((keep, others)) = handles.split_last() else {
+            return Err(Error::BrowserError("The session has no windows left".to_string()));
+        };
+        for handle in others.iter().chain([keep]) {
+            self.switch_window(handle)?;
+            self.command(Method::DELETE, "cookie", None)?;
+            self.execute(&format!("return {};", js::CLEAR_STORAGE), vec![])?;
+            if handle != keep {
+                self.command(Method::DELETE, "window", None)?;
+            }
+        }
+
+        self.handles = vec![keep.clone()];
+        self.current = keep.clone();
+        self.page_events.clear();
+        self.navigate("about:blank")
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.command(Method::DELETE, "", None)?;
+        self.closed = true;
+
+        if let Some(mut child) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WebDriverBrowser {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Convert a WebDriver cookie
+/// A mouse input source performing `actions`
+fn mouse(actions: Vec<Value>) -> Value {
+    json!({ "type": "pointer", "id": "mouse", "parameters": { "pointerType": "mouse" }, "actions": actions })
+}
+
+/// The ids of the element references in a list
+fn element_ids(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|elements| {
+            elements
+                .iter()
+                .filter_map(|element| element[ELEMENT_KEY].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A pointer move to the centre of an element
+fn pointer_move(element: &str) -> Value {
+    json!({ "type": "pointerMove", "duration": 0, "origin": { ELEMENT_KEY: element }, "x": 0, "y": 0 })
+}
+
+fn cookie_from_webdriver(cookie: &Value) -> Cookie {
+    let text = |field: &str| cookie[field].as_str().unwrap_or_default().to_string();
+    Cookie {
+        name: text("name"),
+        value: text("value"),
+        domain: text("domain"),
+        path: cookie["path"].as_str().unwrap_or("/").to_string(),
+        expires: cookie["expiry"].as_f64(),
+        http_only: cookie["httpOnly"].as_bool().unwrap_or(false),
+        secure: cookie["secure"].as_bool().unwrap_or(false),
+        same_site: cookie["sameSite"].as_str().map(str::to_string),
+    }
+}
+
+/// Convert a cookie to a WebDriver cookie object
+fn cookie_to_webdriver(cookie: &Cookie) -> Value {
+    let mut value = json!({
+        "name": cookie.name,
+        "value": cookie.value,
+        "domain": cookie.domain,
+        "path": cookie.path,
+        "httpOnly": cookie.http_only,
+        "secure": cookie.secure,
+    });
+    if let Some(expires) = cookie.expires {
+        value["expiry"] = json!(expires as u64);
+    }
+    if let Some(same_site) = &cookie.same_site {
+        value["sameSite"] = json!(same_site);
+    }
+    value
+}
+
+/// The conventional driver executable for a browser type
+fn driver_executable(browser_type: BrowserType) -> &'static str {
+    match browser_type {
+        BrowserType::Chrome => "chromedriver",
+        BrowserType::Firefox => "geckodriver",
+        BrowserType::Edge => "msedgedriver",
+        BrowserType::Safari => "safaridriver",
+    }
+}
+
+/// Session capabilities for the given configuration
+fn capabilities(config: &BrowserConfig) -> Value {
+    let mut capabilities = json!({
+        "browserName": match config.browser_type {
+            BrowserType::Chrome => "chrome",
+            BrowserType::Firefox => "firefox",
+            BrowserType::Edge => "MicrosoftEdge",
+            BrowserType::Safari => "safari",
+        },
+        "acceptInsecureCerts": config.ignore_https_errors,
+    });
+
+    if let Some(proxy) = &config.proxy {
+        let host = proxy
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(proxy)
+            .trim_end_matches('/');
+        capabilities["proxy"] = json!({
+            "proxyType": "manual",
+            "httpProxy": host,
+            "sslProxy": host,
+        });
+    }
+
+    let emulation = &config.emulation;
+    match config.browser_type {
+        BrowserType::Firefox => {
+            let mut args = Vec::new();
+            if config.headless {
+                args.push("-headless".to_string());
+            }
+            let mut prefs = serde_json::Map::new();
+            if let Some(user_agent) = &config.user_agent {
+                prefs.insert("general.useragent.override".to_string(), json!(user_agent));
+            }
+            if config.block_images {
+                prefs.insert("permissions.default.image".to_string(), json!(2));
+            }
+            if let Some(scale) = emulation.device_scale_factor {
+                prefs.insert("layout.css.devPixelsPerPx".to_string(), json!(scale.to_string()));
+            }
+            if emulation.touch {
+                prefs.insert("dom.w3c_touch_events.enabled".to_string(), json!(1));
+            }
+            if let Some(locale) = &emulation.locale {
+                prefs.insert("intl.accept_languages".to_string(), json!(locale));
+                prefs.insert("intl.locale.requested".to_string(), json!(locale));
+            }
+            if let Some(geolocation) = &emulation.geolocation {
+                let location = json!({
+                    "location": { "lat": geolocation.latitude, "lng": geolocation.longitude },
+                    "accuracy": geolocation.accuracy,
+                });
+                let provider = format!("data:application/json,{}", location);
+                prefs.insert("geo.provider.network.url".to_string(), json!(provider));
+                prefs.insert("geo.prompt.testing".to_string(), json!(true));
+                prefs.insert("geo.prompt.testing.allow".to_string(), json!(true));
+            }
+            if let Some(color_scheme) = emulation.color_scheme {
+                let dark = color_scheme == ColorScheme::Dark;
+                prefs.insert("layout.css.prefers-color-scheme.content-override".to_string(), json!(u8::from(!dark)));
+            }
+            let mut options = json!({ "args": args, "prefs": prefs });
+            if let Some(timezone) = &emulation.timezone {
+                options["env"] = json!({ "TZ": timezone });
+            }
+            capabilities["moz:firefoxOptions"] = options;
+        }
+        BrowserType::Chrome | BrowserType::Edge => {
+            let mut args = vec![format!("--window-size={},{}", config.window_width, config.window_height)];
+            if config.headless {
+                args.push("--headless=new".to_string());
+            }
+            if let Some(user_agent) = &config.user_agent {
+                args.push(format!("--user-agent={}", user_agent));
+            }
+            if let Some(locale) = &emulation.locale {
+                args.push(format!("--lang={}", locale));
+            }
+            // Only the last --blink-settings switch counts, so settings share one
+            let mut blink_settings = Vec::new();
+            if config.block_images {
+                blink_settings.push("imagesEnabled=false".to_string());
+            }
+            if let Some(color_scheme) = emulation.color_scheme {
+                let dark = color_scheme == ColorScheme::Dark;
+                blink_settings.push(format!("preferredColorScheme={}", u8::from(!dark)));
+            }
+            if !blink_settings.is_empty() {
+                args.push(format!("--blink-settings={}", blink_settings.join(",")));
+            }
+            let mut options = json!({ "args": args });
+            if emulation.mobile || emulation.touch || emulation.device_scale_factor.is_some() {
+                let mut mobile_emulation = json!({
+                    "deviceMetrics": {
+                        "width": config.window_width,
+                        "height": config.window_height,
+                        "pixelRatio": emulation.device_scale_factor.unwrap_or(1.0),
+                        "mobile": emulation.mobile,
+                        "touch": emulation.touch,
+                    },
+                });
+                if let Some(user_agent) = &config.user_agent {
+                    mobile_emulation["userAgent"] = json!(user_agent);
+                }
+                options["mobileEmulation"] = mobile_emulation;
+            }
+            if let Some(locale) = &emulation.locale {
+                options["prefs"] = json!({ "intl.accept_languages": locale });
+            }
+            let key = if config.browser_type == BrowserType::Edge {
+                "ms:edgeOptions"
+            } else {
+                "goog:chromeOptions"
+            };
+            capabilities[key] = options;
+        }
+        BrowserType::Safari => {}
+    }
+
+    capabilities
+}
+
+/// Send a WebDriver request and unwrap its `value`
+fn send(client: &Client, method: Method, url: &str, body: Option<Value>) -> Result<Value> {
+    let url = url.trim_end_matches('/');
+    let mut request = client.request(method, url);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+
+    let response = request.send()?;
+    let status = response.status();
+    let payload: Value = response.json().unwrap_or(Value::Null);
+
+    if !status.is_success() || payload["value"]["error"].is_string() {
+        let error = &payload["value"];
+        return Err(Error::BrowserError(format!(
+            "{}: {}",
+            error["error"].as_str().unwrap_or("unknown error"),
+            error["message"].as_str().unwrap_or_else(|| status.as_str())
+        )));
+    }
+
+    Ok(payload["value"].clone())
+}
+
+/// Poll the driver's status endpoint until it reports ready
+fn wait_for_driver(client: &Client, driver_url: &str, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Ok(status) = send(client, Method::GET, &format!("{}/status", driver_url), None) {
+            if status["ready"].as_bool().unwrap_or(true) {
+                return Ok(());
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::TimeoutError(format!("WebDriver at {} did not become ready", driver_url)));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -1,3 +1,7 @@
+//! Configuration for LlamaClick
+//!
+//! Settings are kept as `config.toml` in the user's configuration directory.
+
 pub mod settings;
 
 use directories::ProjectDirs;
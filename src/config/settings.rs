@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Settings for LlamaClick
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Settings {
     /// LLM API settings
     pub llm: LlmSettings,
@@ -55,23 +55,12 @@ pub struct AgentSettings {
 }
 
 /// Telemetry settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TelemetrySettings {
     /// Whether to send anonymous telemetry data
     pub enabled: bool,
 }
 
-impl Default for Settings {
-    fn default() -> Self {
-        Settings {
-            llm: LlmSettings::default(),
-            browser: BrowserSettings::default(),
-            agent: AgentSettings::default(),
-            telemetry: TelemetrySettings::default(),
-        }
-    }
-}
-
 impl Default for LlmSettings {
     fn default() -> Self {
         LlmSettings {
@@ -105,11 +94,3 @@ impl Default for AgentSettings {
         }
     }
 }
-
-impl Default for TelemetrySettings {
-    fn default() -> Self {
-        TelemetrySettings {
-            enabled: false,
-        }
-    }
-} 
\ No newline at end of file
@@ -2,7 +2,6 @@
 //!
 //! This module defines the error types used throughout the LlamaClick library.
 
-use std::fmt;
 use std::io;
 use thiserror::Error;
 
@@ -28,6 +27,14 @@ pub enum Error {
     #[error("Browser error: {0}")]
     BrowserError(String),
 
+    /// Element not found
+    #[error("Element not found: {0}")]
+    ElementNotFound(String),
+
+    /// Operation not supported by the backend
+    #[error("Unsupported operation: {0}")]
+    Unsupported(String),
+
     /// Network error
     #[error("Network error: {0}")]
     NetworkError(String),
@@ -36,6 +43,14 @@ pub enum Error {
     #[error("LLM error: {0}")]
     LlmError(String),
 
+    /// Rate limit reached
+    #[error("Rate limit error: {0}")]
+    RateLimitError(String),
+
+    /// Service temporarily overloaded
+    #[error("Overloaded error: {0}")]
+    OverloadedError(String),
+
     /// Configuration error
     #[error("Configuration error: {0}")]
     ConfigurationError(String),
@@ -95,6 +110,16 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::TimeoutError(err.to_string())
+        } else {
+            Error::NetworkError(err.to_string())
+        }
+    }
+}
+
 impl From<&str> for Error {
     fn from(err: &str) -> Self {
         Error::GenericError(err.to_string())
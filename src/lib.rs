@@ -25,8 +25,15 @@ easy extension and customization:
 For more information, visit [the LlamaClick documentation](https://docs.llamasearch.ai/llamaclick).
 */
 
+#[cfg(feature = "llm")]
+pub mod agent;
+#[cfg(feature = "browser")]
+pub mod browser;
+pub mod config;
 pub mod error;
-mod utils;
+#[cfg(feature = "llm")]
+pub mod llms;
+pub mod utils;
 
 /// Current version of the LlamaClick library
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -77,3 +84,21 @@ pub fn run_automation(objective: &str, url: &str) -> error::Result<String> {
     // This is a placeholder for the actual implementation
     Ok(format!("Successfully executed objective: '{}' on URL: '{}'", objective, url))
 }
+
+/// Run an objective in a browser session, starting at `url`
+///
+/// The result names the page the session ended up on, after any redirects.
+///
+/// # Examples
+///
+/// ```
+/// use llamaclick::browser::{BrowserConfig, BrowserSession, MockBrowser};
+///
+/// let mut session = BrowserSession::new(Box::new(MockBrowser::new()), BrowserConfig::default());
+/// let result = llamaclick::run_automation_in(&mut session, "Find the contact information", "https://example.com");
+/// ```
+#[cfg(feature = "browser")]
+pub fn run_automation_in(session: &mut browser::BrowserSession, objective: &str, url: &str) -> error::Result<String> {
+    session.navigate(url)?;
+    run_automation(objective, &session.current_url()?)
+}
@@ -3,12 +3,15 @@
 //! This module provides interfaces and implementations for interacting with
 //! various LLM providers like OpenAI, Anthropic, and local models.
 
+use crate::config::settings::LlmSettings;
 use crate::error::{Error, Result};
 use async_trait::async_trait;
+use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::time::Duration;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
 
 /// Response from an LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +24,23 @@ pub struct LlmResponse {
     pub duration: Duration,
     /// The token usage
     pub token_usage: Option<TokenUsage>,
+    /// The tools the model asked to call
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// Why the model stopped, as the provider reports it, e.g. `end_turn`,
+    /// `max_tokens` or `tool_use` from Anthropic and `stop` or `length` from OpenAI
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+}
+
+impl LlmResponse {
+    /// The response as the assistant message continuing the conversation
+    pub fn message(&self) -> ChatMessage {
+        ChatMessage {
+            tool_calls: self.tool_calls.clone(),
+            ..ChatMessage::assistant(self.content.as_str())
+        }
+    }
 }
 
 /// Token usage information
@@ -34,11 +54,236 @@ pub struct TokenUsage {
     pub total_tokens: usize,
 }
 
+/// The author of a chat message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Instructions framing the conversation
+    System,
+    /// The user
+    User,
+    /// The model
+    Assistant,
+    /// The result of a tool call
+    Tool,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::System => write!(f, "system"),
+            Role::User => write!(f, "user"),
+            Role::Assistant => write!(f, "assistant"),
+            Role::Tool => write!(f, "tool"),
+        }
+    }
+}
+
+/// A message in a conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// Who wrote the message
+    pub role: Role,
+    /// The text of the message
+    pub content: String,
+    /// The tools an assistant message calls
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// The call a tool message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    /// Create a new message
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+    
+    /// Create a system message
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+    
+    /// Create a user message
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+    
+    /// Create an assistant message
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+    
+    /// Create a message reporting the result of a tool call
+    pub fn tool_result(call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(call_id.into()),
+            ..Self::new(Role::Tool, content)
+        }
+    }
+}
+
+/// A tool the model may call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    /// The tool name
+    pub name: String,
+    /// What the tool does, for the model
+    pub description: String,
+    /// JSON schema of the arguments object
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    /// Create a new tool definition
+    pub fn new(name: impl Into<String>, description: impl Into<String>, parameters: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+        }
+    }
+}
+
+/// A call to a tool requested by the model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Identifies the call, to match its result
+    pub id: String,
+    /// The name of the tool
+    pub name: String,
+    /// The arguments object
+    pub arguments: serde_json::Value,
+}
+
+impl fmt::Display for ToolCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name, self.arguments)
+    }
+}
+
+/// Sampling settings for a request
+///
+/// Settings left unset use the provider's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationOptions {
+    /// The sampling temperature
+    pub temperature: Option<f32>,
+    /// The maximum number of tokens to generate
+    pub max_tokens: Option<u32>,
+    /// The nucleus sampling probability mass
+    pub top_p: Option<f32>,
+    /// Sequences that end generation
+    pub stop: Vec<String>,
+}
+
+impl GenerationOptions {
+    /// Create options using the provider's defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the sampling temperature
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+    
+    /// Set the maximum number of tokens to generate
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+    
+    /// Set the nucleus sampling probability mass
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+    
+    /// Add a stop sequence
+    pub fn with_stop(mut self, stop: impl Into<String>) -> Self {
+        self.stop.push(stop.into());
+        self
+    }
+}
+
+/// A piece of a streamed response
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Text appended to the response
+    Delta(String),
+    /// The finished response, with the whole text and the token usage
+    Done(LlmResponse),
+}
+
+/// A streamed response, ending with [`StreamEvent::Done`] or an error
+pub type ResponseStream = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>;
+
+/// Read a streamed response to the end, handing each delta to `on_delta`
+pub async fn collect_stream(mut stream: ResponseStream, mut on_delta: impl FnMut(&str)) -> Result<LlmResponse> {
+    while let Some(event) = stream.next().await {
+        match event? {
+            StreamEvent::Delta(text) => on_delta(&text),
+            StreamEvent::Done(response) => return Ok(response),
+        }
+    }
+    Err(Error::LlmError("The response stream ended without a final response".to_string()))
+}
+
 /// LLM provider trait
 #[async_trait]
 pub trait LlmProvider: Send + Sync + fmt::Debug {
+    /// Generate the next assistant message of a conversation
+    async fn chat(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<LlmResponse>;
+    
     /// Generate a response from the LLM
-    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse>;
+    ///
+    /// A conversation of one system and one user message.
+    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse> {
+        let messages = [ChatMessage::system(system), ChatMessage::user(prompt)];
+        self.chat(&messages, &GenerationOptions::new().with_temperature(temperature)).await
+    }
+    
+    /// Generate the next assistant message of a conversation, which may call `tools`
+    ///
+    /// Providers without native tool calling describe the tools in a system
+    /// message and ask for a JSON object naming the tool; a reply that parses
+    /// as one becomes the response's single tool call.
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> Result<LlmResponse> {
+        if tools.is_empty() {
+            return self.chat(messages, options).await;
+        }
+        let mut response = self.chat(&prompted_tool_messages(messages, tools), options).await?;
+        let id = format!("call_{}", messages.iter().filter(|message| message.role == Role::Tool).count() + 1);
+        if let Some(call) = parse_prompted_tool_call(&response.content, tools, id) {
+            response.content.clear();
+            response.tool_calls.push(call);
+        }
+        Ok(response)
+    }
+    
+    /// Stream the next assistant message of a conversation
+    ///
+    /// Providers without a streaming API wait for [`chat`](Self::chat) and
+    /// yield the whole message as one delta.
+    async fn chat_stream(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<ResponseStream> {
+        let response = self.chat(messages, options).await?;
+        let events = vec![Ok(StreamEvent::Delta(response.content.clone())), Ok(StreamEvent::Done(response))];
+        Ok(Box::pin(futures_util::stream::iter(events)))
+    }
     
     /// Get the model name
     fn model_name(&self) -> &str;
@@ -74,6 +319,60 @@ impl fmt::Display for LlmProviderType {
     }
 }
 
+/// Configuration option choosing the API a [`LocalProvider`] speaks: `ollama` or `openai`
+pub const LOCAL_API_OPTION: &str = "api";
+
+/// Configuration option overriding the `api-version` an [`AzureOpenAiProvider`] requests
+pub const AZURE_API_VERSION_OPTION: &str = "api_version";
+
+/// The Azure OpenAI API version used by default, the first stable one with `tools`
+pub const AZURE_API_VERSION: &str = "2024-02-01";
+
+/// The API of a local model server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LocalApi {
+    /// Ollama's `/api/chat`
+    #[serde(rename = "ollama")]
+    Ollama,
+    /// `/v1/chat/completions`, as served by llama.cpp, vLLM, LM Studio and others
+    #[serde(rename = "openai")]
+    OpenAiCompatible,
+}
+
+impl LocalApi {
+    /// Where the server usually listens
+    fn default_endpoint(self) -> &'static str {
+        match self {
+            LocalApi::Ollama => "http://localhost:11434",
+            LocalApi::OpenAiCompatible => "http://localhost:8080",
+        }
+    }
+}
+
+impl fmt::Display for LocalApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalApi::Ollama => write!(f, "Ollama"),
+            LocalApi::OpenAiCompatible => write!(f, "OpenAI-compatible"),
+        }
+    }
+}
+
+impl std::str::FromStr for LocalApi {
+    type Err = Error;
+    
+    fn from_str(api: &str) -> Result<Self> {
+        match api.trim().to_ascii_lowercase().as_str() {
+            "ollama" => Ok(LocalApi::Ollama),
+            "openai" | "openai-compatible" => Ok(LocalApi::OpenAiCompatible),
+            _ => Err(Error::ConfigurationError(format!(
+                "Unknown local API '{}'; expected ollama or openai",
+                api
+            ))),
+        }
+    }
+}
+
 /// LLM provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmProviderConfig {
@@ -112,6 +411,13 @@ impl LlmProviderConfig {
         self.options.insert(key.to_string(), value.to_string());
         self
     }
+    
+    /// A local provider configuration for the Ollama server and model named in `settings`
+    pub fn ollama(settings: &LlmSettings) -> Self {
+        Self::new(LlmProviderType::Local, &settings.ollama_model, "")
+            .with_endpoint(&settings.ollama_url)
+            .with_option(LOCAL_API_OPTION, "ollama")
+    }
 }
 
 /// Create an LLM provider from a configuration
@@ -125,6 +431,293 @@ pub fn create_provider(config: LlmProviderConfig) -> Result<Box<dyn LlmProvider>
     }
 }
 
+/// Add the sampling settings to an OpenAI-style chat completion request
+fn with_openai_options(mut payload: serde_json::Value, options: &GenerationOptions) -> serde_json::Value {
+    if let Some(temperature) = options.temperature {
+        payload["temperature"] = serde_json::json!(temperature);
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        payload["max_tokens"] = serde_json::json!(max_tokens);
+    }
+    if let Some(top_p) = options.top_p {
+        payload["top_p"] = serde_json::json!(top_p);
+    }
+    if !options.stop.is_empty() {
+        payload["stop"] = serde_json::json!(options.stop);
+    }
+    payload
+}
+
+/// Write a conversation in the OpenAI chat completion format
+fn openai_messages(messages: &[ChatMessage]) -> Vec<serde_json::Value> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut value = serde_json::json!({ "role": message.role, "content": message.content });
+            if !message.tool_calls.is_empty() {
+                if message.content.is_empty() {
+                    value["content"] = serde_json::Value::Null;
+                }
+                let calls: Vec<_> = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        serde_json::json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": { "name": call.name, "arguments": call.arguments.to_string() },
+                        })
+                    })
+                    .collect();
+                value["tool_calls"] = serde_json::json!(calls);
+            }
+            if let Some(id) = &message.tool_call_id {
+                value["tool_call_id"] = serde_json::json!(id);
+            }
+            value
+        })
+        .collect()
+}
+
+/// Write tool definitions in the OpenAI format
+fn openai_tools(tools: &[ToolDefinition]) -> serde_json::Value {
+    let tools: Vec<_> = tools
+        .iter()
+        .map(|tool| {
+            serde_json::json!({
+                "type": "function",
+                "function": { "name": tool.name, "description": tool.description, "parameters": tool.parameters },
+            })
+        })
+        .collect();
+    serde_json::json!(tools)
+}
+
+/// Read the tool calls of an OpenAI-style response message
+///
+/// The arguments arrive as a JSON string, which must hold an object.
+fn openai_tool_calls(calls: &serde_json::Value, provider: &str) -> Result<Vec<ToolCall>> {
+    let Some(calls) = calls.as_array() else {
+        return Ok(Vec::new());
+    };
+    calls
+        .iter()
+        .map(|call| {
+            let name = call["function"]["name"].as_str().unwrap_or_default().to_string();
+            let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+            let arguments = serde_json::from_str(arguments).map_err(|e| {
+                Error::LlmError(format!("{} called {} with malformed arguments: {}", provider, name, e))
+            })?;
+            Ok(ToolCall {
+                id: call["id"].as_str().unwrap_or_default().to_string(),
+                name,
+                arguments,
+            })
+        })
+        .collect()
+}
+
+/// Describe tools to a model without native tool calling
+fn tool_prompt(tools: &[ToolDefinition]) -> String {
+    let mut prompt = String::from("You can call these tools:\n");
+    for tool in tools {
+        prompt.push_str(&format!("- {}: {}\n", tool.name, tool.description));
+        prompt.push_str(&format!("  Arguments (JSON schema): {}\n", tool.parameters));
+    }
+    prompt.push_str(
+        "To call a tool, reply with only a JSON object of the form {\"tool\": \"<name>\", \"arguments\": {...}}. \
+         Otherwise reply normally.",
+    );
+    prompt
+}
+
+/// Rewrite a conversation with tools for a model without native tool calling
+///
+/// The tool descriptions follow the leading system messages, earlier calls
+/// become the JSON replies that requested them, and tool results become user
+/// messages.
+fn prompted_tool_messages(messages: &[ChatMessage], tools: &[ToolDefinition]) -> Vec<ChatMessage> {
+    let mut prompted = Vec::with_capacity(messages.len() + 1);
+    let leading = messages.iter().take_while(|message| message.role == Role::System).count();
+    for (index, message) in messages.iter().enumerate() {
+        if index == leading {
+            prompted.push(ChatMessage::system(tool_prompt(tools)));
+        }
+        prompted.push(match message.role {
+            Role::Assistant if !message.tool_calls.is_empty() => {
+                let calls: Vec<_> = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| serde_json::json!({ "tool": call.name, "arguments": call.arguments }).to_string())
+                    .collect();
+                ChatMessage::assistant(calls.join("\n"))
+            }
+            Role::Tool => {
+                let name = messages
+                    .iter()
+                    .flat_map(|message| &message.tool_calls)
+                    .find(|call| Some(&call.id) == message.tool_call_id.as_ref())
+                    .map_or("the tool", |call| call.name.as_str());
+                ChatMessage::user(format!("Result of {}: {}", name, message.content))
+            }
+            _ => ChatMessage::new(message.role, message.content.as_str()),
+        });
+    }
+    if leading == messages.len() {
+        prompted.push(ChatMessage::system(tool_prompt(tools)));
+    }
+    prompted
+}
+
+/// Read a JSON tool call from a reply, if it is one naming a known tool
+///
+/// The object may be wrapped in a code fence or surrounded by other text.
+fn parse_prompted_tool_call(reply: &str, tools: &[ToolDefinition], id: String) -> Option<ToolCall> {
+    let (start, end) = (reply.find('{')?, reply.rfind('}')?);
+    let object: serde_json::Value = serde_json::from_str(reply.get(start..=end)?).ok()?;
+    let name = object["tool"].as_str()?;
+    let tool = tools.iter().find(|tool| tool.name == name)?;
+    let arguments = match &object["arguments"] {
+        serde_json::Value::Null => serde_json::json!({}),
+        arguments if arguments.is_object() => arguments.clone(),
+        _ => return None,
+    };
+    Some(ToolCall {
+        id,
+        name: tool.name.clone(),
+        arguments,
+    })
+}
+
+/// Read the token usage of an OpenAI-style response
+fn openai_usage(usage: &serde_json::Value) -> Option<TokenUsage> {
+    usage.as_object().map(|usage| TokenUsage {
+        prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as usize,
+        completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as usize,
+        total_tokens: usage["total_tokens"].as_u64().unwrap_or(0) as usize,
+    })
+}
+
+/// Read the token usage of an Anthropic response
+///
+/// Tokens read from or written to the prompt cache count as prompt tokens.
+fn anthropic_usage(usage: &serde_json::Value) -> Option<TokenUsage> {
+    usage.as_object().map(|usage| {
+        let prompt_tokens = ["input_tokens", "cache_creation_input_tokens", "cache_read_input_tokens"]
+            .iter()
+            .map(|field| usage.get(*field).and_then(serde_json::Value::as_u64).unwrap_or(0) as usize)
+            .sum();
+        let completion_tokens = usage.get("output_tokens").and_then(serde_json::Value::as_u64).unwrap_or(0) as usize;
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    })
+}
+
+/// The error for an Anthropic error type, e.g. `rate_limit_error` or `overloaded_error`
+fn anthropic_error(error_type: Option<&str>, message: &str) -> Error {
+    let message = format!("Anthropic API error: {}", message);
+    match error_type {
+        Some("authentication_error") => Error::AuthenticationError(message),
+        Some("permission_error") => Error::PermissionError(message),
+        Some("not_found_error") => Error::ResourceNotFound(message),
+        Some("rate_limit_error") => Error::RateLimitError(message),
+        Some("overloaded_error") => Error::OverloadedError(message),
+        _ => Error::LlmError(message),
+    }
+}
+
+/// Fail with the body of an unsuccessful response
+async fn check_status(response: reqwest::Response, provider: &str) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let error_text = response.text().await?;
+    Err(Error::LlmError(format!("{} API error: {}", provider, error_text)))
+}
+
+/// Split a conversation into Anthropic's system prompt and alternating turns
+///
+/// System messages are joined into the top-level system prompt, tool calls
+/// and results become `tool_use` and `tool_result` blocks, and consecutive
+/// messages of the same role are merged into one turn. A turn of plain text
+/// is sent as a string.
+fn anthropic_messages(messages: &[ChatMessage]) -> (String, Vec<serde_json::Value>) {
+    let system = messages
+        .iter()
+        .filter(|message| message.role == Role::System)
+        .map(|message| message.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let mut turns: Vec<(Role, Vec<serde_json::Value>)> = Vec::new();
+    for message in messages.iter().filter(|message| message.role != Role::System) {
+        let (role, blocks) = match message.role {
+            Role::Tool => (
+                Role::User,
+                vec![serde_json::json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id,
+                    "content": message.content,
+                })],
+            ),
+            role => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() || message.tool_calls.is_empty() {
+                    blocks.push(serde_json::json!({ "type": "text", "text": message.content }));
+                }
+                blocks.extend(message.tool_calls.iter().map(|call| {
+                    serde_json::json!({ "type": "tool_use", "id": call.id, "name": call.name, "input": call.arguments })
+                }));
+                (role, blocks)
+            }
+        };
+        match turns.last_mut() {
+            Some((last, turn)) if *last == role => {
+                for block in blocks {
+                    match turn.last_mut() {
+                        Some(previous) if previous["type"] == "text" && block["type"] == "text" => {
+                            let before = previous["text"].as_str().unwrap_or_default();
+                            let text = format!("{}\n\n{}", before, block["text"].as_str().unwrap_or_default());
+                            previous["text"] = serde_json::json!(text);
+                        }
+                        _ => turn.push(block),
+                    }
+                }
+            }
+            _ => turns.push((role, blocks)),
+        }
+    }
+    let turns = turns
+        .into_iter()
+        .map(|(role, blocks)| match blocks.as_slice() {
+            [block] if block["type"] == "text" => serde_json::json!({ "role": role, "content": block["text"] }),
+            _ => serde_json::json!({ "role": role, "content": blocks }),
+        })
+        .collect();
+    (system, turns)
+}
+
+/// Render a conversation as a plain text prompt ending with the assistant's turn
+///
+/// System messages open the prompt as they are; the other turns are prefixed
+/// with their speaker.
+fn transcript(messages: &[ChatMessage]) -> String {
+    let mut prompt = String::new();
+    for message in messages {
+        match message.role {
+            Role::System => prompt.push_str(&message.content),
+            Role::User => prompt.push_str(&format!("User: {}", message.content)),
+            Role::Assistant => prompt.push_str(&format!("Assistant: {}", message.content)),
+            Role::Tool => prompt.push_str(&format!("Tool: {}", message.content)),
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str("Assistant:");
+    prompt
+}
+
 /// OpenAI provider
 #[derive(Debug)]
 pub struct OpenAiProvider {
@@ -146,66 +739,72 @@ impl OpenAiProvider {
             client: reqwest::Client::new(),
         })
     }
-}
-
-#[async_trait]
-impl LlmProvider for OpenAiProvider {
-    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse> {
-        let start = std::time::Instant::now();
-        
-        // Build the request payload
-        let payload = serde_json::json!({
+    
+    /// Build the request payload
+    fn payload(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> serde_json::Value {
+        let mut payload = serde_json::json!({
             "model": self.config.model,
-            "messages": [
-                {
-                    "role": "system",
-                    "content": system
-                },
-                {
-                    "role": "user",
-                    "content": prompt
-                }
-            ],
-            "temperature": temperature,
+            "messages": openai_messages(messages),
         });
-        
+        if !tools.is_empty() {
+            payload["tools"] = openai_tools(tools);
+        }
+        with_openai_options(payload, options)
+    }
+    
+    /// Send a request, failing on an error status
+    async fn send(&self, payload: &serde_json::Value) -> Result<reqwest::Response> {
         // Get the API endpoint
         let endpoint = self.config.api_endpoint.as_deref().unwrap_or("https://api.openai.com/v1/chat/completions");
         
-        // Send the request
         let response = self.client
             .post(endpoint)
             .header("Authorization", format!("Bearer {}", self.config.api_key))
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await?;
+        check_status(response, "OpenAI").await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    async fn chat(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<LlmResponse> {
+        self.chat_with_tools(messages, &[], options).await
+    }
+    
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> Result<LlmResponse> {
+        let start = std::time::Instant::now();
         
-        // Check for errors
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(Error::LlmError(format!("OpenAI API error: {}", error_text)));
-        }
+        // Send the request
+        let response = self.send(&self.payload(messages, tools, options)).await?;
         
         // Parse the response
         let response_json: serde_json::Value = response.json().await?;
         
-        // Extract the content
-        let content = response_json["choices"][0]["message"]["content"]
-            .as_str()
-            .ok_or_else(|| Error::LlmError("Failed to extract content from OpenAI response".to_string()))?
-            .to_string();
+        // Extract the content and any tool calls; content is null when the model only calls tools
+        let message = &response_json["choices"][0]["message"];
+        let tool_calls = openai_tool_calls(&message["tool_calls"], "OpenAI")?;
+        let content = match message["content"].as_str() {
+            Some(content) => content.to_string(),
+            None if !tool_calls.is_empty() => String::new(),
+            None => return Err(Error::LlmError("Failed to extract content from OpenAI response".to_string())),
+        };
         
         // Extract token usage if available
-        let token_usage = if let Some(usage) = response_json["usage"].as_object() {
-            Some(TokenUsage {
-                prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as usize,
-                completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as usize,
-                total_tokens: usage["total_tokens"].as_u64().unwrap_or(0) as usize,
-            })
-        } else {
-            None
-        };
+        let token_usage = openai_usage(&response_json["usage"]);
+        let stop_reason = response_json["choices"][0]["finish_reason"].as_str().map(str::to_string);
         
         let duration = start.elapsed();
         
@@ -214,9 +813,20 @@ impl LlmProvider for OpenAiProvider {
             model: self.config.model.clone(),
             duration,
             token_usage,
+            tool_calls,
+            stop_reason,
         })
     }
     
+    async fn chat_stream(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<ResponseStream> {
+        let start = Instant::now();
+        let mut payload = self.payload(messages, &[], options);
+        payload["stream"] = serde_json::json!(true);
+        payload["stream_options"] = serde_json::json!({ "include_usage": true });
+        let response = self.send(&payload).await?;
+        Ok(SseStream::new(response, SseFormat::OpenAi, &self.config.model, start).into_stream())
+    }
+    
     fn model_name(&self) -> &str {
         &self.config.model
     }
@@ -247,57 +857,127 @@ impl AnthropicProvider {
             client: reqwest::Client::new(),
         })
     }
-}
-
-#[async_trait]
-impl LlmProvider for AnthropicProvider {
-    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse> {
-        let start = std::time::Instant::now();
-        
-        // Build the request payload
-        let payload = serde_json::json!({
+    
+    /// Build the request payload; the system prompt is a top-level field
+    fn payload(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> serde_json::Value {
+        let (system, turns) = anthropic_messages(messages);
+        let mut payload = serde_json::json!({
             "model": self.config.model,
-            "messages": [
-                {
-                    "role": "system",
-                    "content": system
-                },
-                {
-                    "role": "user",
-                    "content": prompt
-                }
-            ],
-            "temperature": temperature,
-            "max_tokens": 1024,
+            "messages": turns,
+            "max_tokens": options.max_tokens.unwrap_or(1024),
         });
-        
+        if !system.is_empty() {
+            payload["system"] = serde_json::json!(system);
+        }
+        if let Some(temperature) = options.temperature {
+            payload["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            payload["top_p"] = serde_json::json!(top_p);
+        }
+        if !options.stop.is_empty() {
+            payload["stop_sequences"] = serde_json::json!(options.stop);
+        }
+        if !tools.is_empty() {
+            let tools: Vec<_> = tools
+                .iter()
+                .map(|tool| {
+                    serde_json::json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+            payload["tools"] = serde_json::json!(tools);
+        }
+        payload
+    }
+    
+    /// Send a request, failing on an error status
+    async fn send(&self, payload: &serde_json::Value) -> Result<reqwest::Response> {
         // Get the API endpoint
         let endpoint = self.config.api_endpoint.as_deref().unwrap_or("https://api.anthropic.com/v1/messages");
         
-        // Send the request
         let response = self.client
             .post(endpoint)
             .header("x-api-key", &self.config.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
         
-        // Check for errors
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(Error::LlmError(format!("Anthropic API error: {}", error_text)));
+        // Error payloads name the error type; the status stands in for bodies that are not JSON
+        let status = response.status();
+        let retry_after = response.headers().get("retry-after").and_then(|value| value.to_str().ok());
+        let retry_after = retry_after.map(str::to_string);
+        let error_text = response.text().await?;
+        let body: serde_json::Value = serde_json::from_str(&error_text).unwrap_or_default();
+        let error_type = body["error"]["type"].as_str().or(match status.as_u16() {
+            401 => Some("authentication_error"),
+            403 => Some("permission_error"),
+            404 => Some("not_found_error"),
+            429 => Some("rate_limit_error"),
+            529 => Some("overloaded_error"),
+            _ => None,
+        });
+        let mut message = body["error"]["message"].as_str().unwrap_or(&error_text).to_string();
+        if let Some(seconds) = retry_after {
+            message.push_str(&format!(" (retry after {}s)", seconds));
         }
+        Err(anthropic_error(error_type, &message))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    async fn chat(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<LlmResponse> {
+        self.chat_with_tools(messages, &[], options).await
+    }
+    
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> Result<LlmResponse> {
+        let start = std::time::Instant::now();
+        
+        // Send the request
+        let response = self.send(&self.payload(messages, tools, options)).await?;
         
         // Parse the response
         let response_json: serde_json::Value = response.json().await?;
         
-        // Extract the content
-        let content = response_json["content"][0]["text"]
-            .as_str()
-            .ok_or_else(|| Error::LlmError("Failed to extract content from Anthropic response".to_string()))?
-            .to_string();
+        // Extract the text blocks and the tool_use blocks
+        let blocks = response_json["content"]
+            .as_array()
+            .ok_or_else(|| Error::LlmError("Failed to extract content from Anthropic response".to_string()))?;
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block["type"].as_str() {
+                Some("text") => content.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => tool_calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].clone(),
+                }),
+                _ => {}
+            }
+        }
+        
+        let token_usage = anthropic_usage(&response_json["usage"]);
+        let stop_reason = response_json["stop_reason"].as_str().map(str::to_string);
         
         let duration = start.elapsed();
         
@@ -305,10 +985,20 @@ impl LlmProvider for AnthropicProvider {
             content,
             model: self.config.model.clone(),
             duration,
-            token_usage: None, // Anthropic doesn't provide token usage in the same way
+            token_usage,
+            tool_calls,
+            stop_reason,
         })
     }
     
+    async fn chat_stream(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<ResponseStream> {
+        let start = Instant::now();
+        let mut payload = self.payload(messages, &[], options);
+        payload["stream"] = serde_json::json!(true);
+        let response = self.send(&payload).await?;
+        Ok(SseStream::new(response, SseFormat::Anthropic, &self.config.model, start).into_stream())
+    }
+    
     fn model_name(&self) -> &str {
         &self.config.model
     }
@@ -318,34 +1008,157 @@ impl LlmProvider for AnthropicProvider {
     }
 }
 
-/// Local provider for LLMs
+/// Local provider for models served by Ollama or an OpenAI-compatible server
+///
+/// The [`LOCAL_API_OPTION`] picks the API, Ollama by default. The endpoint is
+/// the server's base URL, e.g. `http://localhost:11434`, defaulting to the
+/// API's usual local address; an API key, when set, is sent as a bearer token.
+/// Tools are offered through the JSON-prompting fallback, since native tool
+/// support varies with the model and server.
 #[derive(Debug)]
 pub struct LocalProvider {
     /// The configuration
     config: LlmProviderConfig,
+    /// The API the server speaks
+    api: LocalApi,
+    /// The HTTP client
+    client: reqwest::Client,
 }
 
 impl LocalProvider {
     /// Create a new local provider
     pub fn new(config: LlmProviderConfig) -> Result<Self> {
-        if config.api_endpoint.is_none() {
-            return Err(Error::ConfigurationError("Local provider requires an API endpoint".to_string()));
-        }
+        let api = match config.options.get(LOCAL_API_OPTION) {
+            Some(api) => api.parse()?,
+            None => LocalApi::Ollama,
+        };
         
         Ok(Self {
             config,
+            api,
+            client: reqwest::Client::new(),
         })
     }
+    
+    /// The API the server speaks
+    pub fn api(&self) -> LocalApi {
+        self.api
+    }
+    
+    /// The URL of an API path on the server
+    fn url(&self, path: &str) -> String {
+        let base = self.config.api_endpoint.as_deref().unwrap_or(self.api.default_endpoint());
+        let base = base.trim_end_matches('/');
+        match self.api {
+            LocalApi::Ollama => format!("{}{}", base, path),
+            LocalApi::OpenAiCompatible => format!("{}/v1{}", base.trim_end_matches("/v1"), path),
+        }
+    }
+    
+    /// Add the API key, if there is one
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.config.api_key.is_empty() {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", self.config.api_key))
+        }
+    }
+    
+    /// Build the request payload
+    fn payload(&self, messages: &[ChatMessage], options: &GenerationOptions) -> serde_json::Value {
+        match self.api {
+            LocalApi::Ollama => {
+                // Sampling settings go in `options`, under Ollama's own names
+                let mut settings = serde_json::json!({});
+                if let Some(temperature) = options.temperature {
+                    settings["temperature"] = serde_json::json!(temperature);
+                }
+                if let Some(max_tokens) = options.max_tokens {
+                    settings["num_predict"] = serde_json::json!(max_tokens);
+                }
+                if let Some(top_p) = options.top_p {
+                    settings["top_p"] = serde_json::json!(top_p);
+                }
+                if !options.stop.is_empty() {
+                    settings["stop"] = serde_json::json!(options.stop);
+                }
+                let messages: Vec<_> = messages
+                    .iter()
+                    .map(|message| serde_json::json!({ "role": message.role, "content": message.content }))
+                    .collect();
+                serde_json::json!({
+                    "model": self.config.model,
+                    "messages": messages,
+                    "stream": false,
+                    "options": settings,
+                })
+            }
+            LocalApi::OpenAiCompatible => {
+                let payload = serde_json::json!({
+                    "model": self.config.model,
+                    "messages": openai_messages(messages),
+                });
+                with_openai_options(payload, options)
+            }
+        }
+    }
+    
+    /// List the models the server offers
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let (path, list, name) = match self.api {
+            LocalApi::Ollama => ("/api/tags", "models", "name"),
+            LocalApi::OpenAiCompatible => ("/models", "data", "id"),
+        };
+        let response = self.authorize(self.client.get(self.url(path))).send().await?;
+        let response_json: serde_json::Value = check_status(response, &self.api.to_string()).await?.json().await?;
+        
+        let models = response_json[list]
+            .as_array()
+            .ok_or_else(|| Error::LlmError(format!("Failed to list the models of the {} server", self.api)))?;
+        Ok(models.iter().filter_map(|model| model[name].as_str()).map(str::to_string).collect())
+    }
 }
 
 #[async_trait]
 impl LlmProvider for LocalProvider {
-    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse> {
+    async fn chat(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<LlmResponse> {
         let start = std::time::Instant::now();
         
-        // For demonstration purposes, we're just echoing back the prompt
-        // In a real implementation, this would connect to a local LLM server
-        let content = format!("Local LLM response to: {}", prompt);
+        // Send the request
+        let path = match self.api {
+            LocalApi::Ollama => "/api/chat",
+            LocalApi::OpenAiCompatible => "/chat/completions",
+        };
+        let request = self.client.post(self.url(path)).json(&self.payload(messages, options));
+        let response = check_status(self.authorize(request).send().await?, &self.api.to_string()).await?;
+        
+        // Parse the response
+        let response_json: serde_json::Value = response.json().await?;
+        
+        // Extract the content, token usage and stop reason
+        let (content, token_usage, stop_reason) = match self.api {
+            LocalApi::Ollama => {
+                let prompt_tokens = response_json["prompt_eval_count"].as_u64();
+                let completion_tokens = response_json["eval_count"].as_u64();
+                let token_usage = (prompt_tokens.is_some() || completion_tokens.is_some()).then(|| {
+                    let (prompt, completion) = (prompt_tokens.unwrap_or(0), completion_tokens.unwrap_or(0));
+                    TokenUsage {
+                        prompt_tokens: prompt as usize,
+                        completion_tokens: completion as usize,
+                        total_tokens: (prompt + completion) as usize,
+                    }
+                });
+                (response_json["message"]["content"].as_str(), token_usage, &response_json["done_reason"])
+            }
+            LocalApi::OpenAiCompatible => (
+                response_json["choices"][0]["message"]["content"].as_str(),
+                openai_usage(&response_json["usage"]),
+                &response_json["choices"][0]["finish_reason"],
+            ),
+        };
+        let content = content
+            .ok_or_else(|| Error::LlmError(format!("Failed to extract content from {} response", self.api)))?
+            .to_string();
         
         let duration = start.elapsed();
         
@@ -353,7 +1166,9 @@ impl LlmProvider for LocalProvider {
             content,
             model: self.config.model.clone(),
             duration,
-            token_usage: None,
+            token_usage,
+            tool_calls: Vec::new(),
+            stop_reason: stop_reason.as_str().map(str::to_string),
         })
     }
     
@@ -391,27 +1206,36 @@ impl HuggingFaceProvider {
 
 #[async_trait]
 impl LlmProvider for HuggingFaceProvider {
-    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse> {
+    async fn chat(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<LlmResponse> {
         let start = std::time::Instant::now();
         
-        // Build the request payload
-        let full_prompt = format!("{}\n{}", system, prompt);
+        // Build the request payload; text generation models take the conversation as one prompt
+        let mut parameters = serde_json::json!({
+            "max_new_tokens": options.max_tokens.unwrap_or(1024),
+            "return_full_text": false,
+        });
+        if let Some(temperature) = options.temperature {
+            parameters["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            parameters["top_p"] = serde_json::json!(top_p);
+        }
+        if !options.stop.is_empty() {
+            parameters["stop"] = serde_json::json!(options.stop);
+        }
         let payload = serde_json::json!({
-            "inputs": full_prompt,
-            "parameters": {
-                "temperature": temperature,
-                "max_length": 1024,
-            }
+            "inputs": transcript(messages),
+            "parameters": parameters,
         });
         
         // Get the API endpoint
-        let endpoint = self.config.api_endpoint.as_deref().unwrap_or(
-            &format!("https://api-inference.huggingface.co/models/{}", self.config.model)
+        let endpoint = self.config.api_endpoint.clone().unwrap_or_else(||
+            format!("https://api-inference.huggingface.co/models/{}", self.config.model)
         );
         
         // Send the request
         let response = self.client
-            .post(endpoint)
+            .post(&endpoint)
             .header("Authorization", format!("Bearer {}", self.config.api_key))
             .header("Content-Type", "application/json")
             .json(&payload)
@@ -448,6 +1272,8 @@ impl LlmProvider for HuggingFaceProvider {
             model: self.config.model.clone(),
             duration,
             token_usage: None, // HuggingFace doesn't provide token usage
+            tool_calls: Vec::new(),
+            stop_reason: None,
         })
     }
     
@@ -485,70 +1311,84 @@ impl AzureOpenAiProvider {
             client: reqwest::Client::new(),
         })
     }
-}
-
-#[async_trait]
-impl LlmProvider for AzureOpenAiProvider {
-    async fn generate_response(&self, system: &str, prompt: &str, temperature: f32) -> Result<LlmResponse> {
-        let start = std::time::Instant::now();
-        
-        // Build the request payload
-        let payload = serde_json::json!({
-            "messages": [
-                {
-                    "role": "system",
-                    "content": system
-                },
-                {
-                    "role": "user",
-                    "content": prompt
-                }
-            ],
-            "temperature": temperature,
+    
+    /// Build the request payload; the deployment in the URL picks the model
+    fn payload(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> serde_json::Value {
+        let mut payload = serde_json::json!({
+            "messages": openai_messages(messages),
             "max_tokens": 800,
         });
-        
+        if !tools.is_empty() {
+            payload["tools"] = openai_tools(tools);
+        }
+        with_openai_options(payload, options)
+    }
+    
+    /// Send a request, failing on an error status
+    async fn send(&self, payload: &serde_json::Value) -> Result<reqwest::Response> {
         // Get the deployment name and endpoint
         let endpoint = self.config.api_endpoint.as_ref().unwrap();
         let deployment_name = &self.config.model;
         
         // Construct the full URL
-        let url = format!("{}/openai/deployments/{}/chat/completions?api-version=2023-05-15", endpoint, deployment_name);
+        let api_version = self
+            .config
+            .options
+            .get(AZURE_API_VERSION_OPTION)
+            .map_or(AZURE_API_VERSION, String::as_str);
+        let url = format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            endpoint, deployment_name, api_version
+        );
         
-        // Send the request
         let response = self.client
             .post(&url)
             .header("api-key", &self.config.api_key)
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await?;
+        check_status(response, "Azure OpenAI").await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AzureOpenAiProvider {
+    async fn chat(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<LlmResponse> {
+        self.chat_with_tools(messages, &[], options).await
+    }
+    
+    async fn chat_with_tools(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        options: &GenerationOptions,
+    ) -> Result<LlmResponse> {
+        let start = std::time::Instant::now();
         
-        // Check for errors
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(Error::LlmError(format!("Azure OpenAI API error: {}", error_text)));
-        }
+        // Send the request
+        let response = self.send(&self.payload(messages, tools, options)).await?;
         
         // Parse the response
         let response_json: serde_json::Value = response.json().await?;
         
-        // Extract the content
-        let content = response_json["choices"][0]["message"]["content"]
-            .as_str()
-            .ok_or_else(|| Error::LlmError("Failed to extract content from Azure OpenAI response".to_string()))?
-            .to_string();
+        // Extract the content and any tool calls; content is null when the model only calls tools
+        let message = &response_json["choices"][0]["message"];
+        let tool_calls = openai_tool_calls(&message["tool_calls"], "Azure OpenAI")?;
+        let content = match message["content"].as_str() {
+            Some(content) => content.to_string(),
+            None if !tool_calls.is_empty() => String::new(),
+            None => return Err(Error::LlmError("Failed to extract content from Azure OpenAI response".to_string())),
+        };
         
         // Extract token usage if available
-        let token_usage = if let Some(usage) = response_json["usage"].as_object() {
-            Some(TokenUsage {
-                prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as usize,
-                completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as usize,
-                total_tokens: usage["total_tokens"].as_u64().unwrap_or(0) as usize,
-            })
-        } else {
-            None
-        };
+        let token_usage = openai_usage(&response_json["usage"]);
+        let stop_reason = response_json["choices"][0]["finish_reason"].as_str().map(str::to_string);
         
         let duration = start.elapsed();
         
@@ -557,9 +1397,19 @@ impl LlmProvider for AzureOpenAiProvider {
             model: self.config.model.clone(),
             duration,
             token_usage,
+            tool_calls,
+            stop_reason,
         })
     }
     
+    async fn chat_stream(&self, messages: &[ChatMessage], options: &GenerationOptions) -> Result<ResponseStream> {
+        let start = Instant::now();
+        let mut payload = self.payload(messages, &[], options);
+        payload["stream"] = serde_json::json!(true);
+        let response = self.send(&payload).await?;
+        Ok(SseStream::new(response, SseFormat::OpenAi, &self.config.model, start).into_stream())
+    }
+    
     fn model_name(&self) -> &str {
         &self.config.model
     }
@@ -567,4 +1417,242 @@ impl LlmProvider for AzureOpenAiProvider {
     fn provider_name(&self) -> &str {
         "Azure OpenAI"
     }
-} 
\ No newline at end of file
+} 
+/// An event of a server-sent event stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct SseEvent {
+    /// The event type, empty for unnamed events
+    event: String,
+    /// The data lines, joined with newlines
+    data: String,
+}
+
+/// Splits a server-sent event stream into events as chunks arrive
+#[derive(Debug, Default)]
+struct SseParser {
+    /// Bytes of the line not yet terminated
+    line: Vec<u8>,
+    /// The event being assembled
+    pending: SseEvent,
+    /// Whether the pending event has any data line
+    has_data: bool,
+}
+
+impl SseParser {
+    /// Feed a chunk, returning the events it completes
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = std::mem::take(&mut self.line);
+            let line = String::from_utf8_lossy(&line);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if line.is_empty() {
+                if std::mem::take(&mut self.has_data) {
+                    events.push(std::mem::take(&mut self.pending));
+                }
+                self.pending = SseEvent::default();
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.pending.event = value.to_string(),
+                "data" => {
+                    if self.has_data {
+                        self.pending.data.push('\n');
+                    }
+                    self.pending.data.push_str(value);
+                    self.has_data = true;
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// The event vocabulary of a provider's stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SseFormat {
+    /// Chat completion chunks ending with `[DONE]`, as sent by OpenAI and Azure OpenAI
+    OpenAi,
+    /// Typed message events ending with `message_stop`
+    Anthropic,
+}
+
+impl SseFormat {
+    /// The provider name used in errors
+    fn provider(self) -> &'static str {
+        match self {
+            SseFormat::OpenAi => "OpenAI",
+            SseFormat::Anthropic => "Anthropic",
+        }
+    }
+}
+
+/// A streamed response being read and assembled
+struct SseStream {
+    /// The response whose body carries the events
+    response: reqwest::Response,
+    /// The event vocabulary
+    format: SseFormat,
+    /// The event parser
+    parser: SseParser,
+    /// The model the request asked for
+    model: String,
+    /// When the request was sent
+    start: Instant,
+    /// The text received so far
+    content: String,
+    /// Prompt and completion tokens reported so far
+    usage: Option<(usize, usize)>,
+    /// Why the model stopped, once reported
+    stop_reason: Option<String>,
+    /// Events ready to be yielded
+    ready: VecDeque<Result<StreamEvent>>,
+    /// Whether the final event or an error has been queued
+    finished: bool,
+}
+
+impl SseStream {
+    /// Read the events of a successful response
+    fn new(response: reqwest::Response, format: SseFormat, model: &str, start: Instant) -> Self {
+        Self {
+            response,
+            format,
+            parser: SseParser::default(),
+            model: model.to_string(),
+            start,
+            content: String::new(),
+            usage: None,
+            stop_reason: None,
+            ready: VecDeque::new(),
+            finished: false,
+        }
+    }
+    
+    /// Yield deltas as chunks arrive, then the assembled response
+    fn into_stream(self) -> ResponseStream {
+        Box::pin(futures_util::stream::unfold(self, |mut stream| async move {
+            loop {
+                if let Some(event) = stream.ready.pop_front() {
+                    return Some((event, stream));
+                }
+                if stream.finished {
+                    return None;
+                }
+                match stream.response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        for event in stream.parser.push(&chunk) {
+                            stream.handle(&event);
+                        }
+                    }
+                    Ok(None) => stream.fail(Error::LlmError(format!(
+                        "{} stream ended before the response was complete",
+                        stream.format.provider()
+                    ))),
+                    Err(e) => stream.fail(e.into()),
+                }
+            }
+        }))
+    }
+    
+    /// Apply one event
+    fn handle(&mut self, event: &SseEvent) {
+        if self.finished {
+            return;
+        }
+        if self.format == SseFormat::OpenAi && event.data == "[DONE]" {
+            return self.finish();
+        }
+        let data: serde_json::Value = match serde_json::from_str(&event.data) {
+            Ok(data) => data,
+            Err(e) => {
+                let provider = self.format.provider();
+                return self.fail(Error::LlmError(format!("Malformed {} stream event: {}", provider, e)));
+            }
+        };
+        if let Some(message) = data["error"]["message"].as_str() {
+            let error = match self.format {
+                SseFormat::OpenAi => Error::LlmError(format!("OpenAI API error: {}", message)),
+                SseFormat::Anthropic => anthropic_error(data["error"]["type"].as_str(), message),
+            };
+            return self.fail(error);
+        }
+        
+        match self.format {
+            SseFormat::OpenAi => {
+                if let Some(text) = data["choices"][0]["delta"]["content"].as_str() {
+                    self.delta(text);
+                }
+                if let Some(reason) = data["choices"][0]["finish_reason"].as_str() {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                if let Some(usage) = openai_usage(&data["usage"]) {
+                    self.usage = Some((usage.prompt_tokens, usage.completion_tokens));
+                }
+            }
+            SseFormat::Anthropic => match data["type"].as_str().unwrap_or(&event.event) {
+                "message_start" => {
+                    let input = anthropic_usage(&data["message"]["usage"]).map_or(0, |usage| usage.prompt_tokens);
+                    self.usage = Some((input, 0));
+                }
+                "content_block_delta" => {
+                    if let Some(text) = data["delta"]["text"].as_str() {
+                        self.delta(text);
+                    }
+                }
+                "message_delta" => {
+                    if let Some(reason) = data["delta"]["stop_reason"].as_str() {
+                        self.stop_reason = Some(reason.to_string());
+                    }
+                    if let Some(output) = data["usage"]["output_tokens"].as_u64() {
+                        let input = self.usage.map_or(0, |(input, _)| input);
+                        self.usage = Some((input, output as usize));
+                    }
+                }
+                "message_stop" => self.finish(),
+                _ => {}
+            },
+        }
+    }
+    
+    /// Queue a piece of text
+    fn delta(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.content.push_str(text);
+            self.ready.push_back(Ok(StreamEvent::Delta(text.to_string())));
+        }
+    }
+    
+    /// Queue the assembled response
+    fn finish(&mut self) {
+        let token_usage = self.usage.map(|(prompt_tokens, completion_tokens)| TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        });
+        self.ready.push_back(Ok(StreamEvent::Done(LlmResponse {
+            content: std::mem::take(&mut self.content),
+            model: self.model.clone(),
+            duration: self.start.elapsed(),
+            token_usage,
+            tool_calls: Vec::new(),
+            stop_reason: self.stop_reason.take(),
+        })));
+        self.finished = true;
+    }
+    
+    /// Queue an error ending the stream
+    fn fail(&mut self, error: Error) {
+        self.ready.push_back(Err(error));
+        self.finished = true;
+    }
+}
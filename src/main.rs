@@ -6,9 +6,21 @@
 
 use clap::{Parser, Subcommand};
 use colored::*;
-use llamaclick::{error::Result, init_logging, run_automation, VERSION};
+use llamaclick::{error::Result, init_logging, VERSION};
 use std::path::PathBuf;
 
+/// Environment variable holding the OpenAI API key
+#[cfg(feature = "llm")]
+const OPENAI_KEY_ENV: &str = "OPENAI_API_KEY";
+
+/// Environment variable holding the Anthropic API key
+#[cfg(feature = "llm")]
+const ANTHROPIC_KEY_ENV: &str = "ANTHROPIC_API_KEY";
+
+/// Environment variable overriding the planner's model
+#[cfg(feature = "llm")]
+const MODEL_ENV: &str = "LLAMACLICK_MODEL";
+
 /// LlamaClick - Enterprise-Grade AI Web Automation
 #[derive(Parser)]
 #[command(
@@ -38,13 +50,44 @@ enum Commands {
     /// Run an automation task
     #[command(about = "Run an automation task")]
     Run {
-        /// The objective to achieve
-        #[arg(help = "The objective to achieve in natural language")]
-        objective: String,
+        /// The objectives to achieve
+        #[arg(required = true, help = "The objectives to achieve in natural language")]
+        objectives: Vec<String>,
+
+        /// URLs to navigate to
+        #[arg(
+            short,
+            long = "url",
+            value_name = "URL",
+            help = "URL to navigate to before executing the objectives; repeat to run them on several pages"
+        )]
+        urls: Vec<String>,
 
-        /// URL to navigate to
-        #[arg(short, long, help = "URL to navigate to before executing the objective")]
-        url: Option<String>,
+        /// Number of browsers to run at once
+        #[arg(long, default_value_t = 4, help = "Run up to this many objective and URL pairs in parallel")]
+        parallel: usize,
+
+        /// Device preset to emulate
+        #[arg(long, value_name = "NAME", help = "Emulate a device preset, e.g. \"iPhone 13\" or \"Pixel 7\"")]
+        device: Option<String>,
+
+        /// Stream a plan for each objective first
+        #[arg(
+            long,
+            help = "Stream a planner agent's plan for each objective before running it, using OPENAI_API_KEY or \
+                    ANTHROPIC_API_KEY, or else the configured Ollama server"
+        )]
+        plan: bool,
+
+        /// Browser to drive
+        #[arg(
+            long,
+            value_name = "NAME",
+            default_value = "chrome",
+            value_parser = ["chrome", "firefox", "edge", "safari", "static"],
+            help = "Browser to drive; \"static\" fetches pages over HTTP without launching one"
+        )]
+        browser: String,
 
         /// Run in headless mode
         #[arg(short = 'H', long, help = "Run in headless mode (no UI)")]
@@ -53,6 +96,21 @@ enum Commands {
         /// Output file for results
         #[arg(short, long, help = "Save results to this file")]
         output: Option<PathBuf>,
+
+        /// Named browser profile
+        #[arg(
+            long,
+            help = "Browser profile whose cookies and storage are restored before each task and saved after the run"
+        )]
+        profile: Option<String>,
+
+        /// HAR file for the run's network traffic
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Record network traffic to this HAR file, placed next to --output when relative"
+        )]
+        har: Option<PathBuf>,
     },
 
     /// Configure the CLI
@@ -101,26 +159,75 @@ fn main() -> Result<()> {
     // Process commands
     match cli.command {
         Commands::Run {
-            objective,
-            url,
+            objectives,
+            urls,
+            parallel,
+            device,
+            plan,
+            browser,
             headless,
             output,
+            profile,
+            har,
         } => {
             println!("{}", "Running automation task...".green().bold());
-            println!("Objective: {}", objective);
-            
-            let url_str = url.unwrap_or_else(|| "https://example.com".to_string());
-            println!("URL: {}", url_str);
+            let urls = if urls.is_empty() { vec!["https://example.com".to_string()] } else { urls };
+            let tasks: Vec<(String, String)> = objectives
+                .iter()
+                .flat_map(|objective| urls.iter().map(move |url| (objective.clone(), url.clone())))
+                .collect();
+            for (objective, url) in &tasks {
+                println!("Objective: {} (URL: {})", objective, url);
+            }
+            println!("Browser: {}", browser);
             println!("Headless: {}", headless);
+            println!("Parallel: {}", parallel.clamp(1, tasks.len()));
+            if let Some(name) = &device {
+                println!("Device: {}", name);
+            }
             
             if let Some(output_path) = &output {
                 println!("Output file: {}", output_path.display());
             }
+
+            if let Some(name) = &profile {
+                print_profile(name)?;
+            }
+
+            let har_path = har.map(|path| artifact_path(path, output.as_deref()));
+            if let Some(path) = &har_path {
+                println!("HAR file: {}", path.display());
+            }
+            
+            if plan {
+                plan_objectives(&objectives)?;
+            }
             
             // Run the automation
-            let result = run_automation(&objective, &url_str)?;
+            let settings = RunSettings {
+                browser,
+                headless,
+                parallel,
+                device,
+                downloads: artifact_path(PathBuf::from("downloads"), output.as_deref()),
+                har: har_path,
+                profile,
+            };
+            let results = run_tasks(tasks, &settings)?;
             
-            println!("\nResult: {}", result);
+            let mut failures = 0;
+            for result in results {
+                match result {
+                    Ok(result) => println!("\nResult: {}", result),
+                    Err(e) => {
+                        failures += 1;
+                        println!("\n{} {}", "✗".red().bold(), e);
+                    }
+                }
+            }
+            if failures > 0 {
+                return Err(llamaclick::error::Error::WorkflowError(format!("{} tasks failed", failures)));
+            }
             println!("\n{}", "✓ Task completed successfully!".green().bold());
             Ok(())
         }
@@ -169,6 +276,193 @@ fn print_banner() {
     println!("{}", banner);
 }
 
+/// Print where a browser profile lives and what it holds
+#[cfg(feature = "browser")]
+fn print_profile(name: &str) -> Result<()> {
+    use llamaclick::browser::StorageState;
+
+    let path = StorageState::profile_path(name)?;
+    match StorageState::load_profile(name)? {
+        Some(state) => println!(
+            "Profile: {} ({}, {} cookies, {} origins)",
+            name,
+            path.display(),
+            state.cookies.len(),
+            state.origins.len()
+        ),
+        None => println!("Profile: {} (new, will be saved to {})", name, path.display()),
+    }
+    Ok(())
+}
+
+/// Browser profiles need the browser feature
+#[cfg(not(feature = "browser"))]
+fn print_profile(_name: &str) -> Result<()> {
+    Err(llamaclick::error::Error::ConfigurationError(
+        "Browser profiles require the browser feature".to_string(),
+    ))
+}
+
+/// Browser settings of a `run`
+#[cfg_attr(not(feature = "browser"), allow(dead_code))]
+struct RunSettings {
+    /// The `--browser` name
+    browser: String,
+    /// Whether browsers run headless
+    headless: bool,
+    /// How many browsers run at once
+    parallel: usize,
+    /// Device preset to emulate
+    device: Option<String>,
+    /// Where downloads are saved
+    downloads: PathBuf,
+    /// HAR file the traffic of every task is merged into
+    har: Option<PathBuf>,
+    /// Browser profile restored before each task and saved with what the successful tasks left
+    profile: Option<String>,
+}
+
+/// Fan objective and URL pairs out across a pool of browsers
+#[cfg(feature = "browser")]
+fn run_tasks(tasks: Vec<(String, String)>, settings: &RunSettings) -> Result<Vec<Result<String>>> {
+    use llamaclick::browser::{
+        Browser, BrowserConfig, BrowserPool, BrowserType, Device, Har, HarOptions, StaticBrowser,
+        StorageState,
+    };
+    use llamaclick::error::Error;
+    use std::sync::Mutex;
+
+    // clap only lets the names listed on --browser through
+    let browser_type = match settings.browser.as_str() {
+        "firefox" => BrowserType::Firefox,
+        "edge" => BrowserType::Edge,
+        "safari" => BrowserType::Safari,
+        _ => BrowserType::Chrome,
+    };
+    let mut config = BrowserConfig::new(browser_type)
+        .with_headless(settings.headless)
+        .with_downloads_dir(&settings.downloads);
+    if let Some(name) = &settings.device {
+        let device = Device::named(name).ok_or_else(|| {
+            let known = Device::names().collect::<Vec<_>>().join(", ");
+            Error::ConfigurationError(format!("Unknown device '{}'; known devices: {}", name, known))
+        })?;
+        config = config.with_device(device);
+    }
+    let har = settings.har.as_ref().map(|_| Mutex::new(Vec::new()));
+    // The profile file is read once and written once, so parallel tasks never race on it
+    let saved = match &settings.profile {
+        Some(name) => StorageState::load_profile(name)?,
+        None => None,
+    };
+    let profile = settings.profile.as_ref().map(|_| Mutex::new(saved.clone().unwrap_or_default()));
+    let mut pool = BrowserPool::new(config.clone(), settings.parallel);
+    if settings.browser == "static" {
+        pool = pool.with_factory(|config| Ok(Box::new(StaticBrowser::new(config.clone())?) as Box<dyn Browser>));
+    }
+    let results = pool.run_each(tasks, |session, (objective, url)| {
+        if let Some(state) = &saved {
+            session.set_storage_state(state)?;
+        }
+        if har.is_some() {
+            session.record_har(HarOptions::default())?;
+        }
+        let result = llamaclick::run_automation_in(session, &objective, &url);
+        if let Some(entries) = &har {
+            // Traffic of failed tasks is kept too, it is often what explains the failure
+            let recorded = session.har()?.log.entries;
+            entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).extend(recorded);
+        }
+        let result = result?;
+        if let Some(profile) = &profile {
+            // Only the last page's storage can be read, so what the profile holds for other origins is kept
+            let state = session.storage_state()?;
+            profile.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).merge(&state);
+        }
+        Ok(result)
+    });
+    pool.shutdown(config.timeout)?;
+    if let (Some(name), Some(profile)) = (&settings.profile, profile) {
+        profile.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()).save_profile(name)?;
+    }
+    if let (Some(path), Some(entries)) = (&settings.har, har) {
+        Har::new(entries.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())).save(path)?;
+    }
+    Ok(results)
+}
+
+/// Without the browser feature, objectives run one after another without a browser
+#[cfg(not(feature = "browser"))]
+fn run_tasks(tasks: Vec<(String, String)>, settings: &RunSettings) -> Result<Vec<Result<String>>> {
+    if settings.device.is_some() {
+        return Err(llamaclick::error::Error::ConfigurationError(
+            "Device emulation requires the browser feature".to_string(),
+        ));
+    }
+    if settings.har.is_some() {
+        return Err(llamaclick::error::Error::ConfigurationError(
+            "HAR recording requires the browser feature".to_string(),
+        ));
+    }
+    Ok(tasks.iter().map(|(objective, url)| llamaclick::run_automation(objective, url)).collect())
+}
+
+/// Stream a planner agent's thoughts on each objective to the terminal
+#[cfg(feature = "llm")]
+fn plan_objectives(objectives: &[String]) -> Result<()> {
+    use llamaclick::agent::{Agent, AgentConfig, AgentType};
+    use std::io::Write;
+
+    let mut planner = Agent::new(AgentConfig::new(AgentType::Planner), planner_llm()?);
+    for objective in objectives {
+        println!("\n{} {}", "Planning:".cyan().bold(), objective);
+        llamaclick::utils::block_on(planner.run_streaming(objective, |delta| {
+            print!("{}", delta.dimmed());
+            let _ = std::io::stdout().flush();
+        }))??;
+        println!();
+        planner.clear_history();
+    }
+    Ok(())
+}
+
+/// The planner's LLM, chosen by which API key is set, falling back to the configured Ollama server
+#[cfg(feature = "llm")]
+fn planner_llm() -> Result<Box<dyn llamaclick::llms::LlmProvider>> {
+    use llamaclick::llms::{create_provider, LlmProviderConfig, LlmProviderType};
+
+    let model = std::env::var(MODEL_ENV).ok();
+    let config = if let Ok(key) = std::env::var(OPENAI_KEY_ENV) {
+        LlmProviderConfig::new(LlmProviderType::OpenAi, model.as_deref().unwrap_or("gpt-4o-mini"), &key)
+    } else if let Ok(key) = std::env::var(ANTHROPIC_KEY_ENV) {
+        LlmProviderConfig::new(LlmProviderType::Anthropic, model.as_deref().unwrap_or("claude-3-5-haiku-latest"), &key)
+    } else {
+        let settings = llamaclick::config::load_settings()?;
+        let mut config = LlmProviderConfig::ollama(&settings.llm);
+        if let Some(model) = model {
+            config.model = model;
+        }
+        config
+    };
+    create_provider(config)
+}
+
+/// Planning needs the llm feature
+#[cfg(not(feature = "llm"))]
+fn plan_objectives(_objectives: &[String]) -> Result<()> {
+    Err(llamaclick::error::Error::ConfigurationError(
+        "Planning requires the llm feature".to_string(),
+    ))
+}
+
+/// Place a relative artifact path next to the output file, if there is one
+fn artifact_path(path: PathBuf, output: Option<&std::path::Path>) -> PathBuf {
+    match output.and_then(|output| output.parent()) {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
 /// Mask API key for display
 fn mask_key(key: &str) -> String {
     if key.len() <= 8 {
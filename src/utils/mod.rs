@@ -16,6 +16,14 @@ pub fn timestamp() -> u64 {
         .as_secs()
 }
 
+/// Get a timestamp as milliseconds since the epoch
+pub fn timestamp_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
 /// Measure the execution time of a function
 pub fn measure_time<F, T>(f: F) -> (T, Duration)
 where
@@ -30,7 +38,7 @@ where
 /// Create a directory if it doesn't exist
 pub fn ensure_dir(path: &Path) -> Result<()> {
     if !path.exists() {
-        fs::create_dir_all(path).map_err(|e| Error::IoError(e))?;
+        fs::create_dir_all(path).map_err(Error::IoError)?;
     }
     Ok(())
 }
@@ -44,15 +52,15 @@ pub fn write_to_file(path: &Path, content: &str) -> Result<()> {
     
     ensure_dir(dir)?;
     
-    let mut file = File::create(path).map_err(|e| Error::IoError(e))?;
-    file.write_all(content.as_bytes()).map_err(|e| Error::IoError(e))?;
+    let mut file = File::create(path).map_err(Error::IoError)?;
+    file.write_all(content.as_bytes()).map_err(Error::IoError)?;
     
     Ok(())
 }
 
 /// Read content from a file
 pub fn read_from_file(path: &Path) -> Result<String> {
-    fs::read_to_string(path).map_err(|e| Error::IoError(e))
+    fs::read_to_string(path).map_err(Error::IoError)
 }
 
 /// Check if a file exists
@@ -82,4 +90,26 @@ pub fn format_duration(duration: Duration) -> String {
         let rem_seconds = seconds % 60;
         format!("{}m {}s", minutes, rem_seconds)
     }
-} 
\ No newline at end of file
+}
+
+/// Run a future to completion from synchronous code
+///
+/// Inside a multi-threaded tokio runtime the current worker is handed over to
+/// the blocking pool while waiting; outside any runtime a temporary one is
+/// created. A current-thread runtime cannot be blocked on and yields an error.
+pub fn block_on<F: std::future::Future>(future: F) -> Result<F::Output> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => match handle.runtime_flavor() {
+            tokio::runtime::RuntimeFlavor::CurrentThread => Err(Error::GenericError(
+                "Cannot block inside a current-thread tokio runtime; use the async API instead".to_string(),
+            )),
+            _ => Ok(tokio::task::block_in_place(|| handle.block_on(future))),
+        },
+        Err(_) => {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            Ok(runtime.block_on(future))
+        }
+    }
+}
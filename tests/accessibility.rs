@@ -0,0 +1,129 @@
+//! Tests for accessibility-tree snapshots
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{cdp_attach_response, CdpServer};
+use llamaclick::browser::{
+    AccessibilityState, Browser, BrowserConfig, BrowserType, CdpBrowser, MockAction, MockBrowser,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const CHECKOUT: &str = r#"<html>
+<head><title>Checkout</title></head>
+<body>
+  <nav aria-label="Main"><ul><li><a href="/">Home</a></li></ul></nav>
+  <main>
+    <h2>Shipping</h2>
+    <div class="field"><label for="zip">Postal code</label><input id="zip" required value="94103"></div>
+    <label><input type="checkbox" checked> Gift wrap</label>
+    <span id="pay-label">Pay now</span>
+    <div class="btn" role="button" aria-labelledby="pay-label" aria-pressed="true" tabindex="0"></div>
+    <button disabled><img src="x.png" alt="Cancel order"></button>
+    <img src="spacer.gif" alt="">
+    <p hidden>Secret</p>
+  </main>
+</body>
+</html>"#;
+
+#[test]
+fn computes_roles_names_and_states_from_the_dom() {
+    let mock = MockBrowser::from_html(CHECKOUT);
+    let tree = mock.accessibility_snapshot().unwrap();
+
+    assert_eq!(tree.role, "document");
+    assert_eq!(tree.name, "Checkout");
+    assert!(tree.find("navigation", "main").is_some());
+    assert!(tree.find("link", "Home").is_some());
+
+    let heading = tree.find("heading", "Shipping").unwrap();
+    assert_eq!(heading.level, Some(2));
+
+    let zip = tree.find("textbox", "Postal code").unwrap();
+    assert_eq!(zip.value.as_deref(), Some("94103"));
+    assert!(zip.has_state(AccessibilityState::Required) && zip.has_state(AccessibilityState::Focusable));
+
+    assert!(tree.find("checkbox", "Gift wrap").unwrap().has_state(AccessibilityState::Checked));
+    assert!(tree.find("button", "Pay now").unwrap().has_state(AccessibilityState::Pressed));
+    assert!(tree.find("button", "Cancel order").unwrap().has_state(AccessibilityState::Disabled));
+
+    assert!(tree.find_all("image").iter().all(|image| image.name == "Cancel order"));
+    assert!(!tree.to_string().contains("Secret"));
+    assert!(tree.descendants().iter().all(|node| node.role != "generic"));
+    assert_eq!(mock.actions(), vec![MockAction::AccessibilitySnapshot]);
+}
+
+#[test]
+fn dom_nodes_carry_selectors_to_their_elements() {
+    let mock = MockBrowser::from_html(CHECKOUT);
+    let tree = mock.accessibility_snapshot().unwrap();
+
+    let pay = tree.find("button", "pay now").unwrap();
+    let attributes = mock.get_attributes(pay.selector.as_ref().unwrap()).unwrap();
+    assert_eq!(attributes["class"], "btn");
+}
+
+#[test]
+fn renders_an_indented_outline() {
+    let tree = MockBrowser::from_html(
+        "<html><body><main><h1>Hi</h1><input type=\"search\" aria-label=\"Search\" value=\"q\"></main></body></html>",
+    )
+    .accessibility_snapshot()
+    .unwrap();
+
+    assert_eq!(
+        tree.to_string(),
+        "document\n  main\n    heading \"Hi\" level=1\n      text \"Hi\"\n    searchbox \"Search\" value=\"q\" [focusable]\n"
+    );
+}
+
+fn ax_node(id: &str, role: &str, name: &str, children: &[&str], extra: Value) -> Value {
+    let mut node = json!({
+        "nodeId": id,
+        "ignored": false,
+        "role": { "type": "role", "value": role },
+        "name": { "type": "computedString", "value": name },
+        "childIds": children,
+    });
+    if id != "1" {
+        node["parentId"] = json!("parent");
+    }
+    for (key, value) in extra.as_object().cloned().unwrap_or_default() {
+        node[key] = value;
+    }
+    node
+}
+
+#[test]
+fn chromium_tree_comes_from_devtools() {
+    let server = CdpServer::start(|method, _| {
+        if let Some(result) = cdp_attach_response(method) {
+            return Ok(result);
+        }
+        match method {
+            "Accessibility.getFullAXTree" => Ok(json!({ "nodes": [
+                ax_node("1", "RootWebArea", "Example", &["2"], json!({})),
+                ax_node("2", "generic", "", &["3", "5"], json!({})),
+                ax_node("3", "checkbox", "Subscribe", &["4"], json!({ "properties": [
+                    { "name": "checked", "value": { "type": "tristate", "value": "true" } },
+                    { "name": "focusable", "value": { "type": "booleanOrUndefined", "value": true } },
+                    { "name": "disabled", "value": { "type": "boolean", "value": false } },
+                ]})),
+                ax_node("4", "StaticText", "Subscribe", &["6"], json!({})),
+                ax_node("5", "heading", "Title", &[], json!({
+                    "ignored": true,
+                })),
+                ax_node("6", "InlineTextBox", "Subscribe", &[], json!({})),
+            ]})),
+            _ => Ok(json!({})),
+        }
+    });
+    let config = BrowserConfig::new(BrowserType::Chrome).with_timeout(Duration::from_secs(2));
+    let browser = CdpBrowser::connect(&server.url, config).unwrap();
+
+    let tree = browser.accessibility_snapshot().unwrap();
+    assert_eq!(tree.to_string(), "document \"Example\"\n  checkbox \"Subscribe\" [checked, focusable]\n    text \"Subscribe\"\n");
+    assert!(server.methods().contains(&"Accessibility.getFullAXTree".to_string()));
+}
@@ -0,0 +1,172 @@
+//! Tests for actionability checks before element actions
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{cdp_attach_response, evaluate_result, CdpServer, HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    ActionCheck, ActionOptions, BoundingBox, BrowserConfig, BrowserSession, BrowserType, CdpBrowser, ElementState,
+    MockBrowser, Selector, WebDriverBrowser, ELEMENT_KEY,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const FORM: &str = r#"<html><body>
+  <form>
+    <fieldset disabled><input name="locked"></fieldset>
+    <input name="email">
+    <button id="save" disabled>Save</button>
+    <button id="later" style="display: none">Later</button>
+    <a id="help" aria-disabled="true" href="/help">Help</a>
+    <button id="go">Go</button>
+  </form>
+</body></html>"#;
+
+fn state(bounds: Option<(f64, f64)>) -> ElementState {
+    ElementState {
+        visible: true,
+        enabled: true,
+        obscured_by: None,
+        bounds: bounds.map(|(x, y)| BoundingBox { x, y, width: 80.0, height: 20.0 }),
+    }
+}
+
+#[test]
+fn states_report_their_first_failing_check() {
+    let still = state(Some((10.0, 10.0)));
+    assert_eq!(still.failing_check(None), Some(ActionCheck::Stable));
+    assert_eq!(still.failing_check(Some(&state(Some((10.0, 40.0))))), Some(ActionCheck::Stable));
+    assert_eq!(still.failing_check(Some(&still)), None);
+    assert_eq!(state(None).failing_check(None), None);
+
+    let covered = ElementState {
+        obscured_by: Some("div#overlay".to_string()),
+        ..state(None)
+    };
+    assert_eq!(covered.failing_check(None), Some(ActionCheck::Unobscured));
+    let hidden = ElementState { visible: false, enabled: false, ..state(None) };
+    assert_eq!(hidden.failing_check(None), Some(ActionCheck::Visible));
+    assert_eq!(ActionCheck::Unobscured.to_string(), "unobscured");
+}
+
+#[test]
+fn sessions_refuse_elements_that_are_not_actionable() {
+    let mock = MockBrowser::from_html(FORM);
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+
+    // Fixture pages cannot change, so failures are reported at once rather than as timeouts
+    let cases = [
+        (Selector::id("save"), "is not enabled"),
+        (Selector::id("later"), "is not visible"),
+        (Selector::id("help"), "is not enabled"),
+    ];
+    for (selector, check) in cases {
+        let err = session.click(&selector).unwrap_err();
+        assert!(matches!(err, Error::BrowserError(_)), "{}", err);
+        assert!(err.to_string().contains(check), "{}", err);
+        mock.assert_not_clicked(&selector);
+    }
+    let err = session.click(&Selector::id("missing")).unwrap_err();
+    assert!(matches!(err, Error::ElementNotFound(_)), "{}", err);
+    let err = session.type_text(&Selector::name("locked"), "x").unwrap_err();
+    assert!(err.to_string().contains("is not enabled"), "{}", err);
+
+    session.type_text(&Selector::name("email"), "llama@app.test").unwrap();
+    session.click(&Selector::id("go")).unwrap();
+    mock.assert_clicked_once(&Selector::id("go"));
+    assert!(session.action_log().iter().rev().take(2).all(|record| record.error.is_none()));
+}
+
+#[test]
+fn forced_actions_skip_the_checks() {
+    let mock = MockBrowser::from_html(FORM);
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+
+    let force = ActionOptions::new().with_force(true);
+    session.click_with(&Selector::id("save"), &force).unwrap();
+    session.type_text_with(&Selector::name("locked"), "x", &force).unwrap();
+    mock.assert_clicked_once(&Selector::id("save"));
+    mock.assert_typed(&Selector::name("locked"), "x");
+}
+
+/// A page where `#save` moves once before settling, and `#covered` sits under an overlay
+fn page(probes: Arc<AtomicUsize>) -> impl Fn(&str, &Value) -> Result<Value, String> {
+    move |method: &str, params: &Value| {
+        if let Some(result) = cdp_attach_response(method) {
+            return Ok(result);
+        }
+        if method != "Runtime.evaluate" {
+            return Ok(json!({}));
+        }
+        let expression = params["expression"].as_str().unwrap_or_default();
+        let value = if expression.contains("obscuredBy") {
+            let probe = probes.fetch_add(1, Ordering::SeqCst);
+            let y = if probe == 0 { 0.0 } else { 40.0 };
+            let covered = expression.contains("covered");
+            json!({
+                "visible": true,
+                "enabled": true,
+                "obscuredBy": if covered { json!("div#overlay.modal") } else { Value::Null },
+                "bounds": { "x": 10.0, "y": y, "width": 80.0, "height": 20.0 },
+            })
+        } else if expression.contains("x: r.left") {
+            json!({ "x": 50.0, "y": 50.0 })
+        } else {
+            json!(null)
+        };
+        Ok(evaluate_result(value))
+    }
+}
+
+#[test]
+fn cdp_sessions_wait_for_elements_to_settle() {
+    let probes = Arc::new(AtomicUsize::new(0));
+    let server = CdpServer::start(page(probes.clone()));
+    let browser = CdpBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Chrome)).unwrap();
+    let config = BrowserConfig::new(BrowserType::Chrome).with_timeout(Duration::from_secs(2));
+    let mut session = BrowserSession::new(Box::new(browser), config);
+
+    session.click(&Selector::id("save")).unwrap();
+    // Moved between the first two probes, then held still
+    assert_eq!(probes.load(Ordering::SeqCst), 3);
+    assert!(server.methods().contains(&"Input.dispatchMouseEvent".to_string()));
+
+    let options = ActionOptions::new().with_timeout(Duration::from_millis(200));
+    let err = session.click_with(&Selector::id("covered"), &options).unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)), "{}", err);
+    assert!(err.to_string().contains("to be unobscured (covered by div#overlay.modal)"), "{}", err);
+}
+
+/// A driver where `#save` (`e-1`) is disabled
+fn driver(request: &HttpRequest) -> HttpResponse {
+    let path = request.path.trim_start_matches("/session/s-1");
+    match (request.method.as_str(), path) {
+        ("POST", "/session") => HttpResponse::json(json!({ "value": { "sessionId": "s-1", "capabilities": {} } })),
+        ("POST", "/elements") => HttpResponse::json(json!({ "value": [{ ELEMENT_KEY: "e-1" }] })),
+        ("POST", "/execute/sync") => HttpResponse::json(json!({
+            "value": { "visible": true, "enabled": false, "obscuredBy": null, "bounds": null }
+        })),
+        _ => HttpResponse::json(json!({ "value": null })),
+    }
+}
+
+#[test]
+fn webdriver_sessions_probe_elements_by_script() {
+    let server = HttpServer::start(driver);
+    let config = BrowserConfig::new(BrowserType::Firefox).with_timeout(Duration::from_millis(200));
+    let browser = WebDriverBrowser::connect(&server.url, config.clone()).unwrap();
+    let mut session = BrowserSession::new(Box::new(browser), config);
+
+    let err = session.click(&Selector::id("save")).unwrap_err();
+    assert!(err.to_string().contains("to be enabled"), "{}", err);
+
+    let requests = server.received();
+    let probe = requests.iter().find(|r| r.path.ends_with("/execute/sync")).unwrap().json();
+    assert!(probe["script"].as_str().unwrap().contains("elementFromPoint"));
+    assert_eq!(probe["args"], json!([{ ELEMENT_KEY: "e-1" }]));
+    assert!(!requests.iter().any(|r| r.path.ends_with("/click")));
+}
@@ -0,0 +1,124 @@
+//! Tests for the async browser session
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use llamaclick::browser::{AsyncBrowser, AsyncBrowserSession, BrowserConfig, BrowserSession, MockBrowser, Selector};
+use llamaclick::error::Error;
+use std::time::{Duration, Instant};
+
+const LOGIN: &str = r#"<html><body>
+  <form><input name="email"><button id="submit">Log in</button></form>
+</body></html>"#;
+
+const LOADED: &str = r#"<html><body><div id="results">3 results</div></body></html>"#;
+
+fn session(mock: &MockBrowser) -> AsyncBrowserSession {
+    BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default()).into()
+}
+
+#[tokio::test]
+async fn runs_operations_on_a_current_thread_runtime() {
+    let mock = MockBrowser::from_pages([("https://app.test/login", LOGIN)]);
+    let browser = session(&mock);
+
+    browser.navigate("https://app.test/login").await.unwrap();
+    browser.type_text(&Selector::name("email"), "llama@example.com").await.unwrap();
+    browser.click(&Selector::id("submit")).await.unwrap();
+
+    assert_eq!(browser.current_url().await.unwrap(), "https://app.test/login");
+    assert_eq!(browser.get_text(&Selector::id("submit")).await.unwrap(), "Log in");
+    assert_eq!(browser.run(|session| session.get_html()).await.unwrap().len(), LOGIN.len());
+    mock.assert_typed(&Selector::name("email"), "llama@example.com");
+    mock.assert_clicked_once(&Selector::id("submit"));
+}
+
+#[tokio::test]
+async fn waiting_leaves_the_runtime_free() {
+    let mock = MockBrowser::from_pages([("https://app.test/login", LOGIN), ("https://app.test/search", LOADED)]);
+    let browser = session(&mock);
+    browser.navigate("https://app.test/login").await.unwrap();
+
+    // On a single-threaded runtime this only runs if the wait yields
+    let loader = {
+        let mut mock = mock.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            llamaclick::browser::Browser::navigate(&mut mock, "https://app.test/search").unwrap();
+        })
+    };
+
+    browser.wait_for_element(&Selector::id("results"), Duration::from_secs(5)).await.unwrap();
+    loader.await.unwrap();
+    assert_eq!(browser.get_text(&Selector::id("results")).await.unwrap(), "3 results");
+}
+
+#[tokio::test]
+async fn waits_time_out() {
+    let mock = MockBrowser::from_html(LOGIN);
+    let browser = session(&mock);
+
+    let started = Instant::now();
+    let result = browser.wait_for_element(&Selector::id("results"), Duration::from_millis(300)).await;
+    assert!(matches!(result, Err(Error::TimeoutError(_))), "{:?}", result);
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn dropped_waits_leave_the_session_usable() {
+    let mock = MockBrowser::from_html(LOGIN);
+    let browser = session(&mock);
+
+    let missing = Selector::id("results");
+    let wait = browser.wait_for_element(&missing, Duration::from_secs(30));
+    assert!(tokio::time::timeout(Duration::from_millis(200), wait).await.is_err());
+
+    assert!(browser.element_exists(&Selector::id("submit")).await.unwrap());
+}
+
+#[cfg(feature = "llm")]
+mod with_llm {
+    use super::*;
+    use common::ScriptedLlm;
+    use llamaclick::agent::{Agent, AgentConfig, AgentManager, AgentType};
+    use llamaclick::browser::SemanticResolver;
+
+    #[tokio::test]
+    async fn resolves_semantic_selectors_without_blocking() {
+        let llm = ScriptedLlm::new("1");
+        let mock = MockBrowser::from_html(LOGIN);
+        let browser: AsyncBrowserSession = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default())
+            .with_semantic_resolver(SemanticResolver::new(Box::new(llm.clone())))
+            .into();
+
+        browser.click(&Selector::semantic("the log in button")).await.unwrap();
+
+        assert_eq!(llm.calls(), 1);
+        let resolved = browser.resolve(&Selector::semantic("the log in button")).await.unwrap();
+        assert_eq!(mock.click_count(&resolved), 1);
+    }
+
+    #[tokio::test]
+    async fn agents_see_the_current_page() {
+        let mock = MockBrowser::from_html(LOGIN);
+        let browser = session(&mock);
+
+        let navigator = ScriptedLlm::new("Click [1]");
+        let mut manager = AgentManager::new();
+        for (agent_type, llm) in [
+            (AgentType::Planner, ScriptedLlm::new("1. Log in")),
+            (AgentType::Navigator, navigator.clone()),
+            (AgentType::Interactor, ScriptedLlm::new("Clicked")),
+            (AgentType::Verifier, ScriptedLlm::new("Done")),
+        ] {
+            manager.add_agent(Agent::new(AgentConfig::new(agent_type), Box::new(llm)));
+        }
+
+        let result = manager.execute_task_with_browser("Log in", &browser).await.unwrap();
+
+        assert_eq!(result, "Done");
+        let prompt = &navigator.prompts()[0];
+        assert!(prompt.contains("1. Log in") && prompt.contains("[1] button \"Log in\""), "{}", prompt);
+    }
+}
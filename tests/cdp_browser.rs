@@ -0,0 +1,405 @@
+//! Tests for the DevTools protocol backend against a stand-in endpoint
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{cdp_attach_response, evaluate_result, CdpServer};
+use llamaclick::browser::{
+    Browser, BrowserConfig, BrowserType, CdpBrowser, Cookie, HarOptions, KeyChord, OriginStorage, PageEvent, PageId,
+    ResourceType, Route, RouteAction, RouteResponse, Selector, StorageItem, StorageState,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn config() -> BrowserConfig {
+    BrowserConfig::new(BrowserType::Chrome).with_timeout(Duration::from_secs(2))
+}
+
+/// Answer page evaluations by looking at the evaluated expression
+fn page_handler(method: &str, params: &Value) -> Result<Value, String> {
+    if let Some(result) = cdp_attach_response(method) {
+        return Ok(result);
+    }
+
+    match method {
+        "Page.navigate" => Ok(json!({ "frameId": "frame-1" })),
+        "Page.captureScreenshot" => Ok(json!({ "data": "aGVsbG8=" })),
+        "Runtime.evaluate" => {
+            let expression = params["expression"].as_str().unwrap_or_default();
+            let value = if expression == "document.readyState" {
+                json!("complete")
+            } else if expression == "location.href" {
+                json!("https://example.com/")
+            } else if expression.contains("#missing") {
+                return Ok(json!({
+                    "result": { "type": "object" },
+                    "exceptionDetails": {
+                        "text": "Uncaught",
+                        "exception": { "description": "Error: __llamaclick_not_found__" }
+                    }
+                }));
+            } else if expression.contains("getBoundingClientRect()") && expression.contains("x: r.left") {
+                json!({ "x": 50.0, "y": 20.0 })
+            } else if expression.contains("innerText") {
+                json!("Example Domain")
+            } else if expression.contains("a.name, a.value") {
+                json!({ "href": "https://www.iana.org/domains/example" })
+            } else if expression.ends_with(".length > 0") {
+                json!(true)
+            } else {
+                json!(null)
+            };
+            Ok(evaluate_result(value))
+        }
+        _ => Ok(json!({})),
+    }
+}
+
+#[test]
+fn attaches_to_existing_page_and_applies_config() {
+    let server = CdpServer::start(page_handler);
+    let config = config()
+        .with_user_agent("LlamaClick/Test")
+        .with_ignore_https_errors(true)
+        .with_block_images(true)
+        .with_window_size(800, 600);
+
+    let browser = CdpBrowser::connect(&server.url, config).unwrap();
+    assert_eq!(browser.target_id(), "page-1");
+
+    let methods = server.methods();
+    assert!(methods.contains(&"Target.attachToTarget".to_string()));
+    assert!(!methods.contains(&"Target.createTarget".to_string()));
+
+    let metrics = &server.params_of("Emulation.setDeviceMetricsOverride")[0];
+    assert_eq!(metrics["width"], 800);
+    assert_eq!(metrics["height"], 600);
+    assert_eq!(server.params_of("Network.setUserAgentOverride")[0]["userAgent"], "LlamaClick/Test");
+    assert_eq!(server.params_of("Security.setIgnoreCertificateErrors")[0]["ignore"], true);
+    assert!(server.params_of("Network.setBlockedURLs")[0]["urls"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("*.png")));
+}
+
+#[test]
+fn proxy_creates_dedicated_browser_context() {
+    let server = CdpServer::start(page_handler);
+    let browser = CdpBrowser::connect(&server.url, config().with_proxy("http://proxy:3128")).unwrap();
+
+    assert_eq!(browser.target_id(), "page-2");
+    assert_eq!(server.params_of("Target.createBrowserContext")[0]["proxyServer"], "http://proxy:3128");
+    assert_eq!(server.params_of("Target.createTarget")[0]["browserContextId"], "context-1");
+}
+
+#[test]
+fn navigates_and_reads_the_page() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    browser.navigate("https://example.com").unwrap();
+    assert_eq!(server.params_of("Page.navigate")[0]["url"], "https://example.com");
+    assert_eq!(browser.current_url().unwrap(), "https://example.com/");
+    assert_eq!(browser.get_text(&Selector::css("h1")).unwrap(), "Example Domain");
+    assert_eq!(
+        browser.get_attributes(&Selector::css("a")).unwrap()["href"],
+        "https://www.iana.org/domains/example"
+    );
+    assert!(browser.element_exists(&Selector::xpath("//h1")).unwrap());
+}
+
+#[test]
+fn click_dispatches_mouse_events_at_element_center() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    browser.click(&Selector::id("submit")).unwrap();
+
+    let events = server.params_of("Input.dispatchMouseEvent");
+    let types: Vec<_> = events.iter().map(|e| e["type"].as_str().unwrap().to_string()).collect();
+    assert_eq!(types, ["mouseMoved", "mousePressed", "mouseReleased"]);
+    assert_eq!(events[1]["x"], 50.0);
+    assert_eq!(events[1]["y"], 20.0);
+}
+
+#[test]
+fn type_text_inserts_text() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    browser.type_text(&Selector::name("q"), "llamas").unwrap();
+    assert_eq!(server.params_of("Input.insertText")[0]["text"], "llamas");
+}
+
+#[test]
+fn rich_input_dispatches_mouse_and_key_events() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    browser.double_click(&Selector::id("row")).unwrap();
+    let clicks: Vec<_> = server
+        .params_of("Input.dispatchMouseEvent")
+        .iter()
+        .filter(|e| e["type"] == "mousePressed")
+        .map(|e| e["clickCount"].as_u64().unwrap())
+        .collect();
+    assert_eq!(clicks, [1, 2]);
+
+    browser.press_key(&KeyChord::parse("Control+a").unwrap()).unwrap();
+    let keys = server.params_of("Input.dispatchKeyEvent");
+    let sequence: Vec<_> = keys
+        .iter()
+        .map(|e| (e["type"].as_str().unwrap(), e["key"].as_str().unwrap(), e["modifiers"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(
+        sequence,
+        [("rawKeyDown", "Control", 2), ("rawKeyDown", "a", 2), ("keyUp", "a", 2), ("keyUp", "Control", 0)]
+    );
+    // Shortcuts type nothing
+    assert!(keys[1].get("text").is_none());
+
+    // The stand-in page has no matching option
+    let result = browser.select_option(&Selector::id("sort"), "Top");
+    assert!(matches!(result, Err(Error::ElementNotFound(_))));
+}
+
+#[test]
+fn missing_element_is_reported_as_not_found() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    let err = browser.click(&Selector::css("#missing")).unwrap_err();
+    assert!(matches!(err, Error::ElementNotFound(_)), "unexpected error: {}", err);
+}
+
+#[test]
+fn protocol_errors_surface_as_browser_errors() {
+    let server = CdpServer::start(|method, params| {
+        if method == "Page.navigate" {
+            return Err("Cannot navigate to invalid URL".to_string());
+        }
+        page_handler(method, params)
+    });
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    let err = browser.navigate("not a url").unwrap_err();
+    assert!(matches!(err, Error::BrowserError(ref m) if m.contains("Cannot navigate")), "{}", err);
+}
+
+#[test]
+fn screenshot_is_decoded_to_file() {
+    let server = CdpServer::start(page_handler);
+    let browser = CdpBrowser::connect(&server.url, config()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shots/page.png");
+
+    browser.take_screenshot(path.to_str().unwrap()).unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"hello");
+}
+
+#[test]
+fn close_only_closes_attached_target() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    browser.close().unwrap();
+    assert_eq!(server.params_of("Target.closeTarget")[0]["targetId"], "page-1");
+    assert!(!server.methods().contains(&"Browser.close".to_string()));
+    assert!(browser.get_html().is_err());
+}
+
+#[test]
+fn pages_map_to_targets_and_popups_are_reported() {
+    let popup_open = Arc::new(AtomicBool::new(false));
+    let server = CdpServer::start({
+        let popup_open = popup_open.clone();
+        move |method, params| match method {
+            "Target.getTargets" => {
+                let mut infos = vec![
+                    json!({ "targetId": "page-1", "type": "page", "url": "https://example.com/" }),
+                    json!({ "targetId": "page-2", "type": "page", "url": "about:blank" }),
+                ];
+                if popup_open.load(Ordering::SeqCst) {
+                    infos.push(json!({
+                        "targetId": "popup-1", "type": "page", "url": "https://auth.test/", "openerId": "page-1"
+                    }));
+                }
+                Ok(json!({ "targetInfos": infos }))
+            }
+            "Target.createTarget" => Ok(json!({ "targetId": "page-3" })),
+            "Target.attachToTarget" => Ok(json!({ "sessionId": format!("session-{}", params["targetId"].as_str().unwrap()) })),
+            _ => page_handler(method, params),
+        }
+    });
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+    assert_eq!(browser.active_page().unwrap(), PageId::new("page-1"));
+    assert!(browser.take_page_events().unwrap().is_empty());
+
+    popup_open.store(true, Ordering::SeqCst);
+    let events = browser.take_page_events().unwrap();
+    assert_eq!(
+        events,
+        [PageEvent::Opened {
+            page: PageId::new("popup-1"),
+            opener: Some(PageId::new("page-1")),
+            url: "https://auth.test/".to_string(),
+        }]
+    );
+
+    browser.switch_to_page(&PageId::new("popup-1")).unwrap();
+    assert_eq!(browser.target_id(), "popup-1");
+    assert_eq!(server.params_of("Target.activateTarget")[0]["targetId"], "popup-1");
+    assert_eq!(server.params_of("Target.attachToTarget").last().unwrap()["targetId"], "popup-1");
+
+    let created = browser.new_page(None).unwrap();
+    assert_eq!(created, PageId::new("page-3"));
+    browser.close_page(&created).unwrap();
+    assert_eq!(server.params_of("Target.closeTarget")[0]["targetId"], "page-3");
+    assert_eq!(browser.active_page().unwrap(), PageId::new("popup-1"));
+}
+
+#[test]
+fn storage_state_uses_storage_and_dom_storage_domains() {
+    let server = CdpServer::start(|method, params| match method {
+        "Storage.getCookies" => Ok(json!({ "cookies": [{
+            "name": "sid", "value": "1", "domain": ".example.com", "path": "/",
+            "expires": -1, "httpOnly": true, "secure": true, "sameSite": "Lax", "size": 4
+        }] })),
+        "Runtime.evaluate" if params["expression"].as_str().unwrap_or_default().contains("localStorage") => {
+            Ok(evaluate_result(json!({
+                "origin": "https://example.com",
+                "localStorage": [{ "name": "k", "value": "v" }],
+                "sessionStorage": []
+            })))
+        }
+        _ => page_handler(method, params),
+    });
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+
+    let state = browser.storage_state().unwrap();
+    let mut sid = Cookie::new("sid", "1", ".example.com");
+    (sid.http_only, sid.secure, sid.same_site) = (true, true, Some("Lax".to_string()));
+    assert_eq!(state.cookies, [sid]);
+    assert_eq!(state.origin("https://example.com").unwrap().local_storage[0].value, "v");
+
+    browser
+        .set_storage_state(&StorageState {
+            cookies: state.cookies.clone(),
+            origins: vec![OriginStorage {
+                origin: "https://other.test".to_string(),
+                local_storage: Vec::new(),
+                session_storage: vec![StorageItem {
+                    name: "cart".to_string(),
+                    value: "3".to_string(),
+                }],
+            }],
+        })
+        .unwrap();
+    let cookies = &server.params_of("Storage.setCookies")[0]["cookies"];
+    assert_eq!(cookies[0]["domain"], ".example.com");
+    assert!(cookies[0].get("expires").is_none());
+    let item = &server.params_of("DOMStorage.setDOMStorageItem")[0];
+    assert_eq!(item["storageId"], json!({ "securityOrigin": "https://other.test", "isLocalStorage": false }));
+    assert_eq!((&item["key"], &item["value"]), (&json!("cart"), &json!("3")));
+}
+
+#[test]
+fn routes_answer_paused_requests_through_the_fetch_domain() {
+    let server = CdpServer::start(page_handler);
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+    browser
+        .set_routes(&[
+            Route::new(
+                "**/api/*",
+                RouteAction::Continue {
+                    headers: vec![("X-Test".to_string(), "1".to_string())],
+                },
+            ),
+            Route::fulfill("**/api/user", RouteResponse::json(&json!({ "id": 7 }))),
+            Route::abort("**/ads/**").with_resource_type(ResourceType::Image),
+        ])
+        .unwrap();
+    assert_eq!(server.params_of("Fetch.enable")[0]["patterns"][0]["urlPattern"], "*");
+
+    let paused = |id: &str, url: &str, kind: &str| {
+        json!({
+            "requestId": id,
+            "request": { "url": url, "method": "GET", "headers": { "Accept": "*/*" } },
+            "resourceType": kind,
+        })
+    };
+    server.push_event("Fetch.requestPaused", paused("r1", "https://example.com/api/user", "XHR"));
+    server.push_event("Fetch.requestPaused", paused("r2", "https://example.com/ads/a/b.png", "Image"));
+    server.push_event("Fetch.requestPaused", paused("r3", "https://example.com/api/items", "Fetch"));
+    server.push_event("Fetch.requestPaused", paused("r4", "https://example.com/", "Document"));
+    browser.navigate("https://example.com/").unwrap();
+    browser.current_url().unwrap();
+
+    let fulfilled = &server.params_of("Fetch.fulfillRequest")[0];
+    assert_eq!((&fulfilled["requestId"], &fulfilled["responseCode"]), (&json!("r1"), &json!(200)));
+    assert_eq!(fulfilled["body"], "eyJpZCI6N30=");
+    assert_eq!(server.params_of("Fetch.failRequest")[0]["errorReason"], "BlockedByClient");
+
+    let continued = server.params_of("Fetch.continueRequest");
+    assert_eq!(continued[0]["requestId"], "r3");
+    assert_eq!(
+        continued[0]["headers"],
+        json!([{ "name": "Accept", "value": "*/*" }, { "name": "X-Test", "value": "1" }])
+    );
+    assert_eq!(continued[1], json!({ "requestId": "r4" }));
+
+    browser.set_routes(&[]).unwrap();
+    assert!(server.methods().contains(&"Fetch.disable".to_string()));
+}
+
+#[test]
+fn har_recording_follows_network_events() {
+    let server = CdpServer::start(|method, params| match method {
+        "Network.getResponseBody" => Ok(json!({ "body": "aGk=", "base64Encoded": true })),
+        _ => page_handler(method, params),
+    });
+    let mut browser = CdpBrowser::connect(&server.url, config()).unwrap();
+    browser.set_har_recording(Some(HarOptions::default().with_bodies())).unwrap();
+    assert!(server.methods().contains(&"Network.enable".to_string()));
+
+    let request = |id: &str, url: &str, timestamp: f64| {
+        json!({
+            "requestId": id,
+            "timestamp": timestamp,
+            "wallTime": 1_700_000_000.0,
+            "request": { "url": url, "method": "GET", "headers": { "Accept": "text/html" } },
+        })
+    };
+    server.push_event("Network.requestWillBeSent", request("n1", "https://example.com/", 10.0));
+    server.push_event(
+        "Network.responseReceived",
+        json!({
+            "requestId": "n1",
+            "timestamp": 10.25,
+            "response": { "status": 200, "headers": { "Content-Type": "text/plain" } },
+        }),
+    );
+    server.push_event("Network.loadingFinished", json!({ "requestId": "n1", "timestamp": 10.5 }));
+    server.push_event("Network.requestWillBeSent", request("n2", "https://example.com/gone", 11.0));
+    server.push_event(
+        "Network.loadingFailed",
+        json!({ "requestId": "n2", "timestamp": 11.5, "errorText": "net::ERR_NAME_NOT_RESOLVED" }),
+    );
+    browser.navigate("https://example.com/").unwrap();
+
+    let entries = browser.take_har_entries().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].started_date_time, "2023-11-14T22:13:20.000Z");
+    assert_eq!((entries[0].timings.wait, entries[0].timings.receive), (250.0, 250.0));
+    assert_eq!(entries[0].response.content.text.as_deref(), Some("hi"));
+    assert_eq!(entries[0].response.content.mime_type, "text/plain");
+    assert_eq!(server.params_of("Network.getResponseBody")[0]["requestId"], "n1");
+    assert_eq!(entries[1].response.error.as_deref(), Some("net::ERR_NAME_NOT_RESOLVED"));
+
+    browser.set_har_recording(None).unwrap();
+    assert!(server.methods().contains(&"Network.disable".to_string()));
+}
@@ -0,0 +1,374 @@
+//! Shared fixtures for the integration tests
+//!
+//! These are local stand-ins for the remote endpoints LlamaClick talks to, so
+//! the tests run without a real browser, driver or LLM provider.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tungstenite::Message;
+
+/// Handler deciding the result of each DevTools command
+pub type CdpHandler = dyn Fn(&str, &Value) -> Result<Value, String> + Send + Sync;
+
+/// A stand-in DevTools websocket endpoint that replays scripted responses
+pub struct CdpServer {
+    /// The websocket URL to connect to
+    pub url: String,
+    /// Every command received, as `(method, params)`
+    pub requests: Arc<Mutex<Vec<(String, Value)>>>,
+    /// Events sent ahead of the next response
+    events: Arc<Mutex<Vec<Value>>>,
+}
+
+impl CdpServer {
+    /// Start a server answering every command with `handler`
+    pub fn start(handler: impl Fn(&str, &Value) -> Result<Value, String> + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/devtools/browser/test", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let pending = events.clone();
+        let handler: Arc<CdpHandler> = Arc::new(handler);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { break };
+                let recorded = recorded.clone();
+                let pending = pending.clone();
+                let handler = handler.clone();
+                thread::spawn(move || {
+                    let Ok(mut socket) = tungstenite::accept(stream) else { return };
+                    while let Ok(message) = socket.read() {
+                        let Message::Text(text) = message else { continue };
+                        let request: Value = serde_json::from_str(text.as_str()).unwrap();
+                        let method = request["method"].as_str().unwrap_or_default().to_string();
+                        let params = request["params"].clone();
+                        recorded.lock().unwrap().push((method.clone(), params.clone()));
+
+                        let mut response = match handler(&method, &params) {
+                            Ok(result) => json!({ "id": request["id"], "result": result }),
+                            Err(message) => json!({
+                                "id": request["id"],
+                                "error": { "code": -32000, "message": message },
+                            }),
+                        };
+                        if let Some(session_id) = request.get("sessionId") {
+                            response["sessionId"] = session_id.clone();
+                        }
+                        let events: Vec<Value> = pending.lock().unwrap().drain(..).collect();
+                        for event in events {
+                            if socket.send(Message::text(event.to_string())).is_err() {
+                                return;
+                            }
+                        }
+                        if socket.send(Message::text(response.to_string())).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        Self { url, requests, events }
+    }
+
+    /// Queue an event, sent just before the next command's response
+    pub fn push_event(&self, method: &str, params: Value) {
+        self.events.lock().unwrap().push(json!({ "method": method, "params": params, "sessionId": "session-1" }));
+    }
+
+    /// Methods received so far, in order
+    pub fn methods(&self) -> Vec<String> {
+        self.requests.lock().unwrap().iter().map(|(method, _)| method.clone()).collect()
+    }
+
+    /// Parameters of every received call to `method`
+    pub fn params_of(&self, method: &str) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(m, _)| m == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+}
+
+/// The user agent [`cdp_attach_response`] reports for the browser
+pub const CHROME_USER_AGENT: &str =
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) HeadlessChrome/120.0.0.0 Safari/537.36";
+
+/// Default answers for the commands issued while attaching to a page
+pub fn cdp_attach_response(method: &str) -> Option<Value> {
+    match method {
+        "Target.getTargets" => Some(json!({
+            "targetInfos": [{ "targetId": "page-1", "type": "page", "url": "about:blank" }]
+        })),
+        "Target.createTarget" => Some(json!({ "targetId": "page-2" })),
+        "Target.createBrowserContext" => Some(json!({ "browserContextId": "context-1" })),
+        "Target.attachToTarget" => Some(json!({ "sessionId": "session-1" })),
+        "Browser.getVersion" => Some(json!({ "product": "Chrome/120.0.0.0", "userAgent": CHROME_USER_AGENT })),
+        _ => None,
+    }
+}
+
+/// Wrap a value as a `Runtime.evaluate` result
+pub fn evaluate_result(value: Value) -> Value {
+    json!({ "result": { "type": "object", "value": value } })
+}
+
+/// A request received by [`HttpServer`]
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// The HTTP method
+    pub method: String,
+    /// The request path including the query string
+    pub path: String,
+    /// The request headers, with lowercased names
+    pub headers: Vec<(String, String)>,
+    /// The request body
+    pub body: String,
+}
+
+impl HttpRequest {
+    /// The body parsed as JSON
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// The first header with the given (lowercase) name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// A response produced by an [`HttpServer`] handler
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// The status code
+    pub status: u16,
+    /// The response headers
+    pub headers: Vec<(String, String)>,
+    /// The response body
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// A 200 response with a JSON body
+    pub fn json(value: Value) -> Self {
+        Self::with_status(200, value)
+    }
+
+    /// A JSON response with the given status
+    pub fn with_status(status: u16, value: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    /// A 200 response with an HTML body
+    pub fn html(body: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "text/html; charset=utf-8".to_string())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    /// Add a header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Handler deciding the response to each HTTP request
+pub type HttpHandler = dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync;
+
+/// A local HTTP fixture server
+pub struct HttpServer {
+    /// The base URL, without a trailing slash
+    pub url: String,
+    /// Every request received, in order
+    pub requests: Arc<Mutex<Vec<HttpRequest>>>,
+}
+
+impl HttpServer {
+    /// Start a server answering every request with `handler`
+    pub fn start(handler: impl Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static) -> Self {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let handler: Arc<HttpHandler> = Arc::new(handler);
+
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let received = HttpRequest {
+                    method: request.method().as_str().to_string(),
+                    path: request.url().to_string(),
+                    headers: request
+                        .headers()
+                        .iter()
+                        .map(|h| (h.field.as_str().as_str().to_ascii_lowercase(), h.value.as_str().to_string()))
+                        .collect(),
+                    body,
+                };
+                recorded.lock().unwrap().push(received.clone());
+
+                let reply = handler(&received);
+                let mut response = tiny_http::Response::from_data(reply.body).with_status_code(reply.status);
+                for (name, value) in reply.headers {
+                    response.add_header(tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+                }
+                let _ = request.respond(response);
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Requests received so far
+    pub fn received(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// A stand-in streaming endpoint writing a server-sent event body in pieces
+///
+/// Each piece is flushed separately after a short pause, so events and even
+/// UTF-8 characters can be split across the chunks the client reads.
+pub struct SseServer {
+    /// The URL to post to
+    pub url: String,
+    /// The JSON body of every request received
+    pub requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl SseServer {
+    /// Start a server answering every request with `pieces`
+    pub fn start(pieces: Vec<Vec<u8>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/stream", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    if std::io::BufRead::read_line(&mut reader, &mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                let _ = std::io::Read::read_exact(&mut reader, &mut body);
+                recorded.lock().unwrap().push(serde_json::from_slice(&body).unwrap_or(Value::Null));
+
+                let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n";
+                let _ = std::io::Write::write_all(&mut stream, head.as_bytes());
+                for piece in &pieces {
+                    let _ = std::io::Write::write_all(&mut stream, piece);
+                    let _ = std::io::Write::flush(&mut stream);
+                    thread::sleep(std::time::Duration::from_millis(5));
+                }
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Request bodies received so far
+    pub fn received(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// An LLM provider answering prompts with scripted replies
+#[cfg(feature = "llm")]
+#[derive(Debug, Clone)]
+pub struct ScriptedLlm {
+    /// The replies in order; the last one answers every later prompt
+    pub replies: Vec<String>,
+    /// Every conversation received
+    pub chats: Arc<Mutex<Vec<Vec<llamaclick::llms::ChatMessage>>>>,
+}
+
+#[cfg(feature = "llm")]
+impl ScriptedLlm {
+    /// Create a provider that always answers `reply`
+    pub fn new(reply: &str) -> Self {
+        Self::sequence(&[reply])
+    }
+
+    /// Create a provider answering `replies` in turn, then repeating the last one
+    pub fn sequence(replies: &[&str]) -> Self {
+        Self {
+            replies: replies.iter().map(|reply| reply.to_string()).collect(),
+            chats: Arc::default(),
+        }
+    }
+
+    /// The last message of every conversation received so far
+    pub fn prompts(&self) -> Vec<String> {
+        let chats = self.chats.lock().unwrap();
+        chats.iter().map(|chat| chat.last().map(|m| m.content.clone()).unwrap_or_default()).collect()
+    }
+
+    /// Every conversation received so far
+    pub fn chats(&self) -> Vec<Vec<llamaclick::llms::ChatMessage>> {
+        self.chats.lock().unwrap().clone()
+    }
+
+    /// Number of prompts received so far
+    pub fn calls(&self) -> usize {
+        self.chats.lock().unwrap().len()
+    }
+}
+
+#[cfg(feature = "llm")]
+#[async_trait::async_trait]
+impl llamaclick::llms::LlmProvider for ScriptedLlm {
+    async fn chat(
+        &self,
+        messages: &[llamaclick::llms::ChatMessage],
+        _options: &llamaclick::llms::GenerationOptions,
+    ) -> llamaclick::error::Result<llamaclick::llms::LlmResponse> {
+        let mut chats = self.chats.lock().unwrap();
+        let reply = self.replies[chats.len().min(self.replies.len() - 1)].clone();
+        chats.push(messages.to_vec());
+        Ok(llamaclick::llms::LlmResponse {
+            content: reply,
+            model: "scripted".to_string(),
+            duration: std::time::Duration::ZERO,
+            token_usage: None,
+            tool_calls: Vec::new(),
+            stop_reason: None,
+        })
+    }
+
+    fn model_name(&self) -> &str {
+        "scripted"
+    }
+
+    fn provider_name(&self) -> &str {
+        "Scripted"
+    }
+}
@@ -0,0 +1,176 @@
+//! Tests for dialog policies, file uploads and downloads
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{cdp_attach_response, evaluate_result, CdpServer};
+use llamaclick::browser::{
+    mime_type_for, BrowserConfig, BrowserSession, BrowserType, CdpBrowser, DialogAction, DialogKind, DialogPolicy,
+    MockBrowser, Selector,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const FORM: &str = r#"<html><body>
+  <form>
+    <input type="file" id="resume" name="resume">
+    <input type="text" id="name">
+  </form>
+</body></html>"#;
+
+fn mock_session(mock: &MockBrowser) -> BrowserSession {
+    BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default())
+}
+
+#[test]
+fn dialogs_are_answered_by_the_policy() {
+    let mock = MockBrowser::from_html(FORM);
+    let mut session = mock_session(&mock);
+
+    assert_eq!(mock.open_dialog(DialogKind::Confirm, "Leave?"), DialogAction::Dismiss);
+
+    session.set_dialog_policy(DialogPolicy::Accept).unwrap();
+    assert_eq!(mock.open_dialog(DialogKind::Alert, "Saved"), DialogAction::Accept);
+    assert_eq!(mock.open_dialog(DialogKind::Prompt, "Name?"), DialogAction::AcceptWith(String::new()));
+
+    session
+        .set_dialog_policy(DialogPolicy::callback(|dialog| match dialog.kind {
+            DialogKind::Prompt => DialogAction::AcceptWith("Llama".to_string()),
+            _ if dialog.message.contains("Delete") => DialogAction::Dismiss,
+            _ => DialogAction::Accept,
+        }))
+        .unwrap();
+    assert_eq!(mock.open_dialog(DialogKind::Prompt, "Name?"), DialogAction::AcceptWith("Llama".to_string()));
+    assert_eq!(mock.open_dialog(DialogKind::Confirm, "Delete everything?"), DialogAction::Dismiss);
+
+    let dialogs = session.take_dialogs().unwrap();
+    assert_eq!(dialogs.len(), 5);
+    assert_eq!(dialogs[4].dialog.message, "Delete everything?");
+    assert_eq!(dialogs[4].dialog.url, "about:blank");
+    assert!(session.take_dialogs().unwrap().is_empty());
+}
+
+#[test]
+fn file_inputs_take_existing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let resume = dir.path().join("resume.pdf");
+    std::fs::write(&resume, b"%PDF-1.7").unwrap();
+    let mock = MockBrowser::from_html(FORM);
+    let mut session = mock_session(&mock);
+
+    session.set_input_files(&Selector::id("resume"), &[&resume]).unwrap();
+    assert_eq!(mock.input_files(&Selector::id("resume")), vec![resume.canonicalize().unwrap()]);
+
+    let err = session.set_input_files(&Selector::id("name"), &[&resume]).unwrap_err();
+    assert!(err.to_string().contains("is not a file input"), "{}", err);
+
+    let missing = dir.path().join("cover-letter.pdf");
+    let err = session.set_input_files(&Selector::id("resume"), &[missing]).unwrap_err();
+    assert!(matches!(err, Error::ValidationError(_)), "{}", err);
+    assert!(err.to_string().contains("cover-letter.pdf"), "{}", err);
+    assert_eq!(session.action_log().last().unwrap().action, "set_input_files");
+}
+
+#[test]
+fn downloads_are_saved_under_unique_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let mock = MockBrowser::from_html(FORM).with_downloads_dir(dir.path());
+    let mut session = mock_session(&mock);
+
+    mock.start_download("https://app.test/export", "report.csv", "text/csv", "a,b\n1,2\n");
+    mock.start_download("https://app.test/export", "report.csv", "text/csv", "a,b\n3,4\n");
+    mock.start_download("https://app.test/evil", "../../evil.sh", "text/x-sh", "echo");
+
+    let first = session.wait_for_download(Duration::from_secs(1)).unwrap();
+    assert_eq!(first.path, dir.path().join("report.csv"));
+    assert_eq!(first.mime_type, "text/csv");
+    assert_eq!(std::fs::read_to_string(&first.path).unwrap(), "a,b\n1,2\n");
+    let second = session.wait_for_download(Duration::from_secs(1)).unwrap();
+    assert_eq!(second.path, dir.path().join("report (1).csv"));
+    let evil = session.wait_for_download(Duration::from_secs(1)).unwrap();
+    assert_eq!(evil.path, dir.path().join("evil.sh"));
+
+    let err = session.wait_for_download(Duration::from_millis(10)).unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)), "{}", err);
+    assert_eq!(mime_type_for("Quarterly.PDF"), "application/pdf");
+    assert_eq!(mime_type_for("archive"), "application/octet-stream");
+}
+
+fn page(method: &str, params: &Value) -> Result<Value, String> {
+    if let Some(result) = cdp_attach_response(method) {
+        return Ok(result);
+    }
+    match method {
+        "Runtime.evaluate" if params["expression"].as_str().unwrap_or_default().contains("not a file input") => {
+            Ok(json!({ "result": { "type": "object", "subtype": "node", "objectId": "node-7" } }))
+        }
+        "Runtime.evaluate" => Ok(evaluate_result(json!(null))),
+        _ => Ok(json!({})),
+    }
+}
+
+#[test]
+fn cdp_answers_dialogs_while_commands_wait() {
+    let server = CdpServer::start(page);
+    let browser = CdpBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Chrome)).unwrap();
+    let mut session = BrowserSession::new(Box::new(browser), BrowserConfig::new(BrowserType::Chrome));
+
+    session
+        .set_dialog_policy(DialogPolicy::callback(|_| DialogAction::AcceptWith("Llama".to_string())))
+        .unwrap();
+    server.push_event(
+        "Page.javascriptDialogOpening",
+        json!({ "url": "https://app.test/", "message": "Your name?", "type": "prompt", "defaultPrompt": "" }),
+    );
+    session.execute_js("window.prompt('Your name?')").unwrap();
+    // The answer is not awaited, so make sure the server has read it
+    session.execute_js("1").unwrap();
+
+    let answer = &server.params_of("Page.handleJavaScriptDialog")[0];
+    assert_eq!(answer, &json!({ "accept": true, "promptText": "Llama" }));
+    let dialogs = session.take_dialogs().unwrap();
+    assert_eq!(dialogs[0].dialog.kind, DialogKind::Prompt);
+    assert_eq!(dialogs[0].dialog.message, "Your name?");
+}
+
+#[test]
+fn cdp_uploads_files_and_saves_downloads() {
+    let dir = tempfile::tempdir().unwrap();
+    let resume = dir.path().join("resume.pdf");
+    std::fs::write(&resume, b"%PDF-1.7").unwrap();
+    let server = CdpServer::start(page);
+    let config = BrowserConfig::new(BrowserType::Chrome).with_downloads_dir(dir.path());
+    let browser = CdpBrowser::connect(&server.url, config.clone()).unwrap();
+    let mut session = BrowserSession::new(Box::new(browser), config);
+
+    session.set_input_files(&Selector::id("resume"), &[&resume]).unwrap();
+    let upload = &server.params_of("DOM.setFileInputFiles")[0];
+    assert_eq!(upload["objectId"], "node-7");
+    assert_eq!(upload["files"], json!([resume.canonicalize().unwrap()]));
+
+    let behavior = &server.params_of("Browser.setDownloadBehavior")[0];
+    assert_eq!(behavior["behavior"], "allowAndName");
+    assert_eq!(behavior["downloadPath"], json!(dir.path()));
+
+    // The browser writes the file under its guid before reporting completion
+    std::fs::write(dir.path().join("guid-1"), b"%PDF-1.7 export").unwrap();
+    let url = "https://app.test/export";
+    server.push_event(
+        "Browser.downloadWillBegin",
+        json!({ "guid": "guid-1", "url": url, "suggestedFilename": "Q3 revenue.pdf" }),
+    );
+    server.push_event("Browser.downloadProgress", json!({ "guid": "guid-1", "state": "inProgress" }));
+    server.push_event("Browser.downloadProgress", json!({ "guid": "guid-1", "state": "completed" }));
+    let download = session.wait_for_download(Duration::from_secs(2)).unwrap();
+    assert_eq!(download.url, url);
+    assert_eq!(download.path, dir.path().join("Q3 revenue.pdf"));
+    assert_eq!(download.mime_type, "application/pdf");
+    assert_eq!(std::fs::read(&download.path).unwrap(), b"%PDF-1.7 export");
+
+    server.push_event("Browser.downloadWillBegin", json!({ "guid": "guid-2", "url": url, "suggestedFilename": "a" }));
+    server.push_event("Browser.downloadProgress", json!({ "guid": "guid-2", "state": "canceled" }));
+    let err = session.wait_for_download(Duration::from_secs(2)).unwrap_err();
+    assert!(matches!(err, Error::NetworkError(_)), "{}", err);
+}
@@ -0,0 +1,143 @@
+//! Tests for device presets and environment emulation
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{
+    cdp_attach_response, evaluate_result, CdpServer, HttpRequest, HttpResponse, HttpServer, CHROME_USER_AGENT,
+};
+use llamaclick::browser::{
+    Browser, BrowserConfig, BrowserType, CdpBrowser, ColorScheme, Device, Geolocation, StaticBrowser,
+    WebDriverBrowser,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+
+fn page(method: &str, _params: &Value) -> Result<Value, String> {
+    if let Some(result) = cdp_attach_response(method) {
+        return Ok(result);
+    }
+    match method {
+        "Runtime.evaluate" => Ok(evaluate_result(json!(null))),
+        _ => Ok(json!({})),
+    }
+}
+
+fn driver(request: &HttpRequest) -> HttpResponse {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/session") => HttpResponse::json(json!({
+            "value": { "sessionId": "s-1", "capabilities": {} }
+        })),
+        _ => HttpResponse::json(json!({ "value": null })),
+    }
+}
+
+fn berlin() -> BrowserConfig {
+    BrowserConfig::new(BrowserType::Chrome)
+        .with_locale("de-DE")
+        .with_timezone("Europe/Berlin")
+        .with_geolocation(Geolocation::new(52.52, 13.405))
+        .with_color_scheme(ColorScheme::Dark)
+}
+
+#[test]
+fn presets_are_found_by_name() {
+    let iphone = Device::named("iphone 13").unwrap();
+    assert_eq!(iphone.name, "iPhone 13");
+    assert!(Device::named("Nokia 3310").is_none());
+    assert!(Device::names().any(|name| name == "Pixel 7"));
+
+    let config = BrowserConfig::default().with_device(iphone);
+    assert_eq!((config.window_width, config.window_height), (390, 844));
+    assert_eq!(config.user_agent.as_deref(), Some(iphone.user_agent));
+    assert_eq!(config.emulation.device_scale_factor, Some(3.0));
+    assert!(config.emulation.mobile && config.emulation.touch);
+
+    let mut value = serde_json::to_value(BrowserConfig::default()).unwrap();
+    value.as_object_mut().unwrap().remove("emulation");
+    let parsed: BrowserConfig = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(parsed.emulation, Default::default());
+    value["emulation"] = json!({ "locale": "fr-FR", "color_scheme": "dark", "offline": true });
+    let parsed: BrowserConfig = serde_json::from_value(value).unwrap();
+    assert_eq!(parsed.emulation.locale.as_deref(), Some("fr-FR"));
+    assert_eq!(parsed.emulation.color_scheme, Some(ColorScheme::Dark));
+    assert!(parsed.emulation.offline && !parsed.emulation.mobile);
+}
+
+#[test]
+fn cdp_applies_the_emulation_settings() {
+    let server = CdpServer::start(page);
+    let config = berlin().with_device(Device::named("Pixel 7").unwrap()).with_offline(true);
+    CdpBrowser::connect(&server.url, config).unwrap();
+
+    let metrics = &server.params_of("Emulation.setDeviceMetricsOverride")[0];
+    assert_eq!(metrics["width"], 412);
+    assert_eq!(metrics["deviceScaleFactor"], 2.625);
+    assert_eq!(metrics["mobile"], true);
+    assert_eq!(server.params_of("Emulation.setTouchEmulationEnabled")[0]["enabled"], true);
+    assert_eq!(server.params_of("Network.setUserAgentOverride")[0]["acceptLanguage"], "de-DE");
+    assert_eq!(server.params_of("Emulation.setLocaleOverride")[0], json!({ "locale": "de-DE" }));
+    assert_eq!(server.params_of("Emulation.setTimezoneOverride")[0], json!({ "timezoneId": "Europe/Berlin" }));
+    assert_eq!(server.params_of("Browser.grantPermissions")[0]["permissions"], json!(["geolocation"]));
+    let position = &server.params_of("Emulation.setGeolocationOverride")[0];
+    assert_eq!((position["latitude"].as_f64(), position["accuracy"].as_f64()), (Some(52.52), Some(10.0)));
+    let media = &server.params_of("Emulation.setEmulatedMedia")[0];
+    assert_eq!(media["features"], json!([{ "name": "prefers-color-scheme", "value": "dark" }]));
+    assert_eq!(server.params_of("Network.emulateNetworkConditions")[0]["offline"], true);
+}
+
+#[test]
+fn cdp_sends_the_locale_as_accept_language_without_a_user_agent() {
+    let server = CdpServer::start(page);
+    CdpBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Chrome).with_locale("de-DE")).unwrap();
+
+    // The browser's own user agent is kept
+    assert_eq!(server.params_of("Browser.getVersion").len(), 1);
+    let user_agent = json!({ "userAgent": CHROME_USER_AGENT, "acceptLanguage": "de-DE" });
+    assert_eq!(server.params_of("Network.setUserAgentOverride"), [user_agent]);
+
+    let server = CdpServer::start(page);
+    CdpBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Chrome)).unwrap();
+    assert!(!server.methods().iter().any(|method| method == "Network.setUserAgentOverride"));
+}
+
+#[test]
+fn static_backend_sends_the_locale_and_honours_offline() {
+    let server = HttpServer::start(|_| HttpResponse::html("<html><body>Hallo</body></html>"));
+    let mut browser = StaticBrowser::new(BrowserConfig::default().with_locale("de-DE")).unwrap();
+    browser.navigate(&format!("{}/", server.url)).unwrap();
+    assert_eq!(server.received()[0].header("accept-language"), Some("de-DE"));
+
+    let mut offline = StaticBrowser::new(BrowserConfig::default().with_offline(true)).unwrap();
+    let err = offline.navigate(&format!("{}/", server.url)).unwrap_err();
+    assert!(matches!(err, Error::NetworkError(_)), "{}", err);
+    assert!(err.to_string().contains("offline"), "{}", err);
+    assert_eq!(server.received().len(), 1);
+}
+
+#[test]
+fn webdriver_requests_emulation_capabilities() {
+    let server = HttpServer::start(driver);
+    let config = berlin().with_device(Device::named("iPhone 13").unwrap()).with_block_images(true);
+    WebDriverBrowser::connect(&server.url, config).unwrap();
+
+    let chrome = &server.received()[0].json()["capabilities"]["alwaysMatch"]["goog:chromeOptions"];
+    let metrics = &chrome["mobileEmulation"]["deviceMetrics"];
+    assert_eq!((metrics["width"].as_u64(), metrics["pixelRatio"].as_f64()), (Some(390), Some(3.0)));
+    assert_eq!(metrics["touch"], true);
+    let args = chrome["args"].as_array().unwrap();
+    assert!(args.contains(&json!("--lang=de-DE")));
+    assert!(args.contains(&json!("--blink-settings=imagesEnabled=false,preferredColorScheme=0")));
+    assert_eq!(chrome["prefs"]["intl.accept_languages"], "de-DE");
+
+    let server = HttpServer::start(driver);
+    let config = BrowserConfig { browser_type: BrowserType::Firefox, ..berlin() };
+    WebDriverBrowser::connect(&server.url, config).unwrap();
+    let firefox = &server.received()[0].json()["capabilities"]["alwaysMatch"]["moz:firefoxOptions"];
+    assert_eq!(firefox["env"]["TZ"], "Europe/Berlin");
+    assert_eq!(firefox["prefs"]["intl.accept_languages"], "de-DE");
+    assert_eq!(firefox["prefs"]["layout.css.prefers-color-scheme.content-override"], 0);
+    let provider = firefox["prefs"]["geo.provider.network.url"].as_str().unwrap();
+    assert!(provider.contains("\"lat\":52.52"), "{}", provider);
+}
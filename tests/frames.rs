@@ -0,0 +1,168 @@
+//! Tests for selectors scoped through elements, shadow roots and frames
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{cdp_attach_response, evaluate_result, CdpServer, HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    locator, Browser, BrowserConfig, BrowserSession, BrowserType, CdpBrowser, MockBrowser, Selector, WebDriverBrowser,
+    ELEMENT_KEY, SHADOW_ROOT_KEY,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const PAGE: &str = r#"<html><body>
+  <section id="billing"><input name="email" value="billing@app.test"></section>
+  <section id="shipping"><input name="email" value="shipping@app.test"></section>
+  <checkout-form id="checkout">
+    <template shadowrootmode="open"><button class="pay">Pay now</button></template>
+    <span>Light DOM</span>
+  </checkout-form>
+  <iframe id="payment" src="https://pay.test/frame"></iframe>
+</body></html>"#;
+
+#[test]
+fn scopes_limit_the_search_to_an_elements_subtree() {
+    let mock = MockBrowser::from_html(PAGE);
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+
+    let shipping = Selector::within(Selector::id("shipping"), Selector::name("email"));
+    session.type_text(&shipping, "llama@app.test").unwrap();
+    assert_eq!(session.get_text(&shipping).unwrap(), "llama@app.test");
+    mock.assert_typed(&shipping, "llama@app.test");
+
+    let billing = Selector::within(Selector::id("billing"), Selector::xpath("//input"));
+    assert_eq!(session.get_attributes(&billing).unwrap()["value"], "billing@app.test");
+
+    // The scope itself is not part of the search
+    let nested = Selector::within(Selector::id("billing"), Selector::css("section"));
+    assert!(!session.element_exists(&nested).unwrap());
+}
+
+#[test]
+fn declarative_shadow_roots_are_entered() {
+    let mock = MockBrowser::from_html(PAGE);
+
+    let pay = Selector::within(Selector::id("checkout"), Selector::class("pay"));
+    assert_eq!(mock.get_text(&pay).unwrap(), "Pay now");
+    let light = Selector::within(Selector::id("checkout"), Selector::text("Light DOM"));
+    assert!(!mock.element_exists(&light).unwrap());
+
+    let host = Selector::within(Selector::css("body"), Selector::id("checkout"));
+    assert!(mock.element_exists(&Selector::within(host, Selector::css("button"))).unwrap());
+}
+
+#[test]
+fn frames_are_reported_as_unsupported_without_a_live_page() {
+    let mut mock = MockBrowser::from_html(PAGE);
+    let card = Selector::within(Selector::id("payment"), Selector::name("card"));
+
+    let err = mock.click(&card).unwrap_err();
+    assert!(matches!(err, Error::Unsupported(_)), "{}", err);
+    assert!(err.to_string().contains("frame"), "{}", err);
+    assert!(locator(&card).is_err());
+}
+
+#[test]
+fn cdp_resolves_scopes_in_the_page_and_reports_cross_origin_frames() {
+    let server = CdpServer::start(|method: &str, params: &Value| {
+        if let Some(result) = cdp_attach_response(method) {
+            return Ok(result);
+        }
+        if method != "Runtime.evaluate" {
+            return Ok(json!({}));
+        }
+        let expression = params["expression"].as_str().unwrap_or_default();
+        if expression.contains(r#"{"Within":[{"Id":"payment"}"#) {
+            return Ok(json!({
+                "result": { "type": "object" },
+                "exceptionDetails": {
+                    "text": "Uncaught",
+                    "exception": { "description": "Error: __llamaclick_cross_origin__ https://pay.test/frame" }
+                }
+            }));
+        }
+        Ok(evaluate_result(json!("Pay now")))
+    });
+    let browser = CdpBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Chrome)).unwrap();
+
+    let pay = Selector::within(Selector::id("checkout"), Selector::class("pay"));
+    assert_eq!(browser.get_text(&pay).unwrap(), "Pay now");
+    let evaluated = server.params_of("Runtime.evaluate");
+    let expression = evaluated.last().unwrap()["expression"].as_str().unwrap();
+    assert!(expression.contains(r#"{"Within":[{"Id":"checkout"},{"Class":"pay"}]}"#), "{}", expression);
+
+    let card = Selector::within(Selector::id("payment"), Selector::name("card"));
+    let err = browser.get_text(&card).unwrap_err();
+    assert!(matches!(err, Error::BrowserError(_)), "{}", err);
+    assert!(err.to_string().contains("cross-origin frame https://pay.test/frame"), "{}", err);
+}
+
+/// A driver whose page has an iframe (`f-1`) holding `#card` (`e-1`), and a
+/// shadow host (`h-1`) whose shadow root holds `.pay` (`e-2`)
+fn driver(request: &HttpRequest) -> HttpResponse {
+    let path = request.path.trim_start_matches("/session/s-1");
+    let value = request.json()["value"].as_str().unwrap_or_default().to_string();
+    let elements = |id: &str| HttpResponse::json(json!({ "value": [{ ELEMENT_KEY: id }] }));
+    match (request.method.as_str(), path) {
+        ("POST", "/session") => HttpResponse::json(json!({ "value": { "sessionId": "s-1", "capabilities": {} } })),
+        ("POST", "/elements") if value.contains("payment") => elements("f-1"),
+        ("POST", "/elements") if value.contains("checkout") => elements("h-1"),
+        ("POST", "/elements") if value.contains("card") => elements("e-1"),
+        ("POST", "/shadow/r-1/elements") => elements("e-2"),
+        ("GET", "/element/f-1/name") => HttpResponse::json(json!({ "value": "iframe" })),
+        ("GET", "/element/h-1/name") => HttpResponse::json(json!({ "value": "checkout-form" })),
+        ("GET", "/element/h-1/shadow") => HttpResponse::json(json!({ "value": { SHADOW_ROOT_KEY: "r-1" } })),
+        ("GET", "/element/e-2/text") => HttpResponse::json(json!({ "value": "Pay now" })),
+        _ => HttpResponse::json(json!({ "value": null })),
+    }
+}
+
+#[test]
+fn webdriver_switches_into_frames_and_shadow_roots() {
+    let server = HttpServer::start(driver);
+    let config = BrowserConfig::new(BrowserType::Firefox).with_timeout(Duration::from_secs(2));
+    let mut browser = WebDriverBrowser::connect(&server.url, config).unwrap();
+
+    browser
+        .type_text(&Selector::within(Selector::id("payment"), Selector::id("card")), "4242")
+        .unwrap();
+    let pay = Selector::within(Selector::id("checkout"), Selector::class("pay"));
+    assert_eq!(browser.get_text(&pay).unwrap(), "Pay now");
+
+    let requests = server.received();
+    let trail: Vec<_> = requests
+        .iter()
+        .filter(|r| r.path.contains("/frame") || r.path.ends_with("/value"))
+        .map(|r| (r.path.trim_start_matches("/session/s-1").to_string(), r.json()))
+        .collect();
+    assert_eq!(
+        trail,
+        [
+            ("/frame".to_string(), json!({ "id": { ELEMENT_KEY: "f-1" } })),
+            ("/element/e-1/value".to_string(), json!({ "text": "4242" })),
+            ("/frame".to_string(), json!({ "id": null })),
+        ]
+    );
+    // Shadow roots need no switching back
+    assert!(requests.iter().any(|r| r.path.ends_with("/shadow/r-1/elements")));
+}
+
+#[test]
+fn webdriver_leaves_frames_without_matches() {
+    let server = HttpServer::start(driver);
+    let config = BrowserConfig::new(BrowserType::Firefox).with_timeout(Duration::from_secs(2));
+    let browser = WebDriverBrowser::connect(&server.url, config).unwrap();
+
+    let missing = Selector::within(Selector::id("payment"), Selector::id("nothing"));
+    assert!(!browser.element_exists(&missing).unwrap());
+    let frames: Vec<_> = server
+        .received()
+        .into_iter()
+        .filter(|r| r.path.contains("/frame"))
+        .map(|r| r.path.trim_start_matches("/session/s-1").to_string())
+        .collect();
+    assert_eq!(frames, ["/frame", "/frame/parent"]);
+}
@@ -0,0 +1,140 @@
+//! Tests for HAR recording and replay
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    BrowserConfig, BrowserSession, Har, HarEntry, HarOptions, HarRequest, HarResponse, HarTimings, MockAction,
+    MockBrowser, Route, RouteResponse, Selector, StaticBrowser,
+};
+use llamaclick::error::Error;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn site(request: &HttpRequest) -> HttpResponse {
+    match request.path.as_str() {
+        "/" => HttpResponse::html(
+            r#"<form action="/search" method="post"><input name="q" value="llamas"><button>Go</button></form>"#,
+        ),
+        _ => HttpResponse::html(&format!("<p id=\"path\">{}</p>", request.path)).header("X-Served", "yes"),
+    }
+}
+
+fn static_session() -> BrowserSession {
+    let config = BrowserConfig::default();
+    BrowserSession::new(Box::new(StaticBrowser::new(config.clone()).unwrap()), config)
+}
+
+#[test]
+fn records_requests_responses_and_bodies() {
+    let server = HttpServer::start(site);
+    let mut session = static_session();
+    session.record_har(HarOptions::default().with_bodies()).unwrap();
+
+    session.navigate(&format!("{}/", server.url)).unwrap();
+    session.click(&Selector::css("button")).unwrap();
+    session.navigate(&format!("{}/items?page=2&sort=new", server.url)).unwrap();
+    let har = session.har().unwrap();
+
+    assert_eq!((har.log.version.as_str(), har.log.creator.name.as_str()), ("1.2", "LlamaClick"));
+    let entries = &har.log.entries;
+    assert_eq!(entries.len(), 3);
+
+    let search = &entries[1];
+    assert_eq!(search.request.method, "POST");
+    let post = search.request.post_data.as_ref().unwrap();
+    assert_eq!((post.mime_type.as_str(), post.text.as_str()), ("application/x-www-form-urlencoded", "q=llamas"));
+
+    let items = &entries[2];
+    assert_eq!(items.request.query_string.len(), 2);
+    assert_eq!(items.request.query_string[0].name, "page");
+    assert_eq!((items.response.status, items.response.status_text.as_str()), (200, "OK"));
+    assert!(items.response.headers.iter().any(|h| h.name == "x-served" && h.value == "yes"));
+    assert_eq!(items.response.content.text.as_deref(), Some("<p id=\"path\">/items?page=2&sort=new</p>"));
+    assert!(items.response.content.mime_type.starts_with("text/html"));
+    assert!(items.time >= 0.0 && items.started_date_time.ends_with('Z'));
+
+    // Entries are handed out once and accumulate in the session
+    session.navigate(&format!("{}/more", server.url)).unwrap();
+    assert_eq!(session.stop_har().unwrap().log.entries.len(), 4);
+    assert!(matches!(session.har(), Err(Error::BrowserError(_))));
+}
+
+#[test]
+fn replays_a_saved_archive_offline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("runs/site.har");
+    let server = HttpServer::start(site);
+
+    let mut recording = static_session();
+    recording.record_har(HarOptions::default().with_bodies()).unwrap();
+    recording.navigate(&format!("{}/", server.url)).unwrap();
+    recording.click(&Selector::css("button")).unwrap();
+    recording.save_har(&path).unwrap();
+    let served = server.received().len();
+
+    let mut replay = static_session();
+    replay.route_from_har(&path).unwrap();
+    replay.navigate(&format!("{}/", server.url)).unwrap();
+    replay.click(&Selector::css("button")).unwrap();
+    assert_eq!(replay.get_text(&Selector::id("path")).unwrap(), "/search");
+    assert_eq!(server.received().len(), served);
+
+    let missing = replay.navigate(&format!("{}/elsewhere", server.url));
+    assert!(matches!(missing, Err(Error::NetworkError(_))));
+
+    // Routes added after the archive take precedence over it
+    replay
+        .route(Route::fulfill(format!("{}/elsewhere", server.url), RouteResponse::html("")))
+        .unwrap();
+    replay.navigate(&format!("{}/elsewhere", server.url)).unwrap();
+}
+
+#[test]
+fn mock_records_failures_without_bodies() {
+    let mock = MockBrowser::new().with_page("https://app.test/", "<h1>Home</h1>");
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+    session.record_har(HarOptions::default()).unwrap();
+
+    session.navigate("https://app.test/").unwrap();
+    session.route(Route::abort("**/ads/**")).unwrap();
+    assert!(mock.fetch("GET", "https://app.test/ads/banner").is_err());
+    assert_eq!(mock.fetch("GET", "https://app.test/missing").unwrap().status, 404);
+
+    let har = session.stop_har().unwrap();
+    let entries = &har.log.entries;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].response.content.size, 13);
+    assert!(entries[0].response.content.text.is_none());
+    assert_eq!(entries[1].response.status, 0);
+    assert!(entries[1].response.error.as_deref().unwrap().contains("aborted"));
+    assert_eq!(entries[2].response.status, 404);
+
+    let json: serde_json::Value = serde_json::from_str(&har.to_json().unwrap()).unwrap();
+    assert_eq!(
+        json["log"]["entries"][1]["response"]["_error"],
+        "Network error: Request to https://app.test/ads/banner was aborted"
+    );
+    assert_eq!(json["log"]["entries"][0]["response"]["redirectURL"], "");
+    assert_eq!(mock.actions().last(), Some(&MockAction::SetHarRecording(false)));
+}
+
+#[test]
+fn entries_use_iso_timestamps_and_binary_bodies_round_trip() {
+    let started = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+    let body = [0xff, 0x00, 0x10];
+    let entry = HarEntry::new(
+        started,
+        HarRequest::new("get", "https://app.test/logo.png", &[]),
+        HarResponse::new(200, &[("Content-Type".to_string(), "image/png".to_string())], &body, true),
+        HarTimings::new(12.0, 3.0),
+    );
+    assert_eq!(entry.started_date_time, "2023-11-14T22:13:20.123Z");
+    assert_eq!((entry.request.method.as_str(), entry.time), ("GET", 15.0));
+    assert_eq!(entry.response.content.encoding.as_deref(), Some("base64"));
+
+    let har = Har::from_json(&Har::new(vec![entry]).to_json().unwrap()).unwrap();
+    assert_eq!(har.log.entries[0].response.body().unwrap(), body);
+    assert!(matches!(Har::from_json("{}"), Err(Error::ConfigurationError(_))));
+}
@@ -0,0 +1,120 @@
+//! Tests for hover, select, check, scroll, drag and key chord input
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    BrowserConfig, BrowserSession, KeyChord, MockAction, MockBrowser, Modifier, Selector, StaticBrowser,
+};
+use llamaclick::error::Error;
+
+const FORM: &str = r#"
+<form action="/search">
+  <input name="q" value="">
+  <select name="sort"><option value="new">Newest</option><option value="top">Top rated</option></select>
+  <input type="checkbox" name="remote" id="remote">
+  <input type="radio" name="size" value="s" id="small" checked>
+  <input type="radio" name="size" value="l" id="large">
+  <textarea name="comment"></textarea>
+  <button>Search</button>
+</form>"#;
+
+fn site(request: &HttpRequest) -> HttpResponse {
+    match request.path.as_str() {
+        "/" => HttpResponse::html(FORM),
+        path => HttpResponse::html(&format!("<p id=\"query\">{}</p>", path)),
+    }
+}
+
+fn session(server: &HttpServer) -> BrowserSession {
+    let config = BrowserConfig::default();
+    let mut session = BrowserSession::new(Box::new(StaticBrowser::new(config.clone()).unwrap()), config);
+    session.navigate(&format!("{}/", server.url)).unwrap();
+    session
+}
+
+#[test]
+fn key_chords_parse_and_display() {
+    let chord = KeyChord::parse("ctrl+Shift+a").unwrap();
+    assert_eq!(chord.modifiers, [Modifier::Control, Modifier::Shift]);
+    assert_eq!((chord.key.as_str(), chord.to_string().as_str()), ("a", "Control+Shift+a"));
+    assert_eq!(chord.text(), None);
+
+    assert_eq!(KeyChord::parse("Shift+a").unwrap().text().as_deref(), Some("A"));
+    assert_eq!(KeyChord::parse("enter").unwrap().key, "Enter");
+    assert_eq!(KeyChord::parse("Control++").unwrap().key, "+");
+    assert_eq!("Cmd+Space".parse::<KeyChord>().unwrap().to_string(), "Meta+Space");
+    for bad in ["Hyper+a", "Control+Nope", ""] {
+        assert!(matches!(KeyChord::parse(bad), Err(Error::ValidationError(_))), "{:?}", bad);
+    }
+}
+
+#[test]
+fn static_form_controls_submit_with_enter() {
+    let server = HttpServer::start(site);
+    let mut session = session(&server);
+
+    session.select_option(&Selector::name("sort"), "Top rated").unwrap();
+    session.check(&Selector::id("remote")).unwrap();
+    session.check(&Selector::id("remote")).unwrap();
+    session.check(&Selector::id("large")).unwrap();
+    assert!(matches!(session.uncheck(&Selector::id("large")), Err(Error::BrowserError(_))));
+    session.type_text(&Selector::name("comment"), "hi").unwrap();
+    session.press_key("Enter").unwrap();
+    session.type_text(&Selector::name("q"), "llama").unwrap();
+    session.press_key("s").unwrap();
+    session.press_key("Backspace").unwrap();
+    session.press_key("Shift+s").unwrap();
+    assert_eq!(session.get_text(&Selector::name("q")).unwrap(), "llamaS");
+    session.scroll_into_view(&Selector::css("button")).unwrap();
+    session.hover(&Selector::css("button")).unwrap();
+
+    session.press_key("Enter").unwrap();
+    assert_eq!(
+        session.get_text(&Selector::id("query")).unwrap(),
+        "/search?q=llamaS&sort=top&remote=on&size=l&comment=hi%0A"
+    );
+}
+
+#[test]
+fn static_rejects_inputs_it_cannot_perform() {
+    let server = HttpServer::start(site);
+    let mut session = session(&server);
+
+    assert!(matches!(session.press_key("a"), Err(Error::BrowserError(_))));
+    assert!(matches!(
+        session.select_option(&Selector::name("sort"), "Oldest"),
+        Err(Error::ElementNotFound(_))
+    ));
+    assert!(matches!(session.check(&Selector::name("q")), Err(Error::BrowserError(_))));
+    assert!(matches!(session.double_click(&Selector::css("button")), Err(Error::Unsupported(_))));
+    assert!(matches!(
+        session.drag_and_drop(&Selector::id("remote"), &Selector::id("large")),
+        Err(Error::Unsupported(_))
+    ));
+    assert_eq!(session.action_log().last().unwrap().action, "drag_and_drop");
+}
+
+#[test]
+fn mock_records_rich_input() {
+    let mock = MockBrowser::from_html(FORM);
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+
+    session.hover(&Selector::css("button")).unwrap();
+    session.right_click(&Selector::name("q")).unwrap();
+    session.drag_and_drop(&Selector::id("remote"), &Selector::id("large")).unwrap();
+    session.scroll_by(0.0, 400.0).unwrap();
+    session.select_option(&Selector::name("sort"), "top").unwrap();
+    session.check(&Selector::id("remote")).unwrap();
+    session.press_key("Control+a").unwrap();
+
+    assert_eq!(session.get_text(&Selector::name("sort")).unwrap(), "top");
+    assert!(session.get_attributes(&Selector::id("remote")).unwrap().contains_key("checked"));
+    assert_eq!(mock.pressed_keys(), ["Control+a"]);
+    assert!(mock.actions().contains(&MockAction::ScrollBy(0.0, 400.0)));
+
+    mock.fail_on(Selector::id("missing-menu"), || Error::ElementNotFound("menu".to_string()));
+    assert!(session.hover(&Selector::id("missing-menu")).is_err());
+}
@@ -0,0 +1,263 @@
+//! Tests for the chat wire formats of the LLM providers against a local server
+
+#![cfg(feature = "llm")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer, ScriptedLlm};
+use llamaclick::agent::{Agent, AgentConfig, AgentType};
+use llamaclick::config::settings::LlmSettings;
+use llamaclick::error::Error;
+use llamaclick::llms::{
+    AnthropicProvider, AzureOpenAiProvider, ChatMessage, GenerationOptions, HuggingFaceProvider, LlmProvider,
+    LlmProviderConfig, LlmProviderType, LocalApi, LocalProvider, OpenAiProvider, Role, AZURE_API_VERSION_OPTION,
+    LOCAL_API_OPTION,
+};
+use serde_json::json;
+
+fn completion(_: &HttpRequest) -> HttpResponse {
+    HttpResponse::json(json!({
+        "choices": [{ "message": { "role": "assistant", "content": "Sure." } }],
+        "usage": { "prompt_tokens": 12, "completion_tokens": 2, "total_tokens": 14 },
+    }))
+}
+
+fn conversation() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::system("You drive a browser."),
+        ChatMessage::user("Open the pricing page."),
+        ChatMessage::assistant("Clicked Pricing."),
+        ChatMessage::user("Now the enterprise tier."),
+    ]
+}
+
+#[tokio::test]
+async fn openai_providers_send_every_turn() {
+    let server = HttpServer::start(completion);
+    let config = LlmProviderConfig::new(LlmProviderType::OpenAi, "gpt-4o", "sk-test")
+        .with_endpoint(&format!("{}/v1/chat/completions", server.url));
+    let options = GenerationOptions::new().with_temperature(0.2).with_max_tokens(64).with_stop("\n\n");
+
+    let response = OpenAiProvider::new(config).unwrap().chat(&conversation(), &options).await.unwrap();
+    assert_eq!(response.content, "Sure.");
+    assert_eq!(response.token_usage.unwrap().total_tokens, 14);
+    let body = server.received()[0].json();
+    assert_eq!(body["messages"], serde_json::to_value(conversation()).unwrap());
+    assert_eq!(body["messages"][2], json!({ "role": "assistant", "content": "Clicked Pricing." }));
+    assert_eq!((body["max_tokens"].as_u64(), body["stop"].clone()), (Some(64), json!(["\n\n"])));
+    assert!((body["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+    assert!(body.get("top_p").is_none());
+
+    let server = HttpServer::start(completion);
+    let config =
+        LlmProviderConfig::new(LlmProviderType::AzureOpenAi, "planner", "azure-key").with_endpoint(&server.url);
+    let provider = AzureOpenAiProvider::new(config).unwrap();
+    provider.chat(&conversation(), &GenerationOptions::new()).await.unwrap();
+    let request = &server.received()[0];
+    assert_eq!(request.path, "/openai/deployments/planner/chat/completions?api-version=2024-02-01");
+    assert_eq!(request.header("api-key"), Some("azure-key"));
+    assert_eq!(request.json()["messages"].as_array().unwrap().len(), 4);
+    assert_eq!(request.json()["max_tokens"], 800);
+
+    let config = LlmProviderConfig::new(LlmProviderType::AzureOpenAi, "planner", "azure-key")
+        .with_endpoint(&server.url)
+        .with_option(AZURE_API_VERSION_OPTION, "2024-10-21");
+    AzureOpenAiProvider::new(config).unwrap().chat(&conversation(), &GenerationOptions::new()).await.unwrap();
+    assert!(server.received()[1].path.ends_with("?api-version=2024-10-21"), "{}", server.received()[1].path);
+}
+
+#[tokio::test]
+async fn anthropic_lifts_the_system_prompt_and_merges_turns() {
+    let server = HttpServer::start(|_| {
+        HttpResponse::json(json!({ "content": [{ "type": "text", "text": "Done." }], "stop_reason": "end_turn" }))
+    });
+    let config = LlmProviderConfig::new(LlmProviderType::Anthropic, "claude-3-5-sonnet-latest", "sk-ant-test")
+        .with_endpoint(&format!("{}/v1/messages", server.url));
+    let mut messages = conversation();
+    messages.push(ChatMessage::user("Then stop."));
+    messages.insert(1, ChatMessage::system("Be brief."));
+
+    let options = GenerationOptions::new().with_stop("END");
+    let response = AnthropicProvider::new(config).unwrap().chat(&messages, &options).await.unwrap();
+    assert_eq!(response.content, "Done.");
+    let request = &server.received()[0];
+    assert_eq!(request.header("x-api-key"), Some("sk-ant-test"));
+    let body = request.json();
+    assert_eq!(body["system"], "You drive a browser.\n\nBe brief.");
+    assert_eq!(
+        body["messages"],
+        json!([
+            { "role": "user", "content": "Open the pricing page." },
+            { "role": "assistant", "content": "Clicked Pricing." },
+            { "role": "user", "content": "Now the enterprise tier.\n\nThen stop." },
+        ])
+    );
+    assert_eq!((body["max_tokens"].as_u64(), body["stop_sequences"].clone()), (Some(1024), json!(["END"])));
+    assert!(body.get("temperature").is_none());
+}
+
+fn anthropic(url: &str) -> AnthropicProvider {
+    let config = LlmProviderConfig::new(LlmProviderType::Anthropic, "claude-3-5-haiku-latest", "sk-ant-test")
+        .with_endpoint(url);
+    AnthropicProvider::new(config).unwrap()
+}
+
+#[tokio::test]
+async fn anthropic_reports_usage_stop_reasons_and_typed_errors() {
+    let server = HttpServer::start(|_| {
+        HttpResponse::json(json!({
+            "type": "message",
+            "content": [{ "type": "text", "text": "The Enterprise tier costs" }, { "type": "text", "text": " $99." }],
+            "stop_reason": "max_tokens",
+            "usage": { "input_tokens": 20, "cache_read_input_tokens": 100, "output_tokens": 8 },
+        }))
+    });
+    let options = GenerationOptions::new().with_max_tokens(8);
+    let response = anthropic(&server.url).chat(&conversation(), &options).await.unwrap();
+    assert_eq!(response.content, "The Enterprise tier costs $99.");
+    assert_eq!(response.stop_reason.as_deref(), Some("max_tokens"));
+    let usage = response.token_usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (120, 8, 128));
+    assert_eq!(server.received()[0].json()["max_tokens"], 8);
+
+    let error = |status, kind: &str, message: &str| {
+        let body = json!({ "type": "error", "error": { "type": kind, "message": message } });
+        HttpResponse::with_status(status, body)
+    };
+    let server = HttpServer::start(move |request| match request.path.as_str() {
+        "/overloaded" => error(529, "overloaded_error", "Overloaded"),
+        "/limited" => error(429, "rate_limit_error", "Too many tokens").header("retry-after", "30"),
+        "/key" => error(401, "authentication_error", "invalid x-api-key"),
+        _ => HttpResponse { status: 429, ..HttpResponse::html("Slow down") },
+    });
+    let chat = |path: &str| {
+        let provider = anthropic(&format!("{}{}", server.url, path));
+        async move { provider.chat(&conversation(), &GenerationOptions::new()).await.unwrap_err() }
+    };
+    let err = chat("/overloaded").await;
+    assert!(matches!(err, Error::OverloadedError(_)), "{}", err);
+    assert_eq!(err.to_string(), "Overloaded error: Anthropic API error: Overloaded");
+    let err = chat("/limited").await;
+    assert!(matches!(err, Error::RateLimitError(_)), "{}", err);
+    assert!(err.to_string().ends_with("Too many tokens (retry after 30s)"), "{}", err);
+    assert!(matches!(chat("/key").await, Error::AuthenticationError(_)));
+    // Without a JSON payload the status decides
+    let err = chat("/proxy").await;
+    assert!(matches!(err, Error::RateLimitError(_)), "{}", err);
+    assert!(err.to_string().ends_with("Slow down"), "{}", err);
+}
+
+#[tokio::test]
+async fn text_generation_providers_get_a_transcript() {
+    let server = HttpServer::start(|_| HttpResponse::json(json!([{ "generated_text": " The Enterprise tab." }])));
+    let config = LlmProviderConfig::new(LlmProviderType::HuggingFace, "mistral", "hf-test").with_endpoint(&server.url);
+
+    let response = HuggingFaceProvider::new(config)
+        .unwrap()
+        .chat(&conversation(), &GenerationOptions::new().with_max_tokens(32))
+        .await
+        .unwrap();
+    assert_eq!(response.content, " The Enterprise tab.");
+    let body = server.received()[0].json();
+    assert_eq!(
+        body["inputs"],
+        "You drive a browser.\nUser: Open the pricing page.\nAssistant: Clicked Pricing.\n\
+         User: Now the enterprise tier.\nAssistant:"
+    );
+    assert_eq!(body["parameters"], json!({ "max_new_tokens": 32, "return_full_text": false }));
+}
+
+fn local_server(request: &HttpRequest) -> HttpResponse {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/api/chat") => HttpResponse::json(json!({
+            "model": "llama3",
+            "message": { "role": "assistant", "content": "The Enterprise tab." },
+            "done": true,
+            "prompt_eval_count": 26,
+            "eval_count": 5,
+        })),
+        ("GET", "/api/tags") => {
+            HttpResponse::json(json!({ "models": [{ "name": "llama3:latest" }, { "name": "qwen2.5:7b" }] }))
+        }
+        ("POST", "/v1/chat/completions") => completion(request),
+        ("GET", "/v1/models") => HttpResponse::json(json!({ "object": "list", "data": [{ "id": "mistral-7b" }] })),
+        _ => HttpResponse::with_status(404, json!({ "error": "not found" })),
+    }
+}
+
+#[tokio::test]
+async fn local_servers_speak_ollama_or_openai() {
+    let server = HttpServer::start(local_server);
+    let config =
+        LlmProviderConfig::new(LlmProviderType::Local, "llama3", "").with_endpoint(&format!("{}/", server.url));
+    let ollama = LocalProvider::new(config).unwrap();
+    assert_eq!(ollama.api(), LocalApi::Ollama);
+
+    let options = GenerationOptions::new().with_temperature(0.5).with_max_tokens(32).with_stop("\n");
+    let response = ollama.chat(&conversation(), &options).await.unwrap();
+    assert_eq!(response.content, "The Enterprise tab.");
+    let usage = response.token_usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (26, 5, 31));
+    let request = &server.received()[0];
+    assert_eq!(request.header("authorization"), None);
+    let body = request.json();
+    assert_eq!((body["model"].clone(), body["stream"].clone()), (json!("llama3"), json!(false)));
+    assert_eq!(body["messages"][3], json!({ "role": "user", "content": "Now the enterprise tier." }));
+    assert_eq!(body["options"], json!({ "temperature": 0.5, "num_predict": 32, "stop": ["\n"] }));
+    assert_eq!(ollama.list_models().await.unwrap(), ["llama3:latest", "qwen2.5:7b"]);
+
+    let config = LlmProviderConfig::new(LlmProviderType::Local, "mistral-7b", "local-key")
+        .with_endpoint(&format!("{}/v1", server.url))
+        .with_option(LOCAL_API_OPTION, "openai");
+    let compatible = LocalProvider::new(config).unwrap();
+    let response = compatible.chat(&conversation(), &GenerationOptions::new().with_max_tokens(16)).await.unwrap();
+    assert_eq!(response.content, "Sure.");
+    assert_eq!(response.token_usage.unwrap().total_tokens, 14);
+    let request = &server.received()[2];
+    assert_eq!(request.header("authorization"), Some("Bearer local-key"));
+    assert_eq!(request.json()["messages"], serde_json::to_value(conversation()).unwrap());
+    assert_eq!(request.json()["max_tokens"], 16);
+    assert_eq!(compatible.list_models().await.unwrap(), ["mistral-7b"]);
+
+    // Settings name an Ollama server and model
+    let settings = LlmSettings {
+        ollama_url: server.url.clone(),
+        ollama_model: "qwen2.5:7b".to_string(),
+        ..LlmSettings::default()
+    };
+    let configured = LocalProvider::new(LlmProviderConfig::ollama(&settings)).unwrap();
+    assert_eq!((configured.api(), configured.model_name()), (LocalApi::Ollama, "qwen2.5:7b"));
+    configured.chat(&conversation(), &options).await.unwrap();
+    let request = server.received().pop().unwrap();
+    assert_eq!((request.path.as_str(), request.json()["model"].clone()), ("/api/chat", json!("qwen2.5:7b")));
+
+    let config = LlmProviderConfig::new(LlmProviderType::Local, "llama3", "").with_option(LOCAL_API_OPTION, "grpc");
+    let err = LocalProvider::new(config).unwrap_err();
+    assert!(err.to_string().contains("Unknown local API 'grpc'"), "{}", err);
+    let config = LlmProviderConfig::new(LlmProviderType::Local, "llama3", "")
+        .with_endpoint(&format!("{}/missing", server.url));
+    let err = LocalProvider::new(config).unwrap().chat(&conversation(), &options).await.unwrap_err();
+    assert!(err.to_string().starts_with("LLM error: Ollama API error"), "{}", err);
+}
+
+#[tokio::test]
+async fn agents_send_their_history() {
+    let llm = ScriptedLlm::new("Step 1: open the site");
+    let config = AgentConfig::new(AgentType::Planner).with_system_message("Plan.");
+    let mut agent = Agent::new(config, Box::new(llm.clone()));
+
+    agent.run("book a table").await.unwrap();
+    agent.run("for two people").await.unwrap();
+
+    let chats = llm.chats();
+    assert_eq!(chats[0].len(), 2);
+    let roles: Vec<Role> = chats[1].iter().map(|message| message.role).collect();
+    assert_eq!(roles, [Role::System, Role::User, Role::Assistant, Role::User]);
+    assert_eq!(chats[1][0].content, "Plan.");
+    assert_eq!(chats[1][2].content, "Step 1: open the site");
+    assert!(chats[1][3].content.ends_with("for two people"));
+
+    agent.clear_history();
+    agent.run("again").await.unwrap();
+    assert_eq!(llm.chats()[2].len(), 2);
+}
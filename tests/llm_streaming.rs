@@ -0,0 +1,168 @@
+//! Tests for streamed LLM responses against a local server-sent event stand-in
+
+#![cfg(feature = "llm")]
+
+mod common;
+
+use common::{HttpResponse, HttpServer, SseServer};
+use futures_util::StreamExt;
+use llamaclick::agent::{Agent, AgentConfig, AgentType};
+use llamaclick::error::Error;
+use llamaclick::llms::{
+    collect_stream, AnthropicProvider, AzureOpenAiProvider, ChatMessage, GenerationOptions, LlmProvider,
+    LlmProviderConfig, LlmProviderType, LocalProvider, OpenAiProvider, StreamEvent,
+};
+use serde_json::json;
+
+fn openai(url: &str) -> OpenAiProvider {
+    let config = LlmProviderConfig::new(LlmProviderType::OpenAi, "gpt-4o", "sk-test").with_endpoint(url);
+    OpenAiProvider::new(config).unwrap()
+}
+
+fn anthropic(url: &str) -> AnthropicProvider {
+    let config = LlmProviderConfig::new(LlmProviderType::Anthropic, "claude-3-5-haiku-latest", "sk-ant-test")
+        .with_endpoint(url);
+    AnthropicProvider::new(config).unwrap()
+}
+
+fn question() -> Vec<ChatMessage> {
+    vec![ChatMessage::system("Plan briefly."), ChatMessage::user("Find the price")]
+}
+
+/// One unnamed event
+fn data(json: &str) -> Vec<u8> {
+    format!("data: {}\n\n", json).into_bytes()
+}
+
+/// Collect a stream's deltas and final response
+async fn read(provider: &dyn LlmProvider) -> (Vec<String>, llamaclick::error::Result<String>) {
+    let mut deltas = Vec::new();
+    let stream = provider.chat_stream(&question(), &GenerationOptions::new()).await.unwrap();
+    let response = collect_stream(stream, |delta| deltas.push(delta.to_string())).await;
+    (deltas, response.map(|response| response.content))
+}
+
+#[tokio::test]
+async fn openai_streams_deltas_across_chunk_boundaries() {
+    let euro = "€".as_bytes();
+    let server = SseServer::start(vec![
+        b": keep-alive\r\n\r\n".to_vec(),
+        br#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#.to_vec(),
+        b"\r\n\r\n".to_vec(),
+        b"data: {\"choices\":[{\"delta\":{\"content\":\"Open \"}}]}\n\ndata: {\"choi".to_vec(),
+        b"ces\":[{\"delta\":{\"content\":\"pricing: 5 ".to_vec(),
+        euro[..1].to_vec(),
+        [&euro[1..], b"\"}}]}\n\n".as_slice()].concat(),
+        data(r#"{"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":4,"total_tokens":13}}"#),
+        data("[DONE]"),
+    ]);
+    let provider = openai(&server.url);
+
+    let mut stream = provider.chat_stream(&question(), &GenerationOptions::new()).await.unwrap();
+    let mut deltas = Vec::new();
+    let mut done = None;
+    while let Some(event) = stream.next().await {
+        match event.unwrap() {
+            StreamEvent::Delta(text) => deltas.push(text),
+            StreamEvent::Done(response) => done = Some(response),
+        }
+    }
+
+    assert_eq!(deltas, ["Open ", "pricing: 5 €"]);
+    let response = done.unwrap();
+    assert_eq!(response.content, "Open pricing: 5 €");
+    assert_eq!(response.model, "gpt-4o");
+    let usage = response.token_usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (9, 4, 13));
+    let body = &server.received()[0];
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+}
+
+#[tokio::test]
+async fn anthropic_streams_typed_events() {
+    let events = [
+        r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":25,"output_tokens":1}}}"#,
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+        r#"{"type":"ping"}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"1. Open"}}"#,
+        r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" pricing"}}"#,
+        r#"{"type":"content_block_stop","index":0}"#,
+        r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#,
+        r#"{"type":"message_stop"}"#,
+    ];
+    let pieces = events
+        .iter()
+        .map(|data| {
+            let name = serde_json::from_str::<serde_json::Value>(data).unwrap()["type"].as_str().unwrap().to_string();
+            format!("event: {}\ndata: {}\n\n", name, data).into_bytes()
+        })
+        .collect();
+    let server = SseServer::start(pieces);
+    let provider = anthropic(&server.url);
+
+    let stream = provider.chat_stream(&question(), &GenerationOptions::new()).await.unwrap();
+    let mut deltas = Vec::new();
+    let response = collect_stream(stream, |delta| deltas.push(delta.to_string())).await.unwrap();
+    assert_eq!(deltas, ["1. Open", " pricing"]);
+    assert_eq!(response.content, "1. Open pricing");
+    let usage = response.token_usage.unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (25, 7, 32));
+    assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+    let body = &server.received()[0];
+    assert_eq!((body["stream"].clone(), body["system"].clone()), (json!(true), json!("Plan briefly.")));
+}
+
+#[tokio::test]
+async fn stream_failures_surface_as_errors() {
+    let server = SseServer::start(vec![
+        data(r#"{"type":"content_block_delta","delta":{"type":"text_delta","text":"1."}}"#),
+        data(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#),
+    ]);
+    let (deltas, response) = read(&anthropic(&server.url)).await;
+    assert_eq!(deltas, ["1."]);
+    let err = response.unwrap_err();
+    assert!(matches!(err, Error::OverloadedError(_)), "{}", err);
+    assert!(err.to_string().contains("Overloaded"), "{}", err);
+
+    let server = SseServer::start(vec![data(r#"{"choices":[{"delta":{"content":"Half"}}]}"#)]);
+    let (deltas, response) = read(&openai(&server.url)).await;
+    assert_eq!(deltas, ["Half"]);
+    let err = response.unwrap_err();
+    assert!(matches!(err, Error::LlmError(_)), "{}", err);
+    assert!(err.to_string().contains("ended before the response was complete"), "{}", err);
+
+    let server = HttpServer::start(|_| HttpResponse::with_status(401, json!({ "error": { "message": "Bad key" } })));
+    let err = match openai(&server.url).chat_stream(&question(), &GenerationOptions::new()).await {
+        Ok(_) => panic!("an unauthorized request should fail"),
+        Err(err) => err,
+    };
+    assert!(err.to_string().contains("Bad key"), "{}", err);
+}
+
+#[tokio::test]
+async fn agents_stream_their_replies() {
+    let server = SseServer::start(vec![
+        data(r#"{"choices":[{"delta":{"content":"Step 1"}}]}"#),
+        data(r#"{"choices":[{"delta":{"content":": search"}}]}"#),
+        data("[DONE]"),
+    ]);
+    let endpoint = server.url.trim_end_matches("/v1/stream").to_string();
+    let config =
+        LlmProviderConfig::new(LlmProviderType::AzureOpenAi, "planner", "azure-key").with_endpoint(&endpoint);
+    let llm = AzureOpenAiProvider::new(config).unwrap();
+    let mut agent = Agent::new(AgentConfig::new(AgentType::Planner), Box::new(llm));
+
+    let mut shown = String::new();
+    let reply = agent.run_streaming("find the price", |delta| shown.push_str(delta)).await.unwrap();
+    assert_eq!((reply.as_str(), shown.as_str()), ("Step 1: search", "Step 1: search"));
+    assert_eq!(agent.history()[1].content, "Step 1: search");
+    assert_eq!(server.received()[0]["stream"], true);
+
+    // Providers without a streaming API answer in one piece
+    let server = HttpServer::start(|_| HttpResponse::json(json!({ "message": { "content": "Open pricing" } })));
+    let config = LlmProviderConfig::new(LlmProviderType::Local, "llama3", "").with_endpoint(&server.url);
+    let (deltas, response) = read(&LocalProvider::new(config).unwrap()).await;
+    assert_eq!(deltas, ["Open pricing"]);
+    assert_eq!(response.unwrap(), deltas[0]);
+}
@@ -0,0 +1,93 @@
+//! Tests for the in-memory mock backend
+
+#![cfg(feature = "browser")]
+
+use llamaclick::browser::{Browser, BrowserConfig, BrowserSession, MockAction, MockBrowser, Selector};
+use llamaclick::error::Error;
+use std::time::{Duration, Instant};
+
+const LOGIN: &str = r#"<html><body>
+  <form><input name="email"><button id="submit">Log in</button></form>
+  <a href="https://app.test/dashboard">Dashboard</a>
+</body></html>"#;
+
+const DASHBOARD: &str = "<html><body><h1>Dashboard</h1></body></html>";
+
+#[test]
+fn resolves_selectors_against_fixture() {
+    let browser = MockBrowser::from_html(LOGIN);
+
+    assert!(browser.element_exists(&Selector::name("email")).unwrap());
+    assert!(browser.element_exists(&Selector::xpath("//button[@id='submit']")).unwrap());
+    assert_eq!(browser.get_text(&Selector::id("submit")).unwrap(), "Log in");
+    assert!(matches!(browser.get_text(&Selector::css(".nope")), Err(Error::ElementNotFound(_))));
+}
+
+#[test]
+fn records_actions_through_a_session() {
+    let mock = MockBrowser::from_pages([
+        ("https://app.test/login", LOGIN),
+        ("https://app.test/dashboard", DASHBOARD),
+    ]);
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+
+    session.navigate("https://app.test/login").unwrap();
+    session.type_text(&Selector::name("email"), "llama@example.com").unwrap();
+    session.click(&Selector::id("submit")).unwrap();
+    session.click(&Selector::text("Dashboard")).unwrap();
+
+    mock.assert_navigated_to("https://app.test/login");
+    mock.assert_typed(&Selector::name("email"), "llama@example.com");
+    mock.assert_clicked_once(&Selector::id("submit"));
+    mock.assert_not_clicked(&Selector::css("a"));
+    assert_eq!(session.current_url().unwrap(), "https://app.test/dashboard");
+    assert_eq!(session.get_text(&Selector::css("h1")).unwrap(), "Dashboard");
+    assert_eq!(
+        mock.actions()[..2],
+        [
+            MockAction::Navigate("https://app.test/login".to_string()),
+            MockAction::TypeText(Selector::name("email"), "llama@example.com".to_string()),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "to be clicked 1 time(s), but it was clicked 2 time(s)")]
+fn click_assertion_reports_count() {
+    let mut mock = MockBrowser::from_html(LOGIN);
+    mock.click(&Selector::id("submit")).unwrap();
+    mock.click(&Selector::id("submit")).unwrap();
+    mock.assert_clicked_once(&Selector::id("submit"));
+}
+
+#[test]
+fn injected_failures_and_delays() {
+    let mut mock = MockBrowser::from_html(LOGIN);
+    let submit = Selector::id("submit");
+
+    mock.fail_times(submit.clone(), 2, || Error::BrowserError("detached".to_string()));
+    assert!(mock.click(&submit).is_err());
+    assert!(mock.click(&submit).is_err());
+    assert!(mock.click(&submit).is_ok());
+    mock.assert_clicked_times(&submit, 3);
+
+    mock.fail_on(Selector::name("email"), || Error::TimeoutError("stuck".to_string()));
+    assert!(matches!(mock.type_text(&Selector::name("email"), "x"), Err(Error::TimeoutError(_))));
+    mock.clear_injections(&Selector::name("email"));
+    assert!(mock.type_text(&Selector::name("email"), "x").is_ok());
+
+    mock.delay_on(submit.clone(), Duration::from_millis(50));
+    let start = Instant::now();
+    mock.click(&submit).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn unknown_pages_and_closed_browser_fail() {
+    let mut mock = MockBrowser::new().with_page("https://a.test/", "<p>a</p>");
+
+    assert!(mock.navigate("https://b.test/").is_err());
+    mock.navigate("https://a.test/").unwrap();
+    mock.close().unwrap();
+    assert!(mock.get_html().is_err());
+}
@@ -0,0 +1,111 @@
+//! Tests for distilling pages into LLM-friendly snapshots
+
+#![cfg(feature = "browser")]
+
+use llamaclick::browser::{estimate_tokens, Browser, BoundingBox, MockAction, MockBrowser, PageSnapshot};
+use llamaclick::error::Error;
+use serde_json::json;
+
+const SIGNUP: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Sign up</title><style>body { color: red }</style></head>
+<body>
+  <script>window.tracking = "Secret tracking text";</script>
+  <div class="page"><div class="container"><div class="row">
+    <h1>Create   your account</h1>
+    <p>Already a member? <a href="/login">Log in</a></p>
+  </div></div></div>
+  <form>
+    <label for="email">Email address</label>
+    <input id="email" type="email" value="llama@example.com">
+    <input type="password" name="password" placeholder="Password" value="hunter2">
+    <label><input type="checkbox" name="terms" checked> I accept the terms</label>
+    <select name="plan"><option>Free</option><option selected>Pro</option></select>
+    <input type="hidden" name="csrf" value="token">
+    <div style="display: none"><button>Invisible</button></div>
+    <span role="button" aria-label="Close dialog" tabindex="0">×</span>
+    <input type="submit" value="Create account" disabled>
+  </form>
+</body>
+</html>"#;
+
+#[test]
+fn distills_text_and_interactive_elements() {
+    let snapshot = PageSnapshot::from_html("https://app.test/signup", SIGNUP);
+    let rendered = snapshot.to_string();
+
+    assert_eq!(snapshot.title, "Sign up");
+    assert_eq!(
+        rendered,
+        "Page: Sign up\n\
+         URL: https://app.test/signup\n\
+         \n\
+         # Create your account\n\
+         Already a member?\n\
+         [0] link \"Log in\" -> /login\n\
+         [1] textbox \"Email address\" value=\"llama@example.com\"\n\
+         [2] textbox \"Password\" value=\"••••\"\n\
+         [3] checkbox \"I accept the terms\" checked\n\
+         [4] combobox \"plan\" value=\"Pro\"\n\
+         [5] button \"Close dialog\"\n\
+         [6] button \"Create account\" disabled\n"
+    );
+    assert!(!rendered.contains("tracking") && !rendered.contains("Invisible") && !rendered.contains("csrf"));
+}
+
+#[test]
+fn element_selectors_reach_the_live_page() {
+    let mock = MockBrowser::from_html(SIGNUP);
+    let snapshot = PageSnapshot::from_html("about:blank", SIGNUP);
+
+    for element in &snapshot.elements {
+        assert!(mock.element_exists(&element.selector).unwrap(), "{:?}", element);
+    }
+    assert_eq!(mock.get_attributes(&snapshot.selector(2).unwrap()).unwrap()["name"], "password");
+    assert!(matches!(snapshot.selector(42), Err(Error::ElementNotFound(_))));
+}
+
+#[test]
+fn render_keeps_elements_within_the_token_budget() {
+    let mut html = String::from("<html><body><h1>Archive</h1>");
+    for i in 0..200 {
+        html.push_str(&format!("<p>Paragraph {} with some filler text to read through.</p>", i));
+    }
+    html.push_str("<button>Next page</button><a href=\"/more\">More</a></body></html>");
+    let snapshot = PageSnapshot::from_html("https://app.test/archive", &html);
+
+    let rendered = snapshot.render(300);
+    assert!(estimate_tokens(&rendered) <= 300, "{} tokens", estimate_tokens(&rendered));
+    assert!(rendered.contains("[0] button \"Next page\""));
+    assert!(rendered.contains("[1] link \"More\" -> /more"));
+    assert!(rendered.contains("# Archive\nParagraph 0 "));
+    assert!(rendered.ends_with("(0 elements and 180 text blocks omitted)\n"), "{}", rendered);
+
+    let tiny = snapshot.render(30);
+    assert!(tiny.contains("[0] button") && !tiny.contains("Paragraph"), "{}", tiny);
+}
+
+#[test]
+fn capture_measures_bounding_boxes_when_scripts_run() {
+    let mock = MockBrowser::from_html("<html><body><button>Go</button><a href=\"#top\">Top</a></body></html>");
+    let unmeasured = PageSnapshot::capture(&mut mock.clone()).unwrap();
+    assert_eq!(unmeasured.elements[0].bounds, None);
+
+    let script = mock
+        .actions()
+        .into_iter()
+        .find_map(|action| match action {
+            MockAction::ExecuteJs(script) => Some(script),
+            _ => None,
+        })
+        .unwrap();
+    let mock = mock.with_script_result(script, json!([[10.0, 20.0, 80.0, 32.0], null]));
+
+    let snapshot = PageSnapshot::capture(&mut mock.clone()).unwrap();
+    assert_eq!(
+        snapshot.elements[0].bounds,
+        Some(BoundingBox { x: 10.0, y: 20.0, width: 80.0, height: 32.0 })
+    );
+    assert_eq!(snapshot.elements[1].bounds, None);
+    assert!(snapshot.to_string().contains("[0] button \"Go\" @10,20 80x32"));
+}
@@ -0,0 +1,122 @@
+//! Tests for page handles, popups and the page-aware action log
+
+#![cfg(feature = "browser")]
+
+use llamaclick::browser::{
+    Browser, BrowserConfig, BrowserSession, MockAction, MockBrowser, PageEvent, PageId, Selector,
+};
+use llamaclick::error::Error;
+use std::time::Duration;
+
+const SHOP: &str = r#"<html><body>
+  <h1>Shop</h1>
+  <a id="help" href="/help" target="_blank">Help</a>
+  <a id="login" href="/login">Log in</a>
+</body></html>"#;
+
+const HELP: &str = "<html><body><h1>Help</h1></body></html>";
+
+const LOGIN: &str = r#"<html><body><button id="oauth">Sign in with Llama</button></body></html>"#;
+
+const OAUTH: &str = r#"<html><body><button id="allow">Allow</button></body></html>"#;
+
+fn mock() -> MockBrowser {
+    MockBrowser::from_pages([
+        ("https://shop.test/", SHOP),
+        ("https://shop.test/help", HELP),
+        ("https://shop.test/login", LOGIN),
+        ("https://auth.test/authorize", OAUTH),
+    ])
+}
+
+#[test]
+fn opens_lists_switches_and_closes_pages() {
+    let mut browser = mock();
+    browser.navigate("https://shop.test/").unwrap();
+    let first = browser.active_page().unwrap();
+
+    let second = browser.new_page(Some("https://shop.test/login")).unwrap();
+    assert_ne!(first, second);
+    assert_eq!(browser.active_page().unwrap(), second);
+    assert!(browser.element_exists(&Selector::id("oauth")).unwrap());
+
+    let pages = browser.pages().unwrap();
+    let urls: Vec<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+    assert_eq!(urls, ["https://shop.test/", "https://shop.test/login"]);
+
+    browser.switch_to_page(&first).unwrap();
+    assert_eq!(browser.current_url().unwrap(), "https://shop.test/");
+    assert!(!browser.element_exists(&Selector::id("oauth")).unwrap());
+
+    // Closing the active page falls back to the most recently opened one
+    browser.close_page(&first).unwrap();
+    assert_eq!(browser.active_page().unwrap(), second);
+    assert!(matches!(browser.close_page(&second), Err(Error::BrowserError(_))));
+    assert!(matches!(browser.switch_to_page(&first), Err(Error::BrowserError(_))));
+}
+
+#[test]
+fn target_blank_links_open_in_a_background_page() {
+    let mut browser = mock();
+    browser.navigate("https://shop.test/").unwrap();
+    let shop = browser.active_page().unwrap();
+
+    browser.click(&Selector::id("help")).unwrap();
+    assert_eq!(browser.active_page().unwrap(), shop);
+    assert_eq!(browser.current_url().unwrap(), "https://shop.test/");
+
+    let events = browser.take_page_events().unwrap();
+    let [PageEvent::Opened { page, opener, url }] = events.as_slice() else {
+        panic!("expected one opened page, got {:?}", events);
+    };
+    assert_eq!(opener.as_ref(), Some(&shop));
+    assert_eq!(url, "https://shop.test/help");
+
+    browser.switch_to_page(page).unwrap();
+    assert_eq!(browser.get_text(&Selector::css("h1")).unwrap(), "Help");
+    assert!(browser.take_page_events().unwrap().is_empty());
+}
+
+#[test]
+fn session_waits_for_a_popup_and_logs_page_ids() {
+    let mock = mock();
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+    session.navigate("https://shop.test/login").unwrap();
+    let login = session.active_page().unwrap();
+
+    session.click(&Selector::id("oauth")).unwrap();
+    mock.open_popup("https://auth.test/authorize").unwrap();
+
+    let popup = session.wait_for_popup(Duration::from_secs(1)).unwrap();
+    session.switch_to_page(&popup).unwrap();
+    session.click(&Selector::id("allow")).unwrap();
+    session.close_page(&popup).unwrap();
+    session.click(&Selector::id("oauth")).unwrap();
+
+    let log: Vec<(&str, &PageId)> = session
+        .action_log()
+        .iter()
+        .map(|record| (record.action.as_str(), &record.page))
+        .collect();
+    assert_eq!(
+        log,
+        [
+            ("navigate", &login),
+            ("click", &login),
+            ("switch_to_page", &login),
+            ("click", &popup),
+            ("close_page", &popup),
+            ("click", &login),
+        ]
+    );
+    assert!(mock.actions().contains(&MockAction::ClosePage(popup)));
+}
+
+#[test]
+fn waiting_for_a_popup_times_out() {
+    let mut session = BrowserSession::new(Box::new(mock()), BrowserConfig::default());
+    session.navigate("https://shop.test/").unwrap();
+
+    let result = session.wait_for_popup(Duration::from_millis(150));
+    assert!(matches!(result, Err(Error::TimeoutError(_))));
+}
@@ -0,0 +1,228 @@
+//! Tests for pools of leased browser sessions
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use llamaclick::browser::{
+    Browser, BrowserConfig, BrowserPool, MockAction, MockBrowser, OriginStorage, StorageItem, StorageState,
+    FIXTURE_URL,
+};
+use llamaclick::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const PAGE: &str = "https://app.test/";
+
+/// A pool of mock browsers, along with every browser it has launched
+fn mock_pool(max_sessions: usize) -> (BrowserPool, Arc<Mutex<Vec<MockBrowser>>>) {
+    let launched = Arc::new(Mutex::new(Vec::new()));
+    let browsers = Arc::clone(&launched);
+    let pool = BrowserPool::new(BrowserConfig::default(), max_sessions)
+        .with_factory(move |_config| {
+            let mock = MockBrowser::new().with_page(PAGE, "<html><body><h1>App</h1></body></html>");
+            browsers.lock().unwrap().push(mock.clone());
+            Ok(Box::new(mock) as Box<dyn Browser>)
+        })
+        .with_acquire_timeout(Duration::from_millis(100));
+    (pool, launched)
+}
+
+fn storage() -> StorageState {
+    StorageState {
+        cookies: Vec::new(),
+        origins: vec![OriginStorage {
+            origin: "https://app.test".to_string(),
+            local_storage: vec![StorageItem { name: "token".to_string(), value: "secret".to_string() }],
+            ..Default::default()
+        }],
+    }
+}
+
+#[test]
+fn leases_are_bounded_and_reused() {
+    let (pool, launched) = mock_pool(2);
+
+    let first = pool.acquire().unwrap();
+    let second = pool.acquire().unwrap();
+    assert!(pool.try_acquire().unwrap().is_none());
+    let err = pool.acquire().unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)), "{}", err);
+    assert!(err.to_string().contains("one of 2 pooled sessions"), "{}", err);
+
+    drop(first);
+    let third = pool.acquire().unwrap();
+    assert_eq!(third.uses(), 2);
+    assert_eq!(launched.lock().unwrap().len(), 2);
+
+    drop((second, third));
+    let stats = pool.stats();
+    assert_eq!((stats.idle, stats.leased, stats.launched, stats.recycled), (2, 0, 2, 0));
+}
+
+#[test]
+fn returned_sessions_are_reset_between_leases() {
+    let (pool, launched) = mock_pool(1);
+
+    let mut lease = pool.acquire().unwrap();
+    lease.navigate(PAGE).unwrap();
+    lease.set_storage_state(&storage()).unwrap();
+    drop(lease);
+
+    let lease = pool.acquire().unwrap();
+    assert_eq!(lease.current_url().unwrap(), FIXTURE_URL);
+    assert!(lease.storage_state().unwrap().is_empty());
+    assert!(lease.action_log().is_empty());
+    assert!(launched.lock().unwrap()[0].actions().contains(&MockAction::Reset));
+    drop(lease);
+
+    let (shared, _) = mock_pool(1);
+    let shared = shared.with_isolation(false);
+    let mut lease = shared.acquire().unwrap();
+    lease.navigate(PAGE).unwrap();
+    drop(lease);
+    assert_eq!(shared.acquire().unwrap().current_url().unwrap(), PAGE);
+}
+
+#[test]
+fn browsers_are_recycled_after_max_uses_and_on_crash() {
+    let (pool, launched) = mock_pool(1);
+    let pool = pool.with_max_uses(2);
+
+    drop(pool.acquire().unwrap());
+    drop(pool.acquire().unwrap());
+    let lease = pool.acquire().unwrap();
+    assert_eq!(lease.uses(), 1);
+    assert_eq!(launched.lock().unwrap().len(), 2);
+    assert!(launched.lock().unwrap()[0].actions().contains(&MockAction::Close));
+
+    // The browser dies while idle, so the next lease gets a new one
+    drop(lease);
+    launched.lock().unwrap()[1].clone().close().unwrap();
+    let lease = pool.acquire().unwrap();
+    lease.health_check().unwrap();
+    assert_eq!(launched.lock().unwrap().len(), 3);
+
+    lease.discard();
+    assert_eq!(pool.stats().recycled, 3);
+    assert_eq!(pool.stats().idle, 0);
+}
+
+#[test]
+fn tasks_fan_out_within_the_limit() {
+    let (pool, launched) = mock_pool(3);
+    let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+
+    let results = pool.run_each((0..8).collect(), |session, n: usize| {
+        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(20));
+        running.fetch_sub(1, Ordering::SeqCst);
+        if n == 5 {
+            return Err(Error::WorkflowError("task 5 failed".to_string()));
+        }
+        session.navigate(PAGE)?;
+        Ok(n * 10)
+    });
+
+    assert_eq!(results.len(), 8);
+    for (n, result) in results.iter().enumerate() {
+        match result {
+            Ok(value) => assert_eq!(*value, n * 10),
+            Err(e) => assert_eq!((n, e.to_string().contains("task 5 failed")), (5, true)),
+        }
+    }
+    assert!(peak.load(Ordering::SeqCst) <= 3);
+    assert!(launched.lock().unwrap().len() <= 3);
+}
+
+#[test]
+fn a_panicking_task_fails_alone_and_recycles_its_browser() {
+    let (pool, _) = mock_pool(1);
+
+    let results = pool.run_each(vec![1, 2, 3], |session, n: usize| {
+        session.navigate(PAGE)?;
+        assert_ne!(n, 2, "task 2 panics");
+        Ok(n)
+    });
+
+    assert_eq!((results[0].as_ref().ok(), results[2].as_ref().ok()), (Some(&1), Some(&3)));
+    assert!(results[1].as_ref().unwrap_err().to_string().contains("panicked"));
+    assert_eq!((pool.stats().launched, pool.stats().recycled), (2, 1));
+}
+
+#[test]
+fn shutdown_waits_for_leases_and_closes_browsers() {
+    let (pool, launched) = mock_pool(2);
+
+    let held = pool.acquire().unwrap();
+    drop(pool.acquire().unwrap());
+    let err = pool.shutdown(Duration::from_millis(50)).unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)), "{}", err);
+    assert!(pool.acquire().is_err());
+
+    let returning = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(held);
+    });
+    pool.shutdown(Duration::from_secs(2)).unwrap();
+    returning.join().unwrap();
+
+    let stats = pool.stats();
+    assert_eq!((stats.idle, stats.leased), (0, 0));
+    for mock in launched.lock().unwrap().iter() {
+        assert_eq!(mock.actions().last(), Some(&MockAction::Close));
+    }
+}
+
+#[test]
+fn devtools_and_webdriver_browsers_are_reset_in_place() {
+    use common::{cdp_attach_response, evaluate_result, CdpServer, HttpRequest, HttpResponse, HttpServer};
+    use llamaclick::browser::{CdpBrowser, WebDriverBrowser};
+    use serde_json::json;
+
+    let server = CdpServer::start(|method, params| {
+        Ok(cdp_attach_response(method).unwrap_or_else(|| match method {
+            "Runtime.evaluate" if params["expression"] == "document.readyState" => evaluate_result(json!("complete")),
+            "Runtime.evaluate" => evaluate_result(json!(PAGE)),
+            _ => json!({}),
+        }))
+    });
+    let url = server.url.clone();
+    let pool = BrowserPool::new(BrowserConfig::default(), 1)
+        .with_factory(move |config| Ok(Box::new(CdpBrowser::connect(&url, config.clone())?) as Box<dyn Browser>));
+    for _ in 0..3 {
+        pool.acquire().unwrap().navigate(PAGE).unwrap();
+    }
+    let stats = pool.stats();
+    assert_eq!((stats.launched, stats.recycled), (1, 0));
+    // Each lease ends by moving to a fresh context, dropping the previous one
+    assert_eq!(server.params_of("Target.createBrowserContext").len(), 3);
+    assert_eq!(server.params_of("Target.closeTarget"), [json!({ "targetId": "page-1" })]);
+    assert_eq!(server.params_of("Target.disposeBrowserContext").len(), 2);
+
+    let driver = HttpServer::start(|request: &HttpRequest| {
+        match (request.method.as_str(), request.path.trim_start_matches("/session/s-1")) {
+            ("POST", "/session") => HttpResponse::json(json!({ "value": { "sessionId": "s-1", "capabilities": {} } })),
+            ("GET", "/window") => HttpResponse::json(json!({ "value": "w-1" })),
+            ("GET", "/window/handles") => HttpResponse::json(json!({ "value": ["w-1", "w-2"] })),
+            ("GET", "/url") => HttpResponse::json(json!({ "value": PAGE })),
+            _ => HttpResponse::json(json!({ "value": null })),
+        }
+    });
+    let url = driver.url.clone();
+    let pool = BrowserPool::new(BrowserConfig::default(), 1)
+        .with_factory(move |config| Ok(Box::new(WebDriverBrowser::connect(&url, config.clone())?) as Box<dyn Browser>));
+    drop(pool.acquire().unwrap());
+    assert_eq!(pool.acquire().unwrap().uses(), 2);
+    let requests = driver.received();
+    let sent = |method: &str, path: &str| {
+        requests.iter().filter(|request| request.method == method && request.path.ends_with(path)).count()
+    };
+    // Both leases are reset, clearing two windows and closing one
+    assert_eq!((sent("DELETE", "/cookie"), sent("DELETE", "/window")), (4, 2));
+    assert!(requests.iter().any(|request| request.body.contains("localStorage.clear()")));
+    assert_eq!(pool.stats().recycled, 0);
+}
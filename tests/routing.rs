@@ -0,0 +1,113 @@
+//! Tests for request routing against a local fixture site
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    glob_match, BrowserConfig, BrowserSession, MockBrowser, ResourceType, Route, RouteAction, RouteResponse,
+    Selector, StaticBrowser,
+};
+use llamaclick::error::Error;
+use serde_json::json;
+
+fn site(request: &HttpRequest) -> HttpResponse {
+    match request.path.as_str() {
+        "/" => HttpResponse::html(r#"<form action="/api/search" method="post"><button>Go</button></form>"#),
+        "/headers" => HttpResponse::html(&format!(
+            "<p id=\"auth\">{}</p><p id=\"agent\">{}</p>",
+            request.header("authorization").unwrap_or("none"),
+            request.header("user-agent").unwrap_or("none")
+        )),
+        _ => HttpResponse::html("<p id=\"real\">Served by the site</p>"),
+    }
+}
+
+fn session(server: &HttpServer) -> BrowserSession {
+    let config = BrowserConfig::default().with_user_agent("LlamaClick/Routing");
+    let mut session = BrowserSession::new(Box::new(StaticBrowser::new(config.clone()).unwrap()), config);
+    session.navigate(&format!("{}/", server.url)).unwrap();
+    session
+}
+
+#[test]
+fn globs_match_within_and_across_segments() {
+    assert!(glob_match("**/api/*", "https://app.test/v1/api/users"));
+    assert!(!glob_match("**/api/*", "https://app.test/api/users/1"));
+    assert!(glob_match("https://*.test/**", "https://cdn.test/a/b.png"));
+    assert!(glob_match("**/item-?", "https://app.test/item-7"));
+    assert!(!glob_match("**/item-?", "https://app.test/item-42"));
+}
+
+#[test]
+fn fulfills_matching_requests_without_the_network() {
+    let server = HttpServer::start(site);
+    let mut session = session(&server);
+
+    session
+        .route(Route::fulfill("**/api/*", RouteResponse::html("<p id=\"stub\">Stubbed</p>")).with_method("POST"))
+        .unwrap();
+    session.click(&Selector::css("button")).unwrap();
+    assert_eq!(session.get_text(&Selector::id("stub")).unwrap(), "Stubbed");
+    assert!(server.received().iter().all(|request| request.path != "/api/search"));
+
+    // The method filter lets GET requests through
+    session.navigate(&format!("{}/api/search", server.url)).unwrap();
+    assert!(session.element_exists(&Selector::id("real")).unwrap());
+}
+
+#[test]
+fn aborts_and_unroutes() {
+    let server = HttpServer::start(site);
+    let mut session = session(&server);
+
+    session.route(Route::abort("**/tracker/**")).unwrap();
+    let result = session.navigate(&format!("{}/tracker/pixel", server.url));
+    assert!(matches!(result, Err(Error::NetworkError(_))));
+
+    session.unroute("**/tracker/**").unwrap();
+    assert!(session.routes().is_empty());
+    session.navigate(&format!("{}/tracker/pixel", server.url)).unwrap();
+}
+
+#[test]
+fn continues_with_modified_headers() {
+    let server = HttpServer::start(site);
+    let mut session = session(&server);
+
+    let headers = vec![
+        ("Authorization".to_string(), "Bearer test".to_string()),
+        ("User-Agent".to_string(), "Routed/1.0".to_string()),
+    ];
+    session.route(Route::new("**/headers", RouteAction::Continue { headers })).unwrap();
+    // Routes for other URLs do not shadow it
+    session.route(Route::new("**/other", RouteAction::pass())).unwrap();
+
+    session.navigate(&format!("{}/headers", server.url)).unwrap();
+    assert_eq!(session.get_text(&Selector::id("auth")).unwrap(), "Bearer test");
+    assert_eq!(session.get_text(&Selector::id("agent")).unwrap(), "Routed/1.0");
+}
+
+#[test]
+fn mock_routes_navigations_and_fetches() {
+    let mock = MockBrowser::new().with_page("https://app.test/", "<h1>Home</h1>");
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default());
+
+    session
+        .route(Route::fulfill("**/api/user", RouteResponse::json(&json!({ "name": "Llama" }))))
+        .unwrap();
+    session
+        .route(Route::abort("**/ads/**").with_resource_type(ResourceType::Fetch))
+        .unwrap();
+    session.navigate("https://app.test/").unwrap();
+
+    let user = mock.fetch("get", "https://app.test/api/user").unwrap();
+    assert_eq!(user.text(), r#"{"name":"Llama"}"#);
+    assert!(matches!(mock.fetch("GET", "https://app.test/ads/banner"), Err(Error::NetworkError(_))));
+    assert_eq!(mock.fetch("GET", "https://app.test/missing").unwrap().status, 404);
+
+    let requests = mock.requests();
+    assert_eq!(requests[0].resource_type, ResourceType::Document);
+    assert_eq!(requests.len(), 4);
+}
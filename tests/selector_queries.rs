@@ -0,0 +1,154 @@
+//! Tests for compound selectors and the selector query syntax
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{cdp_attach_response, evaluate_result, CdpServer, HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    Browser, BrowserConfig, BrowserSession, BrowserType, CdpBrowser, MockBrowser, Relation, Selector,
+    WebDriverBrowser, ELEMENT_KEY,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+
+const INVOICES: &str = r#"<html><body>
+  <table>
+    <tr data-id="41"><td>Invoice 41</td><td><button>Delete</button></td></tr>
+    <tr data-id="42"><td>Invoice 42</td><td><button>Delete</button></td></tr>
+    <tr data-id="43" hidden><td>Invoice 43</td><td><button>Delete</button></td></tr>
+  </table>
+  <a id="help" style="display: none">Help</a>
+  <a id="contact">Contact</a>
+</body></html>"#;
+
+#[test]
+fn queries_round_trip_through_text() {
+    let queries = [
+        r#"css("tr.invoice > td:nth-child(2)")"#,
+        r#"within(has-text(css("tr"), "Invoice 42"), text("Delete"))"#,
+        r#"nth(visible(class("row")), 2)"#,
+        r#"or(id("save"), name("save"), semantic("the \"save\" button"))"#,
+        r#"near(css("input"), text("Email"), 80)"#,
+        r#"below(xpath("//input[@type='password']"), text("Password"))"#,
+        r#"left-of(css("button"), css("a")), "#,
+    ];
+    for query in &queries[..6] {
+        let selector = Selector::parse(query).unwrap();
+        assert_eq!(selector.to_string(), *query);
+        assert_eq!(selector.to_string().parse::<Selector>().unwrap(), selector);
+    }
+
+    let spaced = Selector::parse(" within ( id(\"rows\") ,nth( css(\"tr\"),0 ) ) ").unwrap();
+    assert_eq!(spaced, Selector::within(Selector::id("rows"), Selector::css("tr").nth(0)));
+    assert_eq!(
+        Selector::parse(r#"near(css("input"), text("Email"))"#).unwrap(),
+        Selector::css("input").near(Selector::text("Email"))
+    );
+    assert_eq!(
+        Selector::parse(r#"right-of(css("input"), id("x"))"#).unwrap(),
+        Selector::css("input").relative(Relation::RightOf, Selector::id("x"))
+    );
+    assert_eq!(
+        Selector::id("a").or(Selector::id("b")).or(Selector::id("c")),
+        Selector::Or(vec![Selector::id("a"), Selector::id("b"), Selector::id("c")])
+    );
+
+    for bad in [
+        queries[6],
+        "button.primary",
+        r#"css("unterminated)"#,
+        r#"nth(css("tr"))"#,
+        r#"has-text(css("tr"), 42)"#,
+        r#"blink(css("tr"))"#,
+        "or()",
+    ] {
+        let err = Selector::parse(bad).unwrap_err();
+        assert!(matches!(err, Error::ValidationError(_)), "{}: {}", bad, err);
+    }
+    assert!(Selector::parse("blink(css(\"tr\"))").unwrap_err().to_string().contains("unknown selector function"));
+}
+
+#[test]
+fn compound_selectors_resolve_without_a_live_page() {
+    let mut mock = MockBrowser::from_html(INVOICES);
+
+    let delete = Selector::parse(r#"within(has-text(css("tr"), "Invoice 42"), text("Delete"))"#).unwrap();
+    mock.click(&delete).unwrap();
+    mock.assert_clicked_once(&delete);
+
+    let row = |selector: Selector| mock.get_attributes(&selector).unwrap()["data-id"].clone();
+    assert_eq!(row(Selector::css("tr").nth(1)), "42");
+    assert_eq!(row(Selector::css("tr").has_text("Invoice 43")), "43");
+    assert!(!mock.element_exists(&Selector::css("tr").has_text("Invoice 43").visible()).unwrap());
+    assert!(!mock.element_exists(&Selector::css("tr").nth(3)).unwrap());
+    assert_eq!(mock.get_text(&Selector::css("a").visible()).unwrap(), "Contact");
+
+    // Alternatives come back in document order, without duplicates
+    let links = Selector::id("contact").or(Selector::css("a")).or(Selector::id("help"));
+    assert_eq!(mock.get_text(&links).unwrap(), "Help");
+    assert_eq!(mock.get_text(&links.nth(1)).unwrap(), "Contact");
+    assert!(!mock.element_exists(&Selector::css("a").nth(0).nth(1)).unwrap());
+
+    let err = mock.click(&Selector::css("button").below(Selector::text("Invoice 41"))).unwrap_err();
+    assert!(matches!(err, Error::Unsupported(_)), "{}", err);
+    assert!(err.to_string().contains("below(css(\"button\"), text(\"Invoice 41\"))"), "{}", err);
+}
+
+#[test]
+fn sessions_log_compound_selectors() {
+    let mock = MockBrowser::from_html(INVOICES);
+    let mut session = BrowserSession::new(Box::new(mock), BrowserConfig::default());
+
+    let second = Selector::css("button").nth(1);
+    session.click(&second).unwrap();
+    assert_eq!(session.action_log()[0].selector, Some(second));
+}
+
+#[test]
+fn cdp_sends_compound_selectors_to_the_page() {
+    let server = CdpServer::start(|method: &str, _params: &Value| {
+        Ok(cdp_attach_response(method).unwrap_or_else(|| evaluate_result(json!(true))))
+    });
+    let browser = CdpBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Chrome)).unwrap();
+
+    let below = Selector::css("input").below(Selector::text("Email"));
+    let near = Selector::name("email").near(Selector::id("f"));
+    assert!(browser.element_exists(&below.or(near).nth(0)).unwrap());
+
+    let evaluated = server.params_of("Runtime.evaluate");
+    let expression = evaluated.last().unwrap()["expression"].as_str().unwrap();
+    let below = r#"{"Relative":[{"Css":"input"},"Below",{"Text":"Email"}]}"#;
+    let near = r#"{"Relative":[{"Name":"email"},{"Near":50},{"Id":"f"}]}"#;
+    assert!(expression.contains(&format!(r#"{{"Nth":[{{"Or":[{},{}]}},0]}}"#, below, near)), "{}", expression);
+}
+
+/// A driver where `#rows` (`e-1`) has no shadow root and scripts find `e-2`
+fn driver(request: &HttpRequest) -> HttpResponse {
+    let path = request.path.trim_start_matches("/session/s-1");
+    match (request.method.as_str(), path) {
+        ("POST", "/session") => HttpResponse::json(json!({ "value": { "sessionId": "s-1", "capabilities": {} } })),
+        ("POST", "/elements") => HttpResponse::json(json!({ "value": [{ ELEMENT_KEY: "e-1" }] })),
+        ("POST", "/execute/sync") => HttpResponse::json(json!({ "value": [{ ELEMENT_KEY: "e-2" }] })),
+        ("GET", "/element/e-1/shadow") => HttpResponse::with_status(404, json!({
+            "value": { "error": "no such shadow root", "message": "", "stacktrace": "" }
+        })),
+        _ => HttpResponse::json(json!({ "value": null })),
+    }
+}
+
+#[test]
+fn webdriver_resolves_compound_selectors_by_script() {
+    let server = HttpServer::start(driver);
+    let mut browser = WebDriverBrowser::connect(&server.url, BrowserConfig::new(BrowserType::Firefox)).unwrap();
+
+    browser
+        .click(&Selector::within(Selector::id("rows"), Selector::css("tr").has_text("Invoice 42")))
+        .unwrap();
+
+    let requests = server.received();
+    let script = requests.iter().find(|r| r.path.ends_with("/execute/sync")).unwrap().json();
+    assert!(script["script"].as_str().unwrap().contains(r#"{"HasText":[{"Css":"tr"},"Invoice 42"]}"#));
+    assert_eq!(script["args"], json!([{ ELEMENT_KEY: "e-1" }]));
+    assert!(requests.iter().any(|r| r.path == "/session/s-1/element/e-2/click"));
+}
@@ -0,0 +1,190 @@
+//! Tests for self-healing selectors
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use llamaclick::browser::{
+    BrowserConfig, BrowserSession, ElementFingerprint, FingerprintStore, MockBrowser, Selector, SelfHealing,
+};
+use llamaclick::error::Error;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const URL: &str = "https://app.test/settings/42";
+
+const BEFORE: &str = r#"<html><body><main>
+  <h1>Settings</h1>
+  <form>
+    <input name="email" type="email" placeholder="Email">
+    <button class="btn btn-save" type="submit">Save</button>
+    <button class="btn btn-cancel" type="button">Cancel</button>
+  </form>
+</main></body></html>"#;
+
+/// The same page after a redesign renamed its classes and field names
+const AFTER: &str = r#"<html><body><main>
+  <h1>Settings</h1>
+  <form>
+    <input name="user_email" type="email" placeholder="Email">
+    <button class="button button--primary" type="submit">Save</button>
+    <button class="button button--secondary" type="button">Cancel</button>
+  </form>
+</main></body></html>"#;
+
+fn open(html: &str, healing: SelfHealing) -> (MockBrowser, BrowserSession) {
+    let mock = MockBrowser::from_pages([(URL, html)]);
+    let mut session = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default()).with_self_healing(healing);
+    session.navigate(URL).unwrap();
+    (mock, session)
+}
+
+/// Run the steps once against the old page and return the fingerprints they recorded
+fn recorded() -> FingerprintStore {
+    let (_, mut session) = open(BEFORE, SelfHealing::new());
+    session.type_text(&Selector::name("email"), "llama@app.test").unwrap();
+    session.click(&Selector::class("btn-save")).unwrap();
+    assert!(session.healed_selectors().is_empty());
+    session.self_healing().unwrap().store().clone()
+}
+
+#[test]
+fn fingerprints_compare_elements() {
+    let save = ElementFingerprint::from_html(BEFORE, &Selector::class("btn-save")).unwrap();
+    assert_eq!(save.tag, "button");
+    assert_eq!(save.text, "Save");
+    assert_eq!(save.attributes["type"], "submit");
+    assert_eq!(save.path, ["html", "body", "main", "form", "button"]);
+    assert_eq!(save.neighbours, ["Cancel"]);
+    assert_eq!(save.similarity(&save), 1.0);
+
+    let renamed = ElementFingerprint::from_html(AFTER, &Selector::css("button[type=submit]")).unwrap();
+    let cancel = ElementFingerprint::from_html(AFTER, &Selector::css("button[type=button]")).unwrap();
+    assert!(save.similarity(&renamed) > 0.6, "{}", save.similarity(&renamed));
+    assert!(save.similarity(&cancel) < save.similarity(&renamed));
+}
+
+#[test]
+fn fingerprint_stores_persist_across_runs() {
+    let store = recorded();
+    assert_eq!(store.len(), 2);
+    assert!(store.get("https://app.test/settings/7?tab=profile", &Selector::class("btn-save")).is_some());
+    assert!(store.get("https://app.test/billing", &Selector::class("btn-save")).is_none());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fingerprints.json");
+    store.save(&path).unwrap();
+    assert_eq!(FingerprintStore::load(&path).unwrap(), store);
+}
+
+#[test]
+fn broken_selectors_heal_to_the_most_similar_element() {
+    let (mock, mut session) = open(AFTER, SelfHealing::new().with_store(recorded()));
+
+    session.type_text(&Selector::name("email"), "llama@app.test").unwrap();
+    session.click(&Selector::class("btn-save")).unwrap();
+
+    let healed = session.healed_selectors();
+    assert_eq!(healed.len(), 2);
+    assert_eq!((healed[1].url.as_str(), &healed[1].selector), (URL, &Selector::class("btn-save")));
+    assert!(healed[1].confidence >= 0.6 && healed[1].confidence < 1.0, "{}", healed[1].confidence);
+    assert!(healed[1].replacement.to_string().ends_with("button:nth-of-type(1)\")"), "{}", healed[1].replacement);
+    mock.assert_typed(&healed[0].replacement, "llama@app.test");
+    mock.assert_clicked_once(&healed[1].replacement);
+
+    // The action succeeded, so the log shows no error
+    assert!(session.action_log().iter().all(|record| record.error.is_none()));
+}
+
+#[test]
+fn dissimilar_pages_and_unknown_selectors_are_not_healed() {
+    let gone = BEFORE.replace(r#"<button class="btn btn-save" type="submit">Save</button>"#, "");
+    let (_, mut session) = open(&gone, SelfHealing::new().with_store(recorded()));
+    let err = session.click(&Selector::class("btn-save")).unwrap_err();
+    assert!(matches!(err, Error::ElementNotFound(_)), "{}", err);
+
+    let err = session.click(&Selector::id("never-seen")).unwrap_err();
+    assert!(matches!(err, Error::ElementNotFound(_)), "{}", err);
+    assert!(session.healed_selectors().is_empty());
+
+    let strict = SelfHealing::new().with_store(recorded()).with_threshold(0.99);
+    let (_, mut session) = open(AFTER, strict);
+    assert!(session.click(&Selector::class("btn-save")).is_err());
+}
+
+#[test]
+fn missing_elements_are_healed_without_waiting_for_them() {
+    use common::{cdp_attach_response, evaluate_result, CdpServer};
+    use llamaclick::browser::{BrowserType, CdpBrowser};
+
+    // A DevTools page showing the redesign, where probes for the old class find nothing
+    let server = CdpServer::start(|method: &str, params: &Value| {
+        if let Some(result) = cdp_attach_response(method) {
+            return Ok(result);
+        }
+        let expression = params["expression"].as_str().unwrap_or_default();
+        let value = match expression {
+            "document.documentElement.outerHTML" => json!(AFTER),
+            "location.href" => json!(URL),
+            _ if expression.contains("btn-save") && expression.ends_with(".length > 0") => json!(false),
+            _ if expression.contains("btn-save") => {
+                return Ok(json!({ "exceptionDetails": { "text": "Error: __llamaclick_not_found__" } }));
+            }
+            _ if expression.contains("obscuredBy") => json!({
+                "visible": true,
+                "enabled": true,
+                "obscuredBy": null,
+                "bounds": { "x": 10.0, "y": 10.0, "width": 80.0, "height": 20.0 },
+            }),
+            _ if expression.contains("x: r.left") => json!({ "x": 50.0, "y": 20.0 }),
+            _ => json!(null),
+        };
+        Ok(evaluate_result(value))
+    });
+    let config = BrowserConfig::new(BrowserType::Chrome).with_timeout(Duration::from_secs(10));
+    let browser = CdpBrowser::connect(&server.url, config.clone()).unwrap();
+    let mut session =
+        BrowserSession::new(Box::new(browser), config).with_self_healing(SelfHealing::new().with_store(recorded()));
+
+    let started = Instant::now();
+    session.click(&Selector::class("btn-save")).unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5), "{:?}", started.elapsed());
+    assert_eq!(session.healed_selectors().len(), 1);
+    assert!(server.methods().contains(&"Input.dispatchMouseEvent".to_string()));
+}
+
+/// A page where two buttons match the recorded Save button equally well
+const TWIN_BUTTONS: &str = r#"<html><body><main>
+  <h1>Settings</h1>
+  <form>
+    <input name="email" type="email" placeholder="Email">
+    <button class="button" type="submit">Save</button>
+    <button class="button" type="submit">Save</button>
+  </form>
+</main></body></html>"#;
+
+#[test]
+fn ties_are_not_healed_without_an_llm() {
+    let (mock, mut session) = open(TWIN_BUTTONS, SelfHealing::new().with_store(recorded()));
+    let err = session.click(&Selector::class("btn-save")).unwrap_err();
+    assert!(matches!(err, Error::ElementNotFound(_)), "{}", err);
+    mock.assert_not_clicked(&Selector::class("btn-save"));
+}
+
+#[cfg(feature = "llm")]
+#[test]
+fn an_llm_breaks_ties_between_candidates() {
+    let llm = common::ScriptedLlm::new("2");
+    let healing = SelfHealing::new().with_store(recorded()).with_llm(Box::new(llm.clone()));
+    let (mock, mut session) = open(TWIN_BUTTONS, healing);
+
+    session.click(&Selector::class("btn-save")).unwrap();
+    let replacement = &session.healed_selectors()[0].replacement;
+    assert!(replacement.to_string().ends_with("button:nth-of-type(2)\")"), "{}", replacement);
+    mock.assert_clicked_once(replacement);
+
+    assert_eq!(llm.calls(), 1);
+    let prompt = &llm.prompts()[0];
+    assert!(prompt.contains(r#""class":"btn btn-save""#), "{}", prompt);
+    assert!(prompt.contains("\n1. ") && prompt.contains("\n2. "), "{}", prompt);
+}
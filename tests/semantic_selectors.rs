@@ -0,0 +1,97 @@
+//! Tests for LLM-backed resolution of semantic selectors
+
+#![cfg(all(feature = "browser", feature = "llm"))]
+
+mod common;
+
+use common::ScriptedLlm;
+use llamaclick::browser::{url_pattern, BrowserConfig, BrowserSession, MockBrowser, Selector, SemanticResolver};
+use llamaclick::error::Error;
+
+const CART: &str = r#"<html><body>
+  <a href="/">Home</a>
+  <button class="secondary">Keep shopping</button>
+  <div class="actions"><button class="primary blue">Checkout</button></div>
+</body></html>"#;
+
+fn session(llm: &ScriptedLlm, mock: &MockBrowser) -> BrowserSession {
+    BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default())
+        .with_semantic_resolver(SemanticResolver::new(Box::new(llm.clone())))
+}
+
+#[test]
+fn session_clicks_the_element_the_llm_picks() {
+    let llm = ScriptedLlm::new("2");
+    let mock = MockBrowser::from_pages([("https://shop.test/cart/1234", CART)]);
+    let mut session = session(&llm, &mock);
+    session.navigate("https://shop.test/cart/1234").unwrap();
+
+    session.click(&Selector::semantic("the blue checkout button")).unwrap();
+
+    let prompt = &llm.prompts()[0];
+    assert!(prompt.contains("the blue checkout button"));
+    assert!(prompt.contains("[2] button \"Checkout\""), "{}", prompt);
+    let clicked = session.resolve(&Selector::semantic("the blue checkout button")).unwrap();
+    mock.assert_clicked_once(&clicked);
+    assert_eq!(session.get_text(&clicked).unwrap(), "Checkout");
+}
+
+#[test]
+fn caches_per_url_pattern_and_description() {
+    let llm = ScriptedLlm::new("Element 2");
+    let mock = MockBrowser::from_pages([
+        ("https://shop.test/cart/1234", CART),
+        ("https://shop.test/cart/98?ref=mail", CART),
+    ]);
+    let mut session = session(&llm, &mock);
+
+    session.navigate("https://shop.test/cart/1234").unwrap();
+    session.click(&Selector::semantic("checkout")).unwrap();
+    session.navigate("https://shop.test/cart/98?ref=mail").unwrap();
+    session.click(&Selector::semantic("checkout")).unwrap();
+    assert_eq!(llm.calls(), 1);
+
+    session.click(&Selector::semantic("keep shopping")).unwrap();
+    assert_eq!(llm.calls(), 2);
+    assert!(session
+        .semantic_resolver()
+        .unwrap()
+        .cached("https://shop.test/cart/7", "checkout")
+        .is_some());
+}
+
+#[test]
+fn reports_no_match_and_unparseable_answers() {
+    let mock = MockBrowser::from_html(CART);
+
+    let none = session(&ScriptedLlm::new("-1"), &mock).click(&Selector::semantic("a search box"));
+    assert!(matches!(none, Err(Error::ElementNotFound(_))));
+
+    let garbled = session(&ScriptedLlm::new("the checkout one"), &mock).click(&Selector::semantic("checkout"));
+    assert!(matches!(garbled, Err(Error::LlmError(_))));
+    assert_eq!(mock.click_count(&Selector::semantic("checkout")), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resolves_inside_a_runtime() {
+    let llm = ScriptedLlm::new("1");
+    let mock = MockBrowser::from_html(CART);
+    let resolver = SemanticResolver::new(Box::new(llm.clone()));
+
+    let selector = resolver.resolve(&mock, &Selector::semantic("keep shopping")).await.unwrap();
+    assert!(matches!(selector, Selector::Css(_)));
+    assert_eq!(resolver.resolve_blocking(&mock, &Selector::semantic("keep shopping")).unwrap(), selector);
+    assert_eq!(llm.calls(), 1);
+}
+
+#[test]
+fn url_patterns_drop_ids_and_queries() {
+    assert_eq!(url_pattern("https://shop.test/orders/1234?x=1"), "https://shop.test/orders/*");
+    assert_eq!(url_pattern("https://shop.test/orders/98"), "https://shop.test/orders/*");
+    assert_eq!(
+        url_pattern("https://shop.test/u/3f2a9c1e-7b4d-4e2a-9c1e-7b4d4e2a9c1e/profile"),
+        "https://shop.test/u/*/profile"
+    );
+    assert_eq!(url_pattern("https://shop.test/"), "https://shop.test/");
+    assert_eq!(url_pattern("https://shop.test/about"), "https://shop.test/about");
+}
@@ -0,0 +1,163 @@
+//! Tests for the static HTTP backend against a local fixture site
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{Browser, BrowserConfig, PageEvent, Selector, StaticBrowser};
+use llamaclick::error::Error;
+
+const HOME: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Fixture</title><script>var ignored = "Welcome";</script></head>
+<body>
+  <h1 id="title" class="heading main">Welcome to the fixture</h1>
+  <p class="intro">Server-rendered   content</p>
+  <a id="about" href="/about">About us</a>
+  <form id="login" action="/login" method="post">
+    <input type="text" name="username" value="">
+    <input type="password" name="password">
+    <input type="checkbox" name="remember" value="yes">
+    <input type="hidden" name="csrf" value="token-123">
+    <button type="submit" name="action" value="sign-in">Sign in</button>
+  </form>
+  <form id="search" action="/search">
+    <input type="search" name="q">
+    <select name="sort"><option value="relevance">Relevance</option><option value="date" selected>Date</option></select>
+    <input type="submit" value="Go">
+  </form>
+</body>
+</html>"#;
+
+fn site(request: &HttpRequest) -> HttpResponse {
+    let path = request.path.split('?').next().unwrap_or_default();
+    match (request.method.as_str(), path) {
+        ("GET", "/") => HttpResponse::html(HOME),
+        ("GET", "/about") => HttpResponse::html("<html><body><h1>About</h1></body></html>"),
+        ("GET", "/portal") => HttpResponse::html(r#"<html><body><a href="/about" target="_blank">About</a></body></html>"#),
+        ("POST", "/login") => HttpResponse::html(&format!("<html><body><pre id=\"echo\">{}</pre></body></html>", request.body)),
+        ("GET", "/search") => {
+            let query = request.path.split_once('?').map(|(_, q)| q).unwrap_or_default();
+            HttpResponse::html(&format!("<html><body><pre id=\"echo\">{}</pre></body></html>", query))
+        }
+        _ => HttpResponse::html("<html><body>Not found</body></html>"),
+    }
+}
+
+fn open(server: &HttpServer) -> StaticBrowser {
+    let mut browser = StaticBrowser::new(BrowserConfig::default().with_user_agent("LlamaClick/Static")).unwrap();
+    browser.navigate(&format!("{}/", server.url)).unwrap();
+    browser
+}
+
+#[test]
+fn resolves_every_selector_kind() {
+    let server = HttpServer::start(site);
+    let browser = open(&server);
+
+    let heading = "Welcome to the fixture";
+    assert_eq!(browser.get_text(&Selector::css("h1.heading")).unwrap(), heading);
+    assert_eq!(browser.get_text(&Selector::xpath("//h1[@id='title']")).unwrap(), heading);
+    assert_eq!(browser.get_text(&Selector::id("title")).unwrap(), heading);
+    assert_eq!(browser.get_text(&Selector::class("main")).unwrap(), heading);
+    assert_eq!(browser.get_text(&Selector::class("intro")).unwrap(), "Server-rendered content");
+    assert_eq!(browser.get_attributes(&Selector::name("csrf")).unwrap()["value"], "token-123");
+    assert_eq!(browser.get_attributes(&Selector::text("Sign in")).unwrap()["value"], "sign-in");
+
+    assert!(browser.element_exists(&Selector::text("About us")).unwrap());
+    assert!(!browser.element_exists(&Selector::text("ignored")).unwrap());
+    assert!(!browser.element_exists(&Selector::css("#missing")).unwrap());
+    assert!(matches!(browser.get_text(&Selector::css("#missing")), Err(Error::ElementNotFound(_))));
+}
+
+#[test]
+fn sends_configured_user_agent_and_exposes_html() {
+    let server = HttpServer::start(site);
+    let browser = open(&server);
+
+    assert_eq!(server.received()[0].header("user-agent"), Some("LlamaClick/Static"));
+    assert_eq!(browser.current_url().unwrap(), format!("{}/", server.url));
+    assert_eq!(browser.status().unwrap(), 200);
+    assert!(browser.get_html().unwrap().contains("<title>Fixture</title>"));
+}
+
+#[test]
+fn clicking_a_link_navigates() {
+    let server = HttpServer::start(site);
+    let mut browser = open(&server);
+
+    browser.click(&Selector::id("about")).unwrap();
+    assert_eq!(browser.current_url().unwrap(), format!("{}/about", server.url));
+    assert_eq!(browser.get_text(&Selector::css("h1")).unwrap(), "About");
+}
+
+#[test]
+fn submits_post_form_with_typed_values() {
+    let server = HttpServer::start(site);
+    let mut browser = open(&server);
+
+    browser.type_text(&Selector::name("username"), "llama").unwrap();
+    browser.type_text(&Selector::name("password"), "s3cret&more").unwrap();
+    browser.click(&Selector::name("remember")).unwrap();
+    assert!(browser.get_attributes(&Selector::name("remember")).unwrap().contains_key("checked"));
+    browser.click(&Selector::text("Sign in")).unwrap();
+
+    assert_eq!(browser.current_url().unwrap(), format!("{}/login", server.url));
+    assert_eq!(
+        browser.get_text(&Selector::id("echo")).unwrap(),
+        "username=llama&password=s3cret%26more&remember=yes&csrf=token-123&action=sign-in"
+    );
+}
+
+#[test]
+fn submits_get_form_as_query_string() {
+    let server = HttpServer::start(site);
+    let mut browser = open(&server);
+
+    browser.type_text(&Selector::name("q"), "rust browsers").unwrap();
+    assert_eq!(browser.get_text(&Selector::name("q")).unwrap(), "rust browsers");
+    browser.click(&Selector::css("#search input[type=submit]")).unwrap();
+
+    assert_eq!(browser.get_text(&Selector::id("echo")).unwrap(), "q=rust+browsers&sort=date");
+}
+
+#[test]
+fn typing_into_non_input_fails() {
+    let server = HttpServer::start(site);
+    let mut browser = open(&server);
+
+    assert!(browser.type_text(&Selector::css("h1"), "nope").is_err());
+}
+
+#[test]
+fn javascript_is_unsupported() {
+    let server = HttpServer::start(site);
+    let mut browser = open(&server);
+
+    let err = browser.execute_js("document.title").unwrap_err();
+    assert!(matches!(err, Error::Unsupported(ref m) if m.contains("not supported")), "{}", err);
+}
+
+#[test]
+fn target_blank_links_open_new_pages() {
+    let server = HttpServer::start(site);
+    let mut browser = open(&server);
+    browser.navigate("/portal").unwrap();
+    let portal = browser.active_page().unwrap();
+
+    browser.click(&Selector::text("About")).unwrap();
+    assert_eq!(browser.current_url().unwrap(), format!("{}/portal", server.url));
+    let popup = match browser.take_page_events().unwrap().as_slice() {
+        [PageEvent::Opened { page, opener, .. }] if opener.as_ref() == Some(&portal) => page.clone(),
+        events => panic!("unexpected page events {:?}", events),
+    };
+
+    browser.switch_to_page(&popup).unwrap();
+    assert_eq!(browser.get_text(&Selector::css("h1")).unwrap(), "About");
+
+    let tab = browser.new_page(Some(&format!("{}/", server.url))).unwrap();
+    assert_eq!(browser.pages().unwrap().len(), 3);
+    browser.close_page(&tab).unwrap();
+    assert_eq!(browser.active_page().unwrap(), popup);
+}
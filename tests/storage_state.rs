@@ -0,0 +1,111 @@
+//! Tests for saving and restoring cookies and web storage
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    Browser, BrowserConfig, BrowserSession, Cookie, MockBrowser, OriginStorage, StaticBrowser, StorageItem,
+    StorageState,
+};
+use llamaclick::error::Error;
+use serde_json::json;
+
+fn state() -> StorageState {
+    let mut session = Cookie::new("session", "abc123", ".app.test");
+    session.http_only = true;
+    session.expires = Some(4_102_444_800.0);
+    StorageState {
+        cookies: vec![session, Cookie::new("theme", "dark", "app.test")],
+        origins: vec![OriginStorage {
+            origin: "https://app.test".to_string(),
+            local_storage: vec![StorageItem {
+                name: "token".to_string(),
+                value: "t-1".to_string(),
+            }],
+            session_storage: Vec::new(),
+        }],
+    }
+}
+
+#[test]
+fn session_saves_and_loads_state_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state.json");
+
+    let mut source = MockBrowser::new();
+    source.set_storage_state(&state()).unwrap();
+    BrowserSession::new(Box::new(source), BrowserConfig::default())
+        .save_storage_state(&path)
+        .unwrap();
+
+    let saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["cookies"][0]["httpOnly"], true);
+    assert_eq!(saved["origins"][0]["localStorage"][0], json!({ "name": "token", "value": "t-1" }));
+
+    let target = MockBrowser::new();
+    let mut session = BrowserSession::new(Box::new(target.clone()), BrowserConfig::default());
+    session.load_storage_state(&path).unwrap();
+    assert_eq!(target.storage_state().unwrap(), state());
+}
+
+#[test]
+fn merging_replaces_matching_entries() {
+    let mut merged = state();
+    let mut update = StorageState::default();
+    update.cookies.push(Cookie::new("theme", "light", "app.test"));
+    update.origins.push(OriginStorage {
+        origin: "https://app.test".to_string(),
+        ..OriginStorage::default()
+    });
+    merged.merge(&update);
+
+    assert_eq!(merged.cookies.len(), 2);
+    assert!(merged.cookies.contains(&Cookie::new("theme", "light", "app.test")));
+    assert!(merged.origin("https://app.test").unwrap().local_storage.is_empty());
+}
+
+#[test]
+fn static_browser_round_trips_cookies() {
+    let server = HttpServer::start(|request: &HttpRequest| match request.path.as_str() {
+        "/login" => HttpResponse::html("<p>Signed in</p>").header("Set-Cookie", "session=abc123; Path=/; HttpOnly"),
+        _ => HttpResponse::html(&format!("<p id=\"cookie\">{}</p>", request.header("cookie").unwrap_or("none"))),
+    });
+
+    let mut browser = StaticBrowser::new(BrowserConfig::default()).unwrap();
+    browser.navigate(&format!("{}/login", server.url)).unwrap();
+    let saved = browser.storage_state().unwrap();
+    assert_eq!(saved.cookies.len(), 1);
+    assert_eq!((saved.cookies[0].name.as_str(), saved.cookies[0].http_only), ("session", true));
+
+    let mut restored = StaticBrowser::new(BrowserConfig::default()).unwrap();
+    restored.set_storage_state(&saved).unwrap();
+    restored.navigate(&format!("{}/home", server.url)).unwrap();
+    assert_eq!(restored.get_text(&llamaclick::browser::Selector::id("cookie")).unwrap(), "session=abc123");
+}
+
+#[test]
+fn profile_names_are_validated() {
+    assert!(StorageState::profile_path("work").unwrap().ends_with("profiles/work.json"));
+    for name in ["", "../escape", "a b"] {
+        assert!(matches!(StorageState::profile_path(name), Err(Error::ValidationError(_))), "{:?}", name);
+    }
+}
+
+#[cfg(feature = "security")]
+#[test]
+fn encrypted_state_needs_the_passphrase() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state.json");
+    state().save_encrypted(&path, "correct horse").unwrap();
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("abc123"));
+    assert!(matches!(StorageState::load(&path), Err(Error::ConfigurationError(_))));
+    assert!(matches!(
+        StorageState::load_encrypted(&path, "wrong"),
+        Err(Error::ConfigurationError(_))
+    ));
+    assert_eq!(StorageState::load_encrypted(&path, "correct horse").unwrap(), state());
+}
@@ -0,0 +1,225 @@
+//! Tests for tool calling across the LLM providers and the browser tools
+
+#![cfg(feature = "llm")]
+
+mod common;
+
+use common::{HttpResponse, HttpServer, ScriptedLlm};
+use llamaclick::llms::{
+    AnthropicProvider, ChatMessage, GenerationOptions, LlmProvider, LlmProviderConfig, LlmProviderType,
+    LocalProvider, OpenAiProvider, Role, ToolCall, ToolDefinition,
+};
+use serde_json::json;
+
+fn weather() -> ToolDefinition {
+    ToolDefinition::new(
+        "weather",
+        "Look up the weather in a city",
+        json!({ "type": "object", "properties": { "city": { "type": "string" } }, "required": ["city"] }),
+    )
+}
+
+fn question() -> Vec<ChatMessage> {
+    vec![ChatMessage::system("Answer briefly."), ChatMessage::user("Is it raining in Lima?")]
+}
+
+#[tokio::test]
+async fn openai_sends_tools_and_reads_their_calls() {
+    let server = HttpServer::start(|request| {
+        if request.json()["messages"].as_array().unwrap().len() > 2 {
+            return HttpResponse::json(json!({ "choices": [{ "message": { "content": "No, it is dry." } }] }));
+        }
+        HttpResponse::json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": null, "tool_calls": [{
+                "id": "call_abc",
+                "type": "function",
+                "function": { "name": "weather", "arguments": "{\"city\":\"Lima\"}" }
+            }] } }]
+        }))
+    });
+    let config = LlmProviderConfig::new(LlmProviderType::OpenAi, "gpt-4o", "sk-test")
+        .with_endpoint(&format!("{}/v1/chat/completions", server.url));
+    let provider = OpenAiProvider::new(config).unwrap();
+    let options = GenerationOptions::new();
+
+    let mut messages = question();
+    let response = provider.chat_with_tools(&messages, &[weather()], &options).await.unwrap();
+    assert_eq!(response.content, "");
+    let call = &response.tool_calls[0];
+    assert_eq!((call.id.as_str(), call.name.as_str()), ("call_abc", "weather"));
+    assert_eq!(call.arguments, json!({ "city": "Lima" }));
+    let tools = &server.received()[0].json()["tools"];
+    assert_eq!(tools[0]["type"], "function");
+    assert_eq!(tools[0]["function"]["name"], "weather");
+    assert_eq!(tools[0]["function"]["parameters"]["required"], json!(["city"]));
+
+    messages.push(response.message());
+    messages.push(ChatMessage::tool_result("call_abc", "Sunny, 24°C"));
+    let response = provider.chat_with_tools(&messages, &[weather()], &options).await.unwrap();
+    assert_eq!(response.content, "No, it is dry.");
+    assert!(response.tool_calls.is_empty());
+    let sent = &server.received()[1].json()["messages"];
+    assert_eq!(sent[2]["content"], json!(null));
+    assert_eq!(sent[2]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Lima\"}");
+    assert_eq!(sent[3], json!({ "role": "tool", "content": "Sunny, 24°C", "tool_call_id": "call_abc" }));
+
+    let server = HttpServer::start(|_| {
+        HttpResponse::json(json!({ "choices": [{ "message": { "content": null, "tool_calls": [{
+            "id": "call_1", "type": "function", "function": { "name": "weather", "arguments": "{\"city\":" }
+        }] } }] }))
+    });
+    let config = LlmProviderConfig::new(LlmProviderType::OpenAi, "gpt-4o", "sk-test").with_endpoint(&server.url);
+    let err = OpenAiProvider::new(config).unwrap().chat_with_tools(&question(), &[weather()], &options).await;
+    assert!(err.unwrap_err().to_string().contains("malformed arguments"));
+}
+
+#[tokio::test]
+async fn anthropic_maps_tools_onto_tool_use_blocks() {
+    let server = HttpServer::start(|_| {
+        HttpResponse::json(json!({
+            "content": [
+                { "type": "text", "text": "Let me check." },
+                { "type": "tool_use", "id": "toolu_1", "name": "weather", "input": { "city": "Lima" } },
+            ],
+            "stop_reason": "tool_use",
+        }))
+    });
+    let config = LlmProviderConfig::new(LlmProviderType::Anthropic, "claude-3-5-sonnet-latest", "sk-ant-test")
+        .with_endpoint(&format!("{}/v1/messages", server.url));
+    let provider = AnthropicProvider::new(config).unwrap();
+
+    let mut messages = question();
+    let response = provider.chat_with_tools(&messages, &[weather()], &GenerationOptions::new()).await.unwrap();
+    assert_eq!(response.content, "Let me check.");
+    assert_eq!(response.tool_calls[0].to_string(), r#"weather({"city":"Lima"})"#);
+    let tools = &server.received()[0].json()["tools"];
+    assert_eq!(tools[0]["name"], "weather");
+    assert_eq!(tools[0]["input_schema"], weather().parameters);
+
+    messages.push(response.message());
+    messages.push(ChatMessage::tool_result("toolu_1", "Sunny"));
+    messages.push(ChatMessage::user("And tomorrow?"));
+    provider.chat_with_tools(&messages, &[weather()], &GenerationOptions::new()).await.unwrap();
+    let sent = &server.received()[1].json()["messages"];
+    assert_eq!(
+        sent[1],
+        json!({ "role": "assistant", "content": [
+            { "type": "text", "text": "Let me check." },
+            { "type": "tool_use", "id": "toolu_1", "name": "weather", "input": { "city": "Lima" } },
+        ] })
+    );
+    assert_eq!(
+        sent[2],
+        json!({ "role": "user", "content": [
+            { "type": "tool_result", "tool_use_id": "toolu_1", "content": "Sunny" },
+            { "type": "text", "text": "And tomorrow?" },
+        ] })
+    );
+}
+
+#[tokio::test]
+async fn other_providers_are_prompted_for_json_calls() {
+    let llm = ScriptedLlm::new("```json\n{\"tool\": \"weather\", \"arguments\": {\"city\": \"Lima\"}}\n```");
+    let mut messages = question();
+    let response = llm.chat_with_tools(&messages, &[weather()], &GenerationOptions::new()).await.unwrap();
+    assert_eq!(response.content, "");
+    assert_eq!(response.tool_calls, [ToolCall {
+        id: "call_1".to_string(),
+        name: "weather".to_string(),
+        arguments: json!({ "city": "Lima" }),
+    }]);
+    let chat = &llm.chats()[0];
+    assert_eq!(chat.iter().map(|message| message.role).collect::<Vec<_>>(), [Role::System, Role::System, Role::User]);
+    assert!(chat[1].content.contains("- weather: Look up the weather in a city"), "{}", chat[1].content);
+
+    messages.push(response.message());
+    messages.push(ChatMessage::tool_result("call_1", "Sunny"));
+    let response = llm.chat_with_tools(&messages, &[weather()], &GenerationOptions::new()).await.unwrap();
+    assert_eq!(response.tool_calls[0].id, "call_2");
+    let chat = &llm.chats()[1];
+    assert_eq!(chat[3].content, r#"{"arguments":{"city":"Lima"},"tool":"weather"}"#);
+    assert_eq!((chat[4].role, chat[4].content.as_str()), (Role::User, "Result of weather: Sunny"));
+
+    // Replies naming no known tool are plain answers
+    let server = HttpServer::start(|_| HttpResponse::json(json!({ "message": { "content": "Use {weather}." } })));
+    let config = LlmProviderConfig::new(LlmProviderType::Local, "llama3", "").with_endpoint(&server.url);
+    let local = LocalProvider::new(config).unwrap();
+    let response = local.chat_with_tools(&question(), &[weather()], &GenerationOptions::new()).await.unwrap();
+    assert_eq!(response.content, "Use {weather}.");
+    assert!(response.tool_calls.is_empty());
+    let prompt = server.received()[0].json()["messages"][1]["content"].clone();
+    assert!(prompt.as_str().unwrap().contains("You can call these tools"), "{}", prompt);
+}
+
+#[cfg(feature = "browser")]
+#[tokio::test]
+async fn the_interactor_acts_through_browser_tools() {
+    use llamaclick::agent::{browser_tools, Agent, AgentConfig, AgentManager, AgentType};
+    use llamaclick::browser::{AsyncBrowserSession, BrowserConfig, BrowserSession, MockBrowser, Selector};
+
+    let names: Vec<_> = browser_tools().into_iter().map(|tool| tool.name).collect();
+    assert_eq!(names, ["navigate", "click", "type", "extract"]);
+
+    let mock = MockBrowser::from_html(r#"<form><input id="email"><button id="submit">Log in</button></form>"#);
+    let browser: AsyncBrowserSession = BrowserSession::new(Box::new(mock.clone()), BrowserConfig::default()).into();
+    let interactor = ScriptedLlm::sequence(&[
+        r#"{"tool": "click", "arguments": {"selector": "text(\"Log in\")"}}"#,
+        "I logged in.",
+    ]);
+    let mut manager = AgentManager::new();
+    for (agent_type, llm) in [
+        (AgentType::Planner, ScriptedLlm::new("1. Log in")),
+        (AgentType::Navigator, ScriptedLlm::new("Click the Log in button")),
+        (AgentType::Interactor, interactor.clone()),
+        (AgentType::Verifier, ScriptedLlm::new("Done")),
+    ] {
+        manager.add_agent(Agent::new(AgentConfig::new(agent_type), Box::new(llm)));
+    }
+
+    let result = manager.execute_task_with_browser("Log in", &browser).await.unwrap();
+    assert_eq!(result, "Done");
+    mock.assert_clicked_once(&Selector::text("Log in"));
+    assert!(interactor.chats()[0][1].content.contains("- type: "));
+    // The call and its result are kept as messages, and the result is sent back
+    let history = manager.get_agent(AgentType::Interactor).unwrap().history();
+    let roles: Vec<Role> = history.iter().map(|message| message.role).collect();
+    assert_eq!(roles, [Role::User, Role::Assistant, Role::Tool, Role::Assistant]);
+    assert_eq!(history[1].tool_calls[0].to_string(), r#"click({"selector":"text(\"Log in\")"})"#);
+    assert_eq!((history[2].tool_call_id.as_deref(), history[2].content.as_str()), (Some("call_1"), "Clicked"));
+    assert_eq!(history[3].content, "I logged in.");
+    assert_eq!(interactor.chats()[1].last().unwrap().content, "Result of click: Clicked");
+
+    let typing =
+        ScriptedLlm::sequence(&[r##"{"tool": "type", "arguments": {"selector": "#email", "text": "a@b.c"}}"##, ""]);
+    let mut agent = Agent::new(AgentConfig::new(AgentType::Interactor), Box::new(typing));
+    assert!(agent.interact("Fill in the email", &browser).await.unwrap().ends_with(": Typed"));
+    mock.assert_typed(&Selector::css("#email"), "a@b.c");
+    let missing = ScriptedLlm::sequence(&[
+        r#"{"tool": "extract", "arguments": {"selector": "id(\"total\")"}}"#,
+        "There is no total.",
+    ]);
+    let mut agent = Agent::new(AgentConfig::new(AgentType::Interactor), Box::new(missing));
+    let report = agent.interact("Read the total", &browser).await.unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert!(lines[0].starts_with(r#"extract({"selector":"id(\"total\")"}) failed: "#), "{}", report);
+    assert_eq!(lines[1], "There is no total.");
+    assert!(agent.history()[2].content.starts_with("Failed: "));
+
+    // An agent that never stops calling tools is cut off
+    let looping = ScriptedLlm::new(r#"{"tool": "extract", "arguments": {"selector": "button"}}"#);
+    let mut agent = Agent::new(AgentConfig::new(AgentType::Interactor), Box::new(looping.clone()));
+    let err = agent.interact("Read the button", &browser).await.unwrap_err();
+    assert!(err.to_string().contains("still calling tools"), "{}", err);
+    assert_eq!(looping.calls(), 11);
+
+    // A final answer right after the last allowed round still counts
+    let extract = r#"{"tool": "extract", "arguments": {"selector": "button"}}"#;
+    let mut replies = vec![extract; 10];
+    replies.push("The button says Log in.");
+    let patient = ScriptedLlm::sequence(&replies);
+    let mut agent = Agent::new(AgentConfig::new(AgentType::Interactor), Box::new(patient.clone()));
+    let report = agent.interact("Read the button", &browser).await.unwrap();
+    assert_eq!(report.lines().count(), 11);
+    assert!(report.ends_with("\nThe button says Log in."), "{}", report);
+    assert_eq!(patient.calls(), 11);
+}
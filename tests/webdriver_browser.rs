@@ -0,0 +1,233 @@
+//! Tests for the WebDriver backend against a mock driver
+
+#![cfg(feature = "browser")]
+
+mod common;
+
+use common::{HttpRequest, HttpResponse, HttpServer};
+use llamaclick::browser::{
+    locator, Browser, BrowserConfig, BrowserType, KeyChord, PageEvent, PageId, Selector, WebDriverBrowser, ELEMENT_KEY,
+};
+use llamaclick::error::Error;
+use serde_json::json;
+use std::time::Duration;
+
+fn config(browser_type: BrowserType) -> BrowserConfig {
+    BrowserConfig::new(browser_type).with_timeout(Duration::from_secs(2))
+}
+
+/// A driver with one page containing `#found` and nothing else
+fn driver(request: &HttpRequest) -> HttpResponse {
+    let path = request.path.trim_start_matches("/session/s-1");
+    match (request.method.as_str(), path) {
+        ("POST", "/session") => HttpResponse::json(json!({
+            "value": { "sessionId": "s-1", "capabilities": {} }
+        })),
+        ("POST", "/elements") => {
+            let found = request.json()["value"].as_str().unwrap_or_default().contains("found");
+            let elements = if found { json!([{ ELEMENT_KEY: "e-1" }]) } else { json!([]) };
+            HttpResponse::json(json!({ "value": elements }))
+        }
+        ("GET", "/url") => HttpResponse::json(json!({ "value": "https://example.com/" })),
+        ("GET", "/element/e-1/text") => HttpResponse::json(json!({ "value": "Found it" })),
+        ("GET", "/element/e-1/displayed") => HttpResponse::json(json!({ "value": true })),
+        ("POST", "/execute/sync") => {
+            let script = request.json()["script"].as_str().unwrap_or_default().to_string();
+            if script.contains("attributes") {
+                HttpResponse::json(json!({ "value": { "id": "found", "class": "primary" } }))
+            } else {
+                HttpResponse::json(json!({ "value": 42 }))
+            }
+        }
+        ("GET", "/source") => HttpResponse::json(json!({ "value": "<html></html>" })),
+        ("POST", "/url") => HttpResponse::with_status(500, json!({
+            "value": { "error": "unknown error", "message": "Reached error page: about:neterror", "stacktrace": "" }
+        })),
+        _ => HttpResponse::json(json!({ "value": null })),
+    }
+}
+
+#[test]
+fn selectors_map_to_locator_strategies() {
+    let cases = [
+        (Selector::css("a.b"), "css selector", "a.b"),
+        (Selector::xpath("//a"), "xpath", "//a"),
+        (Selector::id("main"), "css selector", "[id=\"main\"]"),
+        (Selector::class("btn"), "css selector", "[class~=\"btn\"]"),
+        (Selector::name("q"), "css selector", "[name=\"q\"]"),
+    ];
+    for (selector, using, value) in cases {
+        let locator = locator(&selector).unwrap();
+        assert_eq!((locator.using, locator.value.as_str()), (using, value));
+    }
+
+    let text = locator(&Selector::text("it's \"here\"")).unwrap();
+    assert_eq!(text.using, "xpath");
+    assert!(text.value.contains("concat('it', \"'\", 's \"here\"')"), "{}", text.value);
+
+    assert!(locator(&Selector::semantic("the login button")).is_err());
+}
+
+#[test]
+fn session_requests_firefox_capabilities() {
+    let server = HttpServer::start(driver);
+    let config = config(BrowserType::Firefox)
+        .with_user_agent("LlamaClick/Test")
+        .with_proxy("http://proxy:3128")
+        .with_ignore_https_errors(true)
+        .with_window_size(1024, 768);
+
+    let browser = WebDriverBrowser::connect(&server.url, config).unwrap();
+    assert_eq!(browser.session_id(), "s-1");
+
+    let requests = server.received();
+    let capabilities = &requests[0].json()["capabilities"]["alwaysMatch"];
+    assert_eq!(capabilities["browserName"], "firefox");
+    assert_eq!(capabilities["acceptInsecureCerts"], true);
+    assert_eq!(capabilities["proxy"]["httpProxy"], "proxy:3128");
+    assert_eq!(capabilities["moz:firefoxOptions"]["args"], json!(["-headless"]));
+    assert_eq!(capabilities["moz:firefoxOptions"]["prefs"]["general.useragent.override"], "LlamaClick/Test");
+
+    assert_eq!(requests[1].path, "/session/s-1/window/rect");
+    assert_eq!(requests[1].json(), json!({ "width": 1024, "height": 768 }));
+}
+
+#[test]
+fn session_requests_edge_capabilities() {
+    let server = HttpServer::start(driver);
+    WebDriverBrowser::connect(&server.url, config(BrowserType::Edge).with_block_images(true)).unwrap();
+
+    let capabilities = &server.received()[0].json()["capabilities"]["alwaysMatch"];
+    assert_eq!(capabilities["browserName"], "MicrosoftEdge");
+    let args = capabilities["ms:edgeOptions"]["args"].as_array().unwrap();
+    assert!(args.contains(&json!("--headless=new")));
+    assert!(args.contains(&json!("--blink-settings=imagesEnabled=false")));
+}
+
+#[test]
+fn element_commands_use_found_element() {
+    let server = HttpServer::start(driver);
+    let mut browser = WebDriverBrowser::connect(&server.url, config(BrowserType::Firefox)).unwrap();
+    let selector = Selector::id("found");
+
+    browser.click(&selector).unwrap();
+    browser.type_text(&selector, "hello").unwrap();
+    assert_eq!(browser.get_text(&selector).unwrap(), "Found it");
+    assert_eq!(browser.get_attributes(&selector).unwrap()["class"], "primary");
+    assert!(browser.element_exists(&selector).unwrap());
+    browser.wait_for_element(&selector, Duration::from_millis(200)).unwrap();
+
+    let paths: Vec<_> = server.received().iter().map(|r| format!("{} {}", r.method, r.path)).collect();
+    assert!(paths.contains(&"POST /session/s-1/element/e-1/click".to_string()));
+    assert!(paths.contains(&"POST /session/s-1/element/e-1/clear".to_string()));
+    let typed = server
+        .received()
+        .into_iter()
+        .find(|r| r.path.ends_with("/element/e-1/value"))
+        .unwrap();
+    assert_eq!(typed.json()["text"], "hello");
+}
+
+#[test]
+fn rich_input_performs_and_releases_actions() {
+    let server = HttpServer::start(driver);
+    let mut browser = WebDriverBrowser::connect(&server.url, config(BrowserType::Firefox)).unwrap();
+
+    browser.right_click(&Selector::id("found")).unwrap();
+    browser.press_key(&KeyChord::parse("Shift+Enter").unwrap()).unwrap();
+
+    let actions: Vec<_> = server
+        .received()
+        .into_iter()
+        .filter(|r| r.path.ends_with("/actions"))
+        .collect();
+    let methods: Vec<_> = actions.iter().map(|r| r.method.as_str()).collect();
+    assert_eq!(methods, ["POST", "DELETE", "POST", "DELETE"]);
+
+    let pointer = &actions[0].json()["actions"][0];
+    assert_eq!(pointer["actions"][0]["origin"][ELEMENT_KEY], "e-1");
+    assert_eq!(pointer["actions"][1], json!({ "type": "pointerDown", "button": 2 }));
+
+    let keys = &actions[2].json()["actions"][0]["actions"];
+    let values: Vec<_> = keys.as_array().unwrap().iter().map(|k| k["value"].as_str().unwrap()).collect();
+    assert_eq!(values, ["\u{E008}", "\u{E007}", "\u{E007}", "\u{E008}"]);
+}
+
+#[test]
+fn missing_element_is_reported_as_not_found() {
+    let server = HttpServer::start(driver);
+    let mut browser = WebDriverBrowser::connect(&server.url, config(BrowserType::Firefox)).unwrap();
+
+    let err = browser.click(&Selector::css("#nope")).unwrap_err();
+    assert!(matches!(err, Error::ElementNotFound(_)), "{}", err);
+    assert!(!browser.element_exists(&Selector::css("#nope")).unwrap());
+
+    let err = browser
+        .wait_for_element(&Selector::css("#nope"), Duration::from_millis(150))
+        .unwrap_err();
+    assert!(matches!(err, Error::TimeoutError(_)), "{}", err);
+}
+
+#[test]
+fn driver_errors_carry_webdriver_error_code() {
+    let server = HttpServer::start(driver);
+    let mut browser = WebDriverBrowser::connect(&server.url, config(BrowserType::Edge)).unwrap();
+
+    let err = browser.navigate("https://unreachable.invalid").unwrap_err();
+    match err {
+        Error::BrowserError(message) => {
+            assert_eq!(message, "unknown error: Reached error page: about:neterror")
+        }
+        other => panic!("unexpected error: {}", other),
+    }
+}
+
+#[test]
+fn scripts_and_session_lifecycle() {
+    let server = HttpServer::start(driver);
+    let mut browser = WebDriverBrowser::connect(&server.url, config(BrowserType::Firefox)).unwrap();
+
+    assert_eq!(browser.execute_js("6 * 7").unwrap(), json!(42));
+    assert_eq!(browser.current_url().unwrap(), "https://example.com/");
+    assert_eq!(browser.get_html().unwrap(), "<html></html>");
+
+    browser.close().unwrap();
+    let last = server.received().pop().unwrap();
+    assert_eq!((last.method.as_str(), last.path.as_str()), ("DELETE", "/session/s-1"));
+    assert!(browser.get_html().is_err());
+}
+
+#[test]
+fn pages_map_to_window_handles() {
+    let server = HttpServer::start(|request| {
+        let path = request.path.trim_start_matches("/session/s-1");
+        match (request.method.as_str(), path) {
+            ("GET", "/window") => HttpResponse::json(json!({ "value": "w-1" })),
+            ("GET", "/window/handles") => HttpResponse::json(json!({ "value": ["w-1", "w-2"] })),
+            ("POST", "/window/new") => HttpResponse::json(json!({ "value": { "handle": "w-3", "type": "tab" } })),
+            _ => driver(request),
+        }
+    });
+    let mut browser = WebDriverBrowser::connect(&server.url, config(BrowserType::Firefox)).unwrap();
+    assert_eq!(browser.active_page().unwrap(), PageId::new("w-1"));
+
+    let events = browser.take_page_events().unwrap();
+    assert!(
+        matches!(events.as_slice(), [PageEvent::Opened { page, .. }] if *page == PageId::new("w-2")),
+        "{:?}",
+        events
+    );
+
+    let tab = browser.new_page(None).unwrap();
+    assert_eq!(tab, PageId::new("w-3"));
+    browser.switch_to_page(&PageId::new("w-1")).unwrap();
+
+    let switches: Vec<_> = server
+        .received()
+        .iter()
+        .filter(|request| request.method == "POST" && request.path == "/session/s-1/window")
+        .map(|request| request.json()["handle"].clone())
+        .collect();
+    assert_eq!(switches.last().unwrap(), "w-1");
+    assert!(switches.contains(&json!("w-3")));
+}